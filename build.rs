@@ -1,12 +1,16 @@
 use std::{
-    env::current_dir,
+    env::{self, current_dir},
     fs::{self, File},
     io::Write,
+    process::Command,
+    time::{SystemTime, UNIX_EPOCH},
 };
 
 use cainome::rs::ExecutionVersion;
 
 fn main() {
+    embed_build_info();
+
     //Generate Starknet bindings
     let strk_abi_base = current_dir()
         .expect("failed to get current dir")
@@ -14,17 +18,19 @@ fn main() {
     let strk_bind_base = current_dir()
         .expect("failed to get current dir")
         .join("src/bindings");
-    let strk_deployments = [("Liquidate", "liquidate")];
+    // (contract name, abi file, bindings module) - one module per version of a contract.
+    let strk_deployments = [("Liquidate", "vesu_v2_periphery_Liquidate", "liquidate_v1")];
 
     // create destination folders if they doesn't exist
     fs::create_dir_all(strk_bind_base.clone()).expect("error creating output folders");
     let mut file = File::create(strk_bind_base.join("mod.rs")).expect("failed to create mod.rs");
+    file.write_all(b"#![allow(clippy::all, unused_assignments, unreachable_patterns)]\n")
+        .expect("failed to write into mod.rs");
 
-    for (abi_file, bind_out) in strk_deployments {
-        let contract_files =
-            strk_abi_base.join(format!("vesu_v2_periphery_{abi_file}.contract_class.json"));
+    for (contract_name, abi_file, bind_out) in strk_deployments {
+        let contract_files = strk_abi_base.join(format!("{abi_file}.contract_class.json"));
         let contract_files = contract_files.to_str().unwrap();
-        let abigen = cainome::rs::Abigen::new(abi_file, contract_files)
+        let abigen = cainome::rs::Abigen::new(contract_name, contract_files)
             .with_execution_version(ExecutionVersion::V3)
             .with_derives(vec![
                 "Debug".into(),
@@ -44,9 +50,36 @@ fn main() {
             )
             .unwrap_or_else(|_| panic!("Fail to write bindings to file in {:?}", strk_bind_base));
 
-        file.write_all(b"#![allow(clippy::all, unused_assignments, unreachable_patterns)]\n")
-            .expect("failed to write into mod.rs");
-        file.write_all(format!("pub mod {};", bind_out).as_bytes())
+        file.write_all(format!("pub mod {};\n", bind_out).as_bytes())
             .expect("failed to write into mod.rs");
     }
 }
+
+/// Embeds the git SHA, build time & enabled features, read by `utils::build_info`.
+fn embed_build_info() {
+    let git_sha = Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|sha| sha.trim().to_string())
+        .unwrap_or_else(|| "unknown".into());
+
+    let build_timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+
+    let mut features: Vec<String> = env::vars()
+        .filter_map(|(key, _)| {
+            key.strip_prefix("CARGO_FEATURE_")
+                .map(|feature| feature.to_lowercase().replace('_', "-"))
+        })
+        .collect();
+    features.sort();
+
+    println!("cargo:rustc-env=GIT_SHA={git_sha}");
+    println!("cargo:rustc-env=BUILD_TIMESTAMP={build_timestamp}");
+    println!("cargo:rustc-env=BUILD_FEATURES={}", features.join(","));
+}
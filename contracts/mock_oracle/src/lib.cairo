@@ -0,0 +1,43 @@
+use starknet::ContractAddress;
+
+/// The price of an asset, as returned by the Vesu oracle.
+#[derive(Copy, Drop, Serde)]
+pub struct AssetPrice {
+    pub value: u256,
+    pub is_valid: bool,
+}
+
+#[starknet::interface]
+pub trait IMockOracle<TContractState> {
+    /// Same interface as the `price` of the Vesu oracle, the price having 18
+    /// decimals. Not valid until a price got set.
+    fn price(self: @TContractState, asset: ContractAddress) -> AssetPrice;
+    /// Sets the price of an asset. Anyone can call it, only deploy it for tests.
+    fn set_price(ref self: TContractState, asset: ContractAddress, value: u256);
+}
+
+/// A Vesu oracle whose prices are pushed by `vesu-v2-liquidator test-feeder`,
+/// for the end-to-end tests of the liquidator on a devnet or Sepolia.
+#[starknet::contract]
+pub mod MockOracle {
+    use starknet::ContractAddress;
+    use starknet::storage::{Map, StorageMapReadAccess, StorageMapWriteAccess};
+    use super::AssetPrice;
+
+    #[storage]
+    struct Storage {
+        prices: Map<ContractAddress, u256>,
+    }
+
+    #[abi(embed_v0)]
+    impl MockOracleImpl of super::IMockOracle<ContractState> {
+        fn price(self: @ContractState, asset: ContractAddress) -> AssetPrice {
+            let value = self.prices.read(asset);
+            AssetPrice { value, is_valid: value != 0 }
+        }
+
+        fn set_price(ref self: ContractState, asset: ContractAddress, value: u256) {
+            self.prices.write(asset, value);
+        }
+    }
+}
@@ -1,34 +1,46 @@
 use std::{path::PathBuf, str::FromStr};
 
-use anyhow::{Result, anyhow};
+use anyhow::{Context, Result, anyhow};
 use clap::Args;
 use starknet::core::types::Felt;
 
-fn parse_felt(s: &str) -> Result<Felt> {
+pub fn parse_felt(s: &str) -> Result<Felt> {
     Felt::from_str(s).map_err(|_| anyhow!("Could not convert {s} to Felt"))
 }
 
 #[derive(Clone, Debug, Args)]
 pub struct AccountParams {
-    /// Account address of the liquidator account
+    /// Account address of the liquidator account, not needed without the
+    /// monitoring.
     #[clap(long, value_parser = parse_felt, value_name = "LIQUIDATOR ACCOUNT ADDRESS", env = "ACCOUNT_ADDRESS")]
-    pub account_address: Felt,
+    pub account_address: Option<Felt>,
 
     /// Private key of the liquidator account
     #[clap(long, value_parser = parse_felt, value_name = "LIQUIDATOR PRIVATE KEY", env = "PRIVATE_KEY")]
     pub private_key: Option<Felt>,
 
     /// Keystore path for the liquidator account
-    #[clap(long, value_name = "LIQUIDATOR KEYSTORE")]
+    #[clap(long, value_name = "LIQUIDATOR KEYSTORE", env = "KEYSTORE_PATH")]
     pub keystore_path: Option<PathBuf>,
 
     /// Keystore password for the liquidator account
-    #[clap(long, value_name = "LIQUIDATOR KEYSTORE PASSWORD")]
+    #[clap(
+        long,
+        value_name = "LIQUIDATOR KEYSTORE PASSWORD",
+        env = "KEYSTORE_PASSWORD"
+    )]
     pub keystore_password: Option<String>,
 }
 
 impl AccountParams {
+    /// The address of the liquidator account.
+    pub fn account_address(&self) -> Result<Felt> {
+        self.account_address
+            .context("Missing liquidator account address. Use --account-address.")
+    }
+
     pub fn validate(&self) -> Result<()> {
+        self.account_address()?;
         match (
             &self.private_key,
             &self.keystore_path,
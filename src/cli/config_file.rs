@@ -0,0 +1,103 @@
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result, anyhow};
+use clap::CommandFactory;
+use serde_yaml::Value;
+
+use crate::cli::RunCmd;
+
+const CONFIG_FLAG: &str = "--config";
+const CONFIG_ENV: &str = "CONFIG_PATH";
+
+/// Returns the CLI args completed with the values of the `--config` YAML file.
+/// The file uses the CLI options as keys (`rpc-url: ...` or `rpc_url: ...`) and
+/// only applies to the options not already set from the CLI or the env, so the
+/// precedence is CLI > env > file > defaults.
+pub fn args_with_config_file() -> Result<Vec<OsString>> {
+    let args: Vec<OsString> = std::env::args_os().collect();
+
+    let Some(path) = config_path(&args) else {
+        return Ok(args);
+    };
+
+    let content = std::fs::read_to_string(&path)
+        .with_context(|| format!("Could not read the config file {}", path.display()))?;
+    let values: BTreeMap<String, Value> = serde_yaml::from_str(&content)
+        .with_context(|| format!("Invalid config file {}", path.display()))?;
+
+    let command = RunCmd::command();
+    let mut file_args: Vec<OsString> = Vec::new();
+
+    for (key, value) in values {
+        let long = key.replace('_', "-");
+        if long == "config" {
+            continue;
+        }
+
+        let arg = command
+            .get_arguments()
+            .find(|arg| arg.get_long() == Some(long.as_str()))
+            .ok_or_else(|| anyhow!("Unknown option `{key}` in {}", path.display()))?;
+
+        let short = arg.get_short().map(|short| format!("-{short}"));
+        let set_in_cli = args.iter().any(|a| {
+            let a = a.to_string_lossy();
+            a == format!("--{long}")
+                || a.starts_with(&format!("--{long}="))
+                || short.as_deref().is_some_and(|short| a.starts_with(short))
+        });
+        let set_in_env = arg
+            .get_env()
+            .is_some_and(|env| std::env::var_os(env).is_some());
+        if set_in_cli || set_in_env {
+            continue;
+        }
+
+        match value {
+            Value::Bool(true) => file_args.push(format!("--{long}").into()),
+            Value::Bool(false) | Value::Null => {}
+            Value::Sequence(values) => {
+                for value in values {
+                    file_args.push(format!("--{long}={}", scalar(&key, value)?).into());
+                }
+            }
+            value => file_args.push(format!("--{long}={}", scalar(&key, value)?).into()),
+        }
+    }
+
+    // The options must come before the subcommand, if any.
+    let mut args = args.into_iter();
+    Ok(args
+        .next()
+        .into_iter()
+        .chain(file_args)
+        .chain(args)
+        .collect())
+}
+
+/// Returns the path of the config file, from the CLI or the env.
+fn config_path(args: &[OsString]) -> Option<PathBuf> {
+    let mut args = args.iter().map(|a| a.to_string_lossy());
+    while let Some(arg) = args.next() {
+        if arg == CONFIG_FLAG {
+            return args.next().map(|path| PathBuf::from(path.as_ref()));
+        }
+        if let Some(path) = arg.strip_prefix(&format!("{CONFIG_FLAG}=")) {
+            return Some(PathBuf::from(path));
+        }
+    }
+    std::env::var_os(CONFIG_ENV).map(PathBuf::from)
+}
+
+fn scalar(key: &str, value: Value) -> Result<String> {
+    match value {
+        Value::String(s) => Ok(s),
+        Value::Number(n) => Ok(n.to_string()),
+        Value::Bool(b) => Ok(b.to_string()),
+        _ => Err(anyhow!(
+            "Option `{key}` must be a string, a number or a bool"
+        )),
+    }
+}
@@ -0,0 +1,275 @@
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{Result, anyhow, bail, ensure};
+use colored::Colorize;
+use evian::vesu::v2::data::VesuDataClient;
+use num_traits::Pow;
+use pragma_common::starknet::FallbackProvider;
+use rust_decimal::Decimal;
+use starknet::core::types::{BlockId, BlockTag, ContractClass, Felt};
+use starknet::providers::Provider;
+use strum::IntoEnumIterator;
+
+use crate::cli::RunCmd;
+use crate::config::addresses::{AddressBook, NETWORK};
+use crate::config::onchain_assets::ONCHAIN_ASSETS;
+use crate::services::indexer::ApibaraEndpoint;
+use crate::services::monitoring::lltv_check::{fetch_pair_lltvs, zero_lltv_pairs};
+use crate::services::oracle::OracleService;
+use crate::types::currency::Currency;
+use crate::types::keeper::KeeperRegistry;
+use crate::types::liquidate_contract::LiquidateContractVersion;
+use crate::types::pool::PoolName;
+use crate::utils::erc20::balance_of;
+
+/// Outcome of a single diagnostic.
+struct Check {
+    name: String,
+    result: Result<String>,
+}
+
+/// Runs every diagnostic & prints a pass/fail report.
+/// Fails if any of the checks failed.
+pub async fn run_doctor(run_cmd: &RunCmd) -> Result<()> {
+    let mut checks = Vec::new();
+
+    for rpc_url in run_cmd.rpc_urls() {
+        checks.push(Check {
+            name: format!("RPC {rpc_url}"),
+            result: check_rpc(rpc_url).await,
+        });
+    }
+
+    let provider = FallbackProvider::new(run_cmd.rpc_urls())?;
+    let account_address = run_cmd.account_params.account_address()?;
+
+    for endpoint in run_cmd.apibara_endpoints()? {
+        checks.push(Check {
+            name: format!("Apibara {endpoint}"),
+            result: check_apibara_endpoint(&provider, &endpoint).await,
+        });
+    }
+    checks.push(Check {
+        name: format!("Account {account_address:#x}"),
+        result: check_account(&provider, account_address, run_cmd.fee_token.currency()).await,
+    });
+    let liquidate_contract = AddressBook::get().liquidate_contract;
+    checks.push(Check {
+        name: format!("Liquidate contract {liquidate_contract:#x}"),
+        result: check_contract(&provider, liquidate_contract, account_address).await,
+    });
+    for (pool, address) in &run_cmd.pool_liquidate_contract {
+        checks.push(Check {
+            name: format!("Liquidate contract of {pool} {address:#x}"),
+            result: check_contract(&provider, *address, account_address).await,
+        });
+    }
+    checks.push(Check {
+        name: "Vesu oracle".into(),
+        result: check_oracle(&provider).await,
+    });
+    checks.push(Check {
+        name: "Pairs LLTV".into(),
+        result: check_pair_lltvs(&provider).await,
+    });
+    checks.push(Check {
+        name: "Config consistency".into(),
+        result: check_config(),
+    });
+
+    println!("\n🩺 Doctor report\n");
+    let mut failures = 0;
+    for check in &checks {
+        match &check.result {
+            Ok(details) => println!("{} {} - {details}", "PASS".green(), check.name),
+            Err(e) => {
+                failures += 1;
+                println!("{} {} - {e}", "FAIL".red(), check.name);
+            }
+        }
+    }
+    println!();
+
+    if failures > 0 {
+        bail!("{failures}/{} checks failed", checks.len());
+    }
+    Ok(())
+}
+
+async fn check_rpc(rpc_url: url::Url) -> Result<String> {
+    let provider = FallbackProvider::new(vec![rpc_url])?;
+    let started_at = Instant::now();
+    let block_number = provider.block_number().await?;
+    Ok(format!(
+        "block #{block_number} in {:?}",
+        started_at.elapsed()
+    ))
+}
+
+/// How long the DNA stream has to send its first message.
+const APIBARA_CHECK_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Opens a stream from the chain head with the API key of the endpoint: an
+/// invalid key gets the stream rejected by the DNA server.
+async fn check_apibara_endpoint(
+    provider: &FallbackProvider,
+    endpoint: &ApibaraEndpoint,
+) -> Result<String> {
+    ensure!(!endpoint.api_key.trim().is_empty(), "key is empty");
+    ensure!(
+        endpoint.api_key.starts_with("dna_"),
+        "key should start with `dna_`"
+    );
+
+    let head_block = provider.block_number().await?;
+    let started_at = Instant::now();
+    let (mut rx_messages, mut handle) = endpoint.indexer(provider, head_block)?.start(None).await?;
+    let first_message = tokio::time::timeout(APIBARA_CHECK_TIMEOUT, async {
+        tokio::select! {
+            Some(_) = rx_messages.recv() => Ok(()),
+            res = &mut handle => Err(anyhow!("stream stopped: {res:?}")),
+        }
+    })
+    .await;
+    handle.abort();
+
+    match first_message {
+        Ok(Ok(())) => Ok(format!("stream opened in {:?}", started_at.elapsed())),
+        Ok(Err(e)) => Err(e),
+        Err(_) => bail!(
+            "no message from the stream in {}s",
+            APIBARA_CHECK_TIMEOUT.as_secs()
+        ),
+    }
+}
+
+async fn check_account(
+    provider: &FallbackProvider,
+    account_address: Felt,
+    fee_token: Currency,
+) -> Result<String> {
+    provider
+        .get_class_hash_at(BlockId::Tag(BlockTag::Latest), account_address)
+        .await
+        .map_err(|e| anyhow::anyhow!("account is not deployed: {e:?}"))?;
+
+    let balance = balance_of(provider, fee_token.address(), account_address).await?;
+    let balance =
+        Decimal::from_str(&balance.low.to_string())? / Decimal::TEN.pow(fee_token.d_decimals());
+    ensure!(
+        !balance.is_zero(),
+        "account has no {fee_token} to pay for fees"
+    );
+
+    Ok(format!("deployed, {}", fee_token.format_amount(balance)))
+}
+
+async fn check_pair_lltvs(provider: &FallbackProvider) -> Result<String> {
+    let vesu_client = Arc::new(VesuDataClient::new(NETWORK, provider.clone()));
+    let lltvs = fetch_pair_lltvs(&vesu_client).await;
+
+    let zero_pairs = zero_lltv_pairs(&lltvs);
+    ensure!(
+        zero_pairs.is_empty(),
+        "pairs with a zero LLTV, never liquidable: {}",
+        zero_pairs
+            .iter()
+            .map(|(pool, collateral, debt)| format!("{pool} {collateral}/{debt}"))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+
+    let unreadable = lltvs.iter().filter(|(_, lltv)| lltv.is_err()).count();
+    ensure!(
+        unreadable == 0,
+        "could not read the LLTV of {unreadable} pairs"
+    );
+
+    Ok(format!("{} pairs with a non-zero LLTV", lltvs.len()))
+}
+
+async fn check_contract(
+    provider: &FallbackProvider,
+    address: Felt,
+    account_address: Felt,
+) -> Result<String> {
+    let class_hash = provider
+        .get_class_hash_at(BlockId::Tag(BlockTag::Latest), address)
+        .await
+        .map_err(|e| anyhow::anyhow!("contract is not deployed: {e:?}"))?;
+
+    let ContractClass::Sierra(class) = provider
+        .get_class(BlockId::Tag(BlockTag::Latest), class_hash)
+        .await?
+    else {
+        bail!("class hash {class_hash:#x} is not a Sierra class");
+    };
+    let Some(version) = LiquidateContractVersion::from_abi(&class.abi)? else {
+        bail!("class hash {class_hash:#x} implements an unsupported interface");
+    };
+
+    let Some(registry) = KeeperRegistry::detect(provider, address).await? else {
+        return Ok(format!("class hash {class_hash:#x} ({version})"));
+    };
+    ensure!(
+        registry.is_registered(provider, account_address).await?,
+        "the account is not a registered keeper, run `register-keeper`"
+    );
+    Ok(format!(
+        "class hash {class_hash:#x} ({version}), account registered as keeper"
+    ))
+}
+
+async fn check_oracle(provider: &FallbackProvider) -> Result<String> {
+    let oracle = OracleService::new(provider.clone());
+    let started_at = Instant::now();
+    let strk_price = oracle
+        .vesu_price_in_usd(&ONCHAIN_ASSETS[Currency::STRK])
+        .await?;
+    Ok(format!(
+        "STRK at ${} in {:?}",
+        strk_price.round_dp(4),
+        started_at.elapsed()
+    ))
+}
+
+fn check_config() -> Result<String> {
+    let assets = ONCHAIN_ASSETS.all();
+
+    for currency in Currency::iter() {
+        ensure!(
+            ONCHAIN_ASSETS.get_by_ticker(currency.as_ref()).is_some(),
+            "currency {currency} is missing from assets.toml"
+        );
+    }
+
+    for asset in &assets {
+        ensure!(
+            Currency::from_str(&asset.ticker).is_ok(),
+            "asset {} has no matching currency",
+            asset.ticker
+        );
+        ensure!(
+            ONCHAIN_ASSETS
+                .get_by_address(&asset.address)
+                .is_some_and(|a| a.ticker == asset.ticker),
+            "address of asset {} is duplicated",
+            asset.ticker
+        );
+    }
+
+    for pool in PoolName::iter() {
+        ensure!(
+            PoolName::try_from(&pool.pool_address()).is_ok_and(|p| p == pool),
+            "pool {pool} address does not resolve back to it"
+        );
+    }
+
+    Ok(format!(
+        "{} assets, {} pools",
+        assets.len(),
+        PoolName::iter().count()
+    ))
+}
@@ -0,0 +1,85 @@
+use anyhow::{Context, Result};
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use starknet::core::types::Felt;
+
+use crate::cli::{EventsCommand, RunCmd};
+use crate::services::replay::archive::{
+    ArchivedEvent, ArchivedEventKind, EVENT_ARCHIVE_FILE, EventArchive, EventFilter,
+};
+use crate::types::position::Asset;
+
+/// Scale of the deltas of the Vesu events.
+const VESU_SCALE: Decimal = dec!(1_000_000_000_000_000_000);
+
+pub fn run_events(run_cmd: &RunCmd, command: &EventsCommand) -> Result<()> {
+    let state_dir = run_cmd
+        .state_dir
+        .as_deref()
+        .context("The events commands need a --state-dir")?;
+    let archive_path = state_dir.join(EVENT_ARCHIVE_FILE);
+    anyhow::ensure!(
+        archive_path.exists(),
+        "No event archive in {}: the indexer archives its events there once started with --state-dir",
+        state_dir.display()
+    );
+    let archive = EventArchive::open_read_only(&archive_path)?;
+
+    match command {
+        EventsCommand::Query {
+            pool,
+            user,
+            since,
+            limit,
+        } => {
+            let events = archive.query(&EventFilter {
+                pool: *pool,
+                user: *user,
+                since_block: *since,
+                limit: *limit,
+            })?;
+            for event in &events {
+                print_event(event);
+            }
+            println!("\n📚 {} events", events.len());
+            Ok(())
+        }
+    }
+}
+
+fn print_event(event: &ArchivedEvent) {
+    let kind = match event.kind {
+        ArchivedEventKind::Position => "📝 position",
+        ArchivedEventKind::Liquidation => "🔫 liquidation",
+        ArchivedEventKind::Context => "⚙️ context",
+    };
+    let details = match &event.delta {
+        Some(delta) => format!(
+            " of {} - collateral {}, debt {}",
+            delta.user,
+            format_delta(&delta.collateral, delta.collateral_delta),
+            format_delta(&delta.debt, delta.debt_delta),
+        ),
+        None => format!(": {}", event.raw),
+    };
+    println!(
+        "#{}.{} {} {kind}{details} (tx {})",
+        event.block_number, event.event_index, event.pool, event.transaction_hash
+    );
+}
+
+/// Formats the delta in units of the asset if listed, raw otherwise.
+fn format_delta(asset: &str, delta: Decimal) -> String {
+    let listed = Felt::from_hex(asset)
+        .ok()
+        .and_then(|address| Asset::from_address(address).ok());
+    let amount = match listed {
+        Some(listed) => listed.currency.format_amount(delta / VESU_SCALE),
+        None => format!("{delta} (raw) of {asset}"),
+    };
+    if delta.is_sign_negative() {
+        amount
+    } else {
+        format!("+{amount}")
+    }
+}
@@ -0,0 +1,58 @@
+use anyhow::Result;
+use pragma_common::starknet::FallbackProvider;
+use starknet::core::types::{Call, Felt};
+
+use crate::cli::RunCmd;
+use crate::config::addresses::AddressBook;
+use crate::services::audit::TxPurpose;
+use crate::types::account::StarknetAccount;
+use crate::types::keeper::keeper_registries;
+use crate::types::liquidate_contract::LiquidateContracts;
+
+/// Registers the keeper - the account if not set - with every liquidate contract
+/// that has a keeper registration & does not know it yet, in one transaction
+/// sent by the account.
+pub async fn run_register_keeper(run_cmd: &RunCmd, keeper: Option<Felt>) -> Result<()> {
+    let provider = FallbackProvider::new(run_cmd.rpc_urls())?;
+    let account = StarknetAccount::from_cli(provider.clone(), run_cmd.clone()).await?;
+    let keeper = keeper.unwrap_or_else(|| account.account_address());
+
+    let liquidate_contracts = LiquidateContracts::detect(
+        &provider,
+        &account,
+        AddressBook::get().liquidate_contract,
+        &run_cmd.pool_liquidate_contract,
+    )
+    .await?;
+    let registries = keeper_registries(&provider, &liquidate_contracts).await?;
+    if registries.is_empty() {
+        println!(
+            "\n🔑 The liquidate contracts have no keeper registration: nothing to register.\n"
+        );
+        return Ok(());
+    }
+
+    let mut calls: Vec<Call> = Vec::new();
+    for registry in &registries {
+        if registry.is_registered(&provider, keeper).await? {
+            println!(
+                "🔑 {keeper:#x} is already a keeper of {:#x}",
+                registry.contract
+            );
+        } else {
+            calls.push(registry.register_call(keeper));
+        }
+    }
+    if calls.is_empty() {
+        return Ok(());
+    }
+
+    let tx_hash = account
+        .execute_txs(&calls, TxPurpose::KeeperRegistration)
+        .await?;
+    println!(
+        "\n🔑 Registered {keeper:#x} with {} liquidate contracts (tx {tx_hash:#064x})\n",
+        calls.len()
+    );
+    Ok(())
+}
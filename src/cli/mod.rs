@@ -1,17 +1,268 @@
 pub mod account;
+pub mod config_file;
+pub mod doctor;
+pub mod events;
+pub mod keeper;
+pub mod positions;
+pub mod simulate;
+pub mod startup;
+pub mod state;
+#[cfg(feature = "test-feeder")]
+pub mod test_feeder;
+pub mod watch;
 
-use anyhow::{Result, anyhow};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::{Context, Result, anyhow};
+use rust_decimal::Decimal;
+use starknet::core::types::Felt;
 use url::Url;
 
-use crate::cli::account::AccountParams;
+use crate::cli::account::{AccountParams, parse_felt};
+use crate::config::addresses::{AddressBook, NETWORK, NetworkAddresses};
+use crate::config::onchain_assets::AssetClass;
+use crate::services::indexer::{ApibaraEndpoint, IndexerConfig};
+use crate::services::monitoring::depth::DepthCap;
+use crate::services::monitoring::health_summary::HealthyPositionsLog;
+use crate::services::monitoring::opportunities::OpportunityBroadcastConfig;
+use crate::services::monitoring::strategy::OversizedLiquidation;
+use crate::services::monitoring::user_scope::ProtectedUsersAction;
+use crate::services::notifier::{AlertKind, NotifierConfig};
+use crate::services::oracle::OracleMode;
+use crate::services::oracle::sources::{
+    DEFAULT_PRAGMA_API_URL, PragmaApiConfig, PriceSourceConfig, PriceSources, PriceSourcesFile,
+    parse_price_source,
+};
+use crate::services::stream::{EventStreamConfig, ProcessRole};
+use crate::types::account::FeeToken;
+use crate::types::currency::Currency;
+use crate::types::pool::PoolName;
 
 fn parse_url(s: &str) -> Result<Url> {
     s.parse()
         .map_err(|_| anyhow!("Could not convert {s} to Url"))
 }
 
+/// `URL` or `API_KEY@URL`.
+fn parse_apibara_endpoint(s: &str) -> Result<(Option<String>, Url)> {
+    match s.split_once('@') {
+        Some((api_key, url)) if !api_key.contains("://") => {
+            Ok((Some(api_key.to_string()), parse_url(url)?))
+        }
+        _ => Ok((None, parse_url(s)?)),
+    }
+}
+
+fn parse_url_timeout(s: &str) -> Result<(Url, u64)> {
+    let (url, millis) = s
+        .rsplit_once('=')
+        .ok_or_else(|| anyhow!("Expected URL=MILLISECONDS, got {s}"))?;
+    Ok((parse_url(url)?, millis.parse()?))
+}
+
+fn parse_pool_delay(s: &str) -> Result<(PoolName, u64)> {
+    let (pool, secs) = s
+        .split_once('=')
+        .ok_or_else(|| anyhow!("Expected POOL=SECONDS, got {s}"))?;
+    let pool = PoolName::from_str(pool).map_err(|_| anyhow!("Unknown pool {pool}"))?;
+    Ok((pool, secs.parse()?))
+}
+
+fn parse_alert_duration(s: &str) -> Result<(AlertKind, u64)> {
+    let (kind, duration) = s
+        .split_once('=')
+        .ok_or_else(|| anyhow!("Expected KIND=DURATION, got {s}"))?;
+    let kind = AlertKind::from_str(kind).map_err(|_| anyhow!("Unknown notification {kind}"))?;
+    Ok((kind, duration.parse()?))
+}
+
+fn parse_class_amount(s: &str) -> Result<(AssetClass, Decimal)> {
+    let (class, amount) = s
+        .split_once('=')
+        .ok_or_else(|| anyhow!("Expected CLASS=VALUE, got {s}"))?;
+    let class = AssetClass::from_str(class).map_err(|_| anyhow!("Unknown asset class {class}"))?;
+    Ok((class, Decimal::from_str(amount)?))
+}
+
+fn parse_class_asset(s: &str) -> Result<(AssetClass, Currency)> {
+    let (class, ticker) = s
+        .split_once('=')
+        .ok_or_else(|| anyhow!("Expected CLASS=TICKER, got {s}"))?;
+    let class = AssetClass::from_str(class).map_err(|_| anyhow!("Unknown asset class {class}"))?;
+    let currency = Currency::from_str(ticker).map_err(|_| anyhow!("Unknown asset {ticker}"))?;
+    Ok((class, currency))
+}
+
+fn parse_pool_liquidate_contract(s: &str) -> Result<(PoolName, Felt)> {
+    let (pool, address) = s
+        .split_once('=')
+        .ok_or_else(|| anyhow!("Expected POOL=ADDRESS, got {s}"))?;
+    let pool = PoolName::from_str(pool).map_err(|_| anyhow!("Unknown pool {pool}"))?;
+    Ok((pool, parse_felt(address)?))
+}
+
+fn parse_user_delay(s: &str) -> Result<(Felt, u64)> {
+    let (user, secs) = s
+        .split_once('=')
+        .ok_or_else(|| anyhow!("Expected USER ADDRESS=SECONDS, got {s}"))?;
+    Ok((parse_felt(user)?, secs.parse()?))
+}
+
+#[derive(Clone, Debug, clap::Subcommand)]
+pub enum Command {
+    /// Checks the environment & prints a pass/fail report before going live.
+    Doctor,
+    /// Reads positions from the chain state.
+    #[clap(subcommand)]
+    Positions(PositionsCommand),
+    /// Exports or imports the state of `--state-dir`, e.g to move it to
+    /// another host.
+    #[clap(subcommand)]
+    State(StateCommand),
+    /// Reads the events archived in `--state-dir`.
+    #[clap(subcommand)]
+    Events(EventsCommand),
+    /// Builds the liquidation of a live position, swap route included, & prints
+    /// its simulated fee, collateral received & profit. Nothing is sent.
+    SimulateLiquidation {
+        /// Pool of the position, cf: `Re7USDCCore`.
+        #[clap(long, value_name = "POOL")]
+        pool: PoolName,
+        /// Address of the user.
+        #[clap(long, value_parser = parse_felt, value_name = "USER ADDRESS")]
+        user: Felt,
+        #[clap(long, value_name = "TICKER")]
+        collateral: Currency,
+        #[clap(long, value_name = "TICKER")]
+        debt: Currency,
+        /// Debt to repay, in debt units. The whole debt if not set.
+        #[clap(long, value_name = "AMOUNT")]
+        debt_to_repay: Option<Decimal>,
+    },
+    /// Registers the account as a keeper of the liquidate contracts that only
+    /// liquidate for their registered keepers. The transaction is sent by the
+    /// account.
+    RegisterKeeper {
+        /// Keeper to register instead of the account, e.g when the account is
+        /// the one allowed to register the keepers.
+        #[clap(long, value_parser = parse_felt, value_name = "KEEPER ADDRESS")]
+        keeper: Option<Felt>,
+    },
+    /// Pushes prices to a mock Vesu oracle (see `contracts/mock_oracle`) on a
+    /// devnet or Sepolia, to rehearse the liquidations end to end.
+    #[cfg(feature = "test-feeder")]
+    TestFeeder {
+        /// Address of the mock oracle.
+        #[clap(long, value_parser = parse_felt, value_name = "ORACLE ADDRESS")]
+        oracle: Felt,
+        /// USD price to push for an asset, cf: `ETH=2500` or `0x049d...=2500`.
+        #[clap(
+            long = "price",
+            value_parser = parse_feeder_price,
+            value_name = "ASSET=PRICE",
+            required = true
+        )]
+        prices: Vec<(Felt, Decimal)>,
+        /// Pushes the prices again every interval instead of once.
+        #[clap(long, value_name = "SECONDS")]
+        interval_secs: Option<u64>,
+        /// Multiplies the prices at every push, cf: `0.98` to drop them by 2% per
+        /// interval until the positions get liquidable.
+        #[clap(long, value_name = "FACTOR", default_value = "1")]
+        drift: Decimal,
+    },
+}
+
+/// Parses an `ASSET=PRICE`, the asset being a ticker or a token address.
+#[cfg(feature = "test-feeder")]
+fn parse_feeder_price(s: &str) -> Result<(Felt, Decimal)> {
+    let (asset, price) = s
+        .split_once('=')
+        .ok_or_else(|| anyhow!("Expected ASSET=PRICE, got {s}"))?;
+    let asset = match Currency::from_str(asset) {
+        Ok(currency) => currency.address(),
+        Err(_) => parse_felt(asset)?,
+    };
+    Ok((asset, Decimal::from_str(price)?))
+}
+
+#[derive(Clone, Debug, clap::Subcommand)]
+pub enum PositionsCommand {
+    /// Prints the positions of a user in all the monitored pools.
+    Show {
+        /// Address of the user.
+        #[clap(long, value_parser = parse_felt, value_name = "USER ADDRESS")]
+        user: Felt,
+    },
+    /// Shows live the positions by health factor, the prices, the indexer lag &
+    /// the recent liquidations of a running bot.
+    Watch {
+        /// Address of the API of the bot (see `--api-address`).
+        #[clap(
+            long,
+            value_parser = parse_url,
+            value_name = "API URL",
+            env = "WATCH_API_URL",
+            default_value = "http://127.0.0.1:8080"
+        )]
+        api_url: Url,
+        /// Interval between two refreshes.
+        #[clap(long, value_name = "SECONDS", default_value = "2")]
+        refresh_secs: u64,
+    },
+}
+
+#[derive(Clone, Debug, clap::Subcommand)]
+pub enum StateCommand {
+    /// Writes the positions & the block cursor of the state to a single file.
+    Export {
+        #[clap(long, value_name = "SNAPSHOT PATH")]
+        out: PathBuf,
+    },
+    /// Replaces the state with an exported one. The bot must be stopped.
+    Import {
+        #[clap(value_name = "SNAPSHOT PATH")]
+        path: PathBuf,
+        /// Replaces the existing state, if any.
+        #[clap(long)]
+        force: bool,
+    },
+}
+
+#[derive(Clone, Debug, clap::Subcommand)]
+pub enum EventsCommand {
+    /// Prints the archived events matching the filters, oldest first, e.g to
+    /// follow what happened to a position.
+    Query {
+        /// Pool of the events, cf: `Prime`.
+        #[clap(long, value_name = "POOL")]
+        pool: Option<PoolName>,
+        /// Address of the user of the positions.
+        #[clap(long, value_parser = parse_felt, value_name = "USER ADDRESS")]
+        user: Option<Felt>,
+        /// First block of the events.
+        #[clap(long, value_name = "BLOCK")]
+        since: Option<u64>,
+        /// Maximum number of events printed.
+        #[clap(long, value_name = "EVENTS", default_value = "100")]
+        limit: usize,
+    },
+}
+
 #[derive(Clone, Debug, clap::Parser)]
 pub struct RunCmd {
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+
+    /// YAML file defining any of the options below. The CLI & env variables
+    /// override the values of the file.
+    #[clap(long, value_name = "CONFIG PATH", env = "CONFIG_PATH")]
+    pub config: Option<PathBuf>,
+
     #[allow(missing_docs)]
     #[clap(flatten)]
     pub account_params: AccountParams,
@@ -20,24 +271,1060 @@ pub struct RunCmd {
     #[clap(long, value_parser = parse_url, value_name = "RPC URL", env = "RPC_URL")]
     pub rpc_url: Url,
 
-    /// The block you want to start syncing from.
+    /// WebSocket rpc endpoint url, to follow the new blocks as they come & refresh
+    /// the prices & the riskiest positions at each of them.
+    #[clap(long, value_parser = parse_url, value_name = "WS RPC URL", env = "WS_RPC_URL")]
+    pub ws_rpc_url: Option<Url>,
+
+    /// The block you want to start syncing from. By default, each pool is synced
+    /// from its deployment block.
+    #[clap(long, short, value_name = "BLOCK NUMBER", env = "STARTING_BLOCK")]
+    pub starting_block: Option<u64>,
+
+    /// Apibara API Key for indexing, not needed without the indexer.
+    #[clap(long, value_name = "APIBARA API KEY", env = "APIBARA_API_KEY")]
+    pub apibara_api_key: Option<String>,
+
+    /// Apibara DNA streams the indexer fails over to when the current one
+    /// fails, in order, as `URL` or `API_KEY@URL`. The key defaults to
+    /// `--apibara-api-key`.
     #[clap(
         long,
-        short,
-        value_name = "BLOCK NUMBER",
-        env = "STARTING_BLOCK",
-        default_value = "2383614"
+        value_parser = parse_apibara_endpoint,
+        value_name = "[API_KEY@]URL",
+        env = "APIBARA_FALLBACK_ENDPOINTS",
+        value_delimiter = ','
     )]
-    pub starting_block: u64,
+    pub apibara_fallback_endpoint: Vec<(Option<String>, Url)>,
 
-    /// Apibara API Key for indexing.
-    #[clap(long, value_name = "APIBARA API KEY", env = "APIBARA_API_KEY")]
-    pub apibara_api_key: String,
+    /// Number of blocks the indexer can lag behind the chain head before alerting.
+    #[clap(
+        long,
+        value_name = "BLOCKS",
+        env = "MAX_INDEXER_LAG_BLOCKS",
+        default_value = "50"
+    )]
+    pub max_indexer_lag_blocks: u64,
+
+    /// Interval between two enumerations of the pairs of the monitored pools,
+    /// onboarding the new ones. 0 disables the discovery.
+    #[clap(
+        long,
+        value_name = "SECONDS",
+        env = "PAIR_DISCOVERY_INTERVAL_SECS",
+        default_value = "21600"
+    )]
+    pub pair_discovery_interval_secs: u64,
+
+    /// Number of streams indexing the history in parallel when the indexer
+    /// starts far behind the chain head, e.g on a cold start. 1 indexes it with
+    /// a single stream.
+    #[clap(
+        long,
+        value_name = "STREAMS",
+        env = "BACKFILL_CHUNKS",
+        default_value = "1"
+    )]
+    pub backfill_chunks: usize,
+
+    /// Webhook receiving the notifications - positions at risk, protected users,
+    /// depegs & indexer lag - in the Slack & Discord format. They are only logged
+    /// if not set.
+    #[clap(
+        long,
+        value_parser = parse_url,
+        value_name = "WEBHOOK URL",
+        env = "NOTIFY_WEBHOOK_URL"
+    )]
+    pub notify_webhook_url: Option<Url>,
+
+    /// How long a condition must last before being notified, per kind of
+    /// notification, e.g `position-at-risk=60`. Notified right away if not set.
+    #[clap(
+        long,
+        value_parser = parse_alert_duration,
+        value_name = "KIND=SECONDS",
+        env = "NOTIFY_DELAYS",
+        value_delimiter = ','
+    )]
+    pub notify_delay: Vec<(AlertKind, u64)>,
+
+    /// Interval of the reminders while a condition lasts, per kind of
+    /// notification, e.g `depeg=10`. Every 30 minutes if not set, never if zero.
+    #[clap(
+        long,
+        value_parser = parse_alert_duration,
+        value_name = "KIND=MINUTES",
+        env = "NOTIFY_REMINDERS",
+        value_delimiter = ','
+    )]
+    pub notify_reminder: Vec<(AlertKind, u64)>,
+
+    /// Timeout of the RPC calls building, sending & tracking the liquidations.
+    #[clap(
+        long,
+        value_name = "MILLISECONDS",
+        env = "RPC_LIQUIDATION_TIMEOUT_MS",
+        default_value = "5000"
+    )]
+    pub rpc_liquidation_timeout_ms: u64,
+
+    /// Timeout of the other RPC calls: prices, position reads, scans...
+    #[clap(
+        long,
+        value_name = "MILLISECONDS",
+        env = "RPC_BACKGROUND_TIMEOUT_MS",
+        default_value = "10000"
+    )]
+    pub rpc_background_timeout_ms: u64,
+
+    /// Timeout of all the calls to a Starknet RPC node, over the ones of
+    /// their path, e.g `https://my-node.xyz=2000` for a node faster than the
+    /// public ones.
+    #[clap(
+        long,
+        value_parser = parse_url_timeout,
+        value_name = "URL=MILLISECONDS",
+        env = "RPC_NODE_TIMEOUTS_MS",
+        value_delimiter = ','
+    )]
+    pub rpc_node_timeout_ms: Vec<(Url, u64)>,
+
+    /// Consecutive failures or timeouts of a provider (a Starknet RPC node, the
+    /// Ekubo API) before its background calls are skipped for
+    /// `--rpc-breaker-cooldown-secs`.
+    #[clap(
+        long,
+        value_name = "FAILURES",
+        env = "RPC_BREAKER_FAILURES",
+        default_value = "5"
+    )]
+    pub rpc_breaker_failures: u32,
+
+    /// How long the background calls to a failing provider are skipped.
+    #[clap(
+        long,
+        value_name = "SECONDS",
+        env = "RPC_BREAKER_COOLDOWN_SECS",
+        default_value = "30"
+    )]
+    pub rpc_breaker_cooldown_secs: u64,
+
+    /// Restarts the oracle or the indexer once it made no progress - no price
+    /// updated, no message of the stream - for this many minutes. 0 to never
+    /// restart them.
+    #[clap(
+        long,
+        value_name = "MINUTES",
+        env = "WATCHDOG_STALL_MINUTES",
+        default_value = "10"
+    )]
+    pub watchdog_stall_minutes: u64,
+
+    /// How the oracle prices get refreshed.
+    #[clap(
+        long,
+        value_enum,
+        value_name = "MODE",
+        env = "ORACLE_MODE",
+        default_value = "polling"
+    )]
+    pub oracle_mode: OracleMode,
+
+    /// Overrides the price source of an asset, the Vesu oracle by default:
+    /// `pragma-onchain`, `pragma-api` or a fixed `peg:<USD PRICE>`, e.g
+    /// `xSTRK=pragma-onchain`. Takes precedence over `--price-sources-config`.
+    #[clap(
+        long,
+        value_parser = parse_price_source,
+        value_name = "TICKER=SOURCE",
+        env = "PRICE_SOURCES",
+        value_delimiter = ','
+    )]
+    pub price_source: Vec<(String, PriceSourceConfig)>,
+
+    /// TOML file with the price source of the assets, which can combine several
+    /// sources with a median or a priority fallback.
+    #[clap(long, value_name = "PATH", env = "PRICE_SOURCES_CONFIG")]
+    pub price_sources_config: Option<PathBuf>,
+
+    /// Oracle read by the `vesu` price source, the mainnet Vesu oracle if not
+    /// set. Point it to a mock oracle to rehearse liquidations on a devnet.
+    #[clap(
+        long,
+        value_parser = parse_felt,
+        value_name = "ORACLE ADDRESS",
+        env = "VESU_ORACLE_ADDRESS"
+    )]
+    pub vesu_oracle_address: Option<Felt>,
+
+    /// Overrides the addresses of the contracts - the liquidate contract, the
+    /// oracles, the Ekubo router & the pools - with the ones of this TOML file,
+    /// with the layout of `config/addresses.toml`.
+    #[clap(long, value_name = "ADDRESSES PATH", env = "ADDRESSES_FILE")]
+    pub addresses_file: Option<PathBuf>,
+
+    /// The Pragma API, for the assets priced with `pragma-api`.
+    #[clap(
+        long,
+        value_parser = parse_url,
+        value_name = "PRAGMA API URL",
+        env = "PRAGMA_API_URL",
+        default_value = DEFAULT_PRAGMA_API_URL
+    )]
+    pub pragma_api_url: Url,
+
+    #[clap(long, value_name = "PRAGMA API KEY", env = "PRAGMA_API_KEY")]
+    pub pragma_api_key: Option<String>,
+
+    /// Token used to pay the transaction fees.
+    #[clap(
+        long,
+        value_enum,
+        value_name = "TOKEN",
+        env = "FEE_TOKEN",
+        default_value = "strk"
+    )]
+    pub fee_token: FeeToken,
+
+    /// Minimum balance of the fee token to send liquidations. Below it, the
+    /// liquidations are not sent & an error is logged.
+    #[clap(
+        long,
+        value_name = "AMOUNT",
+        env = "MIN_FEE_TOKEN_BALANCE",
+        default_value = "1"
+    )]
+    pub min_fee_token_balance: Decimal,
+
+    /// Maximum number of liquidations batched in a single transaction.
+    #[clap(
+        long,
+        value_name = "COUNT",
+        env = "MAX_LIQUIDATIONS_PER_TX",
+        default_value = "5"
+    )]
+    pub max_liquidations_per_tx: usize,
+
+    /// Address receiving the collateral seized by the liquidations, e.g a
+    /// multisig treasury. The fees are still paid by the signer. Defaults to the
+    /// signer address. The inventory liquidations keep it on the signer.
+    #[clap(
+        long,
+        value_parser = parse_felt,
+        value_name = "ADDRESS",
+        env = "RECIPIENT_ADDRESS",
+        conflicts_with = "enable_treasury"
+    )]
+    pub recipient: Option<Felt>,
+
+    /// Number of consecutive checks a position must be liquidable before we
+    /// attempt to liquidate it.
+    #[clap(
+        long,
+        value_name = "CHECKS",
+        env = "LIQUIDATION_CONFIRMATIONS",
+        default_value = "1"
+    )]
+    pub liquidation_confirmations: usize,
+
+    /// Liquidates without waiting for the confirmations when the LTV exceeds the
+    /// LLTV by more than this many bps.
+    #[clap(long, value_name = "BPS", env = "LIQUIDATION_MARGIN_BPS")]
+    pub liquidation_margin_bps: Option<Decimal>,
+
+    /// A position within this % of its LLTV counts in the value at risk of its pool.
+    #[clap(
+        long,
+        value_name = "PERCENT",
+        env = "VALUE_AT_RISK_THRESHOLD_PCT",
+        default_value = "5"
+    )]
+    pub value_at_risk_threshold_pct: Decimal,
+
+    /// Maximum number of positions kept in memory per pair. Above it, the least
+    /// recently updated dust positions get evicted & re-read from the chain on
+    /// their next event.
+    #[clap(
+        long,
+        value_name = "POSITIONS",
+        env = "MAX_POSITIONS_PER_PAIR",
+        default_value = "5000"
+    )]
+    pub max_positions_per_pair: usize,
+
+    /// Maximum USD value of debt a liquidation can repay. Unlimited if not set.
+    #[clap(long, value_name = "USD", env = "MAX_LIQUIDATION_DEBT_USD")]
+    pub max_liquidation_debt_usd: Option<Decimal>,
+
+    /// What to do with the liquidations repaying more than
+    /// `--max-liquidation-debt-usd`: partially liquidate them or only alert.
+    #[clap(
+        long,
+        value_name = "ACTION",
+        env = "OVERSIZED_LIQUIDATION",
+        default_value = "partial"
+    )]
+    pub oversized_liquidation: OversizedLiquidation,
+
+    /// Maximum price impact of the collateral => debt swap of a liquidation,
+    /// against the oracle prices. Larger liquidations get halved until their
+    /// swap fits, the rest of the debt is liquidated at the next checks.
+    #[clap(long, value_name = "BPS", env = "MAX_PRICE_IMPACT_BPS")]
+    pub max_price_impact_bps: Option<Decimal>,
+
+    /// Maximum price impact of the liquidation swaps per class of the
+    /// collateral, e.g `btc-lst=100`, instead of `--max-price-impact-bps`.
+    #[clap(
+        long,
+        value_parser = parse_class_amount,
+        value_name = "CLASS=BPS",
+        env = "MAX_PRICE_IMPACT_BPS_PER_CLASS",
+        value_delimiter = ','
+    )]
+    pub max_price_impact_bps_per_class: Vec<(AssetClass, Decimal)>,
+
+    /// Minimum USD profit of a liquidation per class of its collateral, e.g
+    /// `stable=5`. These liquidations get simulated to read their profit.
+    #[clap(
+        long,
+        value_parser = parse_class_amount,
+        value_name = "CLASS=USD",
+        env = "MIN_PROFIT_USD_PER_CLASS",
+        value_delimiter = ','
+    )]
+    pub min_profit_usd_per_class: Vec<(AssetClass, Decimal)>,
+
+    /// Minimum profit of a liquidation per class of its collateral, in bps of
+    /// the debt it repays, e.g `stable=20`. Combined with
+    /// `--min-profit-usd-per-class`, the highest minimum applies.
+    #[clap(
+        long,
+        value_parser = parse_class_amount,
+        value_name = "CLASS=BPS",
+        env = "MIN_PROFIT_BPS_PER_CLASS",
+        value_delimiter = ','
+    )]
+    pub min_profit_bps_per_class: Vec<(AssetClass, Decimal)>,
+
+    /// Liquidates the positions borrowing these assets from the balance of the
+    /// signer when it covers their debt, keeping the collateral instead of
+    /// swapping it, e.g `USDC,USDT`.
+    #[clap(
+        long,
+        value_name = "TICKERS",
+        env = "INVENTORY_LIQUIDATION_ASSETS",
+        value_delimiter = ','
+    )]
+    pub inventory_liquidation_assets: Vec<Currency>,
+
+    /// USD value approved to a pool when an inventory liquidation lacks its
+    /// allowance, so the approval is sent once in a while instead of every
+    /// time. The inventory liquidations needing more are not sent.
+    #[clap(
+        long,
+        value_name = "USD",
+        env = "MAX_APPROVAL_USD",
+        default_value = "10000"
+    )]
+    pub max_approval_usd: Decimal,
+
+    /// Only monitors the positions of these users, e.g the vaults of the operator.
+    /// All the users if not set.
+    #[clap(
+        long,
+        value_parser = parse_felt,
+        value_name = "USER ADDRESS",
+        env = "PROTECTED_USERS",
+        value_delimiter = ','
+    )]
+    pub protected_users: Vec<Felt>,
+
+    /// What to do with the liquidable positions of the `--protected-users`:
+    /// liquidate them or only alert.
+    #[clap(
+        long,
+        value_name = "ACTION",
+        env = "PROTECTED_USERS_ACTION",
+        default_value = "liquidate"
+    )]
+    pub protected_users_action: ProtectedUsersAction,
+
+    /// Users whose positions get deleveraged before being liquidable, by
+    /// repaying their debt from the balance of the signer. The signer must own
+    /// these positions or be their delegatee.
+    #[clap(
+        long,
+        value_parser = parse_felt,
+        value_name = "USER ADDRESS",
+        env = "PROTECT_USERS",
+        value_delimiter = ','
+    )]
+    pub protect_users: Vec<Felt>,
+
+    /// % of the LLTV at which the `--protect-users` positions get deleveraged.
+    #[clap(
+        long,
+        value_name = "PERCENT",
+        env = "PROTECT_TRIGGER_PCT",
+        default_value = "90"
+    )]
+    pub protect_trigger_pct: Decimal,
+
+    /// % of the LLTV the `--protect-users` positions get deleveraged down to.
+    #[clap(
+        long,
+        value_name = "PERCENT",
+        env = "PROTECT_TARGET_PCT",
+        default_value = "75"
+    )]
+    pub protect_target_pct: Decimal,
+
+    /// Positions with less debt than this USD value are considered dust.
+    #[clap(
+        long,
+        value_name = "USD",
+        env = "DUST_POSITION_USD",
+        default_value = "1"
+    )]
+    pub dust_position_usd: Decimal,
+
+    /// Interval between two full scans of the known positions from the chain
+    /// state, as a safety net against missed events. 0 disables the scans.
+    #[clap(
+        long,
+        value_name = "SECONDS",
+        env = "FULL_SCAN_INTERVAL_SECS",
+        default_value = "3600"
+    )]
+    pub full_scan_interval_secs: u64,
+
+    /// Number of random positions checked every 10 minutes against the
+    /// collateralization check of their pool. 0 disables the checks.
+    #[clap(
+        long,
+        value_name = "POSITIONS",
+        env = "COLLATERALIZATION_CHECK_SAMPLE",
+        default_value = "20"
+    )]
+    pub collateralization_check_sample: usize,
+
+    /// Maximum relative difference between the local & on-chain LTVs of a
+    /// checked position before alerting, in bps.
+    #[clap(
+        long,
+        value_name = "BPS",
+        env = "COLLATERALIZATION_CHECK_TOLERANCE_BPS",
+        default_value = "100"
+    )]
+    pub collateralization_check_tolerance_bps: Decimal,
+
+    /// Minimum accepted USD price of a stable asset before alerting.
+    #[clap(
+        long,
+        value_name = "USD",
+        env = "STABLE_MIN_PRICE",
+        default_value = "0.98"
+    )]
+    pub stable_min_price: Decimal,
+
+    /// Maximum accepted USD price of a stable asset before alerting.
+    #[clap(
+        long,
+        value_name = "USD",
+        env = "STABLE_MAX_PRICE",
+        default_value = "1.02"
+    )]
+    pub stable_max_price: Decimal,
+
+    /// Pauses the liquidations involving a depegged stable asset.
+    #[clap(long, env = "PAUSE_ON_DEPEG")]
+    pub pause_on_depeg: bool,
+
+    /// Seconds a position must stay liquidable before being liquidated.
+    #[clap(
+        long,
+        value_name = "SECONDS",
+        env = "LIQUIDATION_DELAY_SECS",
+        default_value = "0"
+    )]
+    pub liquidation_delay_secs: u64,
+
+    /// Liquidation delay of the positions of a pool, over
+    /// `--liquidation-delay-secs`, e.g `Prime=30`.
+    #[clap(
+        long,
+        value_parser = parse_pool_delay,
+        value_name = "POOL=SECONDS",
+        env = "POOL_LIQUIDATION_DELAYS",
+        value_delimiter = ','
+    )]
+    pub pool_liquidation_delay: Vec<(PoolName, u64)>,
+
+    /// Liquidation delay of the positions of a user, over the one of its pool,
+    /// e.g `0x123=300`.
+    #[clap(
+        long,
+        value_parser = parse_user_delay,
+        value_name = "USER ADDRESS=SECONDS",
+        env = "USER_LIQUIDATION_DELAYS",
+        value_delimiter = ','
+    )]
+    pub user_liquidation_delay: Vec<(Felt, u64)>,
+
+    /// Liquidate helper contract of a pool, over the default one, e.g
+    /// `Prime=0x123` for a pool needing another swap venue or hook logic.
+    #[clap(
+        long,
+        value_parser = parse_pool_liquidate_contract,
+        value_name = "POOL=ADDRESS",
+        env = "POOL_LIQUIDATE_CONTRACTS",
+        value_delimiter = ','
+    )]
+    pub pool_liquidate_contract: Vec<(PoolName, Felt)>,
+
+    /// Pools whose pairs of two assets of the same underlying, e.g two BTC
+    /// wrappers in `Re7xBTC`, get their LTV from the direct rate of the assets
+    /// only - the last one known if it could not be read - instead of falling
+    /// back to the ratio of their USD prices.
+    #[clap(
+        long,
+        value_name = "POOL",
+        env = "NATIVE_LTV_POOLS",
+        value_delimiter = ','
+    )]
+    pub native_ltv_pool: Vec<PoolName>,
+
+    /// Age past which the last direct rate known of a pair of the
+    /// `--native-ltv-pool` pools is not used anymore: its LTV falls back to
+    /// the ratio of the USD prices.
+    #[clap(
+        long,
+        value_name = "SECONDS",
+        env = "NATIVE_RATE_MAX_AGE_SECS",
+        default_value = "3600"
+    )]
+    pub native_rate_max_age_secs: u64,
+
+    /// How the healthy positions are logged at each check: a summary line
+    /// counting them by health factor, the summary & a debug line per position,
+    /// or nothing. The positions at risk are always logged in detail.
+    #[clap(
+        long,
+        value_name = "MODE",
+        env = "HEALTHY_POSITIONS_LOG",
+        default_value = "summary"
+    )]
+    pub healthy_positions_log: HealthyPositionsLog,
+
+    /// Stops all the transactions submission while this file exists. The
+    /// `KILL_SWITCH=1` env variable has the same effect.
+    #[clap(long, value_name = "KILL SWITCH PATH", env = "KILL_SWITCH_FILE")]
+    pub kill_switch_file: Option<PathBuf>,
+
+    /// The gas prices of the liquidations are kept until a gas price of the
+    /// chain moves by more than this, in bps. Their calls are estimated at
+    /// every send.
+    #[clap(
+        long,
+        value_name = "BPS",
+        env = "FEE_REFRESH_THRESHOLD_BPS",
+        default_value = "1000"
+    )]
+    pub fee_refresh_threshold_bps: Decimal,
+
+    /// Budget of a liquidation attempt until its send - the route, the
+    /// simulation, the pre-checks & the fee estimation - in milliseconds. An attempt running
+    /// out of it gets aborted & retried. 0 for no budget.
+    #[clap(
+        long,
+        value_name = "MILLISECONDS",
+        env = "LIQUIDATION_ATTEMPT_BUDGET_MS",
+        default_value = "3000"
+    )]
+    pub liquidation_attempt_budget_ms: u64,
+
+    /// Maximum number of liquidation transactions of the account pending at
+    /// once, so that a cascade of liquidable positions does not flood the
+    /// sequencer - the other liquidations wait for the next checks. 0 for no
+    /// limit.
+    #[clap(
+        long,
+        value_name = "TXS",
+        env = "MAX_INFLIGHT_TXS",
+        default_value = "0"
+    )]
+    pub max_inflight_txs: usize,
+
+    /// Only simulates the liquidations, without sending them, & writes what
+    /// their profit would have been in a calibration report at this path.
+    #[clap(long, value_name = "REPORT PATH", env = "SIMULATE_REPORT_PATH")]
+    pub simulate_report: Option<PathBuf>,
+
+    /// Only detects the liquidable positions - logging & broadcasting them -
+    /// without liquidating them.
+    #[clap(long, env = "WATCH_ONLY")]
+    pub watch_only: bool,
+
+    /// Once the positions are synced, simulates the liquidation of the riskiest
+    /// position of this many pairs & reports the pairs that would fail - a
+    /// missing route, a changed contract interface or a missing permission of
+    /// the account. 0 to skip it.
+    #[clap(
+        long,
+        value_name = "PAIRS",
+        env = "CAPACITY_CHECK_PAIRS",
+        default_value = "10"
+    )]
+    pub capacity_check_pairs: usize,
+
+    /// The positions with a LTV below this % of their LLTV hibernate: they are
+    /// skipped by the checks until a price moves by `--hibernation-wakeup-pct`
+    /// or an event changes them. 0 to check all the positions.
+    #[clap(
+        long,
+        value_name = "PERCENT",
+        env = "HIBERNATION_LTV_PCT",
+        default_value = "30"
+    )]
+    pub hibernation_ltv_pct: Decimal,
+
+    /// Wakes up a hibernating position once the price of its collateral or of
+    /// its debt moved by more than this % since it hibernated.
+    #[clap(
+        long,
+        value_name = "PERCENT",
+        env = "HIBERNATION_WAKEUP_PCT",
+        default_value = "5"
+    )]
+    pub hibernation_wakeup_pct: Decimal,
+
+    /// Receives every liquidable position as a JSON POST, with what it takes to
+    /// liquidate it.
+    #[clap(
+        long,
+        value_parser = parse_url,
+        value_name = "WEBHOOK URL",
+        env = "OPPORTUNITY_WEBHOOK_URL"
+    )]
+    pub opportunity_webhook_url: Option<Url>,
+
+    /// Redis server of the stream every liquidable position is added to.
+    #[clap(
+        long,
+        value_parser = parse_url,
+        value_name = "REDIS URL",
+        env = "OPPORTUNITY_STREAM_URL"
+    )]
+    pub opportunity_stream_url: Option<Url>,
+
+    /// Key of the Redis stream of the liquidable positions.
+    #[clap(
+        long,
+        value_name = "KEY",
+        env = "OPPORTUNITY_STREAM_KEY",
+        default_value = "vesu-liquidator:opportunities"
+    )]
+    pub opportunity_stream_key: String,
+
+    /// Periodically sweeps the collateral received from liquidations into the
+    /// settlement asset.
+    #[clap(long, env = "ENABLE_TREASURY")]
+    pub enable_treasury: bool,
+
+    /// The asset the treasury sweeps the collateral into.
+    #[clap(
+        long,
+        value_name = "TICKER",
+        env = "SETTLEMENT_ASSET",
+        default_value = "USDC"
+    )]
+    pub settlement_asset: Currency,
+
+    /// The asset the treasury sweeps the collaterals of a class into, instead
+    /// of `--settlement-asset`, e.g `btc=WBTC`.
+    #[clap(
+        long,
+        value_parser = parse_class_asset,
+        value_name = "CLASS=TICKER",
+        env = "SETTLEMENT_ASSET_PER_CLASS",
+        value_delimiter = ','
+    )]
+    pub settlement_asset_per_class: Vec<(AssetClass, Currency)>,
+
+    /// Minimum USD value of a balance before the treasury sweeps it.
+    #[clap(
+        long,
+        value_name = "USD",
+        env = "TREASURY_SWEEP_THRESHOLD_USD",
+        default_value = "100"
+    )]
+    pub treasury_sweep_threshold_usd: Decimal,
+
+    /// Interval between two treasury sweeps.
+    #[clap(
+        long,
+        value_name = "SECONDS",
+        env = "TREASURY_SWEEP_INTERVAL_SECS",
+        default_value = "300"
+    )]
+    pub treasury_sweep_interval_secs: u64,
+
+    /// Targets a starknet-devnet-rs instance forked from mainnet (at --rpc-url).
+    /// The chain id is read from the devnet and the liquidator account is
+    /// impersonated, so no key is required.
+    #[clap(long, env = "DEVNET")]
+    pub devnet: bool,
+
+    /// Directory where the bot persists its state (positions snapshot & WAL of
+    /// the processed events), so it can restart without losing events.
+    #[clap(long, value_name = "STATE DIR", env = "STATE_DIR")]
+    pub state_dir: Option<PathBuf>,
+
+    /// Records every indexed event to this file (JSON lines) so it can be replayed later.
+    #[clap(long, value_name = "RECORD PATH", env = "RECORD_EVENTS_PATH")]
+    pub record: Option<PathBuf>,
+
+    /// Records every indexed event to Parquet files in this directory,
+    /// partitioned by day, for offline analytics.
+    #[clap(long, value_name = "PARQUET DIR", env = "RECORD_PARQUET_DIR")]
+    pub record_parquet_dir: Option<PathBuf>,
+
+    /// Appends every transaction the bot signs - its purpose, calls, fee &
+    /// result - and the outcome of the liquidations to this file (JSON lines).
+    #[clap(long, value_name = "AUDIT LOG PATH", env = "AUDIT_LOG_PATH")]
+    pub audit_log: Option<PathBuf>,
+
+    /// Replays the events of a recording file instead of running the indexer.
+    #[clap(
+        long,
+        value_name = "REPLAY PATH",
+        env = "REPLAY_EVENTS_PATH",
+        conflicts_with = "record"
+    )]
+    pub replay: Option<PathBuf>,
+
+    /// Runs the indexer & the monitoring in this process, or only one of them
+    /// connected to the other processes through `--event-stream-url`.
+    #[clap(long, value_name = "ROLE", env = "PROCESS_ROLE", default_value = "all")]
+    pub role: ProcessRole,
+
+    /// Does not run the oracle, e.g for a standalone indexer. Needs
+    /// `--disable-monitoring`, the monitoring pricing the positions with it.
+    #[clap(long, env = "DISABLE_ORACLE")]
+    pub disable_oracle: bool,
+
+    /// Does not run the monitoring, e.g for a standalone indexer or price
+    /// service. No `--account-address` nor key is needed then.
+    #[clap(long, env = "DISABLE_MONITORING")]
+    pub disable_monitoring: bool,
+
+    /// Does not run the indexer, the monitoring being fed by the
+    /// `--event-stream-url` stream or by `--replay`.
+    #[clap(long, env = "DISABLE_INDEXER")]
+    pub disable_indexer: bool,
+
+    /// Redis server of the stream carrying the indexed events from the indexer
+    /// process to the monitoring ones.
+    #[clap(
+        long,
+        value_parser = parse_url,
+        value_name = "REDIS URL",
+        env = "EVENT_STREAM_URL"
+    )]
+    pub event_stream_url: Option<Url>,
+
+    /// Key of the Redis stream of the indexed events.
+    #[clap(
+        long,
+        value_name = "KEY",
+        env = "EVENT_STREAM_KEY",
+        default_value = "vesu-liquidator:events"
+    )]
+    pub event_stream_key: String,
+
+    /// Approximate number of entries kept in the event & opportunity streams,
+    /// the oldest ones being trimmed.
+    #[clap(
+        long,
+        value_name = "ENTRIES",
+        env = "EVENT_STREAM_MAX_LEN",
+        default_value = "1000000"
+    )]
+    pub event_stream_max_len: usize,
+
+    /// Significant digits of the asset amounts in the logs & reports.
+    #[clap(
+        long,
+        value_name = "DIGITS",
+        env = "DISPLAY_SIGNIFICANT_DIGITS",
+        default_value = "6"
+    )]
+    pub display_significant_digits: u32,
+
+    /// Decimals of the USD values in the logs & reports.
+    #[clap(
+        long,
+        value_name = "DECIMALS",
+        env = "DISPLAY_USD_DECIMALS",
+        default_value = "2"
+    )]
+    pub display_usd_decimals: u32,
+
+    /// Serves the version (`/version`) & metrics (`/metrics`) of the bot on this
+    /// address, e.g `0.0.0.0:8080`.
+    #[clap(long, value_name = "ADDRESS", env = "API_ADDRESS")]
+    pub api_address: Option<SocketAddr>,
 }
 
 impl RunCmd {
     pub fn validate(&mut self) -> Result<()> {
+        if self.fee_token == FeeToken::Eth {
+            return Err(anyhow!(
+                "V3 transactions can only pay their fees in STRK. Use --fee-token strk."
+            ));
+        }
+        if self.protect_target_pct >= self.protect_trigger_pct
+            || self.protect_trigger_pct > Decimal::ONE_HUNDRED
+        {
+            return Err(anyhow!(
+                "--protect-target-pct must be below --protect-trigger-pct, itself at most 100."
+            ));
+        }
+        if self.role != ProcessRole::All && self.event_stream_url.is_none() {
+            return Err(anyhow!(
+                "--role indexer & --role monitor need an --event-stream-url to reach the other processes."
+            ));
+        }
+        if self.role == ProcessRole::Monitor && self.replay.is_some() {
+            return Err(anyhow!(
+                "--replay replaces the indexer in this process, it cannot be used with --role monitor."
+            ));
+        }
+        // The roles are shorthands for the disabled services.
+        match self.role {
+            ProcessRole::All => {}
+            ProcessRole::Indexer if self.disable_indexer => {
+                return Err(anyhow!("--role indexer cannot run with --disable-indexer."));
+            }
+            ProcessRole::Indexer => {
+                self.disable_monitoring = true;
+                self.disable_oracle = true;
+            }
+            ProcessRole::Monitor if self.disable_monitoring => {
+                return Err(anyhow!(
+                    "--role monitor cannot run with --disable-monitoring."
+                ));
+            }
+            ProcessRole::Monitor => self.disable_indexer = true,
+        }
+        if self.disable_oracle && self.disable_monitoring && self.disable_indexer {
+            return Err(anyhow!(
+                "--disable-oracle, --disable-monitoring & --disable-indexer leave nothing to run."
+            ));
+        }
+        if self.disable_oracle && !self.disable_monitoring {
+            return Err(anyhow!(
+                "--disable-oracle needs --disable-monitoring: the monitoring prices the positions with the oracle."
+            ));
+        }
+        if self.disable_indexer
+            && !self.disable_monitoring
+            && self.event_stream_url.is_none()
+            && self.replay.is_none()
+        {
+            return Err(anyhow!(
+                "--disable-indexer needs an --event-stream-url or a --replay file to feed the monitoring."
+            ));
+        }
+        if self.disable_monitoring && !self.disable_indexer && self.event_stream_url.is_none() {
+            return Err(anyhow!(
+                "--disable-monitoring needs an --event-stream-url to publish the indexed events to, or --disable-indexer."
+            ));
+        }
+        if self.disable_monitoring && self.replay.is_some() {
+            return Err(anyhow!(
+                "--replay feeds the monitoring, it cannot be used with --disable-monitoring."
+            ));
+        }
+        if self.command.is_none()
+            && !self.disable_indexer
+            && self.replay.is_none()
+            && self.apibara_api_key.is_none()
+        {
+            return Err(anyhow!(
+                "The indexer needs an --apibara-api-key, or --disable-indexer."
+            ));
+        }
+        if matches!(
+            self.command,
+            Some(Command::Positions(_) | Command::State(_) | Command::Events(_))
+        ) || self.disable_monitoring
+        {
+            // Read-only: the liquidator account is not used.
+            return Ok(());
+        }
+        if self.devnet && self.account_params.private_key.is_none() {
+            // The account gets impersonated on the devnet - no key needed.
+            return Ok(());
+        }
         self.account_params.validate()?;
         Ok(())
     }
+
+    /// Names of the services this process does not run.
+    pub fn disabled_services(&self) -> Vec<&'static str> {
+        [
+            (self.disable_oracle, "oracle"),
+            (self.disable_monitoring, "monitoring"),
+            (self.disable_indexer, "indexer"),
+        ]
+        .into_iter()
+        .filter_map(|(disabled, name)| disabled.then_some(name))
+        .collect()
+    }
+
+    /// Returns the event stream between the processes, if any.
+    pub fn event_stream(&self) -> Option<EventStreamConfig> {
+        self.event_stream_url.clone().map(|url| EventStreamConfig {
+            url,
+            key: self.event_stream_key.clone(),
+            max_len: self.event_stream_max_len,
+        })
+    }
+
+    /// Returns where the liquidable positions get published.
+    pub fn opportunity_broadcast(&self) -> OpportunityBroadcastConfig {
+        OpportunityBroadcastConfig {
+            webhook_url: self.opportunity_webhook_url.clone(),
+            stream: self
+                .opportunity_stream_url
+                .clone()
+                .map(|url| EventStreamConfig {
+                    url,
+                    key: self.opportunity_stream_key.clone(),
+                    max_len: self.event_stream_max_len,
+                }),
+        }
+    }
+
+    /// The configured price source of every asset not priced by the Vesu oracle,
+    /// the CLI ones overriding the ones of `--price-sources-config`.
+    pub fn price_source_configs(&self) -> Result<HashMap<String, PriceSourceConfig>> {
+        let mut configs = match &self.price_sources_config {
+            Some(path) => PriceSourcesFile::load(path)?.sources,
+            None => HashMap::new(),
+        };
+        configs.extend(self.price_source.iter().cloned());
+        Ok(configs)
+    }
+
+    /// Returns the Apibara DNA streams to index from: the default one, then the
+    /// fallbacks.
+    pub fn apibara_endpoints(&self) -> Result<Vec<ApibaraEndpoint>> {
+        let default_key = self
+            .apibara_api_key
+            .clone()
+            .context("Missing Apibara API key. Use --apibara-api-key.")?;
+        let default = ApibaraEndpoint {
+            url: None,
+            api_key: default_key.clone(),
+        };
+        let fallbacks =
+            self.apibara_fallback_endpoint
+                .iter()
+                .map(|(api_key, url)| ApibaraEndpoint {
+                    url: Some(url.clone()),
+                    api_key: api_key.clone().unwrap_or_else(|| default_key.clone()),
+                });
+        Ok(std::iter::once(default).chain(fallbacks).collect())
+    }
+
+    pub fn indexer_config(&self) -> IndexerConfig {
+        IndexerConfig {
+            max_lag_blocks: self.max_indexer_lag_blocks,
+            pair_discovery_interval: (self.pair_discovery_interval_secs > 0)
+                .then(|| Duration::from_secs(self.pair_discovery_interval_secs)),
+            backfill_chunks: self.backfill_chunks,
+        }
+    }
+
+    /// Returns the cap of the liquidations by the depth of their swap route, if
+    /// any maximum price impact is set.
+    pub fn depth_cap(&self) -> Option<DepthCap> {
+        if self.max_price_impact_bps.is_none() && self.max_price_impact_bps_per_class.is_empty() {
+            return None;
+        }
+        Some(DepthCap {
+            max_price_impact_bps: self.max_price_impact_bps,
+            per_class: self
+                .max_price_impact_bps_per_class
+                .iter()
+                .copied()
+                .collect(),
+        })
+    }
+
+    /// Returns the addresses of the contracts: the embedded ones, overridden by
+    /// `--addresses-file` & then by `--vesu-oracle-address`.
+    pub fn address_book(&self) -> Result<AddressBook> {
+        AddressBook::load(
+            &NETWORK,
+            self.addresses_file.as_deref(),
+            NetworkAddresses {
+                vesu_oracle: self.vesu_oracle_address,
+                ..Default::default()
+            },
+        )
+    }
+
+    /// Returns when to notify about the conditions of every kind.
+    pub fn notifier_config(&self) -> NotifierConfig {
+        NotifierConfig {
+            webhook_url: self.notify_webhook_url.clone(),
+            delays: self
+                .notify_delay
+                .iter()
+                .map(|(kind, secs)| (*kind, Duration::from_secs(*secs)))
+                .collect(),
+            reminders: self
+                .notify_reminder
+                .iter()
+                .map(|(kind, minutes)| (*kind, Duration::from_secs(minutes * 60)))
+                .collect(),
+        }
+    }
+
+    /// Returns the price source of every asset.
+    pub fn price_sources(&self) -> Result<PriceSources> {
+        PriceSources::new(
+            self.price_source_configs()?,
+            self.pragma_api_key.clone().map(|api_key| PragmaApiConfig {
+                url: self.pragma_api_url.clone(),
+                api_key,
+            }),
+        )
+    }
+
+    /// Returns the RPC urls used by the provider, by order of priority.
+    pub fn rpc_urls(&self) -> Vec<Url> {
+        // On a devnet, only the devnet itself must be used - falling back to mainnet
+        // RPCs would read a different state.
+        if self.devnet {
+            return vec![self.rpc_url.clone()];
+        }
+
+        vec![
+            self.rpc_url.clone(),
+            "https://api.cartridge.gg/x/starknet/mainnet"
+                .parse()
+                .expect("Coudlnt parse Cartridge RPC URL?"),
+            "https://rpc.pathfinder.equilibrium.co/mainnet/rpc/v0_9"
+                .parse()
+                .expect("Coudlnt parse Equilibrium RPC URL?"),
+            "https://rpc.starknet.lava.build/rpc/v0_9"
+                .parse()
+                .expect("Could not parse Lava RPC URL?"),
+        ]
+    }
 }
@@ -0,0 +1,118 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use colored::Colorize;
+use evian::vesu::v2::data::VesuDataClient;
+use futures_util::future::join_all;
+use pragma_common::starknet::FallbackProvider;
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use starknet::core::types::{BlockId, BlockTag, Felt};
+
+use crate::cli::watch::watch_positions;
+use crate::cli::{PositionsCommand, RunCmd};
+use crate::config::addresses::NETWORK;
+use crate::services::indexer::IndexerService;
+use crate::services::oracle::OracleService;
+use crate::types::pool::PoolName;
+use crate::types::position::VesuPosition;
+use crate::utils::format::format_usd;
+
+pub async fn run_positions(run_cmd: &RunCmd, command: &PositionsCommand) -> Result<()> {
+    match command {
+        PositionsCommand::Show { user } => show_user_positions(run_cmd, *user).await,
+        PositionsCommand::Watch {
+            api_url,
+            refresh_secs,
+        } => watch_positions(api_url, Duration::from_secs(*refresh_secs)).await,
+    }
+}
+
+/// Prints the positions of a user in all the monitored pools, read from the
+/// chain state.
+async fn show_user_positions(run_cmd: &RunCmd, user: Felt) -> Result<()> {
+    let provider = FallbackProvider::new(run_cmd.rpc_urls())?;
+    let vesu_client = Arc::new(VesuDataClient::new(NETWORK, provider.clone()));
+
+    OracleService::new(provider.clone())
+        .with_sources(Arc::new(run_cmd.price_sources()?))
+        .update_prices()
+        .await;
+
+    let fetch_tasks = IndexerService::monitored_pools()
+        .into_iter()
+        .map(|pool_details| {
+            let vesu_client = vesu_client.clone();
+            let provider = provider.clone();
+            async move {
+                let pool_name = PoolName::try_from(&pool_details.pool_address.0)?;
+                VesuPosition::from_onchain(
+                    &vesu_client,
+                    &provider,
+                    pool_name,
+                    pool_details.collateral_address.0,
+                    pool_details.debt_address.0,
+                    user,
+                    BlockId::Tag(BlockTag::Latest),
+                )
+                .await
+            }
+        });
+
+    let mut positions = Vec::new();
+    for result in join_all(fetch_tasks).await {
+        match result {
+            Ok(Some(position)) => positions.push(position),
+            Ok(None) => {}
+            Err(e) => tracing::warn!("Could not read a position of {user:#x}: {e}"),
+        }
+    }
+
+    if positions.is_empty() {
+        println!("No positions found for user {user:#x}");
+        return Ok(());
+    }
+
+    positions.sort_by_key(|p| (p.pool_name, p.collateral.currency, p.debt.currency));
+
+    println!("\nPositions of user {user:#x}\n");
+    for position in &positions {
+        print_position(position);
+    }
+
+    Ok(())
+}
+
+fn print_position(position: &VesuPosition) {
+    println!(
+        "{} {}/{}",
+        position.pool_name.to_string().bold(),
+        position.collateral.currency,
+        position.debt.currency
+    );
+    println!("  {position}");
+
+    if position.collateral_value_in_usd().is_zero() {
+        println!("  {}", "No price available for the collateral".yellow());
+        return;
+    }
+
+    let health_factor = position.health_factor();
+    let health = if health_factor <= Decimal::ONE {
+        "liquidable".red()
+    } else {
+        "healthy".green()
+    };
+    println!(
+        "  Health factor {health_factor:.3} - LTV {:.2}% / LLTV {:.2}% ({health})",
+        position.ltv() * dec!(100),
+        position.lltv * dec!(100)
+    );
+    println!(
+        "  Collateral {} - Debt {} - Liquidation price {}",
+        format_usd(position.collateral_value_in_usd()),
+        format_usd(position.debt_value_in_usd()),
+        format_usd(position.liquidation_price())
+    );
+}
@@ -0,0 +1,121 @@
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use colored::Colorize;
+use evian::vesu::v2::data::VesuDataClient;
+use pragma_common::starknet::FallbackProvider;
+use rust_decimal::Decimal;
+use starknet::core::types::{BlockId, BlockTag, Felt};
+
+use crate::cli::RunCmd;
+use crate::config::addresses::{AddressBook, NETWORK};
+use crate::services::monitoring::calibration::{simulated_amounts, simulation_outcome};
+use crate::services::oracle::OracleService;
+use crate::types::account::StarknetAccount;
+use crate::types::currency::Currency;
+use crate::types::liquidate_contract::LiquidateContracts;
+use crate::types::pool::PoolName;
+use crate::types::position::VesuPosition;
+use crate::utils::format::format_usd;
+
+/// Reads the live position, builds its liquidation with the swap route &
+/// simulates it with the liquidator account. Nothing is sent.
+pub async fn run_simulate_liquidation(
+    run_cmd: &RunCmd,
+    pool: PoolName,
+    user: Felt,
+    (collateral, debt): (Currency, Currency),
+    debt_to_repay: Option<Decimal>,
+) -> Result<()> {
+    let provider = FallbackProvider::new(run_cmd.rpc_urls())?;
+    let vesu_client = Arc::new(VesuDataClient::new(NETWORK, provider.clone()));
+
+    OracleService::new(provider.clone())
+        .with_sources(Arc::new(run_cmd.price_sources()?))
+        .update_prices()
+        .await;
+
+    let position = VesuPosition::from_onchain(
+        &vesu_client,
+        &provider,
+        pool,
+        collateral.address(),
+        debt.address(),
+        user,
+        BlockId::Tag(BlockTag::Latest),
+    )
+    .await?
+    .with_context(|| format!("No {collateral}/{debt} position for {user:#x} in {pool}"))?;
+
+    let account = StarknetAccount::from_cli(provider.clone(), run_cmd.clone()).await?;
+    let liquidate_contracts = LiquidateContracts::detect(
+        &provider,
+        &account,
+        AddressBook::get().liquidate_contract,
+        &run_cmd.pool_liquidate_contract,
+    )
+    .await?;
+    let liquidate_contract = liquidate_contracts.for_pool(pool);
+    let recipient = run_cmd
+        .recipient
+        .unwrap_or_else(|| account.account_address());
+
+    println!("\n🧪 Simulated liquidation\n");
+    println!("{} {collateral}/{debt}", pool.to_string().bold());
+    println!("  {position}");
+    let health_factor = position.health_factor();
+    let health = if position.is_liquidable() {
+        "liquidable".red()
+    } else {
+        "not liquidable".green()
+    };
+    println!(
+        "  Health factor {health_factor:.3} - debt {} ({health})",
+        format_usd(position.debt_value_in_usd())
+    );
+
+    let (liquidation_tx, quote) = position
+        .get_vesu_liquidate_tx(liquidate_contract, &recipient, debt_to_repay)
+        .await
+        .context("Could not build the liquidation")?;
+    println!(
+        "  Ekubo route: {} - quoted {} for {}",
+        quote.route_summary(),
+        collateral.format_amount(quote.collateral_in),
+        debt.format_amount(quote.debt_out)
+    );
+    let simulation = account.simulate_txs(&[liquidation_tx]).await?;
+
+    let (fee_usd, profit_usd, revert_reason) =
+        simulation_outcome(&simulation, liquidate_contract.address());
+    if let Some(revert_reason) = revert_reason {
+        println!("  {} {revert_reason}", "Reverted:".red());
+        return Ok(());
+    }
+
+    if let Some(amounts) = simulated_amounts(&simulation, &position, liquidate_contract.address()) {
+        println!("  Debt repaid: {}", debt.format_amount(amounts.debt_repaid));
+        println!(
+            "  Collateral seized: {}",
+            collateral.format_amount(amounts.collateral_seized)
+        );
+        println!(
+            "  Collateral received: {} ({})",
+            amounts.residual_currency.format_amount(amounts.residual),
+            format_usd(amounts.residual * amounts.residual_currency.price())
+        );
+    }
+    println!(
+        "  Fee: {}",
+        fee_usd.map_or_else(|| "unknown".into(), format_usd)
+    );
+    match profit_usd {
+        Some(profit_usd) if profit_usd > Decimal::ZERO => {
+            println!("  Profit: {}", format_usd(profit_usd).green());
+        }
+        Some(profit_usd) => println!("  Profit: {}", format_usd(profit_usd).red()),
+        None => println!("  Profit: unknown"),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,289 @@
+use anyhow::Result;
+use pragma_common::starknet::FallbackProvider;
+use starknet::core::utils::parse_cairo_short_string;
+use starknet::providers::Provider;
+
+use crate::cli::RunCmd;
+use crate::services::indexer::{IndexerService, PoolStartingBlocks};
+use crate::types::account::StarknetAccount;
+use crate::utils::build_info::BUILD_INFO;
+use crate::utils::format::format_usd;
+
+/// Rough number of blocks the indexer backfills per second, to estimate the
+/// sync time.
+const ESTIMATED_BACKFILL_BLOCKS_PER_SEC: u64 = 200;
+
+/// Logs the resolved configuration so a misconfiguration is obvious right away.
+pub async fn log_resolved_config(
+    run_cmd: &RunCmd,
+    provider: &FallbackProvider,
+    account: &StarknetAccount,
+    starting_blocks: &PoolStartingBlocks,
+) -> Result<()> {
+    let starting_block = starting_blocks.first_block();
+    let network = network_name(provider).await?;
+    let head_block = provider.block_number().await?;
+
+    let blocks_to_sync = head_block.saturating_sub(starting_block);
+    let estimated_sync_secs = blocks_to_sync / ESTIMATED_BACKFILL_BLOCKS_PER_SEC;
+
+    tracing::info!("🏷️ Version: {BUILD_INFO}");
+    tracing::info!(
+        "🌐 Network: {network}{}",
+        if run_cmd.devnet { " (devnet)" } else { "" }
+    );
+    tracing::info!("👤 Account: {:#x}", account.account_address());
+    if let Some(recipient) = run_cmd.recipient {
+        tracing::info!("🏦 Seized collateral recipient: {recipient:#x}");
+    }
+    tracing::info!(
+        "🏊 Monitored pairs: {}",
+        IndexerService::monitored_pools().len()
+    );
+    tracing::info!(
+        "🧱 Starting block: #{starting_block} - head block: #{head_block} ({blocks_to_sync} blocks to sync, ~{}m)",
+        estimated_sync_secs.div_ceil(60)
+    );
+    tracing::info!(
+        "⚙️ Max liquidations per tx: {} - confirmations: {} - margin: {}",
+        run_cmd.max_liquidations_per_tx,
+        run_cmd.liquidation_confirmations,
+        run_cmd
+            .liquidation_margin_bps
+            .map_or_else(|| "none".into(), |bps| format!("{bps}bps"))
+    );
+    tracing::info!(
+        "⚙️ Stables peg: [{}, {}]{} - value at risk threshold: {}%",
+        run_cmd.stable_min_price,
+        run_cmd.stable_max_price,
+        if run_cmd.pause_on_depeg {
+            " (pausing on depeg)"
+        } else {
+            ""
+        },
+        run_cmd.value_at_risk_threshold_pct
+    );
+    if let Some(webhook_url) = &run_cmd.notify_webhook_url {
+        tracing::info!(
+            "📣 Posting the notifications to {}",
+            webhook_url.host_str().unwrap_or("the webhook")
+        );
+    }
+    if let Some(ws_rpc_url) = &run_cmd.ws_rpc_url {
+        tracing::info!("⛓️ Following the new blocks over {ws_rpc_url}");
+    }
+    if !run_cmd.apibara_fallback_endpoint.is_empty() {
+        // The URLs only, they may hold their API key.
+        let hosts: Vec<&str> = run_cmd
+            .apibara_fallback_endpoint
+            .iter()
+            .map(|(_, url)| url.host_str().unwrap_or("?"))
+            .collect();
+        tracing::info!(
+            "🔀 Failing over to the Apibara streams of {} when indexing fails",
+            hosts.join(", ")
+        );
+    }
+    if run_cmd.collateralization_check_sample > 0 {
+        tracing::info!(
+            "🧮 Checking {} random positions against their pool every 10m - tolerance: {}bps",
+            run_cmd.collateralization_check_sample,
+            run_cmd.collateralization_check_tolerance_bps
+        );
+    }
+    if run_cmd.backfill_chunks > 1 {
+        tracing::info!(
+            "⏩ Backfilling the history with up to {} streams in parallel",
+            run_cmd.backfill_chunks
+        );
+    }
+    if run_cmd.pair_discovery_interval_secs > 0 {
+        tracing::info!(
+            "🆕 Onboarding the new pairs of the monitored pools every {}m",
+            run_cmd.pair_discovery_interval_secs / 60
+        );
+    }
+    if let Some(path) = &run_cmd.addresses_file {
+        tracing::info!("📒 Contract addresses overridden by {}", path.display());
+    }
+    if let Some(oracle) = run_cmd.vesu_oracle_address {
+        tracing::info!("🧪 Reading the Vesu prices from the oracle {oracle:#x}");
+    }
+    let mut price_sources: Vec<_> = run_cmd.price_source_configs()?.into_iter().collect();
+    price_sources.sort_by(|(a, _), (b, _)| a.cmp(b));
+    for (ticker, source) in price_sources {
+        tracing::info!("⚙️ Pricing {ticker} from {source}");
+    }
+    tracing::info!(
+        "⚙️ RPC timeouts: {}ms for the liquidations, {}ms otherwise - circuit breaker per node after {} failures for {}s",
+        run_cmd.rpc_liquidation_timeout_ms,
+        run_cmd.rpc_background_timeout_ms,
+        run_cmd.rpc_breaker_failures,
+        run_cmd.rpc_breaker_cooldown_secs
+    );
+    for (url, millis) in &run_cmd.rpc_node_timeout_ms {
+        tracing::info!("⚙️ RPC timeout of {url}: {millis}ms");
+    }
+    if run_cmd.watchdog_stall_minutes > 0 {
+        tracing::info!(
+            "🐕 Restarting the oracle & the indexer after {}m without progress",
+            run_cmd.watchdog_stall_minutes
+        );
+    }
+    if !run_cmd.protected_users.is_empty() {
+        tracing::info!(
+            "🛡️ Only monitoring the positions of {} users ({:?} when liquidable)",
+            run_cmd.protected_users.len(),
+            run_cmd.protected_users_action
+        );
+    }
+    if !run_cmd.protect_users.is_empty() {
+        tracing::info!(
+            "🛡️ Deleveraging the positions of {} users from {}% of their LLTV down to {}%",
+            run_cmd.protect_users.len(),
+            run_cmd.protect_trigger_pct,
+            run_cmd.protect_target_pct
+        );
+    }
+    if run_cmd.liquidation_delay_secs > 0
+        || !run_cmd.pool_liquidation_delay.is_empty()
+        || !run_cmd.user_liquidation_delay.is_empty()
+    {
+        tracing::info!(
+            "⏳ Liquidating the positions after {}s of being liquidable ({} pool & {} user overrides)",
+            run_cmd.liquidation_delay_secs,
+            run_cmd.pool_liquidation_delay.len(),
+            run_cmd.user_liquidation_delay.len()
+        );
+    }
+    for (pool, address) in &run_cmd.pool_liquidate_contract {
+        tracing::info!("🔧 Liquidating the positions of {pool} with contract {address:#x}");
+    }
+    if !run_cmd.native_ltv_pool.is_empty() {
+        let pools: Vec<String> = run_cmd
+            .native_ltv_pool
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        tracing::info!(
+            "₿ LTV of the same underlying pairs of {} in native terms",
+            pools.join(", ")
+        );
+    }
+    if let Some(max_debt_usd) = run_cmd.max_liquidation_debt_usd {
+        tracing::info!(
+            "⚙️ Max debt repaid per liquidation: ${max_debt_usd} ({:?} above)",
+            run_cmd.oversized_liquidation
+        );
+    }
+    if let Some(max_price_impact_bps) = run_cmd.max_price_impact_bps {
+        tracing::info!("🌊 Max price impact of the liquidation swaps: {max_price_impact_bps} bps");
+    }
+    for (class, max_price_impact_bps) in &run_cmd.max_price_impact_bps_per_class {
+        tracing::info!(
+            "🌊 Max price impact of the swaps of the {class} collaterals: {max_price_impact_bps} bps"
+        );
+    }
+    for (class, min_profit_usd) in &run_cmd.min_profit_usd_per_class {
+        tracing::info!(
+            "💸 Min profit of the liquidations of the {class} collaterals: {}",
+            format_usd(*min_profit_usd)
+        );
+    }
+    for (class, min_profit_bps) in &run_cmd.min_profit_bps_per_class {
+        tracing::info!(
+            "💸 Min profit of the liquidations of the {class} collaterals: {min_profit_bps} bps of the debt repaid"
+        );
+    }
+    if run_cmd.enable_treasury {
+        for (class, settlement_asset) in &run_cmd.settlement_asset_per_class {
+            tracing::info!("🏦 Sweeping the {class} collaterals into {settlement_asset}");
+        }
+    }
+    if !run_cmd.inventory_liquidation_assets.is_empty() {
+        let assets: Vec<String> = run_cmd
+            .inventory_liquidation_assets
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        tracing::info!(
+            "📦 Repaying the {} debts from the inventory when it covers them (approving at least {} at once)",
+            assets.join(", "),
+            format_usd(run_cmd.max_approval_usd)
+        );
+    }
+    if run_cmd.liquidation_attempt_budget_ms > 0 {
+        tracing::info!(
+            "⏱️ Budget of a liquidation attempt until its send: {}ms",
+            run_cmd.liquidation_attempt_budget_ms
+        );
+    }
+    if let Some(audit_log) = &run_cmd.audit_log {
+        tracing::info!(
+            "📜 Auditing the signed transactions to {}",
+            audit_log.display()
+        );
+    }
+    if run_cmd.max_inflight_txs > 0 {
+        tracing::info!(
+            "🚦 At most {} liquidation transactions in flight",
+            run_cmd.max_inflight_txs
+        );
+    }
+    tracing::info!(
+        "⛽ Minimum fee balance to send liquidations: {}",
+        run_cmd
+            .fee_token
+            .currency()
+            .format_amount(run_cmd.min_fee_token_balance)
+    );
+    if run_cmd.simulate_report.is_some() {
+        tracing::info!("🧪 Simulate mode: the liquidations will not be sent");
+    }
+    if run_cmd.disable_indexer {
+        tracing::info!(
+            "🔌 Indexer disabled: the monitoring is fed by {}",
+            if run_cmd.replay.is_some() {
+                "the replayed events"
+            } else {
+                "the event stream"
+            }
+        );
+    }
+    if !run_cmd.hibernation_ltv_pct.is_zero() {
+        tracing::info!(
+            "💤 Hibernating the positions below {}% of their LLTV until their prices move by {}%",
+            run_cmd.hibernation_ltv_pct,
+            run_cmd.hibernation_wakeup_pct
+        );
+    }
+    if run_cmd.watch_only {
+        tracing::info!("👀 Watch-only mode: the liquidable positions will not be liquidated");
+    } else if run_cmd.capacity_check_pairs > 0 {
+        tracing::info!(
+            "🧪 Simulating the liquidation of the riskiest position of {} pairs once synced",
+            run_cmd.capacity_check_pairs
+        );
+    }
+    if let Some(webhook_url) = &run_cmd.opportunity_webhook_url {
+        tracing::info!(
+            "📡 Posting the liquidable positions to {}",
+            webhook_url.host_str().unwrap_or("the webhook")
+        );
+    }
+    if let Some(stream_url) = &run_cmd.opportunity_stream_url {
+        tracing::info!(
+            "📡 Adding the liquidable positions to the Redis stream {} of {}",
+            run_cmd.opportunity_stream_key,
+            stream_url.host_str().unwrap_or("the server")
+        );
+    }
+
+    Ok(())
+}
+
+/// Returns the name of the network from its chain id, e.g `SN_MAIN`.
+pub async fn network_name(provider: &FallbackProvider) -> Result<String> {
+    let chain_id = provider.chain_id().await?;
+    Ok(parse_cairo_short_string(&chain_id).unwrap_or_else(|_| format!("{chain_id:#x}")))
+}
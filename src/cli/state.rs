@@ -0,0 +1,77 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+use crate::cli::{RunCmd, StateCommand};
+use crate::services::monitoring::wal::{StateExport, WriteAheadLog};
+use crate::services::replay::RecordedEvent;
+
+pub fn run_state(run_cmd: &RunCmd, command: &StateCommand) -> Result<()> {
+    let state_dir = run_cmd
+        .state_dir
+        .as_deref()
+        .context("The state commands need a --state-dir")?;
+
+    match command {
+        StateCommand::Export { out } => export_state(state_dir, out),
+        StateCommand::Import { path, force } => import_state(state_dir, path, *force),
+    }
+}
+
+/// Writes the positions, the cursor & the events not checkpointed yet of the
+/// state directory to a single file.
+fn export_state(state_dir: &Path, out: &Path) -> Result<()> {
+    anyhow::ensure!(
+        WriteAheadLog::has_state(state_dir),
+        "No state in {}",
+        state_dir.display()
+    );
+
+    let recovered_state = WriteAheadLog::read(state_dir)?;
+    let export = StateExport {
+        snapshot: recovered_state.snapshot,
+        pending_events: recovered_state
+            .events
+            .into_iter()
+            .map(|(metadata, delta)| RecordedEvent { metadata, delta })
+            .collect(),
+    };
+    fs::write(out, serde_json::to_vec(&export)?)
+        .with_context(|| format!("Could not write {}", out.display()))?;
+
+    println!(
+        "📦 Exported {} positions at block {} (+{} events) to {}",
+        export.snapshot.positions.len(),
+        export.snapshot.cursor.block_number,
+        export.pending_events.len(),
+        out.display()
+    );
+    Ok(())
+}
+
+/// Replaces the state of the state directory with an exported one. The bot must
+/// not be running on it.
+fn import_state(state_dir: &Path, path: &Path, force: bool) -> Result<()> {
+    let export: StateExport = serde_json::from_slice(
+        &fs::read(path).with_context(|| format!("Could not read {}", path.display()))?,
+    )
+    .with_context(|| format!("Invalid state export {}", path.display()))?;
+
+    if WriteAheadLog::has_state(state_dir) && !force {
+        anyhow::bail!(
+            "{} already holds a state, use --force to replace it",
+            state_dir.display()
+        );
+    }
+    WriteAheadLog::import(state_dir, &export)?;
+
+    println!(
+        "📦 Imported {} positions at block {} (+{} events) into {}",
+        export.snapshot.positions.len(),
+        export.snapshot.cursor.block_number,
+        export.pending_events.len(),
+        state_dir.display()
+    );
+    Ok(())
+}
@@ -0,0 +1,85 @@
+use std::time::Duration;
+
+use anyhow::{Result, bail};
+use num_traits::Pow;
+use pragma_common::starknet::FallbackProvider;
+use rust_decimal::Decimal;
+use starknet::core::types::{Call, Felt};
+use starknet::macros::selector;
+use starknet::providers::Provider;
+
+use crate::cli::RunCmd;
+use crate::services::audit::TxPurpose;
+use crate::types::account::{StarknetAccount, StarknetAccountBuilder};
+
+/// Decimals of the prices of the Vesu oracle.
+const ORACLE_DECIMALS: u64 = 18;
+
+/// Pushes the prices to the mock oracle, once or every `interval_secs`,
+/// multiplying them by `drift` between two pushes.
+pub async fn run_test_feeder(
+    run_cmd: &RunCmd,
+    oracle: Felt,
+    prices: &[(Felt, Decimal)],
+    interval_secs: Option<u64>,
+    drift: Decimal,
+) -> Result<()> {
+    let provider = FallbackProvider::new(vec![run_cmd.rpc_url.clone()])?;
+    let account = feeder_account(run_cmd, provider).await?;
+
+    let mut prices = prices.to_vec();
+    loop {
+        let calls = prices
+            .iter()
+            .map(|(asset, price)| set_price_call(oracle, *asset, *price))
+            .collect::<Result<Vec<_>>>()?;
+        let tx_hash = account.execute_txs(&calls, TxPurpose::TestFeed).await?;
+
+        for (asset, price) in &prices {
+            println!("🧪 {asset:#x} => ${price}");
+        }
+        println!("🧪 Pushed the prices to the mock oracle {oracle:#x} (tx {tx_hash:#064x})");
+
+        let Some(interval_secs) = interval_secs else {
+            return Ok(());
+        };
+        tokio::time::sleep(Duration::from_secs(interval_secs)).await;
+        for (_, price) in &mut prices {
+            *price *= drift;
+        }
+    }
+}
+
+/// The account signing the pushes, on the chain of the RPC - unlike the
+/// liquidator account, which is on mainnet unless `--devnet`.
+async fn feeder_account(run_cmd: &RunCmd, provider: FallbackProvider) -> Result<StarknetAccount> {
+    let chain_id = provider.chain_id().await?;
+    let params = run_cmd.account_params.clone();
+    let builder = StarknetAccountBuilder::new()
+        .as_account(params.account_address()?)
+        .on_chain(chain_id)
+        .with_provider(provider);
+
+    match (
+        params.private_key,
+        params.keystore_path,
+        params.keystore_password,
+    ) {
+        (Some(private_key), _, _) => builder.from_secret(private_key),
+        (None, Some(path), Some(password)) => builder.from_keystore(path, &password),
+        _ => bail!("The test feeder needs a private key or a keystore to push the prices"),
+    }
+}
+
+/// `set_price(asset, value: u256)` of the mock oracle, the price having the 18
+/// decimals of the Vesu oracle.
+fn set_price_call(oracle: Felt, asset: Felt, price: Decimal) -> Result<Call> {
+    let raw_price: u128 = (price * Decimal::TEN.pow(ORACLE_DECIMALS))
+        .trunc()
+        .try_into()?;
+    Ok(Call {
+        to: oracle,
+        selector: selector!("set_price"),
+        calldata: vec![asset, Felt::from(raw_price), Felt::ZERO],
+    })
+}
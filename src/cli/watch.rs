@@ -0,0 +1,252 @@
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use anyhow::Result;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Cell, Paragraph, Row, Table, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use url::Url;
+
+use crate::services::monitoring::watchlist::{LiquidationStatus, WatchSnapshot};
+use crate::utils::format::{format_amount, format_usd};
+
+/// Maximum number of positions fetched from the bot.
+const POSITIONS_LIMIT: usize = 200;
+const INPUT_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Shows live what a running bot sees, until `q` or `Esc` is pressed.
+pub async fn watch_positions(api_url: &Url, refresh_interval: Duration) -> Result<()> {
+    let watch_url = api_url.join(&format!("watch?limit={POSITIONS_LIMIT}"))?;
+    let client = reqwest::Client::new();
+
+    let mut terminal = ratatui::init();
+    let result = run(&mut terminal, &client, &watch_url, refresh_interval).await;
+    ratatui::restore();
+    result
+}
+
+async fn run(
+    terminal: &mut DefaultTerminal,
+    client: &reqwest::Client,
+    watch_url: &Url,
+    refresh_interval: Duration,
+) -> Result<()> {
+    let mut snapshot: Option<WatchSnapshot> = None;
+    let mut last_error: Option<String> = None;
+    let mut last_refresh: Option<Instant> = None;
+
+    loop {
+        if last_refresh.is_none_or(|at| at.elapsed() >= refresh_interval) {
+            match fetch_snapshot(client, watch_url).await {
+                Ok(latest) => {
+                    snapshot = Some(latest);
+                    last_error = None;
+                }
+                Err(e) => last_error = Some(e.to_string()),
+            }
+            last_refresh = Some(Instant::now());
+        }
+
+        terminal.draw(|frame| draw(frame, snapshot.as_ref(), last_error.as_deref()))?;
+
+        if event::poll(INPUT_POLL_INTERVAL)?
+            && let Event::Key(key) = event::read()?
+            && key.kind == KeyEventKind::Press
+            && matches!(key.code, KeyCode::Char('q') | KeyCode::Esc)
+        {
+            return Ok(());
+        }
+    }
+}
+
+async fn fetch_snapshot(client: &reqwest::Client, watch_url: &Url) -> Result<WatchSnapshot> {
+    Ok(client
+        .get(watch_url.clone())
+        .timeout(Duration::from_secs(5))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?)
+}
+
+fn draw(frame: &mut Frame, snapshot: Option<&WatchSnapshot>, last_error: Option<&str>) {
+    let [header, positions, bottom] = Layout::vertical([
+        Constraint::Length(3),
+        Constraint::Min(8),
+        Constraint::Length(12),
+    ])
+    .areas(frame.area());
+    let [prices, liquidations] =
+        Layout::horizontal([Constraint::Percentage(40), Constraint::Percentage(60)]).areas(bottom);
+
+    draw_header(frame, header, snapshot, last_error);
+
+    let Some(snapshot) = snapshot else {
+        return;
+    };
+    draw_positions(frame, positions, snapshot);
+    draw_prices(frame, prices, snapshot);
+    draw_liquidations(frame, liquidations, snapshot);
+}
+
+fn draw_header(
+    frame: &mut Frame,
+    area: Rect,
+    snapshot: Option<&WatchSnapshot>,
+    last_error: Option<&str>,
+) {
+    let line = match (snapshot, last_error) {
+        (_, Some(e)) => Line::styled(format!("Could not reach the bot: {e}"), Color::Red),
+        (None, None) => Line::raw("Connecting..."),
+        (Some(snapshot), None) => {
+            let lag_color = if snapshot.indexer_lag_blocks > 10 {
+                Color::Yellow
+            } else {
+                Color::Green
+            };
+            Line::styled(
+                format!(
+                    "Indexer lag: {} blocks ({}s) - {} positions watched",
+                    snapshot.indexer_lag_blocks,
+                    snapshot.indexer_lag_seconds,
+                    snapshot.positions.len()
+                ),
+                lag_color,
+            )
+        }
+    };
+
+    frame.render_widget(
+        Paragraph::new(line).block(Block::bordered().title(" Vesu liquidator - q to quit ")),
+        area,
+    );
+}
+
+fn draw_positions(frame: &mut Frame, area: Rect, snapshot: &WatchSnapshot) {
+    let header = Row::new([
+        "Pool",
+        "Pair",
+        "User",
+        "Health",
+        "σ/h away",
+        "LTV / LLTV",
+        "Debt",
+        "Collateral",
+    ])
+    .style(Style::new().add_modifier(Modifier::BOLD));
+
+    let rows = snapshot.positions.iter().map(|p| {
+        Row::new([
+            Cell::from(p.pool.clone()),
+            Cell::from(format!("{}/{}", p.collateral, p.debt)),
+            Cell::from(shorten(&p.user)),
+            Cell::from(format!("{:.3}", p.health_factor)).style(health_color(p.health_factor)),
+            Cell::from(p.sigmas_to_liquidation.map_or_else(
+                || "-".into(),
+                |sigmas| match p.hours_to_liquidation {
+                    Some(hours) => format!("{sigmas:.1} (~{hours:.0}h)"),
+                    None => format!("{sigmas:.1}"),
+                },
+            )),
+            Cell::from(format!(
+                "{:.2}% / {:.2}%",
+                p.ltv * dec!(100),
+                p.lltv * dec!(100)
+            )),
+            Cell::from(format_usd(p.debt_value_usd)),
+            Cell::from(format_usd(p.collateral_value_usd)),
+        ])
+    });
+
+    let widths = [
+        Constraint::Length(14),
+        Constraint::Length(16),
+        Constraint::Length(14),
+        Constraint::Length(8),
+        Constraint::Length(14),
+        Constraint::Length(18),
+        Constraint::Length(14),
+        Constraint::Length(14),
+    ];
+    frame.render_widget(
+        Table::new(rows, widths)
+            .header(header)
+            .block(Block::bordered().title(" Positions by distance to liquidation ")),
+        area,
+    );
+}
+
+fn draw_prices(frame: &mut Frame, area: Rect, snapshot: &WatchSnapshot) {
+    let prices: Vec<String> = snapshot
+        .prices
+        .iter()
+        .map(|(ticker, price)| format!("{ticker} ${}", format_amount(*price)))
+        .collect();
+
+    frame.render_widget(
+        Paragraph::new(prices.join("  "))
+            .wrap(Wrap { trim: true })
+            .block(Block::bordered().title(" Prices ")),
+        area,
+    );
+}
+
+fn draw_liquidations(frame: &mut Frame, area: Rect, snapshot: &WatchSnapshot) {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+
+    let rows = snapshot.liquidations.iter().map(|l| {
+        let status_color = match l.status {
+            LiquidationStatus::Pending => Color::Yellow,
+            LiquidationStatus::Confirmed => Color::Green,
+            LiquidationStatus::Reverted | LiquidationStatus::Expired => Color::Red,
+        };
+        Row::new([
+            Cell::from(format!("{}s ago", now.saturating_sub(l.sent_at))),
+            Cell::from(format!("#{}", l.position_id)),
+            Cell::from(shorten(&l.tx_hash)),
+            Cell::from(l.status.to_string()).style(status_color),
+            Cell::from(
+                l.realized
+                    .map_or_else(String::new, |realized| format_usd(realized.profit_usd())),
+            ),
+        ])
+    });
+
+    let widths = [
+        Constraint::Length(10),
+        Constraint::Length(22),
+        Constraint::Length(14),
+        Constraint::Length(10),
+        Constraint::Length(12),
+    ];
+    frame.render_widget(
+        Table::new(rows, widths).block(Block::bordered().title(" Recent liquidations ")),
+        area,
+    );
+}
+
+fn health_color(health_factor: Decimal) -> Style {
+    if health_factor <= Decimal::ONE {
+        Style::new().fg(Color::Red).add_modifier(Modifier::BOLD)
+    } else if health_factor < dec!(1.1) {
+        Style::new().fg(Color::Yellow)
+    } else {
+        Style::new().fg(Color::Green)
+    }
+}
+
+/// Shortens an hex string to `0x1234…abcd`.
+fn shorten(hex: &str) -> String {
+    if hex.len() <= 12 {
+        return hex.to_string();
+    }
+    format!("{}…{}", &hex[..6], &hex[hex.len() - 4..])
+}
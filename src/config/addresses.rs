@@ -0,0 +1,138 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::sync::OnceLock;
+
+use anyhow::{Context, Result};
+use pragma_common::starknet::StarknetNetwork;
+use serde::Deserialize;
+use starknet::core::types::Felt;
+use strum::IntoEnumIterator;
+
+use crate::types::pool::PoolName;
+
+/// The network of the bot: the only one the Vesu v2 pools are deployed on.
+pub const NETWORK: StarknetNetwork = StarknetNetwork::Mainnet;
+
+static ADDRESS_BOOK: OnceLock<AddressBook> = OnceLock::new();
+
+/// The addresses of a network, as written in addresses.toml & in the override
+/// files: only the overridden ones are set in the latter.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NetworkAddresses {
+    pub liquidate_contract: Option<Felt>,
+    pub vesu_oracle: Option<Felt>,
+    pub pragma_oracle: Option<Felt>,
+    pub ekubo_router: Option<Felt>,
+    #[serde(default)]
+    pub pools: BTreeMap<PoolName, Felt>,
+}
+
+impl NetworkAddresses {
+    /// Returns the addresses, with the ones set in `overrides` replaced.
+    pub fn merge(mut self, overrides: Self) -> Self {
+        self.liquidate_contract = overrides.liquidate_contract.or(self.liquidate_contract);
+        self.vesu_oracle = overrides.vesu_oracle.or(self.vesu_oracle);
+        self.pragma_oracle = overrides.pragma_oracle.or(self.pragma_oracle);
+        self.ekubo_router = overrides.ekubo_router.or(self.ekubo_router);
+        self.pools.extend(overrides.pools);
+        self
+    }
+
+    /// Reads the addresses of the network from a file keyed by network, e.g
+    /// `[mainnet]` - none if the network is missing.
+    fn parse(content: &str, network: &StarknetNetwork) -> Result<Self> {
+        let mut networks: HashMap<String, Self> = toml::from_str(content)?;
+        Ok(networks.remove(&network_key(network)).unwrap_or_default())
+    }
+}
+
+/// Every address the bot depends on, for one network: the embedded defaults of
+/// addresses.toml with the overrides of the operator.
+#[derive(Debug, Clone)]
+pub struct AddressBook {
+    pub liquidate_contract: Felt,
+    pub vesu_oracle: Felt,
+    pub pragma_oracle: Felt,
+    pub ekubo_router: Felt,
+    pools: BTreeMap<PoolName, Felt>,
+}
+
+impl AddressBook {
+    /// Returns the addresses of the network, from the defaults overridden by
+    /// the file at `overrides_path` & then by `overrides`.
+    pub fn load(
+        network: &StarknetNetwork,
+        overrides_path: Option<&Path>,
+        overrides: NetworkAddresses,
+    ) -> Result<Self> {
+        const DEFAULTS: &str = include_str!("../../config/addresses.toml");
+        let mut addresses =
+            NetworkAddresses::parse(DEFAULTS, network).context("Invalid addresses.toml")?;
+        if let Some(path) = overrides_path {
+            let content = std::fs::read_to_string(path)
+                .with_context(|| format!("Could not read {}", path.display()))?;
+            let file_overrides = NetworkAddresses::parse(&content, network)
+                .with_context(|| format!("Invalid address file {}", path.display()))?;
+            addresses = addresses.merge(file_overrides);
+        }
+        Self::resolve(network, addresses.merge(overrides))
+    }
+
+    /// Fails if an address of the network is missing.
+    fn resolve(network: &StarknetNetwork, addresses: NetworkAddresses) -> Result<Self> {
+        let network = network_key(network);
+        let missing = |name: &str| format!("No {name} address for {network}");
+
+        if let Some(pool) = PoolName::iter().find(|pool| !addresses.pools.contains_key(pool)) {
+            anyhow::bail!("No address of the {pool} pool for {network}");
+        }
+        Ok(Self {
+            liquidate_contract: addresses
+                .liquidate_contract
+                .with_context(|| missing("liquidate_contract"))?,
+            vesu_oracle: addresses
+                .vesu_oracle
+                .with_context(|| missing("vesu_oracle"))?,
+            pragma_oracle: addresses
+                .pragma_oracle
+                .with_context(|| missing("pragma_oracle"))?,
+            ekubo_router: addresses
+                .ekubo_router
+                .with_context(|| missing("ekubo_router"))?,
+            pools: addresses.pools,
+        })
+    }
+
+    /// Sets the address book of the whole process. Only the first call has an
+    /// effect.
+    pub fn install(self) {
+        let _ = ADDRESS_BOOK.set(self);
+    }
+
+    /// The installed address book, the defaults of the network if none got
+    /// installed.
+    pub fn get() -> &'static Self {
+        ADDRESS_BOOK.get_or_init(|| {
+            Self::load(&NETWORK, None, NetworkAddresses::default())
+                .expect("Failed to load addresses.toml")
+        })
+    }
+
+    pub fn pool(&self, pool: PoolName) -> Felt {
+        self.pools[&pool]
+    }
+
+    /// Returns the pool at the address, if it is a known one.
+    pub fn pool_name(&self, address: &Felt) -> Option<PoolName> {
+        self.pools
+            .iter()
+            .find(|(_, pool_address)| *pool_address == address)
+            .map(|(pool, _)| *pool)
+    }
+}
+
+/// The key of the network in the address files, e.g `mainnet`.
+fn network_key(network: &StarknetNetwork) -> String {
+    format!("{network:?}").to_lowercase()
+}
@@ -1 +1,2 @@
+pub mod addresses;
 pub mod onchain_assets;
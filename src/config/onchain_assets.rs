@@ -3,6 +3,7 @@ use std::{
     sync::{Arc, LazyLock},
 };
 
+use dashmap::DashMap;
 use serde::{Deserialize, Serialize};
 use starknet::core::types::Felt;
 
@@ -15,6 +16,62 @@ use crate::types::currency::Currency;
 pub static ONCHAIN_ASSETS: LazyLock<Arc<OnchainAssets>> =
     LazyLock::new(|| Arc::new(OnchainAssets::new()));
 
+/// Assets met on chain but missing from assets.toml, with the metadata read from
+/// their contract. Their positions are skipped until they get listed.
+pub static UNLISTED_ASSETS: LazyLock<DashMap<Felt, OnchainAssetConfig>> =
+    LazyLock::new(DashMap::new);
+
+/// The risk class of an asset, so that the policies are configured per class
+/// instead of for every asset.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    Serialize,
+    Deserialize,
+    PartialEq,
+    Eq,
+    Hash,
+    strum::Display,
+    strum::EnumString,
+)]
+#[serde(rename_all = "kebab-case")]
+#[strum(serialize_all = "kebab-case")]
+pub enum AssetClass {
+    /// Pegged to the USD.
+    Stable,
+    /// Yield bearing assets backed by stables.
+    YieldStable,
+    Eth,
+    /// Liquid staking tokens of ETH.
+    EthLst,
+    Strk,
+    /// Liquid staking tokens of STRK.
+    StrkLst,
+    /// BTC & its 1:1 wrappers.
+    Btc,
+    /// Liquid staking & restaking tokens of BTC.
+    BtcLst,
+    /// Not classified, e.g the unlisted assets.
+    #[default]
+    Other,
+}
+
+impl AssetClass {
+    /// The class of the underlying asset, shared by the asset & its liquid
+    /// staking tokens or yield bearing versions. None for the other assets.
+    pub fn underlying(&self) -> Option<Self> {
+        match self {
+            Self::Stable | Self::YieldStable => Some(Self::Stable),
+            Self::Eth | Self::EthLst => Some(Self::Eth),
+            Self::Strk | Self::StrkLst => Some(Self::Strk),
+            Self::Btc | Self::BtcLst => Some(Self::Btc),
+            Self::Other => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub struct OnchainAssetConfig {
     pub name: String,
@@ -22,12 +79,23 @@ pub struct OnchainAssetConfig {
     pub decimals: u32,
     #[serde(deserialize_with = "deserialize_felt_from_str")]
     pub address: Felt,
+    #[serde(default)]
+    pub class: AssetClass,
+}
+
+/// A wrapper (ERC-4626 vault) of an underlying asset.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct WrapperConfig {
+    pub wrapper: String,
+    pub underlying: String,
 }
 
 /// Represents the assets.toml configuration file
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct AssetsConfig {
     pub assets: Vec<OnchainAssetConfig>,
+    #[serde(default)]
+    pub wrappers: Vec<WrapperConfig>,
 }
 
 impl AssetsConfig {
@@ -42,6 +110,8 @@ pub struct OnchainAssets {
     by_ticker: HashMap<String, OnchainAssetConfig>,
     by_address: HashMap<Felt, OnchainAssetConfig>,
     assets: Vec<OnchainAssetConfig>,
+    /// wrapper ticker => underlying ticker
+    underlyings: HashMap<String, String>,
 }
 
 impl OnchainAssets {
@@ -56,10 +126,17 @@ impl OnchainAssets {
             by_address.insert(asset.address, asset.clone());
         }
 
+        let underlyings = assets_config
+            .wrappers
+            .into_iter()
+            .map(|w| (w.wrapper, w.underlying))
+            .collect();
+
         Self {
             by_ticker,
             by_address,
             assets: assets_config.assets,
+            underlyings,
         }
     }
 
@@ -71,6 +148,23 @@ impl OnchainAssets {
         self.by_address.get(address)
     }
 
+    /// Returns the underlying asset of a wrapper, if configured.
+    pub fn underlying_of(&self, wrapper_ticker: &str) -> Option<&OnchainAssetConfig> {
+        self.underlyings
+            .get(wrapper_ticker)
+            .and_then(|underlying| self.get_by_ticker(underlying))
+    }
+
+    /// Returns the configured (wrapper, underlying) assets.
+    pub fn wrappers(&self) -> impl Iterator<Item = (&OnchainAssetConfig, &OnchainAssetConfig)> {
+        self.underlyings.iter().filter_map(|(wrapper, underlying)| {
+            Some((
+                self.get_by_ticker(wrapper)?,
+                self.get_by_ticker(underlying)?,
+            ))
+        })
+    }
+
     pub fn all(&self) -> Vec<OnchainAssetConfig> {
         self.assets.clone()
     }
@@ -0,0 +1,20 @@
+//! Liquidator bot for the Vesu v2 Protocol.
+//!
+//! The `vesu-v2-liquidator` binary wires the services together, but they can be
+//! embedded in another service: the [`IndexerService`] streams the position
+//! events of the Vesu pools, the [`OracleService`] keeps the prices up to date &
+//! the [`MonitoringService`] applies the events to the [`VesuPosition`]s &
+//! liquidates them, as configured by its [`MonitoringConfig`].
+
+pub mod bindings;
+pub mod cli;
+pub mod config;
+pub mod services;
+pub mod types;
+pub mod utils;
+
+pub use services::indexer::{ApibaraEndpoint, IndexerConfig, IndexerService, PoolStartingBlocks};
+pub use services::monitoring::executor::ExecutorConfig;
+pub use services::monitoring::{MonitoringConfig, MonitoringService};
+pub use services::oracle::OracleService;
+pub use types::position::VesuPosition;
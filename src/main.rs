@@ -1,21 +1,62 @@
-pub mod bindings;
-pub mod cli;
-pub mod config;
-pub mod services;
-pub mod types;
-pub mod utils;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
+use anyhow::Context;
 use clap::Parser;
 use pragma_common::services::{Service, ServiceGroup};
 use pragma_common::starknet::FallbackProvider;
 use pragma_common::telemetry::init_telemetry;
 use tokio::sync::{mpsc, oneshot};
 
-use crate::cli::RunCmd;
-use crate::services::indexer::task::IndexerTask;
-use crate::services::monitoring::task::MonitoringTask;
-use crate::services::oracle::task::OracleTask;
-use crate::types::account::StarknetAccount;
+use vesu_v2_liquidator::cli::config_file::args_with_config_file;
+use vesu_v2_liquidator::cli::doctor::run_doctor;
+use vesu_v2_liquidator::cli::events::run_events;
+use vesu_v2_liquidator::cli::keeper::run_register_keeper;
+use vesu_v2_liquidator::cli::positions::run_positions;
+use vesu_v2_liquidator::cli::simulate::run_simulate_liquidation;
+use vesu_v2_liquidator::cli::startup::{log_resolved_config, network_name};
+use vesu_v2_liquidator::cli::state::run_state;
+#[cfg(feature = "test-feeder")]
+use vesu_v2_liquidator::cli::test_feeder::run_test_feeder;
+use vesu_v2_liquidator::cli::{Command, RunCmd};
+use vesu_v2_liquidator::config::addresses::AddressBook;
+use vesu_v2_liquidator::services::api::RuntimeInfo;
+use vesu_v2_liquidator::services::api::task::ApiTask;
+use vesu_v2_liquidator::services::audit::AuditLog;
+use vesu_v2_liquidator::services::chain_head::task::ChainHeadTask;
+use vesu_v2_liquidator::services::indexer::task::IndexerTask;
+use vesu_v2_liquidator::services::indexer::{IndexerService, PoolStartingBlocks};
+use vesu_v2_liquidator::services::monitoring::MonitoringConfig;
+use vesu_v2_liquidator::services::monitoring::collateralization::CollateralizationCheckConfig;
+use vesu_v2_liquidator::services::monitoring::depeg::DepegConfig;
+use vesu_v2_liquidator::services::monitoring::executor::ExecutorConfig;
+use vesu_v2_liquidator::services::monitoring::hibernation::HibernationConfig;
+use vesu_v2_liquidator::services::monitoring::inventory::InventoryConfig;
+use vesu_v2_liquidator::services::monitoring::liquidation_delay::LiquidationDelayConfig;
+use vesu_v2_liquidator::services::monitoring::prechecks::PrecheckConfig;
+use vesu_v2_liquidator::services::monitoring::protect::ProtectConfig;
+use vesu_v2_liquidator::services::monitoring::strategy::{DebtCap, DefaultStrategy};
+use vesu_v2_liquidator::services::monitoring::task::MonitoringTask;
+use vesu_v2_liquidator::services::monitoring::user_scope::UserScope;
+use vesu_v2_liquidator::services::monitoring::wal::WriteAheadLog;
+use vesu_v2_liquidator::services::oracle::price_history::PRICE_HISTORY_FILE;
+use vesu_v2_liquidator::services::oracle::pricing::NativeLtvConfig;
+use vesu_v2_liquidator::services::oracle::task::OracleTask;
+use vesu_v2_liquidator::services::replay::RecordingConfig;
+use vesu_v2_liquidator::services::replay::archive::EVENT_ARCHIVE_FILE;
+use vesu_v2_liquidator::services::replay::task::ReplayTask;
+use vesu_v2_liquidator::services::stream::task::{StreamPublisherTask, StreamSubscriberTask};
+use vesu_v2_liquidator::services::treasury::TreasuryConfig;
+use vesu_v2_liquidator::services::treasury::task::TreasuryTask;
+use vesu_v2_liquidator::services::watchdog::WatchdogConfig;
+use vesu_v2_liquidator::types::account::StarknetAccount;
+use vesu_v2_liquidator::types::keeper::ensure_registered_keeper;
+use vesu_v2_liquidator::types::liquidate_contract::LiquidateContracts;
+use vesu_v2_liquidator::types::pair_config::{PAIR_CONFIGS, PAIR_CONFIGS_FILE};
+use vesu_v2_liquidator::types::pool::PoolName;
+use vesu_v2_liquidator::utils::format::DisplayConfig;
+use vesu_v2_liquidator::utils::kill_switch::KillSwitch;
+use vesu_v2_liquidator::utils::rpc::{RpcConfig, StarknetNode};
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
@@ -23,46 +64,318 @@ async fn main() -> anyhow::Result<()> {
 
     let _ = dotenvy::dotenv();
 
-    let mut run_cmd = RunCmd::parse();
+    let mut run_cmd = RunCmd::parse_from(args_with_config_file()?);
     run_cmd.validate()?;
+    run_cmd.address_book()?.install();
+
+    DisplayConfig {
+        significant_digits: run_cmd.display_significant_digits,
+        usd_decimals: run_cmd.display_usd_decimals,
+    }
+    .install();
+    RpcConfig {
+        liquidation_timeout: Duration::from_millis(run_cmd.rpc_liquidation_timeout_ms),
+        background_timeout: Duration::from_millis(run_cmd.rpc_background_timeout_ms),
+        breaker_failures: run_cmd.rpc_breaker_failures,
+        breaker_cooldown: Duration::from_secs(run_cmd.rpc_breaker_cooldown_secs),
+    }
+    .install();
+    StarknetNode::install(
+        &run_cmd.rpc_urls(),
+        &run_cmd
+            .rpc_node_timeout_ms
+            .iter()
+            .map(|(url, millis)| (url.clone(), Duration::from_millis(*millis)))
+            .collect::<Vec<_>>(),
+    )?;
+    NativeLtvConfig {
+        pools: run_cmd.native_ltv_pool.iter().copied().collect(),
+        max_rate_age: Duration::from_secs(run_cmd.native_rate_max_age_secs),
+    }
+    .install();
+    WatchdogConfig {
+        stall_timeout: (run_cmd.watchdog_stall_minutes > 0)
+            .then(|| Duration::from_secs(run_cmd.watchdog_stall_minutes * 60)),
+    }
+    .install();
+    run_cmd.notifier_config().install();
+    if let Some(audit_log) = &run_cmd.audit_log {
+        AuditLog::open(audit_log)?.install();
+    }
 
     print_app_title();
 
-    let provider = FallbackProvider::new(vec![
-        run_cmd.rpc_url.clone(),
-        "https://api.cartridge.gg/x/starknet/mainnet"
-            .parse()
-            .expect("Coudlnt parse Cartridge RPC URL?"),
-        "https://rpc.pathfinder.equilibrium.co/mainnet/rpc/v0_9"
-            .parse()
-            .expect("Coudlnt parse Equilibrium RPC URL?"),
-        "https://rpc.starknet.lava.build/rpc/v0_9"
-            .parse()
-            .expect("Could not parse Lava RPC URL?"),
-    ])
-    .expect("Could not init the Starknet provider");
+    match &run_cmd.command {
+        Some(Command::Doctor) => return run_doctor(&run_cmd).await,
+        Some(Command::Positions(command)) => return run_positions(&run_cmd, command).await,
+        Some(Command::State(command)) => return run_state(&run_cmd, command),
+        Some(Command::Events(command)) => return run_events(&run_cmd, command),
+        Some(Command::SimulateLiquidation {
+            pool,
+            user,
+            collateral,
+            debt,
+            debt_to_repay,
+        }) => {
+            return run_simulate_liquidation(
+                &run_cmd,
+                *pool,
+                *user,
+                (*collateral, *debt),
+                *debt_to_repay,
+            )
+            .await;
+        }
+        Some(Command::RegisterKeeper { keeper }) => {
+            return run_register_keeper(&run_cmd, *keeper).await;
+        }
+        #[cfg(feature = "test-feeder")]
+        Some(Command::TestFeeder {
+            oracle,
+            prices,
+            interval_secs,
+            drift,
+        }) => return run_test_feeder(&run_cmd, *oracle, prices, *interval_secs, *drift).await,
+        None => {}
+    }
+
+    let provider =
+        FallbackProvider::new(run_cmd.rpc_urls()).expect("Could not init the Starknet provider");
 
-    let account = StarknetAccount::from_cli(provider.clone(), run_cmd.clone())?;
+    if run_cmd.disable_monitoring {
+        return run_without_monitoring(run_cmd, provider).await;
+    }
+
+    let account = StarknetAccount::from_cli(provider.clone(), run_cmd.clone()).await?;
+    account
+        .ensure_fee_token_balance(&provider, run_cmd.fee_token)
+        .await?;
+    let liquidate_contracts = LiquidateContracts::detect(
+        &provider,
+        &account,
+        AddressBook::get().liquidate_contract,
+        &run_cmd.pool_liquidate_contract,
+    )
+    .await?;
+    if !run_cmd.watch_only {
+        ensure_registered_keeper(&provider, &liquidate_contracts, account.account_address())
+            .await?;
+    }
 
-    let oracle_service = OracleTask::new(provider.clone());
+    let oracle_service = oracle_task(&run_cmd, &provider)?;
+
+    let wal = run_cmd
+        .state_dir
+        .as_ref()
+        .map(WriteAheadLog::open)
+        .transpose()?;
+    if let Some(state_dir) = &run_cmd.state_dir
+        && let Err(e) = PAIR_CONFIGS.load(&state_dir.join(PAIR_CONFIGS_FILE))
+    {
+        tracing::warn!("Could not load the cached pair configs, reading them again: {e}");
+    }
+    tokio::spawn(PAIR_CONFIGS.clone().persist_forever());
+
+    PoolName::resolve_deployment_blocks(&provider).await?;
+    // Resume from the last applied event if we have a persisted state.
+    let resume_cursor = wal
+        .as_ref()
+        .and_then(|(_, recovered_state)| recovered_state.cursor());
+    let starting_blocks = PoolStartingBlocks::new(
+        resume_cursor.map_or(run_cmd.starting_block, |cursor| Some(cursor.block_number)),
+    );
+
+    log_resolved_config(&run_cmd, &provider, &account, &starting_blocks).await?;
 
     let (meet_with_monitoring, wait_for_indexer) = oneshot::channel::<()>();
     let (tx_to_monitoring, rx_from_indexer) = mpsc::unbounded_channel();
 
-    let indexer_service = IndexerTask::new(
-        run_cmd.starting_block,
-        run_cmd.apibara_api_key,
+    let mut services = ServiceGroup::default().with(oracle_service);
+
+    if let Some(ws_rpc_url) = run_cmd.ws_rpc_url.clone() {
+        services = services.with(ChainHeadTask::new(ws_rpc_url));
+    }
+
+    if run_cmd.enable_treasury {
+        services = services.with(TreasuryTask::new(
+            account.clone(),
+            provider.clone(),
+            TreasuryConfig {
+                settlement_asset: run_cmd.settlement_asset,
+                settlement_assets_per_class: run_cmd
+                    .settlement_asset_per_class
+                    .iter()
+                    .copied()
+                    .collect(),
+                sweep_threshold_usd: run_cmd.treasury_sweep_threshold_usd,
+                sweep_interval: Duration::from_secs(run_cmd.treasury_sweep_interval_secs),
+                inventory_assets: run_cmd
+                    .inventory_liquidation_assets
+                    .iter()
+                    .copied()
+                    .collect(),
+                fee_token: run_cmd.fee_token.currency(),
+                kill_switch: KillSwitch::new(run_cmd.kill_switch_file.clone()),
+            },
+        ));
+    }
+
+    if let Some(api_address) = run_cmd.api_address {
+        let runtime = RuntimeInfo {
+            network: network_name(&provider).await?,
+            account: format!("{:#x}", account.account_address()),
+            recipient: run_cmd.recipient.map(|recipient| format!("{recipient:#x}")),
+            liquidate_contract: format!("{:#x}", liquidate_contracts.default_contract().address()),
+            liquidate_contract_version: liquidate_contracts
+                .default_contract()
+                .version()
+                .to_string(),
+            pool_liquidate_contracts: liquidate_contracts
+                .overrides()
+                .map(|(pool, contract)| (pool.to_string(), format!("{:#x}", contract.address())))
+                .collect(),
+            monitored_pairs: IndexerService::monitored_pools().len(),
+            oracle_mode: format!("{:?}", run_cmd.oracle_mode),
+            simulate: run_cmd.simulate_report.is_some(),
+            treasury: run_cmd.enable_treasury,
+            started_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+        };
+        services = services.with(ApiTask::new(api_address, runtime));
+    }
+
+    let monitoring_service = MonitoringTask::new(
+        account,
         provider.clone(),
-        tx_to_monitoring,
-        meet_with_monitoring,
+        liquidate_contracts,
+        rx_from_indexer,
+        wait_for_indexer,
+        wal,
+        MonitoringConfig {
+            executor: ExecutorConfig {
+                max_liquidations_per_tx: run_cmd.max_liquidations_per_tx,
+                recipient: run_cmd.recipient,
+                depth_cap: run_cmd.depth_cap(),
+                min_profit_usd: run_cmd.min_profit_usd_per_class.iter().copied().collect(),
+                min_profit_bps: run_cmd.min_profit_bps_per_class.iter().copied().collect(),
+                inventory: InventoryConfig {
+                    assets: run_cmd
+                        .inventory_liquidation_assets
+                        .iter()
+                        .copied()
+                        .collect(),
+                },
+                prechecks: PrecheckConfig {
+                    fee_token: run_cmd.fee_token,
+                    min_fee_token_balance: run_cmd.min_fee_token_balance,
+                    max_approval_usd: run_cmd.max_approval_usd,
+                },
+                kill_switch: KillSwitch::new(run_cmd.kill_switch_file.clone()),
+                simulate_report: run_cmd.simulate_report.clone(),
+                fee_refresh_threshold_bps: run_cmd.fee_refresh_threshold_bps,
+                attempt_budget: (run_cmd.liquidation_attempt_budget_ms > 0)
+                    .then(|| Duration::from_millis(run_cmd.liquidation_attempt_budget_ms)),
+                max_inflight_txs: (run_cmd.max_inflight_txs > 0)
+                    .then_some(run_cmd.max_inflight_txs),
+            },
+            strategy: Arc::new(DefaultStrategy {
+                liquidation_confirmations: run_cmd.liquidation_confirmations,
+                liquidation_margin_bps: run_cmd.liquidation_margin_bps,
+            }),
+            debt_cap: run_cmd
+                .max_liquidation_debt_usd
+                .map(|max_debt_usd| DebtCap {
+                    max_debt_usd,
+                    on_oversized: run_cmd.oversized_liquidation,
+                }),
+            depeg: DepegConfig {
+                min_price: run_cmd.stable_min_price,
+                max_price: run_cmd.stable_max_price,
+                pause_on_depeg: run_cmd.pause_on_depeg,
+            },
+            liquidation_delay: LiquidationDelayConfig {
+                default: Duration::from_secs(run_cmd.liquidation_delay_secs),
+                per_pool: run_cmd
+                    .pool_liquidation_delay
+                    .iter()
+                    .map(|&(pool, secs)| (pool, Duration::from_secs(secs)))
+                    .collect(),
+                per_user: run_cmd
+                    .user_liquidation_delay
+                    .iter()
+                    .map(|&(user, secs)| (user, Duration::from_secs(secs)))
+                    .collect(),
+            },
+            value_at_risk_threshold_pct: run_cmd.value_at_risk_threshold_pct,
+            max_positions_per_pair: run_cmd.max_positions_per_pair,
+            dust_position_usd: run_cmd.dust_position_usd,
+            full_scan_interval: (run_cmd.full_scan_interval_secs > 0)
+                .then(|| Duration::from_secs(run_cmd.full_scan_interval_secs)),
+            user_scope: (!run_cmd.protected_users.is_empty()).then(|| UserScope {
+                users: run_cmd.protected_users.iter().copied().collect(),
+                action: run_cmd.protected_users_action,
+            }),
+            protect: (!run_cmd.protect_users.is_empty()).then(|| ProtectConfig {
+                users: run_cmd.protect_users.iter().copied().collect(),
+                trigger_pct: run_cmd.protect_trigger_pct,
+                target_pct: run_cmd.protect_target_pct,
+            }),
+            healthy_positions_log: run_cmd.healthy_positions_log,
+            collateralization_check: (run_cmd.collateralization_check_sample > 0).then(|| {
+                CollateralizationCheckConfig {
+                    sample_size: run_cmd.collateralization_check_sample,
+                    tolerance_bps: run_cmd.collateralization_check_tolerance_bps,
+                }
+            }),
+            opportunities: run_cmd.opportunity_broadcast(),
+            watch_only: run_cmd.watch_only,
+            capacity_check_pairs: run_cmd.capacity_check_pairs,
+            hibernation: (!run_cmd.hibernation_ltv_pct.is_zero()).then_some(HibernationConfig {
+                ltv_pct: run_cmd.hibernation_ltv_pct,
+                wakeup_move_pct: run_cmd.hibernation_wakeup_pct,
+            }),
+        },
     );
 
-    let monitoring_service =
-        MonitoringTask::new(account, provider.clone(), rx_from_indexer, wait_for_indexer);
+    // When replaying, the recorded events replace the indexer, as the event
+    // stream does for the monitoring processes.
+    let services = if let Some(replay_path) = run_cmd.replay {
+        services.with(ReplayTask::new(
+            replay_path,
+            tx_to_monitoring,
+            meet_with_monitoring,
+        ))
+    } else if let Some(event_stream) = run_cmd.event_stream()
+        && run_cmd.disable_indexer
+    {
+        services.with(StreamSubscriberTask::new(
+            event_stream,
+            tx_to_monitoring,
+            meet_with_monitoring,
+            resume_cursor.map(|cursor| cursor.block_number),
+        ))
+    } else {
+        services.with(IndexerTask::new(
+            starting_blocks,
+            run_cmd.apibara_endpoints()?,
+            provider.clone(),
+            tx_to_monitoring,
+            meet_with_monitoring,
+            RecordingConfig {
+                events_path: run_cmd.record,
+                parquet_dir: run_cmd.record_parquet_dir,
+                archive_path: run_cmd
+                    .state_dir
+                    .as_ref()
+                    .map(|state_dir| state_dir.join(EVENT_ARCHIVE_FILE)),
+            },
+            run_cmd.indexer_config(),
+        ))
+    };
 
-    ServiceGroup::default()
-        .with(oracle_service)
-        .with(indexer_service)
+    services
         .with(monitoring_service)
         .start_and_drive_to_end()
         .await?;
@@ -70,6 +383,118 @@ async fn main() -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Runs the services of a process without monitoring: the oracle & the API
+/// for a price service, the indexer publishing the indexed events to the event
+/// stream of the monitoring processes for an indexer, or both.
+async fn run_without_monitoring(run_cmd: RunCmd, provider: FallbackProvider) -> anyhow::Result<()> {
+    tracing::info!(
+        "🔌 Disabled services: {}",
+        run_cmd.disabled_services().join(", ")
+    );
+
+    let mut services = ServiceGroup::default();
+
+    if !run_cmd.disable_oracle {
+        services = services.with(oracle_task(&run_cmd, &provider)?);
+    }
+
+    if !run_cmd.disable_indexer {
+        let event_stream = run_cmd
+            .event_stream()
+            .context("A standalone indexer needs an --event-stream-url")?;
+        PoolName::resolve_deployment_blocks(&provider).await?;
+        // Resume from the last event published to the stream.
+        let last_published = event_stream.last_published_event().await?;
+        tracing::info!(
+            "📡 Publishing the indexed events to the {} stream of {}{}",
+            event_stream.key,
+            event_stream.url,
+            last_published
+                .as_ref()
+                .map(|metadata| format!(", from block #{}", metadata.block_number))
+                .unwrap_or_default()
+        );
+
+        let (meet_with_monitoring, wait_for_indexer) = oneshot::channel::<()>();
+        let (tx_to_monitoring, rx_from_indexer) = mpsc::unbounded_channel();
+        services = services
+            .with(IndexerTask::new(
+                PoolStartingBlocks::new(
+                    last_published
+                        .as_ref()
+                        .map_or(run_cmd.starting_block, |metadata| {
+                            Some(metadata.block_number)
+                        }),
+                ),
+                run_cmd.apibara_endpoints()?,
+                provider.clone(),
+                tx_to_monitoring,
+                meet_with_monitoring,
+                RecordingConfig {
+                    events_path: run_cmd.record.clone(),
+                    parquet_dir: run_cmd.record_parquet_dir.clone(),
+                    archive_path: run_cmd
+                        .state_dir
+                        .as_ref()
+                        .map(|state_dir| state_dir.join(EVENT_ARCHIVE_FILE)),
+                },
+                run_cmd.indexer_config(),
+            ))
+            .with(StreamPublisherTask::new(
+                event_stream,
+                rx_from_indexer,
+                wait_for_indexer,
+                last_published.and_then(|metadata| metadata.event_id()),
+            ));
+    }
+
+    if let Some(api_address) = run_cmd.api_address {
+        // Nothing gets liquidated: the account & the liquidate contract are
+        // only the configured ones.
+        let runtime = RuntimeInfo {
+            network: network_name(&provider).await?,
+            account: run_cmd
+                .account_params
+                .account_address
+                .map_or_else(|| "none".into(), |address| format!("{address:#x}")),
+            recipient: run_cmd.recipient.map(|recipient| format!("{recipient:#x}")),
+            liquidate_contract: format!("{:#x}", AddressBook::get().liquidate_contract),
+            liquidate_contract_version: "unused".into(),
+            pool_liquidate_contracts: run_cmd
+                .pool_liquidate_contract
+                .iter()
+                .map(|(pool, contract)| (pool.to_string(), format!("{contract:#x}")))
+                .collect(),
+            monitored_pairs: IndexerService::monitored_pools().len(),
+            oracle_mode: format!("{:?}", run_cmd.oracle_mode),
+            simulate: false,
+            treasury: false,
+            started_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+        };
+        services = services.with(ApiTask::new(api_address, runtime));
+    }
+
+    services.start_and_drive_to_end().await?;
+
+    Ok(())
+}
+
+/// The oracle, persisting its price history in the state directory if any.
+fn oracle_task(run_cmd: &RunCmd, provider: &FallbackProvider) -> anyhow::Result<OracleTask> {
+    Ok(OracleTask::new(
+        provider.clone(),
+        run_cmd.oracle_mode,
+        run_cmd
+            .state_dir
+            .as_ref()
+            .map(|state_dir| state_dir.join(PRICE_HISTORY_FILE)),
+        run_cmd.price_sources()?,
+    ))
+}
+
 /// Prints information about the bot parameters.
 fn print_app_title() {
     println!("\n
@@ -0,0 +1,235 @@
+pub mod task;
+
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::routing::get;
+use axum::{Json, Router};
+use futures_util::{Stream, StreamExt, stream};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::services::indexer::delegations::DELEGATIONS;
+use crate::services::indexer::delegations::DELEGATIONS;
+use crate::services::indexer::lag::INDEXER_LAG;
+use crate::services::monitoring::collateralization::COLLATERALIZATION_CHECKS;
+use crate::services::monitoring::latency::{AttemptLatency, LIQUIDATION_LATENCY};
+use crate::services::monitoring::liquidation_error::LIQUIDATION_ERRORS;
+use crate::services::monitoring::pool_pause::POOL_PAUSES;
+use crate::services::monitoring::post_mortem::{RACE_REPORTS, RaceReport};
+use crate::services::monitoring::quarantine::QUARANTINE;
+use crate::services::monitoring::route_quotes::ROUTE_QUOTES;
+use crate::services::monitoring::unknown_pools::UNKNOWN_POOLS;
+use crate::services::monitoring::utilization::POOL_RATES;
+use crate::services::monitoring::value_at_risk::{DebtAtRisk, VALUE_AT_RISK};
+use crate::services::monitoring::watchlist::{WATCHLIST, WatchSnapshot};
+use crate::services::oracle::failures::ORACLE_FAILURES;
+use crate::services::oracle::price_history::{PRICE_HISTORY, PricePoint};
+use crate::services::watchdog::WATCHDOG;
+use crate::types::decimal;
+use crate::utils::build_info::{BUILD_INFO, BuildInfo};
+
+/// Configuration the bot is running with, exposed to the operators.
+#[derive(Debug, Clone, Serialize)]
+pub struct RuntimeInfo {
+    pub network: String,
+    pub account: String,
+    /// Receives the seized collateral, the account if not set.
+    pub recipient: Option<String>,
+    pub liquidate_contract: String,
+    pub liquidate_contract_version: String,
+    /// pool => liquidate contract, for the pools not using the default one.
+    pub pool_liquidate_contracts: BTreeMap<String, String>,
+    pub monitored_pairs: usize,
+    pub oracle_mode: String,
+    pub simulate: bool,
+    pub treasury: bool,
+    /// Unix timestamp of the start of the bot, in seconds.
+    pub started_at: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct VersionResponse {
+    build: BuildInfo,
+    runtime: Arc<RuntimeInfo>,
+}
+
+/// The debt of the positions close to liquidation, to size the liquidity to hold.
+#[derive(Debug, Clone, Serialize)]
+struct ValueAtRiskResponse {
+    #[serde(with = "decimal::string")]
+    total_usd: Decimal,
+    /// pool => USD debt at risk
+    #[serde(with = "decimal::map_string")]
+    per_pool_usd: BTreeMap<String, Decimal>,
+    /// By decreasing USD value.
+    debt_at_risk: Vec<DebtAtRisk>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct PriceHistoryQuery {
+    asset: String,
+    /// Unix timestamps, in seconds.
+    from: Option<u64>,
+    to: Option<u64>,
+}
+
+/// Default number of positions returned by `/watch` & `/events`.
+const DEFAULT_WATCH_LIMIT: usize = 50;
+
+#[derive(Debug, Clone, Deserialize)]
+struct WatchQuery {
+    /// Maximum number of positions returned, by increasing health factor.
+    limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct EventsQuery {
+    /// Maximum number of positions in the snapshots, by increasing health factor.
+    limit: Option<usize>,
+    /// Seconds between two snapshots.
+    interval_secs: Option<u64>,
+}
+
+/// HTTP API exposing the bot version, metrics, price history, watchlist,
+/// liquidation latencies & lost races.
+pub struct ApiService {
+    address: SocketAddr,
+    runtime: Arc<RuntimeInfo>,
+}
+
+impl ApiService {
+    pub fn new(address: SocketAddr, runtime: Arc<RuntimeInfo>) -> Self {
+        Self { address, runtime }
+    }
+
+    pub async fn run_forever(self) -> anyhow::Result<()> {
+        let router = Router::new()
+            .route("/version", get(version))
+            .route("/metrics", get(metrics))
+            .route("/prices/history", get(price_history))
+            .route("/watch", get(watch))
+            .route("/events", get(events))
+            .route("/latency", get(latency))
+            .route("/race-reports", get(race_reports))
+            .route("/value-at-risk", get(value_at_risk))
+            .with_state(self.runtime);
+
+        let listener = tokio::net::TcpListener::bind(self.address).await?;
+        tracing::info!("[🛰️ API] Listening on {}", self.address);
+
+        axum::serve(listener, router).await?;
+        Ok(())
+    }
+}
+
+async fn version(State(runtime): State<Arc<RuntimeInfo>>) -> Json<VersionResponse> {
+    Json(VersionResponse {
+        build: BUILD_INFO,
+        runtime,
+    })
+}
+
+async fn metrics() -> String {
+    BUILD_INFO.prometheus_metric()
+        + &UNKNOWN_POOLS.prometheus_metric()
+        + &QUARANTINE.prometheus_metric()
+        + &DELEGATIONS.prometheus_metric()
+        + &ROUTE_QUOTES.prometheus_metric()
+        + &LIQUIDATION_ERRORS.prometheus_metric()
+        + &LIQUIDATION_LATENCY.prometheus_metric()
+        + &VALUE_AT_RISK.prometheus_metric()
+        + &POOL_RATES.prometheus_metric()
+        + &POOL_PAUSES.prometheus_metric()
+        + &ORACLE_FAILURES.prometheus_metric()
+        + &INDEXER_LAG.prometheus_metric()
+        + &COLLATERALIZATION_CHECKS.prometheus_metric()
+        + &WATCHDOG.prometheus_metric()
+}
+
+async fn value_at_risk() -> Json<ValueAtRiskResponse> {
+    Json(ValueAtRiskResponse {
+        total_usd: VALUE_AT_RISK.total(),
+        per_pool_usd: VALUE_AT_RISK
+            .per_pool()
+            .into_iter()
+            .map(|(pool, value)| (pool.to_string(), value))
+            .collect(),
+        debt_at_risk: VALUE_AT_RISK.debt_at_risk(),
+    })
+}
+
+async fn latency() -> Json<Vec<AttemptLatency>> {
+    Json(LIQUIDATION_LATENCY.recent())
+}
+
+async fn race_reports() -> Json<Vec<RaceReport>> {
+    Json(RACE_REPORTS.recent())
+}
+
+async fn price_history(
+    Query(query): Query<PriceHistoryQuery>,
+) -> Result<Json<Vec<PricePoint>>, StatusCode> {
+    PRICE_HISTORY
+        .query(&query.asset, query.from, query.to)
+        .map(Json)
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+async fn watch(Query(query): Query<WatchQuery>) -> Json<WatchSnapshot> {
+    Json(WatchSnapshot::current(
+        query.limit.unwrap_or(DEFAULT_WATCH_LIMIT),
+    ))
+}
+
+/// Server-Sent Events for the dashboards: a `runtime` event with the version &
+/// configuration, then a `snapshot` event every interval & a `liquidation`
+/// event on every new or updated liquidation.
+async fn events(
+    State(runtime): State<Arc<RuntimeInfo>>,
+    Query(query): Query<EventsQuery>,
+) -> Sse<impl Stream<Item = Result<Event, axum::Error>>> {
+    const DEFAULT_INTERVAL_SECS: u64 = 5;
+
+    let limit = query.limit.unwrap_or(DEFAULT_WATCH_LIMIT);
+    let interval = Duration::from_secs(query.interval_secs.unwrap_or(DEFAULT_INTERVAL_SECS).max(1));
+
+    let runtime_event = Event::default()
+        .event("runtime")
+        .json_data(VersionResponse {
+            build: BUILD_INFO,
+            runtime,
+        });
+    let updates = stream::unfold(
+        (tokio::time::interval(interval), WATCHLIST.subscribe()),
+        move |(mut ticker, mut liquidations)| async move {
+            let event = loop {
+                tokio::select! {
+                    _ = ticker.tick() => {
+                        break Event::default()
+                            .event("snapshot")
+                            .json_data(WatchSnapshot::current(limit));
+                    }
+                    liquidation = liquidations.recv() => match liquidation {
+                        Ok(liquidation) => {
+                            break Event::default().event("liquidation").json_data(liquidation);
+                        }
+                        // The next snapshot has them anyway.
+                        Err(RecvError::Lagged(_)) => continue,
+                        Err(RecvError::Closed) => return None,
+                    },
+                }
+            };
+            Some((event, (ticker, liquidations)))
+        },
+    );
+
+    Sse::new(stream::once(std::future::ready(runtime_event)).chain(updates))
+        .keep_alive(KeepAlive::default())
+}
@@ -0,0 +1,39 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use pragma_common::services::{Service, ServiceRunner};
+
+use crate::services::api::{ApiService, RuntimeInfo};
+
+pub struct ApiTask {
+    address: SocketAddr,
+    runtime: Arc<RuntimeInfo>,
+}
+
+impl ApiTask {
+    pub fn new(address: SocketAddr, runtime: RuntimeInfo) -> Self {
+        Self {
+            address,
+            runtime: Arc::new(runtime),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Service for ApiTask {
+    async fn start<'a>(&mut self, mut runner: ServiceRunner<'a>) -> anyhow::Result<()> {
+        let address = self.address;
+        let runtime = self.runtime.clone();
+
+        runner.spawn_loop(move |ctx| async move {
+            let api_service = ApiService::new(address, runtime);
+            if let Some(result) = ctx.run_until_cancelled(api_service.run_forever()).await {
+                result?;
+            }
+
+            anyhow::Ok(())
+        });
+
+        Ok(())
+    }
+}
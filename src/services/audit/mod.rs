@@ -0,0 +1,167 @@
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use starknet::core::types::{Call, Felt};
+
+use crate::services::monitoring::watchlist::LiquidationStatus;
+
+static AUDIT_LOG: OnceLock<AuditLog> = OnceLock::new();
+
+/// Why the bot signed a transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, strum::Display)]
+#[serde(rename_all = "kebab-case")]
+#[strum(serialize_all = "kebab-case")]
+pub enum TxPurpose {
+    Liquidation,
+    Deleverage,
+    TreasurySweep,
+    KeeperRegistration,
+    /// Prices pushed to a mock oracle by `test-feeder`.
+    TestFeed,
+}
+
+/// A call of a signed transaction, without its calldata.
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditedCall {
+    pub to: String,
+    pub selector: String,
+    pub calldata_len: usize,
+}
+
+impl From<&Call> for AuditedCall {
+    fn from(call: &Call) -> Self {
+        Self {
+            to: format!("{:#064x}", call.to),
+            selector: format!("{:#x}", call.selector),
+            calldata_len: call.calldata.len(),
+        }
+    }
+}
+
+/// An entry of the audit log.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "kebab-case")]
+pub enum AuditEvent {
+    /// A transaction signed & sent, or that failed to be.
+    Signed {
+        purpose: TxPurpose,
+        account: String,
+        /// None when fetched by the account.
+        nonce: Option<String>,
+        calls: Vec<AuditedCall>,
+        /// Max fee of the transaction (FRI), None when estimated by the account.
+        max_fee: Option<u128>,
+        tx_hash: Option<String>,
+        error: Option<String>,
+    },
+    /// The outcome of a sent liquidation.
+    Resolved {
+        tx_hash: String,
+        /// None for an expired transaction.
+        position_id: Option<String>,
+        status: LiquidationStatus,
+        /// The fee paid (FRI), None if the transaction never got a receipt.
+        actual_fee: Option<String>,
+    },
+}
+
+impl AuditEvent {
+    pub fn signed(
+        purpose: TxPurpose,
+        account: Felt,
+        nonce: Option<Felt>,
+        calls: &[Call],
+        max_fee: Option<u128>,
+        result: &Result<Felt>,
+    ) -> Self {
+        let (tx_hash, error) = match result {
+            Ok(tx_hash) => (Some(format!("{tx_hash:#064x}")), None),
+            Err(e) => (None, Some(format!("{e:#}"))),
+        };
+        Self::Signed {
+            purpose,
+            account: format!("{account:#064x}"),
+            nonce: nonce.map(|nonce| format!("{nonce:#x}")),
+            calls: calls.iter().map(AuditedCall::from).collect(),
+            max_fee,
+            tx_hash,
+            error,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct AuditLine<'a> {
+    timestamp: u64,
+    #[serde(flatten)]
+    event: &'a AuditEvent,
+}
+
+/// Appends every transaction the bot signs - and the outcome of its
+/// liquidations - to a JSON lines file, for compliance & incident forensics.
+/// Unlike the telemetry, it is never sampled, exported nor pruned.
+#[derive(Debug)]
+pub struct AuditLog {
+    path: PathBuf,
+    writer: Mutex<BufWriter<File>>,
+}
+
+impl AuditLog {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Could not open audit log {}", path.display()))?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            writer: Mutex::new(BufWriter::new(file)),
+        })
+    }
+
+    /// Sets the audit log of the whole process. Only the first call has an
+    /// effect.
+    pub fn install(self) {
+        let _ = AUDIT_LOG.set(self);
+    }
+
+    /// Appends the event to the audit log of the process, if any. A failed
+    /// write is logged but never fails the transaction it records.
+    pub fn record(event: AuditEvent) {
+        let Some(audit_log) = AUDIT_LOG.get() else {
+            return;
+        };
+        if let Err(e) = audit_log.append(&event) {
+            tracing::error!(
+                "Could not append to the audit log {}: {e:#} - {event:?}",
+                audit_log.path.display()
+            );
+        }
+    }
+
+    fn append(&self, event: &AuditEvent) -> Result<()> {
+        let line = AuditLine {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+            event,
+        };
+        let mut writer = self
+            .writer
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Poisoned audit log"))?;
+        serde_json::to_writer(&mut *writer, &line)?;
+        writer.write_all(b"\n")?;
+        // Flush every entry: the log must survive a crash right after a send.
+        writer.flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,135 @@
+pub mod task;
+
+use std::sync::{Arc, LazyLock};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{Value, json};
+use tokio::sync::watch;
+use tokio_tungstenite::connect_async;
+use tokio_tungstenite::tungstenite::Message;
+use url::Url;
+
+pub static CHAIN_HEAD: LazyLock<Arc<ChainHead>> = LazyLock::new(|| Arc::new(ChainHead::default()));
+
+/// The latest block of the chain, when followed over WebSocket, & the latest
+/// block the prices got refreshed at. Both stay at zero without WebSocket.
+#[derive(Debug)]
+pub struct ChainHead {
+    head: watch::Sender<u64>,
+    refreshed_prices: watch::Sender<u64>,
+}
+
+impl Default for ChainHead {
+    fn default() -> Self {
+        Self {
+            head: watch::channel(0).0,
+            refreshed_prices: watch::channel(0).0,
+        }
+    }
+}
+
+impl ChainHead {
+    pub fn publish_head(&self, block_number: u64) {
+        self.head.send_replace(block_number);
+    }
+
+    pub fn subscribe_heads(&self) -> watch::Receiver<u64> {
+        self.head.subscribe()
+    }
+
+    /// Signals that the prices are up to date with the block, if it is newer than
+    /// the last one signaled.
+    pub fn publish_refreshed_prices(&self, block_number: u64) {
+        self.refreshed_prices.send_if_modified(|refreshed| {
+            let is_newer = block_number > *refreshed;
+            if is_newer {
+                *refreshed = block_number;
+            }
+            is_newer
+        });
+    }
+
+    pub fn subscribe_refreshed_prices(&self) -> watch::Receiver<u64> {
+        self.refreshed_prices.subscribe()
+    }
+}
+
+/// Follows the new blocks with `starknet_subscribeNewHeads` over a WebSocket RPC
+/// & publishes them to `CHAIN_HEAD`, so the prices & the riskiest positions get
+/// refreshed at each block instead of at the next poll.
+pub struct ChainHeadService {
+    ws_url: Url,
+}
+
+impl ChainHeadService {
+    const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+    pub fn new(ws_url: Url) -> Self {
+        Self { ws_url }
+    }
+
+    pub async fn run_forever(self) -> Result<()> {
+        loop {
+            match self.follow_heads().await {
+                Ok(()) => {
+                    tracing::warn!("[⛓️ Chain head] WebSocket closed, reconnecting");
+                }
+                Err(e) => {
+                    tracing::warn!("[⛓️ Chain head] WebSocket failed, reconnecting: {e:#}");
+                }
+            }
+            tokio::time::sleep(Self::RECONNECT_DELAY).await;
+        }
+    }
+
+    /// Subscribes to the new heads & publishes them until the connection closes.
+    /// If the RPC refuses the subscription, e.g because it does not implement it,
+    /// the new blocks are only noticed by polling from then on.
+    async fn follow_heads(&self) -> Result<()> {
+        let (mut ws, _) = connect_async(self.ws_url.as_str())
+            .await
+            .with_context(|| format!("Could not connect to {}", self.ws_url))?;
+
+        let subscribe = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "starknet_subscribeNewHeads",
+            "params": {},
+        });
+        ws.send(Message::Text(subscribe.to_string().into())).await?;
+
+        while let Some(message) = ws.next().await {
+            let text = match message? {
+                Message::Text(text) => text,
+                Message::Close(_) => return Ok(()),
+                _ => continue,
+            };
+            let message: Value = serde_json::from_str(&text)?;
+
+            if message["id"] == 1 {
+                if let Some(error) = message.get("error") {
+                    tracing::warn!(
+                        "[⛓️ Chain head] The RPC refused to subscribe to the new heads ({error}), falling back to polling"
+                    );
+                    return std::future::pending().await;
+                }
+                tracing::info!(
+                    "[⛓️ Chain head] Following the new blocks of {}",
+                    self.ws_url
+                );
+                continue;
+            }
+
+            if message["method"] == "starknet_subscriptionNewHeads"
+                && let Some(block_number) = message["params"]["result"]["block_number"].as_u64()
+            {
+                tracing::debug!("[⛓️ Chain head] New block #{block_number}");
+                CHAIN_HEAD.publish_head(block_number);
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,35 @@
+use pragma_common::services::{Service, ServiceRunner};
+use url::Url;
+
+use crate::services::chain_head::ChainHeadService;
+
+pub struct ChainHeadTask {
+    ws_url: Url,
+}
+
+impl ChainHeadTask {
+    pub fn new(ws_url: Url) -> Self {
+        Self { ws_url }
+    }
+}
+
+#[async_trait::async_trait]
+impl Service for ChainHeadTask {
+    async fn start<'a>(&mut self, mut runner: ServiceRunner<'a>) -> anyhow::Result<()> {
+        let ws_url = self.ws_url.clone();
+
+        runner.spawn_loop(move |ctx| async move {
+            let chain_head_service = ChainHeadService::new(ws_url);
+            if let Some(result) = ctx
+                .run_until_cancelled(chain_head_service.run_forever())
+                .await
+            {
+                result?;
+            }
+
+            anyhow::Ok(())
+        });
+
+        Ok(())
+    }
+}
@@ -0,0 +1,76 @@
+use anyhow::Result;
+use evian::{
+    utils::indexer::handler::{OutputEvent, StarknetEventMetadata},
+    vesu::v2::data::indexer::{VesuDataIndexer, events::VesuEvent},
+};
+use pragma_common::starknet::fallback_provider::FallbackProvider;
+use tokio::sync::mpsc;
+
+/// An event of a backfill chunk, applied once the previous chunks are merged.
+pub type ChunkEvent = (StarknetEventMetadata, VesuEvent);
+
+/// Minimum number of blocks of a chunk: below, a stream of its own costs more
+/// than it saves.
+const MIN_CHUNK_BLOCKS: u64 = 50_000;
+
+/// A range of blocks indexed by its own stream during the backfill.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BackfillChunk {
+    pub from_block: u64,
+    /// Excluded.
+    pub to_block: u64,
+}
+
+/// Splits the blocks from `from_block` to `to_block` (excluded) into at most
+/// `chunks` consecutive chunks of the same size, none of them smaller than
+/// `MIN_CHUNK_BLOCKS`.
+pub fn backfill_chunks(from_block: u64, to_block: u64, chunks: usize) -> Vec<BackfillChunk> {
+    let blocks = to_block.saturating_sub(from_block);
+    let chunks = (chunks as u64).min(blocks / MIN_CHUNK_BLOCKS).max(1);
+    let chunk_blocks = blocks.div_ceil(chunks);
+
+    let mut backfill = Vec::with_capacity(chunks as usize);
+    let mut chunk_start = from_block;
+    while chunk_start < to_block {
+        let chunk_end = (chunk_start + chunk_blocks).min(to_block);
+        backfill.push(BackfillChunk {
+            from_block: chunk_start,
+            to_block: chunk_end,
+        });
+        chunk_start = chunk_end;
+    }
+    backfill
+}
+
+/// Streams the events of the chunk from the indexer, started at the first block
+/// of the chunk, until the first event past its end - or until the tip of the
+/// chain if there is none. Dropping the stream once done stops it.
+pub async fn index_chunk(
+    vesu_indexer: VesuDataIndexer<FallbackProvider>,
+    chunk: BackfillChunk,
+    tx_events: mpsc::UnboundedSender<ChunkEvent>,
+) -> Result<()> {
+    let (mut rx_messages, mut vesu_handle) = vesu_indexer.start(None).await?;
+
+    loop {
+        tokio::select! {
+            Some(msg) = rx_messages.recv() => match msg {
+                OutputEvent::Event { event_metadata, event } => {
+                    if event_metadata.block_number >= chunk.to_block {
+                        return Ok(());
+                    }
+                    tx_events.send((event_metadata, event))?;
+                }
+                OutputEvent::Synced => return Ok(()),
+                OutputEvent::Finalized(_) | OutputEvent::Invalidated(_) => {}
+            },
+            res = &mut vesu_handle => {
+                anyhow::bail!(
+                    "Stream of blocks #{}-#{} stopped: {res:?}",
+                    chunk.from_block,
+                    chunk.to_block - 1
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,197 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::sync::LazyLock;
+use std::time::Duration;
+
+use anyhow::Result;
+use dashmap::DashMap;
+use pragma_common::starknet::FallbackProvider;
+use starknet::core::types::{BlockId, EventFilter, Felt};
+use starknet::macros::selector;
+use starknet::providers::Provider;
+
+use crate::services::indexer::IndexerService;
+use crate::types::pool::PoolName;
+use crate::utils::rpc::{RpcPath, guarded_starknet};
+
+const EVENTS_CHUNK_SIZE: u64 = 1_000;
+
+// The delegations of the monitored pools, indexed from their deployment block.
+pub static DELEGATIONS: LazyLock<Delegations> = LazyLock::new(Delegations::default);
+
+/// A user allowing (or no longer allowing) another address to modify its
+/// positions in a pool.
+#[derive(Debug, Clone, Copy)]
+pub struct DelegationChange {
+    pub pool: PoolName,
+    pub delegator: Felt,
+    pub delegatee: Felt,
+    pub delegation: bool,
+    pub block_number: u64,
+}
+
+/// The addresses allowed to modify the positions of each user, from the
+/// `ModifyDelegation` events of the monitored pools.
+///
+/// Vesu positions cannot be transferred: a delegatee modifies the position of
+/// its delegator, still keyed by the delegator, through `ModifyPosition`
+/// events the indexer already streams. So the delegations never remap a
+/// position, they tell who can move it.
+#[derive(Debug, Default)]
+pub struct Delegations {
+    /// (pool, delegator) => delegatees, only while it has some.
+    delegatees: DashMap<(PoolName, Felt), HashSet<Felt>>,
+}
+
+impl Delegations {
+    /// Applies the change, dropping the delegator once all its delegations
+    /// are revoked.
+    pub fn apply(&self, change: &DelegationChange) {
+        let key = (change.pool, change.delegator);
+        if change.delegation {
+            self.delegatees
+                .entry(key)
+                .or_default()
+                .insert(change.delegatee);
+            return;
+        }
+        if let Some(mut delegatees) = self.delegatees.get_mut(&key) {
+            delegatees.remove(&change.delegatee);
+        }
+        self.delegatees
+            .remove_if(&key, |_, delegatees| delegatees.is_empty());
+    }
+
+    /// Number of addresses allowed to modify the positions of `delegator`.
+    pub fn delegatees_count(&self, pool: PoolName, delegator: Felt) -> usize {
+        self.delegatees
+            .get(&(pool, delegator))
+            .map_or(0, |delegatees| delegatees.len())
+    }
+
+    pub fn prometheus_metric(&self) -> String {
+        let mut per_pool: HashMap<PoolName, usize> = HashMap::new();
+        for entry in self.delegatees.iter() {
+            *per_pool.entry(entry.key().0).or_default() += 1;
+        }
+
+        let mut metric = String::from(
+            "# HELP delegators Users of the monitored pools delegating their positions to another address.\n\
+             # TYPE delegators gauge\n",
+        );
+        for (pool, delegators) in per_pool {
+            metric.push_str(&format!("delegators{{pool=\"{pool}\"}} {delegators}\n"));
+        }
+        metric
+    }
+}
+
+/// Indexes the `ModifyDelegation` events of the monitored pools every
+/// `interval`, from their deployment block - the Apibara stream of Vesu
+/// does not carry them. A failed read is retried from the same block.
+pub async fn index_delegations_forever(provider: FallbackProvider, interval: Duration) {
+    let mut next_blocks: HashMap<PoolName, u64> = HashMap::new();
+    let mut interval = tokio::time::interval(interval);
+    loop {
+        interval.tick().await;
+        let head_block = match guarded_starknet(&provider, RpcPath::Background, |node| {
+            node.block_number()
+        })
+        .await
+        {
+            Ok(head_block) => head_block,
+            Err(e) => {
+                tracing::debug!("[🔢 Indexer] Could not fetch the head block: {e}");
+                continue;
+            }
+        };
+
+        let pools: BTreeSet<PoolName> = IndexerService::monitored_pairs()
+            .into_iter()
+            .map(|(pool, _, _)| pool)
+            .collect();
+        for pool in pools {
+            let from_block = *next_blocks
+                .entry(pool)
+                .or_insert_with(|| pool.deployment_block());
+            if from_block > head_block {
+                continue;
+            }
+            let changes = match pool_changes(&provider, pool, from_block, head_block).await {
+                Ok(changes) => changes,
+                Err(e) => {
+                    tracing::debug!(
+                        "[🔢 Indexer] Could not read the delegation events of {pool}: {e}"
+                    );
+                    continue;
+                }
+            };
+            for change in &changes {
+                DELEGATIONS.apply(change);
+                tracing::debug!(
+                    "[🔢 Indexer] 🤝 {:#x} {} {:#x} on {} at block #{} ({} delegatees)",
+                    change.delegator,
+                    if change.delegation {
+                        "delegated to"
+                    } else {
+                        "revoked the delegation of"
+                    },
+                    change.delegatee,
+                    change.pool,
+                    change.block_number,
+                    DELEGATIONS.delegatees_count(change.pool, change.delegator)
+                );
+            }
+            next_blocks.insert(pool, head_block + 1);
+        }
+    }
+}
+
+/// Reads the `ModifyDelegation` events of the pool, keyed by (delegator,
+/// delegatee) with the data (delegation).
+async fn pool_changes(
+    provider: &FallbackProvider,
+    pool: PoolName,
+    from_block: u64,
+    to_block: u64,
+) -> Result<Vec<DelegationChange>> {
+    let filter = EventFilter {
+        from_block: Some(BlockId::Number(from_block)),
+        to_block: Some(BlockId::Number(to_block)),
+        address: Some(pool.pool_address()),
+        keys: Some(vec![vec![selector!("ModifyDelegation")]]),
+    };
+
+    let mut changes = Vec::new();
+    let mut continuation_token = None;
+    loop {
+        let page = guarded_starknet(provider, RpcPath::Background, |node| {
+            node.get_events(
+                filter.clone(),
+                continuation_token.clone(),
+                EVENTS_CHUNK_SIZE,
+            )
+        })
+        .await?;
+
+        for event in page.events {
+            let (Some(delegator), Some(delegatee), Some(delegation)) =
+                (event.keys.get(1), event.keys.get(2), event.data.first())
+            else {
+                continue;
+            };
+            changes.push(DelegationChange {
+                pool,
+                delegator: *delegator,
+                delegatee: *delegatee,
+                delegation: *delegation != Felt::ZERO,
+                block_number: event.block_number.unwrap_or(to_block),
+            });
+        }
+
+        continuation_token = page.continuation_token;
+        if continuation_token.is_none() {
+            break;
+        }
+    }
+    Ok(changes)
+}
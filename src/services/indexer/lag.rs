@@ -0,0 +1,75 @@
+use std::sync::{
+    Arc, LazyLock,
+    atomic::{AtomicU64, Ordering},
+};
+
+// Latest measured lag of the indexer, readable from anywhere in the code.
+pub static INDEXER_LAG: LazyLock<Arc<IndexerLag>> =
+    LazyLock::new(|| Arc::new(IndexerLag::default()));
+
+/// Lag between the chain head and the last block processed by the indexer.
+#[derive(Debug, Default)]
+pub struct IndexerLag {
+    blocks: AtomicU64,
+    seconds: AtomicU64,
+    /// Last block finalized, as notified by the indexer. Zero until notified.
+    finalized_block: AtomicU64,
+    finalized_lag_blocks: AtomicU64,
+}
+
+impl IndexerLag {
+    pub fn update(&self, blocks: u64, seconds: u64, head_block: u64) {
+        self.blocks.store(blocks, Ordering::Relaxed);
+        self.seconds.store(seconds, Ordering::Relaxed);
+        if let Some(finalized_block) = self.finalized_block() {
+            self.finalized_lag_blocks.store(
+                head_block.saturating_sub(finalized_block),
+                Ordering::Relaxed,
+            );
+        }
+    }
+
+    /// Records a finalized block. The finalized block never goes back.
+    pub fn finalize(&self, block_number: u64) {
+        self.finalized_block
+            .fetch_max(block_number, Ordering::Relaxed);
+    }
+
+    /// Number of blocks the indexer is behind the chain head.
+    pub fn blocks(&self) -> u64 {
+        self.blocks.load(Ordering::Relaxed)
+    }
+
+    /// Number of seconds between the chain head and the last processed block.
+    pub fn seconds(&self) -> u64 {
+        self.seconds.load(Ordering::Relaxed)
+    }
+
+    /// The last finalized block, None until the indexer notified one.
+    pub fn finalized_block(&self) -> Option<u64> {
+        let finalized_block = self.finalized_block.load(Ordering::Relaxed);
+        (finalized_block > 0).then_some(finalized_block)
+    }
+
+    /// Number of blocks between the chain head and the last finalized block.
+    pub fn finalized_lag_blocks(&self) -> u64 {
+        self.finalized_lag_blocks.load(Ordering::Relaxed)
+    }
+
+    pub fn prometheus_metric(&self) -> String {
+        format!(
+            "# HELP indexer_lag_blocks Blocks between the chain head and the last block processed by the indexer.\n\
+             # TYPE indexer_lag_blocks gauge\n\
+             indexer_lag_blocks {}\n\
+             # HELP indexer_lag_seconds Seconds between the chain head and the last block processed by the indexer.\n\
+             # TYPE indexer_lag_seconds gauge\n\
+             indexer_lag_seconds {}\n\
+             # HELP indexer_finalized_lag_blocks Blocks between the chain head and the last finalized block.\n\
+             # TYPE indexer_finalized_lag_blocks gauge\n\
+             indexer_finalized_lag_blocks {}\n",
+            self.blocks(),
+            self.seconds(),
+            self.finalized_lag_blocks()
+        )
+    }
+}
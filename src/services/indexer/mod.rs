@@ -1,6 +1,14 @@
+pub mod backfill;
+pub mod delegations;
+pub mod lag;
+pub mod pairs;
 pub mod task;
 
-use std::{collections::HashSet, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::Duration,
+};
 
 use anyhow::Result;
 use evian::{
@@ -13,85 +21,543 @@ use evian::{
         },
     },
 };
-use pragma_common::starknet::{StarknetNetwork, fallback_provider::FallbackProvider};
+use pragma_common::starknet::fallback_provider::FallbackProvider;
 use rust_decimal::Decimal;
-use starknet::core::types::Felt;
+use serde::{Deserialize, Serialize};
+use starknet::core::types::{BlockId, BlockTag, Felt, MaybePreConfirmedBlockWithTxHashes};
+use starknet::providers::Provider;
 use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+use url::Url;
+
+use crate::config::addresses::NETWORK;
+use crate::services::indexer::backfill::{BackfillChunk, ChunkEvent, backfill_chunks, index_chunk};
+use crate::services::indexer::delegations::index_delegations_forever;
+use crate::services::indexer::lag::INDEXER_LAG;
+use crate::services::indexer::pairs::{
+    ListedPair, MONITORED_PAIRS, discover_pairs_forever, onboard_pairs,
+};
+use crate::services::notifier::{AlertKind, NOTIFIER};
+use crate::services::replay::EventSink;
+use crate::services::watchdog::{WATCHDOG, WatchedService};
+use crate::types::{currency::Currency, decimal, pair_config::PAIR_CONFIGS, pool::PoolName};
+
+/// An indexed event sent from the indexer to the monitoring service.
+pub type IndexedEvent = (EventMetadata, PositionDelta);
+
+/// The block the indexing of each monitored pool starts from, so a pool created
+/// later is not synced from the first one & an older one is not missed.
+#[derive(Debug, Clone)]
+pub struct PoolStartingBlocks(HashMap<PoolName, u64>);
+
+impl PoolStartingBlocks {
+    /// Starts every monitored pool from its deployment block, or from `floor` if
+    /// later, e.g the `--starting-block` or the cursor of the persisted state.
+    pub fn new(floor: Option<u64>) -> Self {
+        let floor = floor.unwrap_or_default();
+        Self(
+            IndexerService::monitored_pairs()
+                .into_iter()
+                .map(|(pool, _, _)| (pool, pool.deployment_block().max(floor)))
+                .collect(),
+        )
+    }
+
+    /// The block the indexer starts from, the earliest of the pools.
+    pub fn first_block(&self) -> u64 {
+        self.0.values().copied().min().unwrap_or_default()
+    }
 
-use crate::types::{currency::Currency, pool::PoolName};
+    /// The block the indexing of the pool starts from.
+    pub fn starting_block(&self, pool: PoolName) -> u64 {
+        self.0
+            .get(&pool)
+            .copied()
+            .unwrap_or_else(|| pool.deployment_block())
+    }
+
+    /// Whether an event of the pool at the block must be indexed.
+    pub fn includes(&self, pool: PoolName, block_number: u64) -> bool {
+        self.0
+            .get(&pool)
+            .is_some_and(|starting_block| block_number >= *starting_block)
+    }
+}
+
+/// An Apibara DNA stream the events can be indexed from.
+#[derive(Debug, Clone)]
+pub struct ApibaraEndpoint {
+    /// None for the default stream of the network.
+    pub url: Option<Url>,
+    pub api_key: String,
+}
+
+impl ApibaraEndpoint {
+    /// The Vesu indexer reading from this endpoint from the block.
+    pub fn indexer(
+        &self,
+        provider: &FallbackProvider,
+        starting_block: u64,
+    ) -> Result<VesuDataIndexer<FallbackProvider>> {
+        let vesu_client = Arc::new(VesuDataClient::new(NETWORK, provider.clone()));
+
+        let vesu_indexer = VesuDataIndexer::new(
+            vesu_client,
+            self.api_key.clone(),
+            IndexerService::monitored_pools(),
+            self.url
+                .as_ref()
+                .map(|url| url.as_str().parse())
+                .transpose()?,
+            starting_block,
+        )?;
+
+        Ok(vesu_indexer)
+    }
+}
+
+impl std::fmt::Display for ApibaraEndpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.url {
+            Some(url) => write!(f, "{url}"),
+            None => write!(f, "the default DNA stream"),
+        }
+    }
+}
+
+/// How the indexer follows the chain head & the listings of new pairs.
+#[derive(Debug, Clone)]
+pub struct IndexerConfig {
+    /// Number of blocks the indexer can lag behind the chain head before alerting.
+    pub max_lag_blocks: u64,
+    /// How often the pairs of the monitored pools are enumerated to onboard
+    /// the new ones. Never if None.
+    pub pair_discovery_interval: Option<Duration>,
+    /// Number of streams indexing the history in parallel when the indexer
+    /// starts far behind the chain head. 1 to index it with a single stream.
+    pub backfill_chunks: usize,
+}
 
 pub struct IndexerService {
     pub current_block: u64,
-    pub apibara_api_key: String,
+    starting_blocks: PoolStartingBlocks,
+    /// Read in order, failing over to the next one when the current one fails.
+    endpoints: Vec<ApibaraEndpoint>,
     pub provider: FallbackProvider,
-    pub tx_to_monitoring: mpsc::UnboundedSender<(StarknetEventMetadata, PositionDelta)>,
+    pub tx_to_monitoring: mpsc::UnboundedSender<IndexedEvent>,
     meet_with_monitoring: Option<oneshot::Sender<()>>,
+    /// Record the events, e.g to replay them later.
+    sinks: Vec<Box<dyn EventSink>>,
+    config: IndexerConfig,
+    last_event_id: Option<EventId>,
+    /// Last block the stream is known to have delivered: the block of its last
+    /// message, its last finalized block or the head it synced to. Unlike the
+    /// `current_block`, it also moves when the blocks have no Vesu event.
+    delivered_block: u64,
+    /// The discovered pairs, onboarded at the next block boundary.
+    onboarding: Vec<ListedPair>,
+    /// The onboarded pairs being backfilled from their listing block.
+    catch_up: Option<CatchUp>,
 }
 
+/// The stream rewound to the listing block of the onboarded pairs: until the
+/// block it restarted at, only their events are sent to the monitoring.
 #[derive(Debug, Clone)]
+struct CatchUp {
+    pairs: HashSet<(Felt, Felt, Felt)>,
+    /// Excluded.
+    to_block: u64,
+}
+
+/// The metadata of an indexed event that we care about.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventMetadata {
+    pub block_number: u64,
+    pub from_address: Felt,
+    #[serde(default)]
+    pub transaction_hash: Option<Felt>,
+    /// Index of the event among the indexed events of its block.
+    #[serde(default)]
+    pub event_index: Option<u64>,
+    /// True if the event comes from a liquidation.
+    #[serde(default)]
+    pub is_liquidation: bool,
+    /// True if the event backfills a pair onboarded after its block: it is
+    /// behind the cursor of the monitoring.
+    #[serde(default)]
+    pub is_backfill: bool,
+}
+
+/// Position of an event in the stream. Stable across restarts since the indexer
+/// always restarts from the beginning of a block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct EventId {
+    pub block_number: u64,
+    pub event_index: u64,
+}
+
+impl EventMetadata {
+    pub fn event_id(&self) -> Option<EventId> {
+        self.event_index.map(|event_index| EventId {
+            block_number: self.block_number,
+            event_index,
+        })
+    }
+
+    pub fn liquidation(mut self) -> Self {
+        self.is_liquidation = true;
+        self
+    }
+
+    pub fn backfill(mut self) -> Self {
+        self.is_backfill = true;
+        self
+    }
+}
+
+impl From<&StarknetEventMetadata> for EventMetadata {
+    fn from(value: &StarknetEventMetadata) -> Self {
+        Self {
+            block_number: value.block_number,
+            from_address: value.from_address,
+            transaction_hash: Some(value.transaction_hash),
+            event_index: None,
+            is_liquidation: false,
+            is_backfill: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PositionDelta {
     pub collateral_address: Felt,
     pub debt_address: Felt,
     pub user_address: Felt,
+    #[serde(with = "decimal::string")]
     pub collateral_delta: Decimal,
+    #[serde(with = "decimal::string")]
     pub debt_delta: Decimal,
 }
 
 impl IndexerService {
     pub fn new(
-        starting_block: u64,
-        apibara_api_key: String,
+        starting_blocks: PoolStartingBlocks,
+        endpoints: Vec<ApibaraEndpoint>,
         provider: FallbackProvider,
-        tx_to_monitoring: mpsc::UnboundedSender<(StarknetEventMetadata, PositionDelta)>,
+        tx_to_monitoring: mpsc::UnboundedSender<IndexedEvent>,
         meet_with_monitoring: oneshot::Sender<()>,
+        sinks: Vec<Box<dyn EventSink>>,
+        config: IndexerConfig,
     ) -> Self {
+        let first_block = starting_blocks.first_block();
         Self {
-            current_block: starting_block,
-            apibara_api_key,
+            current_block: first_block,
+            starting_blocks,
+            endpoints,
             provider,
             tx_to_monitoring,
             meet_with_monitoring: Some(meet_with_monitoring),
+            sinks,
+            config,
+            last_event_id: None,
+            delivered_block: first_block.saturating_sub(1),
+            onboarding: Vec::new(),
+            catch_up: None,
         }
     }
 
+    const LAG_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+    /// Delay before reconnecting once all the endpoints failed in a row.
+    const FAILOVER_DELAY: Duration = Duration::from_secs(5);
+    /// Interval of the stall checks of the watchdog.
+    const WATCHDOG_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+    /// Interval between two reads of the delegation events.
+    const DELEGATION_INDEXING_INTERVAL: Duration = Duration::from_secs(60);
+
+    /// Indexes from the endpoints, failing over to the next one when the
+    /// current one fails & resuming from the last processed block.
     async fn run_forever(&mut self) -> anyhow::Result<()> {
-        let vesu_indexer = self.initialize_indexer().await?;
+        anyhow::ensure!(
+            !self.endpoints.is_empty(),
+            "No Apibara endpoint to index from"
+        );
+
+        tokio::spawn(index_delegations_forever(
+            self.provider.clone(),
+            Self::DELEGATION_INDEXING_INTERVAL,
+        ));
+
+        let (tx_new_pairs, mut rx_new_pairs) = mpsc::unbounded_channel();
+        if let Some(interval) = self.config.pair_discovery_interval {
+            tokio::spawn(discover_pairs_forever(
+                self.provider.clone(),
+                interval,
+                tx_new_pairs,
+            ));
+        }
+
+        if self.config.backfill_chunks > 1
+            && let Err(e) = self.backfill().await
+        {
+            // The block of the last event may have more events, the monitoring
+            // skips the ones it already applied.
+            if let Some(last_event_id) = self.last_event_id.take() {
+                self.current_block = last_event_id.block_number;
+            }
+            tracing::error!(
+                "[🔢 Indexer] Parallel backfill failed, resuming with a single stream from block #{}: {e:#}",
+                self.current_block
+            );
+        }
+
+        let mut endpoint_index = 0;
+        let mut failures_in_a_row = 0;
+        loop {
+            let endpoint = self.endpoints[endpoint_index].clone();
+            let last_event_id = self.last_event_id;
+            let e = match self.index_from(&endpoint, &mut rx_new_pairs).await {
+                // Restarts the stream on the same endpoint, with the new pairs.
+                Ok(()) => {
+                    self.onboard();
+                    continue;
+                }
+                Err(e) => e,
+            };
+            // Made some progress, so the endpoint worked before failing.
+            if self.last_event_id != last_event_id {
+                failures_in_a_row = 0;
+            }
+            failures_in_a_row += 1;
+
+            // The block of the last event may have more events, the monitoring
+            // skips the ones it already applied.
+            if let Some(last_event_id) = self.last_event_id.take() {
+                self.current_block = last_event_id.block_number;
+            }
+            endpoint_index = (endpoint_index + 1) % self.endpoints.len();
+            tracing::error!(
+                "[🔢 Indexer] 🔀 Indexing from {endpoint} failed, failing over to {} from block #{}: {e:#}",
+                self.endpoints[endpoint_index],
+                self.current_block
+            );
+            if failures_in_a_row >= self.endpoints.len() {
+                failures_in_a_row = 0;
+                tokio::time::sleep(Self::FAILOVER_DELAY).await;
+            }
+        }
+    }
+
+    /// Monitors the pairs being onboarded & rewinds the stream to the earliest
+    /// of their listing blocks, to backfill their positions up to the block the
+    /// stream restarts at.
+    fn onboard(&mut self) {
+        let pairs = std::mem::take(&mut self.onboarding);
+        let onboarded = onboard_pairs(&pairs);
+        let restart_block = self.current_block;
+
+        let mut from_block = restart_block;
+        for listed in &onboarded {
+            let (pool, collateral, debt) = listed.pair;
+            let listing_block = listed
+                .listing_block
+                .max(self.starting_blocks.starting_block(pool));
+            from_block = from_block.min(listing_block);
+            tracing::info!(
+                "[🔢 Indexer] 🆕 Onboarded the pair {pool} {collateral}/{debt}, backfilled from block #{listing_block}"
+            );
+            NOTIFIER.notify(format!(
+                "New pair {collateral}/{debt} listed in {pool} at block #{listing_block}, now monitored"
+            ));
+        }
+        if from_block >= restart_block {
+            return;
+        }
+
+        // Still catching up with pairs onboarded before, e.g when the stream
+        // failed: they are backfilled again from the earliest block.
+        let mut catch_up = self.catch_up.take().unwrap_or(CatchUp {
+            pairs: HashSet::new(),
+            to_block: restart_block,
+        });
+        catch_up.pairs.extend(onboarded.iter().map(|listed| {
+            let (pool, collateral, debt) = listed.pair;
+            (pool.pool_address(), collateral.address(), debt.address())
+        }));
+        self.catch_up = Some(catch_up);
+        self.current_block = from_block;
+        self.last_event_id = None;
+        tracing::info!(
+            "[🔢 Indexer] ⏪ Backfilling the onboarded pairs from block #{from_block} to #{}",
+            restart_block - 1
+        );
+    }
+
+    /// Indexes the blocks up to the chain head with `backfill_chunks` streams
+    /// of the first endpoint in parallel, merging their events in order: the
+    /// events of a chunk are buffered until the previous ones are merged.
+    /// Skipped when the head is too close for several chunks.
+    async fn backfill(&mut self) -> Result<()> {
+        let head_block = self.provider.block_number().await?;
+        let chunks = backfill_chunks(
+            self.current_block,
+            head_block + 1,
+            self.config.backfill_chunks,
+        );
+        if chunks.len() < 2 {
+            return Ok(());
+        }
+        tracing::info!(
+            "[🔢 Indexer] ⏩ Backfilling blocks #{}-#{head_block} with {} streams in parallel",
+            self.current_block,
+            chunks.len()
+        );
+
+        let endpoint = self.endpoints[0].clone();
+        let mut streams = Vec::with_capacity(chunks.len());
+        let mut handles = Vec::with_capacity(chunks.len());
+        for chunk in &chunks {
+            let vesu_indexer = self.initialize_indexer(&endpoint, chunk.from_block).await?;
+            let (tx_events, rx_events) = mpsc::unbounded_channel();
+            handles.push(tokio::spawn(index_chunk(vesu_indexer, *chunk, tx_events)));
+            streams.push(rx_events);
+        }
+        WATCHDOG.beat(WatchedService::Indexer);
+
+        let merged = self.merge_chunks(&chunks, streams, &mut handles).await;
+        for handle in &handles {
+            handle.abort();
+        }
+        merged
+    }
+
+    /// Applies the events of the chunks, one chunk after the other.
+    async fn merge_chunks(
+        &mut self,
+        chunks: &[BackfillChunk],
+        streams: Vec<mpsc::UnboundedReceiver<ChunkEvent>>,
+        handles: &mut [JoinHandle<Result<()>>],
+    ) -> Result<()> {
+        let mut watchdog_interval = tokio::time::interval(Self::WATCHDOG_CHECK_INTERVAL);
+        for ((chunk, mut rx_events), handle) in chunks.iter().zip(streams).zip(handles) {
+            loop {
+                tokio::select! {
+                    event = rx_events.recv() => {
+                        // The chunk is done, or failed, once its stream dropped.
+                        let Some((event_metadata, event)) = event else {
+                            break;
+                        };
+                        WATCHDOG.beat(WatchedService::Indexer);
+                        self.handle_event(&event_metadata, event)?;
+                    }
+                    _ = watchdog_interval.tick() => {
+                        if let Some(stalled_for) = WATCHDOG.check(WatchedService::Indexer) {
+                            anyhow::bail!(
+                                "No event from the stream of blocks #{}-#{} for {}s",
+                                chunk.from_block,
+                                chunk.to_block - 1,
+                                stalled_for.as_secs()
+                            );
+                        }
+                    }
+                }
+            }
+            handle.await??;
+
+            self.current_block = self.current_block.max(chunk.to_block);
+            self.deliver(chunk.to_block - 1);
+            tracing::info!(
+                "[🔢 Indexer] ⏩ Backfilled blocks #{}-#{}",
+                chunk.from_block,
+                chunk.to_block - 1
+            );
+        }
+        Ok(())
+    }
+
+    /// Indexes from the endpoint until it fails, or until new pairs are
+    /// discovered: the stream must then restart to include them.
+    async fn index_from(
+        &mut self,
+        endpoint: &ApibaraEndpoint,
+        rx_new_pairs: &mut mpsc::UnboundedReceiver<Vec<ListedPair>>,
+    ) -> Result<()> {
+        let vesu_indexer = self
+            .initialize_indexer(endpoint, self.current_block)
+            .await?;
+        let mut lag_interval = tokio::time::interval(Self::LAG_CHECK_INTERVAL);
+        let mut watchdog_interval = tokio::time::interval(Self::WATCHDOG_CHECK_INTERVAL);
 
         let (mut rx_messages, mut vesu_handle) = vesu_indexer.start(None).await?;
 
         tracing::info!(
-            "[🔢 Indexer] 🔌 Connected to Vesu! (from block {})",
+            "[🔢 Indexer] 🔌 Connected to Vesu through {endpoint}! (from block {})",
             self.current_block
         );
+        WATCHDOG.beat(WatchedService::Indexer);
 
         loop {
             tokio::select! {
                 Some(msg) = rx_messages.recv() => {
+                    WATCHDOG.beat(WatchedService::Indexer);
                     match msg {
                         OutputEvent::Event { event_metadata, event } => {
-                            match event {
-                                VesuEvent::Position(position) => {
-                                    self.current_block = event_metadata.block_number + 1;
-                                    self.tx_to_monitoring.send((event_metadata, position.into()))?;
-                                },
-                                VesuEvent::Liquidation(liquidation) => {
-                                    self.current_block = event_metadata.block_number + 1;
-                                    self.tx_to_monitoring.send((event_metadata, liquidation.into()))?;
-                                }
-                                VesuEvent::Context(_) => {
-                                }
+                            self.deliver(event_metadata.block_number);
+                            // Only restarts at a block boundary: the events
+                            // of the new pairs shift the indexes of the others
+                            // in their block, so no block is indexed twice.
+                            if !self.onboarding.is_empty()
+                                && self.last_event_id.is_none_or(|last| event_metadata.block_number > last.block_number)
+                            {
+                                self.current_block = event_metadata.block_number;
+                                return Ok(());
                             }
-
+                            self.handle_event(&event_metadata, event)?;
                         }
                         OutputEvent::Synced => {
                             tracing::info!("[🔢 Indexer] 🥳 Vesu indexer reached the tip of the chain!");
+                            match self.provider.block_number().await {
+                                Ok(head_block) => self.deliver(head_block),
+                                Err(e) => tracing::warn!("[🔢 Indexer] Could not read the head block: {e}"),
+                            }
 
                             if let Some(meet_with_monitoring) = self.meet_with_monitoring.take() {
                                 meet_with_monitoring.send(()).expect("Rendezvous from Indexer dropped?");
                             }
                         }
-                        // TODO: Handle re-orgs.
-                        OutputEvent::Finalized(_) | OutputEvent::Invalidated(_) => { }
+                        OutputEvent::Finalized(block_number) => {
+                            tracing::debug!("[🔢 Indexer] Block #{block_number} finalized");
+                            INDEXER_LAG.finalize(block_number);
+                            self.deliver(block_number);
+                        }
+                        // TODO: Roll the monitoring back on re-orgs.
+                        OutputEvent::Invalidated(last_valid_block) => {
+                            tracing::warn!("[🔢 Indexer] Blocks after #{last_valid_block} re-orged");
+                            for sink in &mut self.sinks {
+                                sink.invalidate(last_valid_block)?;
+                            }
+                        }
+                    }
+                }
+
+                Some(pairs) = rx_new_pairs.recv() => {
+                    self.onboarding.extend(pairs);
+                    // Nothing indexed since the start, so no block to finish.
+                    if self.last_event_id.is_none() {
+                        return Ok(());
+                    }
+                }
+
+                _ = lag_interval.tick() => {
+                    if let Err(e) = self.check_lag().await {
+                        tracing::warn!("[🔢 Indexer] Could not compute the indexer lag: {e}");
+                    }
+                }
+
+                // A stalled stream gets restarted by the failover, from the
+                // last processed block.
+                _ = watchdog_interval.tick() => {
+                    if let Some(stalled_for) = WATCHDOG.check(WatchedService::Indexer) {
+                        anyhow::bail!("No message from the stream for {}s", stalled_for.as_secs());
                     }
                 }
 
@@ -102,99 +568,248 @@ impl IndexerService {
         }
     }
 
-    /// Initialize the Vesu indexer.
-    async fn initialize_indexer(&self) -> Result<VesuDataIndexer<FallbackProvider>> {
-        let vesu_client = Arc::new(VesuDataClient::new(
-            StarknetNetwork::Mainnet,
-            self.provider.clone(),
-        ));
+    /// Records a block delivered by the stream.
+    fn deliver(&mut self, block_number: u64) {
+        self.delivered_block = self.delivered_block.max(block_number);
+    }
 
-        let vesu_indexer = VesuDataIndexer::new(
-            vesu_client,
-            self.apibara_api_key.clone(),
-            Self::monitored_pools(),
-            None,
-            self.current_block,
-        )?;
+    /// Compares the chain head with the last block delivered by the stream,
+    /// updates the `INDEXER_LAG` and warns if we're falling behind.
+    async fn check_lag(&self) -> Result<()> {
+        let processed_block = self
+            .delivered_block
+            .max(self.current_block.saturating_sub(1));
+        let head_block = self.provider.block_number().await?;
+        let lag_blocks = head_block.saturating_sub(processed_block);
 
-        Ok(vesu_indexer)
+        let head_timestamp = self.block_timestamp(BlockId::Tag(BlockTag::Latest)).await?;
+        let processed_timestamp = self
+            .block_timestamp(BlockId::Number(processed_block))
+            .await?;
+        let lag_seconds = head_timestamp.saturating_sub(processed_timestamp);
+
+        INDEXER_LAG.update(lag_blocks, lag_seconds, head_block);
+
+        // Lagging is expected while backfilling.
+        let is_synced = self.meet_with_monitoring.is_none();
+        if is_synced && lag_blocks > self.config.max_lag_blocks {
+            tracing::warn!(
+                "[🔢 Indexer] 🐢 Indexer is lagging: {lag_blocks} blocks ({lag_seconds}s) behind the head (#{head_block})"
+            );
+            NOTIFIER.alert(AlertKind::IndexerLag, "indexer", || {
+                format!(
+                    "The indexer is lagging: {lag_blocks} blocks ({lag_seconds}s) behind the head (#{head_block})"
+                )
+            });
+        } else if !is_synced {
+            tracing::info!(
+                "[🔢 Indexer] ⏩ Syncing: block #{processed_block} / #{head_block} ({lag_blocks} blocks left)"
+            );
+        } else {
+            tracing::debug!(
+                "[🔢 Indexer] Lag: {lag_blocks} blocks ({lag_seconds}s) behind the head (#{head_block})"
+            );
+            NOTIFIER.resolve(AlertKind::IndexerLag, "indexer", || {
+                format!("The indexer caught up with the head (#{head_block})")
+            });
+        }
+
+        Ok(())
+    }
+
+    async fn block_timestamp(&self, block_id: BlockId) -> Result<u64> {
+        let timestamp = match self.provider.get_block_with_tx_hashes(block_id).await? {
+            MaybePreConfirmedBlockWithTxHashes::Block(block) => block.timestamp,
+            MaybePreConfirmedBlockWithTxHashes::PreConfirmedBlock(block) => block.timestamp,
+        };
+        Ok(timestamp)
+    }
+
+    /// Sends the position changes of the event to the monitoring service, or
+    /// drops the cached pair configs of the pool whose parameters changed.
+    fn handle_event(
+        &mut self,
+        event_metadata: &StarknetEventMetadata,
+        event: VesuEvent,
+    ) -> Result<()> {
+        for sink in &mut self.sinks {
+            sink.record_raw(event_metadata, &event)?;
+        }
+        match event {
+            VesuEvent::Position(position) => {
+                self.current_block = event_metadata.block_number + 1;
+                self.send_to_monitoring((EventMetadata::from(event_metadata), position.into()))?;
+            }
+            VesuEvent::Liquidation(liquidation) => {
+                self.current_block = event_metadata.block_number + 1;
+                self.send_to_monitoring((
+                    EventMetadata::from(event_metadata).liquidation(),
+                    liquidation.into(),
+                ))?;
+            }
+            // E.g a change of the parameters of a pool.
+            VesuEvent::Context(_) => {
+                if let Ok(pool) = PoolName::try_from(&event_metadata.from_address) {
+                    PAIR_CONFIGS.invalidate_pool(pool);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Sends the event to the monitoring service, recording it first if needed.
+    /// The events of a pool before its starting block are skipped, so are the
+    /// events of the pairs not being backfilled while catching up.
+    fn send_to_monitoring(&mut self, mut event: IndexedEvent) -> Result<()> {
+        let block_number = event.0.block_number;
+        if let Ok(pool) = PoolName::try_from(&event.0.from_address)
+            && !self.starting_blocks.includes(pool, block_number)
+        {
+            return Ok(());
+        }
+        // The skipped events still count in the indexes, as in a stream
+        // that monitored the pairs from the start.
+        let event_index = match self.last_event_id {
+            Some(last) if last.block_number == block_number => last.event_index + 1,
+            _ => 0,
+        };
+        event.0.event_index = Some(event_index);
+        self.last_event_id = Some(EventId {
+            block_number,
+            event_index,
+        });
+
+        if let Some(catch_up) = &self.catch_up {
+            if block_number >= catch_up.to_block {
+                tracing::info!(
+                    "[🔢 Indexer] ⏩ Backfilled the onboarded pairs up to block #{}",
+                    catch_up.to_block - 1
+                );
+                self.catch_up = None;
+            } else {
+                let pair = (
+                    event.0.from_address,
+                    event.1.collateral_address,
+                    event.1.debt_address,
+                );
+                if !catch_up.pairs.contains(&pair) {
+                    return Ok(());
+                }
+                event.0 = event.0.backfill();
+            }
+        }
+
+        for sink in &mut self.sinks {
+            sink.record(&event)?;
+        }
+        self.tx_to_monitoring.send(event)?;
+        Ok(())
+    }
+
+    /// Initialize the Vesu indexer, reading from the endpoint from the block.
+    async fn initialize_indexer(
+        &self,
+        endpoint: &ApibaraEndpoint,
+        starting_block: u64,
+    ) -> Result<VesuDataIndexer<FallbackProvider>> {
+        endpoint.indexer(&self.provider, starting_block)
     }
 
     /// Returns all the v2 pools monitored by the liquidation bot.
     /// Source: https://vesu.xyz/borrow
-    fn monitored_pools() -> HashSet<PoolDetails> {
-        [
-            PoolName::Re7USDCCore.pool_details(Currency::uniBTC, Currency::USDC),
-            PoolName::Re7USDCCore.pool_details(Currency::LBTC, Currency::USDC),
-            PoolName::Re7USDCCore.pool_details(Currency::tBTC, Currency::USDC),
-            PoolName::Re7USDCCore.pool_details(Currency::solvBTC, Currency::USDC),
-            PoolName::Re7USDCCore.pool_details(Currency::xWBTC, Currency::USDC),
-            PoolName::Re7USDCCore.pool_details(Currency::xLBTC, Currency::USDC),
-            PoolName::Re7USDCCore.pool_details(Currency::xsBTC, Currency::USDC),
-            PoolName::Re7USDCCore.pool_details(Currency::xtBTC, Currency::USDC),
-            PoolName::Re7USDCCore.pool_details(Currency::WBTC, Currency::USDC),
-            PoolName::Re7USDCPrime.pool_details(Currency::WBTC, Currency::USDC),
-            PoolName::Re7xBTC.pool_details(Currency::xtBTC, Currency::solvBTC),
-            PoolName::Re7xBTC.pool_details(Currency::mRe7BTC, Currency::solvBTC),
-            PoolName::Re7xBTC.pool_details(Currency::xsBTC, Currency::solvBTC),
-            PoolName::Re7xBTC.pool_details(Currency::xWBTC, Currency::solvBTC),
-            PoolName::Re7xBTC.pool_details(Currency::xLBTC, Currency::solvBTC),
-            PoolName::Re7xBTC.pool_details(Currency::xtBTC, Currency::tBTC),
-            PoolName::Re7xBTC.pool_details(Currency::mRe7BTC, Currency::tBTC),
-            PoolName::Re7xBTC.pool_details(Currency::xsBTC, Currency::tBTC),
-            PoolName::Re7xBTC.pool_details(Currency::xWBTC, Currency::tBTC),
-            PoolName::Re7xBTC.pool_details(Currency::xLBTC, Currency::tBTC),
-            PoolName::Re7xBTC.pool_details(Currency::xtBTC, Currency::LBTC),
-            PoolName::Re7xBTC.pool_details(Currency::mRe7BTC, Currency::LBTC),
-            PoolName::Re7xBTC.pool_details(Currency::xsBTC, Currency::LBTC),
-            PoolName::Re7xBTC.pool_details(Currency::xWBTC, Currency::LBTC),
-            PoolName::Re7xBTC.pool_details(Currency::xtBTC, Currency::WBTC),
-            PoolName::Re7xBTC.pool_details(Currency::mRe7BTC, Currency::WBTC),
-            PoolName::Re7xBTC.pool_details(Currency::xsBTC, Currency::WBTC),
-            PoolName::Re7xBTC.pool_details(Currency::xWBTC, Currency::WBTC),
-            PoolName::Re7xBTC.pool_details(Currency::xLBTC, Currency::WBTC),
-            PoolName::Re7xBTC.pool_details(Currency::xLBTC, Currency::LBTC),
-            PoolName::Re7USDCFrontier.pool_details(Currency::YBTC_B, Currency::USDC),
-            PoolName::Re7USDCStableCore.pool_details(Currency::mRe7YIELD, Currency::USDC),
-            PoolName::Re7USDCStableCore.pool_details(Currency::sUSN, Currency::USDC),
-            PoolName::Prime.pool_details(Currency::wstETH, Currency::ETH),
-            PoolName::Prime.pool_details(Currency::WBTC, Currency::ETH),
-            PoolName::Prime.pool_details(Currency::STRK, Currency::ETH),
-            PoolName::Prime.pool_details(Currency::USDC, Currency::ETH),
-            PoolName::Prime.pool_details(Currency::USDT, Currency::ETH),
-            PoolName::Prime.pool_details(Currency::wstETH, Currency::STRK),
-            PoolName::Prime.pool_details(Currency::WBTC, Currency::STRK),
-            PoolName::Prime.pool_details(Currency::ETH, Currency::STRK),
-            PoolName::Prime.pool_details(Currency::USDC, Currency::STRK),
-            PoolName::Prime.pool_details(Currency::USDT, Currency::STRK),
-            PoolName::Prime.pool_details(Currency::wstETH, Currency::USDC),
-            PoolName::Prime.pool_details(Currency::WBTC, Currency::USDC),
-            PoolName::Prime.pool_details(Currency::STRK, Currency::USDC),
-            PoolName::Prime.pool_details(Currency::ETH, Currency::USDC),
-            PoolName::Prime.pool_details(Currency::USDT, Currency::USDC),
-            PoolName::Prime.pool_details(Currency::wstETH, Currency::USDT),
-            PoolName::Prime.pool_details(Currency::WBTC, Currency::USDT),
-            PoolName::Prime.pool_details(Currency::STRK, Currency::USDT),
-            PoolName::Prime.pool_details(Currency::ETH, Currency::USDT),
-            PoolName::Prime.pool_details(Currency::USDC, Currency::USDT),
-            PoolName::Prime.pool_details(Currency::wstETH, Currency::WBTC),
-            PoolName::Prime.pool_details(Currency::STRK, Currency::WBTC),
-            PoolName::Prime.pool_details(Currency::ETH, Currency::WBTC),
-            PoolName::Prime.pool_details(Currency::USDC, Currency::WBTC),
-            PoolName::Prime.pool_details(Currency::USDT, Currency::WBTC),
-            PoolName::Prime.pool_details(Currency::WBTC, Currency::wstETH),
-            PoolName::Prime.pool_details(Currency::STRK, Currency::wstETH),
-            PoolName::Prime.pool_details(Currency::ETH, Currency::wstETH),
-            PoolName::Prime.pool_details(Currency::USDC, Currency::wstETH),
-            PoolName::Prime.pool_details(Currency::USDT, Currency::wstETH),
-            PoolName::Prime.pool_details(Currency::xSTRK, Currency::USDC),
-            PoolName::Prime.pool_details(Currency::xSTRK, Currency::STRK),
-            PoolName::Prime.pool_details(Currency::xSTRK, Currency::USDT),
-            PoolName::Prime.pool_details(Currency::xWBTC, Currency::USDC),
-            PoolName::Prime.pool_details(Currency::xWBTC, Currency::WBTC),
-            PoolName::Prime.pool_details(Currency::xWBTC, Currency::USDT),
+    pub fn monitored_pools() -> HashSet<PoolDetails> {
+        Self::monitored_pairs()
+            .into_iter()
+            .map(|(pool, collateral, debt)| pool.pool_details(collateral, debt))
+            .collect()
+    }
+
+    /// Returns the (pool, collateral, debt) pairs monitored by the liquidation
+    /// bot, including the ones onboarded since the start.
+    pub fn monitored_pairs() -> Vec<(PoolName, Currency, Currency)> {
+        MONITORED_PAIRS
+            .read()
+            .expect("poisoned monitored pairs")
+            .clone()
+    }
+
+    /// Returns the pairs listed when the liquidation bot starts.
+    pub fn listed_pairs() -> Vec<(PoolName, Currency, Currency)> {
+        vec![
+            (PoolName::Re7USDCCore, Currency::uniBTC, Currency::USDC),
+            (PoolName::Re7USDCCore, Currency::LBTC, Currency::USDC),
+            (PoolName::Re7USDCCore, Currency::tBTC, Currency::USDC),
+            (PoolName::Re7USDCCore, Currency::solvBTC, Currency::USDC),
+            (PoolName::Re7USDCCore, Currency::xWBTC, Currency::USDC),
+            (PoolName::Re7USDCCore, Currency::xLBTC, Currency::USDC),
+            (PoolName::Re7USDCCore, Currency::xsBTC, Currency::USDC),
+            (PoolName::Re7USDCCore, Currency::xtBTC, Currency::USDC),
+            (PoolName::Re7USDCCore, Currency::WBTC, Currency::USDC),
+            (PoolName::Re7USDCPrime, Currency::WBTC, Currency::USDC),
+            (PoolName::Re7xBTC, Currency::xtBTC, Currency::solvBTC),
+            (PoolName::Re7xBTC, Currency::mRe7BTC, Currency::solvBTC),
+            (PoolName::Re7xBTC, Currency::xsBTC, Currency::solvBTC),
+            (PoolName::Re7xBTC, Currency::xWBTC, Currency::solvBTC),
+            (PoolName::Re7xBTC, Currency::xLBTC, Currency::solvBTC),
+            (PoolName::Re7xBTC, Currency::xtBTC, Currency::tBTC),
+            (PoolName::Re7xBTC, Currency::mRe7BTC, Currency::tBTC),
+            (PoolName::Re7xBTC, Currency::xsBTC, Currency::tBTC),
+            (PoolName::Re7xBTC, Currency::xWBTC, Currency::tBTC),
+            (PoolName::Re7xBTC, Currency::xLBTC, Currency::tBTC),
+            (PoolName::Re7xBTC, Currency::xtBTC, Currency::LBTC),
+            (PoolName::Re7xBTC, Currency::mRe7BTC, Currency::LBTC),
+            (PoolName::Re7xBTC, Currency::xsBTC, Currency::LBTC),
+            (PoolName::Re7xBTC, Currency::xWBTC, Currency::LBTC),
+            (PoolName::Re7xBTC, Currency::xtBTC, Currency::WBTC),
+            (PoolName::Re7xBTC, Currency::mRe7BTC, Currency::WBTC),
+            (PoolName::Re7xBTC, Currency::xsBTC, Currency::WBTC),
+            (PoolName::Re7xBTC, Currency::xWBTC, Currency::WBTC),
+            (PoolName::Re7xBTC, Currency::xLBTC, Currency::WBTC),
+            (PoolName::Re7xBTC, Currency::xLBTC, Currency::LBTC),
+            (PoolName::Re7USDCFrontier, Currency::YBTC_B, Currency::USDC),
+            (
+                PoolName::Re7USDCStableCore,
+                Currency::mRe7YIELD,
+                Currency::USDC,
+            ),
+            (PoolName::Re7USDCStableCore, Currency::sUSN, Currency::USDC),
+            (PoolName::Prime, Currency::wstETH, Currency::ETH),
+            (PoolName::Prime, Currency::WBTC, Currency::ETH),
+            (PoolName::Prime, Currency::STRK, Currency::ETH),
+            (PoolName::Prime, Currency::USDC, Currency::ETH),
+            (PoolName::Prime, Currency::USDT, Currency::ETH),
+            (PoolName::Prime, Currency::wstETH, Currency::STRK),
+            (PoolName::Prime, Currency::WBTC, Currency::STRK),
+            (PoolName::Prime, Currency::ETH, Currency::STRK),
+            (PoolName::Prime, Currency::USDC, Currency::STRK),
+            (PoolName::Prime, Currency::USDT, Currency::STRK),
+            (PoolName::Prime, Currency::wstETH, Currency::USDC),
+            (PoolName::Prime, Currency::WBTC, Currency::USDC),
+            (PoolName::Prime, Currency::STRK, Currency::USDC),
+            (PoolName::Prime, Currency::ETH, Currency::USDC),
+            (PoolName::Prime, Currency::USDT, Currency::USDC),
+            (PoolName::Prime, Currency::wstETH, Currency::USDT),
+            (PoolName::Prime, Currency::WBTC, Currency::USDT),
+            (PoolName::Prime, Currency::STRK, Currency::USDT),
+            (PoolName::Prime, Currency::ETH, Currency::USDT),
+            (PoolName::Prime, Currency::USDC, Currency::USDT),
+            (PoolName::Prime, Currency::wstETH, Currency::WBTC),
+            (PoolName::Prime, Currency::STRK, Currency::WBTC),
+            (PoolName::Prime, Currency::ETH, Currency::WBTC),
+            (PoolName::Prime, Currency::USDC, Currency::WBTC),
+            (PoolName::Prime, Currency::USDT, Currency::WBTC),
+            (PoolName::Prime, Currency::WBTC, Currency::wstETH),
+            (PoolName::Prime, Currency::STRK, Currency::wstETH),
+            (PoolName::Prime, Currency::ETH, Currency::wstETH),
+            (PoolName::Prime, Currency::USDC, Currency::wstETH),
+            (PoolName::Prime, Currency::USDT, Currency::wstETH),
+            (PoolName::Prime, Currency::xSTRK, Currency::USDC),
+            (PoolName::Prime, Currency::xSTRK, Currency::STRK),
+            (PoolName::Prime, Currency::xSTRK, Currency::USDT),
+            (PoolName::Prime, Currency::xWBTC, Currency::USDC),
+            (PoolName::Prime, Currency::xWBTC, Currency::WBTC),
+            (PoolName::Prime, Currency::xWBTC, Currency::USDT),
         ]
-        .into()
     }
 }
 
@@ -0,0 +1,216 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::sync::{LazyLock, RwLock};
+use std::time::Duration;
+
+use anyhow::Result;
+use evian::vesu::v2::data::VesuDataClient;
+use futures_util::{StreamExt, future, stream};
+use pragma_common::starknet::fallback_provider::FallbackProvider;
+use starknet::core::types::{BlockId, EventFilter, Felt};
+use starknet::macros::selector;
+use starknet::providers::Provider;
+use strum::IntoEnumIterator;
+use tokio::sync::mpsc;
+
+use crate::config::addresses::NETWORK;
+use crate::services::indexer::IndexerService;
+use crate::services::monitoring::lltv_check::Pair;
+use crate::types::currency::Currency;
+use crate::types::pool::PoolName;
+use crate::utils::rpc::{RpcPath, guarded_starknet};
+
+/// Number of pair configs read at once while discovering the pairs.
+const DISCOVERY_CONCURRENCY: usize = 8;
+/// Number of events read per `get_events` page.
+const EVENTS_CHUNK_SIZE: u64 = 1_000;
+
+// The monitored pairs: the listed ones, then the ones onboarded since the start.
+pub static MONITORED_PAIRS: LazyLock<RwLock<Vec<Pair>>> =
+    LazyLock::new(|| RwLock::new(IndexerService::listed_pairs()));
+
+/// A pair discovered in a monitored pool, with the block it got listed at: its
+/// positions are backfilled from there.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ListedPair {
+    pub pair: Pair,
+    pub listing_block: u64,
+}
+
+/// Adds the pairs to the monitored ones & returns the ones not already
+/// monitored.
+pub fn onboard_pairs(pairs: &[ListedPair]) -> Vec<ListedPair> {
+    let mut monitored = MONITORED_PAIRS.write().expect("poisoned monitored pairs");
+    let mut onboarded = Vec::new();
+    for listed in pairs {
+        if !monitored.contains(&listed.pair) {
+            monitored.push(listed.pair);
+            onboarded.push(*listed);
+        }
+    }
+    onboarded
+}
+
+/// Discovers the pairs listed in the monitored pools from their `SetPairConfig`
+/// events, keyed by (collateral, debt), reading each pool from its deployment
+/// block once, then only the new blocks.
+/// NOTE: An asset missing from `assets.toml` cannot be discovered, its pairs
+/// only show up in the unknown assets.
+#[derive(Debug, Default)]
+pub struct PairDiscovery {
+    /// The next block to read the events of each pool from.
+    next_blocks: HashMap<PoolName, u64>,
+    /// The pairs not monitored yet, with the block of their first config.
+    listed: HashMap<Pair, u64>,
+}
+
+impl PairDiscovery {
+    /// Returns the pairs of the monitored pools that are not monitored yet &
+    /// have a non-zero LLTV.
+    pub async fn discover_new_pairs(
+        &mut self,
+        provider: &FallbackProvider,
+    ) -> Result<Vec<ListedPair>> {
+        let head_block =
+            guarded_starknet(provider, RpcPath::Background, |node| node.block_number()).await?;
+        let monitored: HashSet<Pair> = IndexerService::monitored_pairs().into_iter().collect();
+        let pools: BTreeSet<_> = monitored.iter().map(|(pool, _, _)| *pool).collect();
+
+        for pool in pools {
+            let from_block = *self
+                .next_blocks
+                .entry(pool)
+                .or_insert_with(|| pool.deployment_block());
+            if from_block > head_block {
+                continue;
+            }
+            for (pair, block_number) in
+                pair_listings(provider, pool, from_block, head_block).await?
+            {
+                self.listed.entry(pair).or_insert(block_number);
+            }
+            self.next_blocks.insert(pool, head_block + 1);
+        }
+        self.listed.retain(|pair, _| !monitored.contains(pair));
+
+        // A pair stays listed with a zero LLTV until its config is complete.
+        let candidates: Vec<ListedPair> = self
+            .listed
+            .iter()
+            .map(|(pair, listing_block)| ListedPair {
+                pair: *pair,
+                listing_block: *listing_block,
+            })
+            .collect();
+        let new_pairs = stream::iter(candidates)
+            .map(|listed| async move {
+                let (pool, collateral, debt) = listed.pair;
+                let pair_config =
+                    guarded_starknet(provider, RpcPath::Background, |node| async move {
+                        VesuDataClient::new(NETWORK, node.clone())
+                            .pair_config(
+                                pool.pool_address(),
+                                collateral.address(),
+                                debt.address(),
+                                None,
+                            )
+                            .await
+                    })
+                    .await;
+                pair_config
+                    .is_ok_and(|pair_config| !pair_config.max_ltv.is_zero())
+                    .then_some(listed)
+            })
+            .buffer_unordered(DISCOVERY_CONCURRENCY)
+            .filter_map(future::ready)
+            .collect()
+            .await;
+        Ok(new_pairs)
+    }
+}
+
+/// Reads the `SetPairConfig` events of the pool between the blocks, returning
+/// the pairs of the known assets with the block of their first config.
+async fn pair_listings(
+    provider: &FallbackProvider,
+    pool: PoolName,
+    from_block: u64,
+    to_block: u64,
+) -> Result<Vec<(Pair, u64)>> {
+    let filter = EventFilter {
+        from_block: Some(BlockId::Number(from_block)),
+        to_block: Some(BlockId::Number(to_block)),
+        address: Some(pool.pool_address()),
+        keys: Some(vec![vec![selector!("SetPairConfig")]]),
+    };
+
+    let mut listings = Vec::new();
+    let mut continuation_token = None;
+    loop {
+        let page = guarded_starknet(provider, RpcPath::Background, |node| {
+            node.get_events(
+                filter.clone(),
+                continuation_token.clone(),
+                EVENTS_CHUNK_SIZE,
+            )
+        })
+        .await?;
+
+        for event in page.events {
+            let (Some(collateral), Some(debt)) = (
+                event.keys.get(1).and_then(currency_at),
+                event.keys.get(2).and_then(currency_at),
+            ) else {
+                continue;
+            };
+            listings.push((
+                (pool, collateral, debt),
+                event.block_number.unwrap_or(to_block),
+            ));
+        }
+
+        continuation_token = page.continuation_token;
+        if continuation_token.is_none() {
+            break;
+        }
+    }
+    Ok(listings)
+}
+
+/// The known asset at the address, if any.
+fn currency_at(address: &Felt) -> Option<Currency> {
+    Currency::iter().find(|currency| currency.address() == *address)
+}
+
+/// Discovers the new pairs every `interval`, sending them to the indexer until
+/// it stops listening.
+pub async fn discover_pairs_forever(
+    provider: FallbackProvider,
+    interval: Duration,
+    tx_new_pairs: mpsc::UnboundedSender<Vec<ListedPair>>,
+) {
+    let mut discovery = PairDiscovery::default();
+    loop {
+        tokio::time::sleep(interval).await;
+        if tx_new_pairs.is_closed() {
+            return;
+        }
+
+        let new_pairs = match discovery.discover_new_pairs(&provider).await {
+            Ok(new_pairs) => new_pairs,
+            Err(e) => {
+                tracing::warn!("[🔢 Indexer] Could not discover the new pairs: {e:#}");
+                continue;
+            }
+        };
+        tracing::debug!(
+            "[🔢 Indexer] Discovered {} new pairs in the monitored pools",
+            new_pairs.len()
+        );
+        if new_pairs.is_empty() {
+            continue;
+        }
+        if tx_new_pairs.send(new_pairs).is_err() {
+            return;
+        }
+    }
+}
@@ -1,34 +1,42 @@
-use evian::utils::indexer::handler::StarknetEventMetadata;
 use pragma_common::{
     services::{Service, ServiceRunner},
     starknet::FallbackProvider,
 };
 use tokio::sync::{mpsc, oneshot};
 
-use crate::services::indexer::{IndexerService, PositionDelta};
+use crate::services::{
+    indexer::{ApibaraEndpoint, IndexedEvent, IndexerConfig, IndexerService, PoolStartingBlocks},
+    replay::RecordingConfig,
+};
 
 pub struct IndexerTask {
-    starting_block: u64,
-    apibara_api_key: String,
+    starting_blocks: PoolStartingBlocks,
+    endpoints: Vec<ApibaraEndpoint>,
     provider: FallbackProvider,
-    tx_to_monitoring: mpsc::UnboundedSender<(StarknetEventMetadata, PositionDelta)>,
+    tx_to_monitoring: mpsc::UnboundedSender<IndexedEvent>,
     meet_with_monitoring: Option<oneshot::Sender<()>>,
+    recording: RecordingConfig,
+    config: IndexerConfig,
 }
 
 impl IndexerTask {
     pub fn new(
-        starting_block: u64,
-        apibara_api_key: String,
+        starting_blocks: PoolStartingBlocks,
+        endpoints: Vec<ApibaraEndpoint>,
         provider: FallbackProvider,
-        tx_to_monitoring: mpsc::UnboundedSender<(StarknetEventMetadata, PositionDelta)>,
+        tx_to_monitoring: mpsc::UnboundedSender<IndexedEvent>,
         meet_with_monitoring: oneshot::Sender<()>,
+        recording: RecordingConfig,
+        config: IndexerConfig,
     ) -> Self {
         Self {
-            starting_block,
-            apibara_api_key,
+            starting_blocks,
+            endpoints,
             provider,
             tx_to_monitoring,
             meet_with_monitoring: Some(meet_with_monitoring),
+            recording,
+            config,
         }
     }
 }
@@ -36,22 +44,26 @@ impl IndexerTask {
 #[async_trait::async_trait]
 impl Service for IndexerTask {
     async fn start<'a>(&mut self, mut runner: ServiceRunner<'a>) -> anyhow::Result<()> {
-        let starting_block = self.starting_block;
-        let apibara_api_key = self.apibara_api_key.clone();
+        let starting_blocks = self.starting_blocks.clone();
+        let endpoints = self.endpoints.clone();
         let provider = self.provider.clone();
         let tx_to_monitoring = self.tx_to_monitoring.clone();
+        let config = self.config.clone();
         let meet_with_monitoring = self
             .meet_with_monitoring
             .take()
             .expect("IndexerTask cannot be launched twice");
+        let sinks = self.recording.open_sinks(&self.provider)?;
 
         runner.spawn_loop(move |ctx| async move {
             let mut indexer_service = IndexerService::new(
-                starting_block,
-                apibara_api_key,
+                starting_blocks.clone(),
+                endpoints,
                 provider,
                 tx_to_monitoring,
                 meet_with_monitoring,
+                sinks,
+                config,
             );
             if let Some(result) = ctx.run_until_cancelled(indexer_service.run_forever()).await {
                 result?;
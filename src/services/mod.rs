@@ -1,3 +1,11 @@
+pub mod api;
+pub mod audit;
+pub mod chain_head;
 pub mod indexer;
 pub mod monitoring;
+pub mod notifier;
 pub mod oracle;
+pub mod replay;
+pub mod stream;
+pub mod treasury;
+pub mod watchdog;
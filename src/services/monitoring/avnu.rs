@@ -0,0 +1,45 @@
+use anyhow::{Context, Result};
+use serde_json::Value;
+use starknet::core::types::Felt;
+
+const AVNU_QUOTE_ENDPOINT: &str = "https://starknet.api.avnu.fi/swap/v2/quotes";
+
+/// Quotes receiving exactly `amount` (raw) of `to_token` on AVNU & returns the
+/// raw amount of `from_token` its best quote takes.
+pub async fn get_avnu_exact_output_quote(
+    from_token: Felt,
+    to_token: Felt,
+    amount: u128,
+) -> Result<u128> {
+    let response = reqwest::Client::new()
+        .get(AVNU_QUOTE_ENDPOINT)
+        .query(&[
+            ("sellTokenAddress", from_token.to_fixed_hex_string()),
+            ("buyTokenAddress", to_token.to_fixed_hex_string()),
+            ("buyAmount", format!("{amount:#x}")),
+        ])
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        anyhow::bail!("API request failed with status: {}", response.status());
+    }
+
+    let json_value: Value = serde_json::from_str(&response.text().await?)?;
+
+    json_value
+        .as_array()
+        .context("the quotes are not an array")?
+        .iter()
+        .map(|quote| {
+            let sell_amount = quote["sellAmount"]
+                .as_str()
+                .context("sellAmount is not a string")?;
+            u128::from_str_radix(sell_amount.trim_start_matches("0x"), 16)
+                .context("Failed to parse sellAmount as u128")
+        })
+        .collect::<Result<Vec<_>>>()?
+        .into_iter()
+        .min()
+        .context("No quote returned from AVNU API")
+}
@@ -0,0 +1,255 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use num_traits::Pow;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use starknet::core::types::{
+    ExecuteInvocation, Felt, FunctionInvocation, SimulatedTransaction, TransactionTrace,
+};
+use starknet::macros::selector;
+
+use crate::config::onchain_assets::ONCHAIN_ASSETS;
+use crate::types::currency::Currency;
+use crate::types::decimal;
+use crate::types::position::VesuPosition;
+
+/// Outcome of the simulated liquidation of a position.
+#[derive(Debug, Clone, Serialize)]
+pub struct SimulatedLiquidation {
+    pub position_id: String,
+    pub pool: String,
+    pub collateral: String,
+    pub debt: String,
+    #[serde(with = "decimal::string")]
+    pub debt_value_usd: Decimal,
+    #[serde(default, with = "decimal::option_string")]
+    pub fee_usd: Option<Decimal>,
+    /// Value of the collateral left to the liquidator minus the fee.
+    #[serde(default, with = "decimal::option_string")]
+    pub profit_usd: Option<Decimal>,
+    pub revert_reason: Option<String>,
+    pub simulated_at: u64,
+    /// Number of times the position was seen liquidable.
+    pub times_seen: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct ReportSummary {
+    positions: usize,
+    profitable: usize,
+    reverted: usize,
+    #[serde(default, with = "decimal::option_string")]
+    profit_usd_p10: Option<Decimal>,
+    #[serde(default, with = "decimal::option_string")]
+    profit_usd_p25: Option<Decimal>,
+    #[serde(default, with = "decimal::option_string")]
+    profit_usd_p50: Option<Decimal>,
+    #[serde(default, with = "decimal::option_string")]
+    profit_usd_p75: Option<Decimal>,
+    #[serde(default, with = "decimal::option_string")]
+    profit_usd_p90: Option<Decimal>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct Report<'a> {
+    summary: ReportSummary,
+    liquidations: Vec<&'a SimulatedLiquidation>,
+}
+
+/// Records what the liquidations would have yielded, without sending them, to
+/// calibrate the minimum profit from real data.
+#[derive(Debug)]
+pub struct CalibrationReport {
+    path: PathBuf,
+    by_position: HashMap<String, SimulatedLiquidation>,
+}
+
+impl CalibrationReport {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            by_position: HashMap::new(),
+        }
+    }
+
+    /// Records the simulation of a liquidation, replacing the previous one of the
+    /// same position.
+    pub fn record(
+        &mut self,
+        position: &VesuPosition,
+        liquidate_contract: Felt,
+        simulation: Result<SimulatedTransaction>,
+    ) {
+        let position_id = position.position_id();
+        let times_seen = self
+            .by_position
+            .get(&position_id)
+            .map_or(1, |previous| previous.times_seen + 1);
+
+        let (fee_usd, profit_usd, revert_reason) = match simulation {
+            Ok(simulation) => simulation_outcome(&simulation, liquidate_contract),
+            Err(e) => (None, None, Some(e.to_string())),
+        };
+
+        let simulated_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+
+        self.by_position.insert(
+            position_id.clone(),
+            SimulatedLiquidation {
+                position_id,
+                pool: position.pool_name.to_string(),
+                collateral: position.collateral.currency.to_string(),
+                debt: position.debt.currency.to_string(),
+                debt_value_usd: position.debt_value_in_usd(),
+                fee_usd,
+                profit_usd,
+                revert_reason,
+                simulated_at,
+                times_seen,
+            },
+        );
+    }
+
+    /// Writes the report, overwriting the previous one.
+    pub fn write(&self) -> Result<()> {
+        let mut liquidations: Vec<&SimulatedLiquidation> = self.by_position.values().collect();
+        liquidations.sort_by_key(|l| l.simulated_at);
+
+        let mut profits: Vec<Decimal> = liquidations.iter().filter_map(|l| l.profit_usd).collect();
+        profits.sort();
+
+        let summary = ReportSummary {
+            positions: liquidations.len(),
+            profitable: profits.iter().filter(|p| **p > Decimal::ZERO).count(),
+            reverted: liquidations
+                .iter()
+                .filter(|l| l.revert_reason.is_some())
+                .count(),
+            profit_usd_p10: percentile(&profits, 10),
+            profit_usd_p25: percentile(&profits, 25),
+            profit_usd_p50: percentile(&profits, 50),
+            profit_usd_p75: percentile(&profits, 75),
+            profit_usd_p90: percentile(&profits, 90),
+        };
+
+        let file = File::create(&self.path)
+            .with_context(|| format!("Could not create {}", self.path.display()))?;
+        serde_json::to_writer_pretty(
+            BufWriter::new(file),
+            &Report {
+                summary,
+                liquidations,
+            },
+        )?;
+        Ok(())
+    }
+}
+
+/// Returns the (fee, profit, revert reason) of a simulated liquidation.
+pub fn simulation_outcome(
+    simulation: &SimulatedTransaction,
+    liquidate_contract: Felt,
+) -> (Option<Decimal>, Option<Decimal>, Option<String>) {
+    let fee_usd = Decimal::from_str(&simulation.fee_estimation.overall_fee.to_string())
+        .ok()
+        .map(|fee| fee / Decimal::TEN.pow(Currency::STRK.d_decimals()) * Currency::STRK.price());
+
+    let TransactionTrace::Invoke(trace) = &simulation.transaction_trace else {
+        return (fee_usd, None, None);
+    };
+
+    let invocation = match &trace.execute_invocation {
+        ExecuteInvocation::Success(invocation) => invocation,
+        ExecuteInvocation::Reverted(reverted) => {
+            return (fee_usd, None, Some(reverted.revert_reason.clone()));
+        }
+    };
+
+    let residual_usd = find_liquidate_invocation(invocation, liquidate_contract)
+        .and_then(|liquidate| residual_collateral_usd(&liquidate.result));
+
+    let profit_usd = residual_usd.map(|residual| residual - fee_usd.unwrap_or_default());
+    (fee_usd, profit_usd, None)
+}
+
+/// What a simulated liquidation would do, in the assets units.
+#[derive(Debug, Clone, Copy)]
+pub struct SimulatedAmounts {
+    pub collateral_seized: Decimal,
+    pub debt_repaid: Decimal,
+    /// Collateral left to the recipient once the debt is repaid.
+    pub residual: Decimal,
+    pub residual_currency: Currency,
+}
+
+/// Reads the amounts of a successful simulated liquidation of `position`.
+pub fn simulated_amounts(
+    simulation: &SimulatedTransaction,
+    position: &VesuPosition,
+    liquidate_contract: Felt,
+) -> Option<SimulatedAmounts> {
+    let TransactionTrace::Invoke(trace) = &simulation.transaction_trace else {
+        return None;
+    };
+    let ExecuteInvocation::Success(invocation) = &trace.execute_invocation else {
+        return None;
+    };
+    let result = &find_liquidate_invocation(invocation, liquidate_contract)?.result;
+
+    let residual_token = ONCHAIN_ASSETS.get_by_address(result.get(6)?)?;
+    let residual_currency = Currency::from_str(&residual_token.ticker).ok()?;
+    let amount = |index: usize, decimals: Decimal| -> Option<Decimal> {
+        let low = Decimal::from_str(&result.get(index)?.to_string()).ok()?;
+        Some(low / Decimal::TEN.pow(decimals))
+    };
+
+    Some(SimulatedAmounts {
+        collateral_seized: amount(0, position.collateral.decimals)?,
+        debt_repaid: amount(2, position.debt.decimals)?,
+        residual: amount(4, residual_currency.d_decimals())?,
+        residual_currency,
+    })
+}
+
+fn find_liquidate_invocation(
+    invocation: &FunctionInvocation,
+    liquidate_contract: Felt,
+) -> Option<&FunctionInvocation> {
+    if invocation.contract_address == liquidate_contract
+        && invocation.entry_point_selector == selector!("liquidate")
+    {
+        return Some(invocation);
+    }
+    invocation
+        .calls
+        .iter()
+        .find_map(|call| find_liquidate_invocation(call, liquidate_contract))
+}
+
+/// Value of the residual collateral from the serialized `LiquidateResponse`:
+/// (liquidated_collateral: u256, repaid_debt: u256, residual_collateral: u256, residual_token).
+fn residual_collateral_usd(result: &[Felt]) -> Option<Decimal> {
+    let residual_low = Decimal::from_str(&result.get(4)?.to_string()).ok()?;
+    let residual_token = ONCHAIN_ASSETS.get_by_address(result.get(6)?)?;
+    let residual_currency = Currency::from_str(&residual_token.ticker).ok()?;
+
+    let residual = residual_low / Decimal::TEN.pow(Decimal::from(residual_token.decimals));
+    Some(residual * residual_currency.price())
+}
+
+fn percentile(sorted: &[Decimal], pct: usize) -> Option<Decimal> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let index = (sorted.len() - 1) * pct / 100;
+    Some(sorted[index])
+}
@@ -0,0 +1,131 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use num_traits::Pow;
+use rust_decimal::Decimal;
+use starknet::core::types::{Felt, SimulatedTransaction};
+
+use crate::services::monitoring::calibration::simulation_outcome;
+use crate::services::monitoring::ekubo::get_ekubo_quoted_route;
+use crate::services::monitoring::liquidation_error::LiquidationError;
+use crate::services::notifier::NOTIFIER;
+use crate::types::currency::Currency;
+use crate::types::pool::PoolName;
+use crate::types::position::VesuPosition;
+use crate::utils::rpc::{RpcPath, RpcProvider, guarded};
+
+/// Returns the positions whose liquidation gets simulated at startup: the
+/// riskiest one of each pair, for the `limit` riskiest pairs - so that every
+/// simulation covers another route.
+pub fn capacity_check_positions<'a>(
+    positions: impl Iterator<Item = &'a VesuPosition>,
+    limit: usize,
+) -> Vec<VesuPosition> {
+    let mut riskiest: HashMap<(PoolName, Currency, Currency), &VesuPosition> = HashMap::new();
+    for position in positions.filter(|p| !p.is_closed() && !p.debt.amount.is_zero()) {
+        let pair = (
+            position.pool_name,
+            position.collateral.currency,
+            position.debt.currency,
+        );
+        let riskier = riskiest
+            .get(&pair)
+            .is_none_or(|other| position.health_factor() < other.health_factor());
+        if riskier {
+            riskiest.insert(pair, position);
+        }
+    }
+
+    let mut positions: Vec<&VesuPosition> = riskiest.into_values().collect();
+    positions.sort_by_key(|position| position.health_factor());
+    positions.into_iter().take(limit).cloned().collect()
+}
+
+/// Quotes the swap of the liquidation - the collateral of the position into
+/// all its debt - & checks the collateral covers it, whatever the health of
+/// the position: the liquidation of a healthy one reverts before its swap.
+pub async fn route_capacity(position: &VesuPosition) -> Result<()> {
+    let route = guarded(
+        RpcProvider::Ekubo,
+        RpcPath::Background,
+        get_ekubo_quoted_route(
+            position.debt.address,
+            position.collateral.address,
+            &position.debt.amount,
+            position.debt.decimals,
+        ),
+    )
+    .await
+    .map_err(|e| LiquidationError::RouteNotFound {
+        reason: format!("{e:#}"),
+    })?;
+
+    let collateral_in =
+        Decimal::from(route.amount_in) / Decimal::TEN.pow(position.collateral.decimals);
+    anyhow::ensure!(
+        collateral_in <= position.collateral.amount,
+        "the route takes {} for the debt, more than the {} of collateral",
+        position.collateral.currency.format_amount(collateral_in),
+        position
+            .collateral
+            .currency
+            .format_amount(position.collateral.amount)
+    );
+    Ok(())
+}
+
+/// Whether the simulated liquidation proves the pair liquidable by the bot,
+/// given the outcome of its `route_capacity`. A healthy position reverting on
+/// its health check does once its route is checked: the call to the liquidate
+/// contract & the account worked up to it.
+pub fn simulated_capacity(
+    route: Result<()>,
+    simulation: Result<SimulatedTransaction>,
+    liquidate_contract: Felt,
+) -> Result<()> {
+    route?;
+    let reason = match simulation {
+        Ok(simulation) => simulation_outcome(&simulation, liquidate_contract).2,
+        Err(e) => match e.downcast_ref::<LiquidationError>() {
+            Some(LiquidationError::NotUndercollateralized) => None,
+            _ => return Err(e),
+        },
+    };
+    match reason.map(LiquidationError::revert) {
+        None | Some(LiquidationError::NotUndercollateralized) => Ok(()),
+        Some(e) => Err(e.into()),
+    }
+}
+
+/// Logs the outcome of the simulated liquidations & notifies the pairs that
+/// would fail to be liquidated.
+pub fn report_capacity(outcomes: &[(VesuPosition, Result<()>)]) {
+    let mut failed = Vec::new();
+    for (position, outcome) in outcomes {
+        let pair = format!(
+            "{} {}/{}",
+            position.pool_name, position.collateral.currency, position.debt.currency
+        );
+        match outcome {
+            Ok(()) => tracing::info!("[🔭 Monitoring] 🧪 {pair} is liquidable by the bot"),
+            Err(e) => {
+                tracing::error!("[🔭 Monitoring] 🧪 {pair} would fail to be liquidated: {e:#}");
+                failed.push(format!("{pair} ({e:#})"));
+            }
+        }
+    }
+
+    if failed.is_empty() {
+        tracing::info!(
+            "[🔭 Monitoring] 🧪 Simulated the liquidation of {} positions: all their pairs are liquidable",
+            outcomes.len()
+        );
+        return;
+    }
+    NOTIFIER.notify(format!(
+        "The simulated liquidations of {}/{} pairs failed at startup: {}",
+        failed.len(),
+        outcomes.len(),
+        failed.join(", ")
+    ));
+}
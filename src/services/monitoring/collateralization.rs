@@ -0,0 +1,178 @@
+use std::hash::{BuildHasher, RandomState};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, LazyLock};
+
+use anyhow::Result;
+use futures_util::future::join_all;
+use pragma_common::starknet::FallbackProvider;
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use starknet::core::types::{BlockId, BlockTag, Felt, FunctionCall};
+use starknet::macros::selector;
+use starknet::providers::Provider;
+
+use crate::services::notifier::{AlertKind, NOTIFIER};
+use crate::types::position::VesuPosition;
+use crate::utils::rpc::{RpcPath, guarded_starknet};
+
+// Outcomes of the collateralization checks, readable from the API.
+pub static COLLATERALIZATION_CHECKS: LazyLock<Arc<CollateralizationChecks>> =
+    LazyLock::new(|| Arc::new(CollateralizationChecks::default()));
+
+/// How many positions get checked against the pool & how far their LTV may be.
+#[derive(Debug, Clone, Copy)]
+pub struct CollateralizationCheckConfig {
+    /// Number of positions with debt checked at each round.
+    pub sample_size: usize,
+    /// Maximum relative difference between the local & on-chain LTVs, in bps.
+    pub tolerance_bps: Decimal,
+}
+
+/// What the pool says of a position in `check_collateralization`.
+#[derive(Debug, Clone, Copy)]
+struct OnchainCollateralization {
+    is_collateralized: bool,
+    /// Both values share the scale of the oracle of the pool.
+    collateral_value: Decimal,
+    debt_value: Decimal,
+}
+
+impl OnchainCollateralization {
+    fn ltv(&self) -> Option<Decimal> {
+        (!self.collateral_value.is_zero()).then(|| self.debt_value / self.collateral_value)
+    }
+}
+
+/// Counts the positions checked against the pool & the ones disagreeing.
+#[derive(Debug, Default)]
+pub struct CollateralizationChecks {
+    checked: AtomicU64,
+    disagreements: AtomicU64,
+}
+
+impl CollateralizationChecks {
+    /// Checks a random sample of the positions with debt against the
+    /// `check_collateralization` of their pool, alerting on the ones whose
+    /// local LTV or liquidability disagrees with it - catching a drift of the
+    /// local state or of the pricing early.
+    /// NOTE: The pool reads its oracle at the head while the positions are at
+    /// the last indexed block, so a tolerance is needed.
+    pub async fn check<'a>(
+        &self,
+        provider: &FallbackProvider,
+        positions: impl Iterator<Item = &'a VesuPosition>,
+        config: CollateralizationCheckConfig,
+    ) {
+        let sample = Self::sample(positions, config.sample_size);
+        let reads = sample
+            .iter()
+            .map(|position| read_collateralization(provider, position));
+
+        for (position, onchain) in sample.iter().zip(join_all(reads).await) {
+            let onchain = match onchain {
+                Ok(onchain) => onchain,
+                Err(e) => {
+                    tracing::debug!(
+                        "[🔭 Monitoring] Could not check the collateralization of {position}: {e}"
+                    );
+                    continue;
+                }
+            };
+            self.checked.fetch_add(1, Ordering::Relaxed);
+
+            let position_id = position.position_id();
+            let local_ltv = position.ltv();
+            let is_liquidable = position.health_factor() <= Decimal::ONE;
+            let drift_bps = onchain
+                .ltv()
+                .filter(|ltv| !ltv.is_zero())
+                .map(|ltv| ((local_ltv - ltv) / ltv).abs() * dec!(10_000));
+
+            let disagrees = is_liquidable == onchain.is_collateralized
+                || drift_bps.is_some_and(|drift_bps| drift_bps > config.tolerance_bps);
+            if !disagrees {
+                NOTIFIER.resolve(AlertKind::LtvDrift, &position_id, || {
+                    format!("The LTV of {position} agrees with its pool again")
+                });
+                continue;
+            }
+
+            self.disagreements.fetch_add(1, Ordering::Relaxed);
+            let onchain_ltv = onchain
+                .ltv()
+                .map_or_else(|| "none".into(), |ltv| ltv.round_dp(4).to_string());
+            let message = format!(
+                "The LTV of {position} is {} locally but {onchain_ltv} for its pool ({}), the local state or pricing may have drifted",
+                local_ltv.round_dp(4),
+                if onchain.is_collateralized {
+                    "collateralized"
+                } else {
+                    "undercollateralized"
+                }
+            );
+            tracing::warn!("[🔭 Monitoring] 🧮 {message}");
+            NOTIFIER.alert(AlertKind::LtvDrift, &position_id, || message);
+        }
+    }
+
+    /// Picks up to `size` random positions with debt.
+    fn sample<'a>(
+        positions: impl Iterator<Item = &'a VesuPosition>,
+        size: usize,
+    ) -> Vec<&'a VesuPosition> {
+        let random = RandomState::new();
+        let mut positions: Vec<&VesuPosition> = positions
+            .filter(|position| !position.is_closed() && !position.debt.amount.is_zero())
+            .collect();
+        positions.sort_by_cached_key(|position| random.hash_one(position.position_id()));
+        positions.truncate(size);
+        positions
+    }
+
+    pub fn prometheus_metric(&self) -> String {
+        format!(
+            "# HELP collateralization_checks_total Positions checked against the collateralization check of their pool.\n\
+             # TYPE collateralization_checks_total counter\n\
+             collateralization_checks_total {}\n\
+             # HELP collateralization_disagreements_total Checked positions whose local LTV disagreed with their pool.\n\
+             # TYPE collateralization_disagreements_total counter\n\
+             collateralization_disagreements_total {}\n",
+            self.checked.load(Ordering::Relaxed),
+            self.disagreements.load(Ordering::Relaxed),
+        )
+    }
+}
+
+/// Calls `check_collateralization(collateral, debt, user)` of the pool of the
+/// position, returning (bool, collateral_value: u256, debt_value: u256).
+async fn read_collateralization(
+    provider: &FallbackProvider,
+    position: &VesuPosition,
+) -> Result<OnchainCollateralization> {
+    let request = FunctionCall {
+        contract_address: position.pool_name.pool_address(),
+        entry_point_selector: selector!("check_collateralization"),
+        calldata: vec![
+            position.collateral.address,
+            position.debt.address,
+            position.user_address,
+        ],
+    };
+    let call_result = guarded_starknet(provider, RpcPath::Background, |node| {
+        node.call(request.clone(), BlockId::Tag(BlockTag::Latest))
+    })
+    .await?;
+    anyhow::ensure!(
+        call_result.len() >= 5,
+        "Unexpected collateralization result for user {:#x}",
+        position.user_address
+    );
+
+    Ok(OnchainCollateralization {
+        is_collateralized: call_result[0] != Felt::ZERO,
+        // The low parts of the u256, enough for the values of the positions.
+        collateral_value: Decimal::from_str(&call_result[1].to_string())?,
+        debt_value: Decimal::from_str(&call_result[3].to_string())?,
+    })
+}
@@ -0,0 +1,133 @@
+use std::collections::VecDeque;
+
+use anyhow::Result;
+use pragma_common::starknet::FallbackProvider;
+use starknet::core::types::{Felt, InvokeTransaction, Transaction};
+use starknet::providers::Provider;
+
+/// What a competitor paid for a liquidation it won.
+#[derive(Debug, Clone)]
+pub struct CompetitorLiquidation {
+    pub tx_hash: Felt,
+    pub sender: Felt,
+    pub block_number: u64,
+    pub tip: u64,
+    pub l1_gas_max_price: u128,
+    pub l1_data_gas_max_price: u128,
+    pub l2_gas_max_price: u128,
+}
+
+/// The tip & L2 gas price we should use to win the liquidations against the
+/// observed competitors.
+#[derive(Debug, Clone)]
+pub struct TipRecommendation {
+    pub tip: u64,
+    pub l2_gas_max_price: u128,
+    pub samples: usize,
+}
+
+/// Records the resources paid by the competitors winning liquidations.
+#[derive(Debug)]
+pub struct CompetitorTracker {
+    own_address: Felt,
+    pending: VecDeque<(Felt, u64)>,
+    observed: VecDeque<CompetitorLiquidation>,
+}
+
+impl CompetitorTracker {
+    /// Number of liquidations kept to build the recommendation.
+    const CAPACITY: usize = 100;
+    /// Percentile of the competitors we want to outbid.
+    const TARGET_PERCENTILE: usize = 90;
+
+    pub fn new(own_address: Felt) -> Self {
+        Self {
+            own_address,
+            pending: VecDeque::with_capacity(Self::CAPACITY),
+            observed: VecDeque::with_capacity(Self::CAPACITY),
+        }
+    }
+
+    /// Queues a liquidation tx to analyze. Only the most recent ones are kept.
+    pub fn queue(&mut self, tx_hash: Felt, block_number: u64) {
+        if self.pending.len() == Self::CAPACITY {
+            self.pending.pop_front();
+        }
+        self.pending.push_back((tx_hash, block_number));
+    }
+
+    /// Fetches the queued liquidations & records what the competitors paid.
+    pub async fn analyze_pending(&mut self, provider: &FallbackProvider) {
+        while let Some((tx_hash, block_number)) = self.pending.pop_front() {
+            match self.fetch(provider, tx_hash, block_number).await {
+                Ok(Some(liquidation)) => {
+                    tracing::info!(
+                        "[🔭 Monitoring] 🥷 Competitor {:#x} won a liquidation (tx {tx_hash:#064x}) with a tip of {} & a L2 gas price of {}",
+                        liquidation.sender,
+                        liquidation.tip,
+                        liquidation.l2_gas_max_price
+                    );
+                    if self.observed.len() == Self::CAPACITY {
+                        self.observed.pop_front();
+                    }
+                    self.observed.push_back(liquidation);
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    tracing::debug!(
+                        "[🔭 Monitoring] Could not fetch the liquidation tx {tx_hash:#064x}: {e}"
+                    );
+                }
+            }
+        }
+    }
+
+    /// Returns the competitor liquidation of the tx, None if it was ours or
+    /// not a V3 invoke.
+    async fn fetch(
+        &self,
+        provider: &FallbackProvider,
+        tx_hash: Felt,
+        block_number: u64,
+    ) -> Result<Option<CompetitorLiquidation>> {
+        let Transaction::Invoke(InvokeTransaction::V3(tx)) =
+            provider.get_transaction_by_hash(tx_hash).await?
+        else {
+            return Ok(None);
+        };
+
+        if tx.sender_address == self.own_address {
+            return Ok(None);
+        }
+
+        Ok(Some(CompetitorLiquidation {
+            tx_hash,
+            sender: tx.sender_address,
+            block_number,
+            tip: tx.tip,
+            l1_gas_max_price: tx.resource_bounds.l1_gas.max_price_per_unit,
+            l1_data_gas_max_price: tx.resource_bounds.l1_data_gas.max_price_per_unit,
+            l2_gas_max_price: tx.resource_bounds.l2_gas.max_price_per_unit,
+        }))
+    }
+
+    /// Recommends outbidding `TARGET_PERCENTILE`% of the observed competitors.
+    pub fn recommendation(&self) -> Option<TipRecommendation> {
+        if self.observed.is_empty() {
+            return None;
+        }
+
+        let mut tips: Vec<u64> = self.observed.iter().map(|l| l.tip).collect();
+        tips.sort_unstable();
+        let mut l2_gas_prices: Vec<u128> =
+            self.observed.iter().map(|l| l.l2_gas_max_price).collect();
+        l2_gas_prices.sort_unstable();
+
+        let index = (self.observed.len() - 1) * Self::TARGET_PERCENTILE / 100;
+        Some(TipRecommendation {
+            tip: tips[index].saturating_add(1),
+            l2_gas_max_price: l2_gas_prices[index],
+            samples: self.observed.len(),
+        })
+    }
+}
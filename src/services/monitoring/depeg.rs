@@ -0,0 +1,82 @@
+use std::collections::HashSet;
+
+use rust_decimal::Decimal;
+use strum::IntoEnumIterator;
+
+use crate::services::notifier::{AlertKind, NOTIFIER};
+use crate::types::currency::Currency;
+use crate::types::position::VesuPosition;
+
+#[derive(Debug, Clone)]
+pub struct DepegConfig {
+    /// Minimum accepted USD price of a stable asset.
+    pub min_price: Decimal,
+    /// Maximum accepted USD price of a stable asset.
+    pub max_price: Decimal,
+    /// If true, positions involving a depegged stable asset don't get liquidated.
+    pub pause_on_depeg: bool,
+}
+
+/// Sanity checks of the stable assets prices: the profitability & slippage math
+/// changes drastically when one of them depegs.
+#[derive(Debug)]
+pub struct DepegGuard {
+    config: DepegConfig,
+    depegged: HashSet<Currency>,
+}
+
+impl DepegGuard {
+    pub fn new(config: DepegConfig) -> Self {
+        Self {
+            config,
+            depegged: HashSet::new(),
+        }
+    }
+
+    /// Checks the current price of every stable asset & alerts when one of them
+    /// depegs or gets back to its peg.
+    pub fn update(&mut self) {
+        for currency in Currency::iter().filter(Currency::is_stable) {
+            let price = currency.price();
+            let is_depegged = price < self.config.min_price || price > self.config.max_price;
+
+            if is_depegged && self.depegged.insert(currency) {
+                tracing::error!(
+                    "[🔭 Monitoring] 🚨 {currency} depegged: ${price} is out of [{}, {}]{}",
+                    self.config.min_price,
+                    self.config.max_price,
+                    if self.config.pause_on_depeg {
+                        " - pausing its liquidations"
+                    } else {
+                        ""
+                    }
+                );
+            } else if !is_depegged && self.depegged.remove(&currency) {
+                tracing::info!("[🔭 Monitoring] {currency} is back to its peg (${price})");
+                NOTIFIER.resolve(AlertKind::Depeg, &currency.to_string(), || {
+                    format!("{currency} is back to its peg (${price})")
+                });
+            }
+
+            if is_depegged {
+                NOTIFIER.alert(AlertKind::Depeg, &currency.to_string(), || {
+                    format!(
+                        "{currency} depegged: ${price} is out of [{}, {}]",
+                        self.config.min_price, self.config.max_price
+                    )
+                });
+            }
+        }
+    }
+
+    /// Returns true if the liquidation of the position must be paused.
+    pub fn is_paused(&self, position: &VesuPosition) -> bool {
+        self.config.pause_on_depeg
+            && (self.depegged.contains(&position.collateral.currency)
+                || self.depegged.contains(&position.debt.currency))
+    }
+
+    pub fn depegged(&self) -> &HashSet<Currency> {
+        &self.depegged
+    }
+}
@@ -0,0 +1,112 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use anyhow::Result;
+use futures_util::future::join_all;
+use num_traits::Pow;
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+
+use crate::config::onchain_assets::AssetClass;
+use crate::services::monitoring::ekubo::get_ekubo_exact_output_quote;
+use crate::types::position::VesuPosition;
+use crate::utils::rpc::{RpcPath, RpcProvider, guarded};
+
+/// Caps the debt repaid by a liquidation to what the Ekubo pools of its route
+/// can absorb: the collateral => debt swap must stay below a price impact.
+/// A capped liquidation is partial, the rest of the debt gets liquidated at the
+/// next checks once the pools recovered.
+#[derive(Debug, Clone, Default)]
+pub struct DepthCap {
+    /// Applies to the collaterals without a maximum for their class. None for
+    /// no cap.
+    pub max_price_impact_bps: Option<Decimal>,
+    /// Maximum price impact per class of the collateral.
+    pub per_class: HashMap<AssetClass, Decimal>,
+}
+
+impl DepthCap {
+    /// Number of times the debt to repay is halved before giving up.
+    const MAX_HALVINGS: usize = 5;
+
+    /// The maximum price impact of the swap of the collateral of the position.
+    pub fn max_price_impact_bps(&self, position: &VesuPosition) -> Option<Decimal> {
+        self.per_class
+            .get(&position.collateral.currency.class())
+            .copied()
+            .or(self.max_price_impact_bps)
+    }
+
+    /// Returns the debt to repay, halved until the price impact of its swap is
+    /// below the maximum. None means repaying all the debt, as for the input.
+    /// All the halvings are quoted at once, not to wait for each of them.
+    pub async fn apply(
+        &self,
+        position: &VesuPosition,
+        debt_to_repay: Option<Decimal>,
+    ) -> Result<Option<Decimal>> {
+        let Some(max_price_impact_bps) = self.max_price_impact_bps(position) else {
+            return Ok(debt_to_repay);
+        };
+        let amount = debt_to_repay.unwrap_or(position.debt.amount);
+        let amounts: Vec<Decimal> = (0..=Self::MAX_HALVINGS)
+            .map(|halvings| amount / Decimal::from(1u64 << halvings))
+            .collect();
+        let impacts = join_all(
+            amounts
+                .iter()
+                .map(|amount| price_impact_bps(position, *amount)),
+        )
+        .await;
+
+        for (halvings, (amount, impact_bps)) in amounts.into_iter().zip(impacts).enumerate() {
+            let impact_bps = impact_bps?;
+            if impact_bps <= max_price_impact_bps {
+                if halvings == 0 {
+                    return Ok(debt_to_repay);
+                }
+                tracing::info!(
+                    "[🔭 Monitoring] 🌊 Repaying {} of the debt of {position}, a price impact of {impact_bps:.0} bps",
+                    position.debt.currency.format_amount(amount)
+                );
+                return Ok(Some(amount));
+            }
+        }
+
+        anyhow::bail!(
+            "swapping 1/{} of its debt still has a price impact above {} bps",
+            1 << Self::MAX_HALVINGS,
+            max_price_impact_bps
+        )
+    }
+}
+
+/// Price impact, in bps, of swapping the collateral into `debt_amount` of the
+/// debt asset on Ekubo, against the prices of the oracle. Fees included.
+async fn price_impact_bps(position: &VesuPosition, debt_amount: Decimal) -> Result<Decimal> {
+    let raw_amount: u128 = (debt_amount * Decimal::TEN.pow(position.debt.decimals))
+        .trunc()
+        .try_into()?;
+    let raw_collateral_in = guarded(
+        RpcProvider::Ekubo,
+        RpcPath::Liquidation,
+        get_ekubo_exact_output_quote(
+            position.collateral.address,
+            position.debt.address,
+            raw_amount,
+        ),
+    )
+    .await?;
+
+    let collateral_in = Decimal::from_str(&raw_collateral_in.to_string())?
+        / Decimal::TEN.pow(position.collateral.decimals);
+    let debt_value = debt_amount * position.debt.currency.price();
+    anyhow::ensure!(
+        !debt_value.is_zero(),
+        "no price for {}",
+        position.debt.currency
+    );
+
+    let collateral_value = collateral_in * position.collateral.currency.price();
+    Ok((collateral_value / debt_value - Decimal::ONE) * dec!(10_000))
+}
@@ -2,10 +2,12 @@ use anyhow::{Context, Result};
 use cainome::cairo_serde::{ContractAddress, U256};
 use num_traits::Pow;
 use rust_decimal::Decimal;
+use serde::Serialize;
 use serde_json::Value;
 use starknet::core::types::Felt;
 
-use crate::bindings::liquidate::{I129, PoolKey, RouteNode, Swap, TokenAmount};
+use crate::bindings::liquidate_v1::{I129, PoolKey, RouteNode, Swap, TokenAmount};
+use crate::types::decimal;
 
 const EKUBO_QUOTE_ENDPOINT: &str = "https://quoter-mainnet-api.ekubo.org";
 const SCALE: u128 = 1_000_000_000_000_000_000;
@@ -16,6 +18,27 @@ pub async fn get_ekubo_route(
     amount: &Decimal,
     decimals: Decimal,
 ) -> Result<(Vec<Swap>, Vec<u128>)> {
+    let route = get_ekubo_quoted_route(from_token, to_token, amount, decimals).await?;
+    Ok((route.swaps, route.weights))
+}
+
+/// A route of `get_ekubo_quoted_route`, along with its quote.
+#[derive(Debug, Clone)]
+pub struct QuotedRoute {
+    pub swaps: Vec<Swap>,
+    pub weights: Vec<u128>,
+    /// Raw amount of `to_token` the route takes to buy the amount of
+    /// `from_token`.
+    pub amount_in: u128,
+}
+
+/// Same as `get_ekubo_route`, also returning the amount the route takes.
+pub async fn get_ekubo_quoted_route(
+    from_token: Felt,
+    to_token: Felt,
+    amount: &Decimal,
+    decimals: Decimal,
+) -> Result<QuotedRoute> {
     let amount = amount * Decimal::TEN.pow(decimals);
 
     let amount: u128 = amount.try_into().expect("Should fit in a u128 :)");
@@ -37,6 +60,12 @@ pub async fn get_ekubo_route(
     let response_text = response.text().await?;
     let json_value: Value = serde_json::from_str(&response_text)?;
 
+    let amount_in = json_value["total_calculated"]
+        .as_str()
+        .context("total_calculated is not a string")?
+        .parse::<i128>()?
+        .unsigned_abs();
+
     let splits = json_value["splits"]
         .as_array()
         .context("'splits' is not an array")?;
@@ -48,8 +77,8 @@ pub async fn get_ekubo_route(
     // Handle single split case (100% weight)
     if splits.len() == 1 {
         let route = parse_route(&splits[0])?;
-        return Ok((
-            vec![Swap {
+        return Ok(QuotedRoute {
+            swaps: vec![Swap {
                 route,
                 token_amount: TokenAmount {
                     token: ContractAddress(from_token),
@@ -59,8 +88,9 @@ pub async fn get_ekubo_route(
                     },
                 },
             }],
-            vec![SCALE], // Single weight of 100%
-        ));
+            weights: vec![SCALE], // Single weight of 100%
+            amount_in,
+        });
     }
 
     // Calculate total amount for weight calculation
@@ -124,7 +154,105 @@ pub async fn get_ekubo_route(
     let total_weight: u128 = weights.iter().sum();
     assert!(total_weight == SCALE, "Weights do not sum to SCALE");
 
-    Ok((swaps, weights))
+    Ok(QuotedRoute {
+        swaps,
+        weights,
+        amount_in,
+    })
+}
+
+/// Quotes receiving exactly `amount` (raw) of `to_token` & returns the raw
+/// amount of `from_token` the swap takes.
+pub async fn get_ekubo_exact_output_quote(
+    from_token: Felt,
+    to_token: Felt,
+    amount: u128,
+) -> Result<u128> {
+    let ekubo_api_endpoint = format!(
+        "{EKUBO_QUOTE_ENDPOINT}/-{amount}/{}/{}",
+        to_token.to_fixed_hex_string(),
+        from_token.to_fixed_hex_string()
+    );
+
+    let response = reqwest::Client::new()
+        .get(ekubo_api_endpoint)
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        anyhow::bail!("API request failed with status: {}", response.status());
+    }
+
+    let json_value: Value = serde_json::from_str(&response.text().await?)?;
+
+    Ok(json_value["total_calculated"]
+        .as_str()
+        .context("total_calculated is not a string")?
+        .parse::<i128>()?
+        .unsigned_abs())
+}
+
+/// Quotes selling exactly `amount` (raw) of `from_token` for `to_token`.
+/// Returns the swaps to send to the Ekubo router, along with the expected
+/// raw amount of `to_token` received.
+pub async fn get_ekubo_exact_input_swaps(
+    from_token: Felt,
+    to_token: Felt,
+    amount: u128,
+) -> Result<(Vec<Swap>, u128)> {
+    let ekubo_api_endpoint = format!(
+        "{EKUBO_QUOTE_ENDPOINT}/{amount}/{}/{}",
+        from_token.to_fixed_hex_string(),
+        to_token.to_fixed_hex_string()
+    );
+
+    let response = reqwest::Client::new()
+        .get(ekubo_api_endpoint)
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        anyhow::bail!("API request failed with status: {}", response.status());
+    }
+
+    let json_value: Value = serde_json::from_str(&response.text().await?)?;
+
+    let expected_output = json_value["total_calculated"]
+        .as_str()
+        .context("total_calculated is not a string")?
+        .parse::<i128>()?
+        .unsigned_abs();
+
+    let splits = json_value["splits"]
+        .as_array()
+        .context("'splits' is not an array")?;
+
+    if splits.is_empty() {
+        anyhow::bail!("No splits returned from Ekubo API");
+    }
+
+    let swaps = splits
+        .iter()
+        .map(|split| {
+            let split_amount = split["amount_specified"]
+                .as_str()
+                .context("amount_specified is not a string")?
+                .parse::<i128>()?;
+
+            Ok(Swap {
+                route: parse_route(split)?,
+                token_amount: TokenAmount {
+                    token: ContractAddress(from_token),
+                    amount: I129 {
+                        mag: split_amount.unsigned_abs(),
+                        sign: split_amount.is_negative(),
+                    },
+                },
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok((swaps, expected_output))
 }
 
 fn parse_route(split: &Value) -> Result<Vec<RouteNode>> {
@@ -178,3 +306,87 @@ fn parse_route(split: &Value) -> Result<Vec<RouteNode>> {
         })
         .collect()
 }
+
+/// A hop of a swap route: the Ekubo pool it goes through.
+#[derive(Debug, Clone, Serialize)]
+pub struct RouteHop {
+    pub token0: String,
+    pub token1: String,
+    pub fee: String,
+    pub tick_spacing: u128,
+    pub extension: String,
+    /// The price limit of the hop, bounding its slippage.
+    pub sqrt_ratio_limit: String,
+}
+
+/// A split of a swap route & the share of the amount it swaps.
+#[derive(Debug, Clone, Serialize)]
+pub struct RouteSplit {
+    #[serde(with = "decimal::string")]
+    pub weight_pct: Decimal,
+    pub hops: Vec<RouteHop>,
+}
+
+/// Describes the swaps & weights of `get_ekubo_route`, e.g for the systems
+/// executing the liquidations themselves.
+pub fn describe_route(swaps: &[Swap], weights: &[u128]) -> Vec<RouteSplit> {
+    swaps
+        .iter()
+        .zip(weights)
+        .map(|(swap, weight)| RouteSplit {
+            weight_pct: Decimal::from(*weight) * Decimal::ONE_HUNDRED / Decimal::from(SCALE),
+            hops: swap
+                .route
+                .iter()
+                .map(|node| RouteHop {
+                    token0: node.pool_key.token0.0.to_fixed_hex_string(),
+                    token1: node.pool_key.token1.0.to_fixed_hex_string(),
+                    fee: format!("{:#x}", node.pool_key.fee),
+                    tick_spacing: node.pool_key.tick_spacing,
+                    extension: node.pool_key.extension.0.to_fixed_hex_string(),
+                    sqrt_ratio_limit: if node.sqrt_ratio_limit.high == 0 {
+                        format!("{:#x}", node.sqrt_ratio_limit.low)
+                    } else {
+                        format!(
+                            "{:#x}{:032x}",
+                            node.sqrt_ratio_limit.high, node.sqrt_ratio_limit.low
+                        )
+                    },
+                })
+                .collect(),
+        })
+        .collect()
+}
+
+/// The swap of a liquidation as quoted when building it, to audit the
+/// execution of every trade.
+#[derive(Debug, Clone, Serialize)]
+pub struct LiquidationQuote {
+    /// The splits & hops of the route, each hop bounded by its price limit.
+    pub route: Vec<RouteSplit>,
+    /// Collateral the route takes for the debt repaid, in units of the
+    /// collateral.
+    #[serde(with = "decimal::string")]
+    pub collateral_in: Decimal,
+    /// Debt the route buys, in units of the debt.
+    #[serde(with = "decimal::string")]
+    pub debt_out: Decimal,
+}
+
+impl LiquidationQuote {
+    /// Summary of the route, e.g `60% 2 hops, 40% 1 hop`.
+    pub fn route_summary(&self) -> String {
+        self.route
+            .iter()
+            .map(|split| {
+                format!(
+                    "{}% {} hop{}",
+                    split.weight_pct.round_dp(1),
+                    split.hops.len(),
+                    if split.hops.len() == 1 { "" } else { "s" }
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
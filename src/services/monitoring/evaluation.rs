@@ -0,0 +1,65 @@
+use std::sync::Arc;
+
+use futures_util::future::join_all;
+use rust_decimal::Decimal;
+
+use crate::services::monitoring::protect::ProtectConfig;
+use crate::types::pool::PoolName;
+use crate::types::position::VesuPosition;
+
+/// Number of positions evaluated per blocking task.
+const CHUNK_SIZE: usize = 512;
+
+/// The health of a position, computed off the monitoring loop.
+#[derive(Debug, Clone)]
+pub struct HealthEvaluation {
+    pub key: (PoolName, String),
+    pub ltv: Decimal,
+    pub health_factor: Decimal,
+    pub is_liquidable: bool,
+    /// Liquidable or almost, logged in detail & given to the strategy.
+    pub is_at_risk: bool,
+    /// Debt to repay if it's one of our positions past its deleverage trigger.
+    pub deleverage: Option<Decimal>,
+}
+
+/// Evaluates the health of the positions in parallel blocking tasks, by chunks,
+/// so the Decimal math of thousands of positions does not stall the monitoring
+/// loop. The evaluations are returned in the order of the positions.
+pub async fn evaluate_health(
+    positions: Vec<((PoolName, String), VesuPosition)>,
+    protect: Option<Arc<ProtectConfig>>,
+) -> Vec<HealthEvaluation> {
+    let mut chunks = Vec::new();
+    let mut positions = positions.into_iter().peekable();
+    while positions.peek().is_some() {
+        let chunk: Vec<_> = positions.by_ref().take(CHUNK_SIZE).collect();
+        let protect = protect.clone();
+        chunks.push(tokio::task::spawn_blocking(move || {
+            chunk
+                .into_iter()
+                .map(|(key, position)| HealthEvaluation {
+                    key,
+                    ltv: position.ltv(),
+                    health_factor: position.health_factor(),
+                    is_liquidable: position.is_liquidable(),
+                    is_at_risk: position.is_at_risk(),
+                    deleverage: protect
+                        .as_ref()
+                        .and_then(|protect| protect.debt_to_repay(&position)),
+                })
+                .collect::<Vec<_>>()
+        }));
+    }
+
+    let mut evaluations = Vec::new();
+    for chunk in join_all(chunks).await {
+        match chunk {
+            Ok(chunk) => evaluations.extend(chunk),
+            Err(e) => {
+                tracing::error!("[🔭 Monitoring] Could not evaluate a chunk of positions: {e}")
+            }
+        }
+    }
+    evaluations
+}
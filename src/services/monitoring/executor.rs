@@ -0,0 +1,1373 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use futures_util::future::join_all;
+use num_traits::Pow;
+use pragma_common::starknet::FallbackProvider;
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use serde_json::json;
+use starknet::core::types::{BlockId, BlockTag, Call, ExecutionResult, Felt, StarknetError};
+use starknet::providers::{Provider, ProviderError};
+use tokio::sync::mpsc;
+use tracing::Instrument;
+use uuid::Uuid;
+
+use crate::config::onchain_assets::AssetClass;
+use crate::services::audit::{AuditEvent, AuditLog, TxPurpose};
+use crate::services::monitoring::calibration::{CalibrationReport, simulation_outcome};
+use crate::services::monitoring::capacity::{report_capacity, route_capacity, simulated_capacity};
+use crate::services::monitoring::depth::DepthCap;
+use crate::services::monitoring::ekubo::LiquidationQuote;
+use crate::services::monitoring::fee_cache::{FeeCache, FeeEstimates, GasPrices, TransactionFee};
+use crate::services::monitoring::in_flight::InFlightLiquidations;
+use crate::services::monitoring::inventory::{InventoryConfig, inventory_liquidation_call};
+use crate::services::monitoring::latency::{LIQUIDATION_LATENCY, Stage, StageTimings};
+use crate::services::monitoring::liquidation_error::{LIQUIDATION_ERRORS, LiquidationError};
+use crate::services::monitoring::post_mortem::report_lost_race_in_background;
+use crate::services::monitoring::prechecks::{AccountPrechecks, PrecheckConfig, RequiredAllowance};
+use crate::services::monitoring::protect::{DeleverageIntent, deleverage_calls};
+use crate::services::monitoring::receipt::{RealizedLiquidation, Repayment, realized_liquidation};
+use crate::services::monitoring::route_quotes::compare_routes_in_background;
+use crate::services::monitoring::strategy::{LiquidationDecision, apply_debt_floor};
+use crate::services::monitoring::utilization::POOL_RATES;
+use crate::services::monitoring::watchlist::{LiquidationStatus, WATCHLIST};
+use crate::services::notifier::NOTIFIER;
+use crate::types::account::StarknetAccount;
+use crate::types::currency::Currency;
+use crate::types::liquidate_contract::LiquidateContracts;
+use crate::types::position::{Asset, VesuPosition};
+use crate::utils::erc20::balance_of;
+use crate::utils::format::format_usd;
+use crate::utils::kill_switch::KillSwitch;
+use crate::utils::rpc::{RpcPath, guarded_starknet};
+
+/// A liquidation of ours confirmed on-chain, reported back to the monitoring.
+#[derive(Debug, Clone)]
+pub struct ConfirmedLiquidation {
+    pub position_id: String,
+    pub tx_hash: Felt,
+    /// Block of the transaction.
+    pub block_number: u64,
+}
+
+/// A position the monitoring wants liquidated.
+#[derive(Debug, Clone)]
+pub struct LiquidationIntent {
+    /// Shared by the intents of a position while it stays liquidable, so its
+    /// retries, re-queues & sends can be correlated in the logs & the API.
+    pub id: Uuid,
+    pub position: VesuPosition,
+    /// If None, all the debt gets repaid.
+    pub debt_to_repay: Option<Decimal>,
+    /// Why the position gets liquidated, for the logs.
+    pub context: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct ExecutorConfig {
+    /// Maximum number of liquidations batched in a single transaction.
+    pub max_liquidations_per_tx: usize,
+    /// Receives the seized collateral. The signer if not set, or for the
+    /// inventory liquidations.
+    pub recipient: Option<Felt>,
+    /// If set, caps the debt repaid to what the swap route can absorb.
+    pub depth_cap: Option<DepthCap>,
+    /// Minimum profit of a liquidation per class of its collateral, in USD. The
+    /// liquidations of these collaterals are simulated to read their profit.
+    pub min_profit_usd: HashMap<AssetClass, Decimal>,
+    /// Minimum profit of a liquidation per class of its collateral, in bps of
+    /// the debt it repays. Combined with `min_profit_usd`, the highest applies.
+    pub min_profit_bps: HashMap<AssetClass, Decimal>,
+    /// The debt assets repaid from the balance of the signer when it's enough.
+    pub inventory: InventoryConfig,
+    pub prechecks: PrecheckConfig,
+    pub kill_switch: KillSwitch,
+    /// If set, the liquidations are simulated instead of sent & a calibration
+    /// report is written to this path.
+    pub simulate_report: Option<PathBuf>,
+    /// The fee of an intent is estimated again once a gas price moved by more
+    /// than this since its estimation, in bps.
+    pub fee_refresh_threshold_bps: Decimal,
+    /// Budget of a liquidation attempt until its send: the route, the
+    /// simulation, the pre-checks & the fee estimation. None for no budget.
+    pub attempt_budget: Option<Duration>,
+    /// Maximum number of liquidation transactions of the account pending at
+    /// once, None for no limit.
+    pub max_inflight_txs: Option<usize>,
+}
+
+/// A liquidation built & ready to be sent.
+#[derive(Clone)]
+struct PreparedLiquidation {
+    intent_id: Uuid,
+    position: VesuPosition,
+    calls: Vec<Call>,
+    /// Debt repaid, None for all of it.
+    debt_to_repay: Option<Decimal>,
+    /// Allowance of the signer the calls need.
+    allowance: Option<RequiredAllowance>,
+    /// The approval of the allowance when the signer misses it, sent in the
+    /// same transaction, before the calls.
+    approval: Option<Call>,
+    /// Repaid from the inventory instead of a swap of the collateral.
+    from_inventory: bool,
+    /// The swap of the collateral into the debt, None from the inventory.
+    quote: Option<LiquidationQuote>,
+    /// Profit of the simulated liquidation, in USD - None if not simulated.
+    expected_profit_usd: Option<Decimal>,
+    /// When the attempt runs out of its budget, None for no budget.
+    deadline: Option<tokio::time::Instant>,
+    timings: StageTimings,
+}
+
+impl PreparedLiquidation {
+    /// The calls to simulate, approving the allowance they need first.
+    fn simulation_calls(&self) -> anyhow::Result<Vec<Call>> {
+        let mut calls = Vec::with_capacity(self.calls.len() + 1);
+        if let Some(allowance) = &self.allowance {
+            calls.push(allowance.approve_call()?);
+        }
+        calls.extend(self.calls.iter().cloned());
+        Ok(calls)
+    }
+
+    /// The calls to send, preceded by the approval they miss.
+    fn sent_calls(&self) -> Vec<Call> {
+        self.approval
+            .iter()
+            .chain(self.calls.iter())
+            .cloned()
+            .collect()
+    }
+}
+
+/// The liquidation built by an attempt that ran out of its budget, reused by
+/// the next attempt of its intent.
+struct CachedLiquidation {
+    liquidation: PreparedLiquidation,
+    /// The debt to repay of the intent, the route being built for it.
+    debt_to_repay: Option<Decimal>,
+    cached_at: Instant,
+}
+
+/// The attempts that ran out of their budget, with the liquidation they built
+/// if they got past the route.
+#[derive(Default)]
+struct TimedOutAttempts {
+    routes: HashMap<Uuid, Option<PreparedLiquidation>>,
+}
+
+impl TimedOutAttempts {
+    /// Records the attempt if the error is its timeout, returning whether it is.
+    fn record(
+        &mut self,
+        e: &anyhow::Error,
+        intent_id: Uuid,
+        liquidation: Option<PreparedLiquidation>,
+    ) -> bool {
+        let is_timeout = is_attempt_timeout(e);
+        if is_timeout {
+            self.routes.insert(intent_id, liquidation);
+        }
+        is_timeout
+    }
+}
+
+fn is_attempt_timeout(e: &anyhow::Error) -> bool {
+    matches!(
+        e.downcast_ref::<LiquidationError>(),
+        Some(LiquidationError::AttemptTimeout { .. })
+    )
+}
+
+/// Runs a stage of a liquidation attempt, aborting it with an `AttemptTimeout`
+/// once past the deadline of the attempt.
+async fn within_budget<T>(
+    deadline: Option<tokio::time::Instant>,
+    stage: Stage,
+    stage_future: impl Future<Output = anyhow::Result<T>>,
+) -> anyhow::Result<T> {
+    let Some(deadline) = deadline else {
+        return stage_future.await;
+    };
+    match tokio::time::timeout_at(deadline, stage_future).await {
+        Ok(result) => result,
+        Err(_) => {
+            LIQUIDATION_LATENCY.record_budget_exceeded(stage);
+            Err(LiquidationError::AttemptTimeout { stage }.into())
+        }
+    }
+}
+
+/// The monitoring end of the channels with the executor.
+pub struct ExecutorHandle {
+    pub tx_intents: mpsc::UnboundedSender<Vec<LiquidationIntent>>,
+    /// Liquidations only simulated, to check that their pairs are liquidable.
+    pub tx_capacity_checks: mpsc::UnboundedSender<Vec<LiquidationIntent>>,
+    pub tx_deleverages: mpsc::UnboundedSender<DeleverageIntent>,
+    pub rx_confirmations: mpsc::UnboundedReceiver<ConfirmedLiquidation>,
+    /// The fees estimated for the liquidations of the intents, in USD.
+    pub fee_estimates: FeeEstimates,
+}
+
+/// Sends the liquidations queued by the monitoring: dedups the intents per
+/// position, batches them, retries the failed sends & tracks the confirmation
+/// of the sent transactions, with the nonce of the account it shares with the
+/// treasury.
+/// Also sends the deleverages of our own positions, sharing the nonce.
+pub struct LiquidationExecutor {
+    account: StarknetAccount,
+    provider: FallbackProvider,
+    liquidate_contracts: LiquidateContracts,
+    rx_intents: mpsc::UnboundedReceiver<Vec<LiquidationIntent>>,
+    rx_capacity_checks: mpsc::UnboundedReceiver<Vec<LiquidationIntent>>,
+    rx_deleverages: mpsc::UnboundedReceiver<DeleverageIntent>,
+    tx_confirmations: mpsc::UnboundedSender<ConfirmedLiquidation>,
+    in_flight: InFlightLiquidations,
+    /// Last deleverage sent per position.
+    deleveraged_at: HashMap<String, Instant>,
+    prechecks: AccountPrechecks,
+    /// Profit of the confirmed liquidations since the start, in USD.
+    realized_profit_usd: Decimal,
+    /// Set in simulate mode, where the liquidations are only simulated.
+    calibration: Option<CalibrationReport>,
+    fee_cache: FeeCache,
+    /// intent => the liquidation its last attempt built before running out of
+    /// its budget.
+    artifacts: HashMap<Uuid, CachedLiquidation>,
+    config: ExecutorConfig,
+}
+
+impl LiquidationExecutor {
+    const CONFIRMATIONS_INTERVAL: Duration = Duration::from_secs(2);
+    const GAS_PRICES_INTERVAL: Duration = Duration::from_secs(15);
+    /// Number of times a liquidation tx is sent before giving up.
+    const MAX_SEND_ATTEMPTS: usize = 3;
+    const RETRY_DELAY: Duration = Duration::from_millis(500);
+    /// Minimum delay between two deleverages of a position, so its event can
+    /// arrive before the next one.
+    const DELEVERAGE_COOLDOWN: Duration = Duration::from_secs(60);
+    /// How long the liquidation of a timed out attempt is reused, its route
+    /// being stale after.
+    const ARTIFACTS_MAX_AGE: Duration = Duration::from_secs(30);
+
+    pub fn new(
+        account: StarknetAccount,
+        provider: FallbackProvider,
+        liquidate_contracts: LiquidateContracts,
+        config: ExecutorConfig,
+    ) -> (Self, ExecutorHandle) {
+        let (tx_intents, rx_intents) = mpsc::unbounded_channel();
+        let (tx_capacity_checks, rx_capacity_checks) = mpsc::unbounded_channel();
+        let (tx_deleverages, rx_deleverages) = mpsc::unbounded_channel();
+        let (tx_confirmations, rx_confirmations) = mpsc::unbounded_channel();
+        let fee_cache = FeeCache::new(config.fee_refresh_threshold_bps);
+        let fee_estimates = fee_cache.estimates();
+
+        let executor = Self {
+            account,
+            provider,
+            liquidate_contracts,
+            rx_intents,
+            rx_capacity_checks,
+            rx_deleverages,
+            tx_confirmations,
+            in_flight: InFlightLiquidations::new(),
+            deleveraged_at: HashMap::new(),
+            prechecks: AccountPrechecks::new(config.prechecks.clone()),
+            realized_profit_usd: Decimal::ZERO,
+            calibration: config.simulate_report.as_ref().map(CalibrationReport::new),
+            fee_cache,
+            artifacts: HashMap::new(),
+            config,
+        };
+        let handle = ExecutorHandle {
+            tx_intents,
+            tx_capacity_checks,
+            tx_deleverages,
+            rx_confirmations,
+            fee_estimates,
+        };
+        (executor, handle)
+    }
+
+    pub async fn run_forever(mut self) -> anyhow::Result<()> {
+        let mut confirmations_interval = tokio::time::interval(Self::CONFIRMATIONS_INTERVAL);
+        let mut gas_prices_interval = tokio::time::interval(Self::GAS_PRICES_INTERVAL);
+
+        loop {
+            tokio::select! {
+                maybe_intents = self.rx_intents.recv() => {
+                    let Some(intents) = maybe_intents else {
+                        return Ok(());
+                    };
+
+                    let intents = self.drain_intents(intents);
+                    self.execute(intents).await;
+                },
+                Some(intents) = self.rx_capacity_checks.recv() => {
+                    self.check_capacity(intents).await;
+                },
+                Some(deleverage) = self.rx_deleverages.recv() => {
+                    self.deleverage(deleverage).await;
+                },
+                _ = confirmations_interval.tick() => {
+                    self.resolve_in_flight_liquidations().await;
+                }
+                _ = gas_prices_interval.tick() => {
+                    if let Err(e) = self.refresh_gas_prices().await {
+                        tracing::debug!("[🔭 Monitoring] Could not read the gas prices: {e}");
+                    }
+                }
+            }
+        }
+    }
+
+    /// Gives the latest gas prices to the fee cache, off the hot path.
+    async fn refresh_gas_prices(&mut self) -> anyhow::Result<()> {
+        let block = guarded_starknet(&self.provider, RpcPath::Background, |node| {
+            node.get_block_with_tx_hashes(BlockId::Tag(BlockTag::Latest))
+        })
+        .await?;
+        self.fee_cache.update_prices(GasPrices::of_block(&block)?);
+        Ok(())
+    }
+
+    /// The fee of the liquidation, its calls - with the approval they miss -
+    /// estimated at every send so a liquidation that would revert is never
+    /// sent blind.
+    async fn liquidation_fee(
+        &mut self,
+        liquidation: &mut PreparedLiquidation,
+    ) -> anyhow::Result<TransactionFee> {
+        let simulation_started_at = Instant::now();
+        let estimate = self.account.estimate_txs(&liquidation.sent_calls()).await?;
+        liquidation
+            .timings
+            .record(Stage::Simulation, simulation_started_at.elapsed());
+        let fee = self.fee_cache.fee(&estimate);
+        self.fee_cache.record(liquidation.intent_id, &fee);
+        Ok(fee)
+    }
+
+    /// The address receiving the seized collateral.
+    fn recipient(&self) -> Felt {
+        self.config
+            .recipient
+            .unwrap_or_else(|| self.account.account_address())
+    }
+
+    /// Balance of the signer in the asset.
+    async fn signer_balance(&self, asset: &Asset) -> anyhow::Result<Decimal> {
+        let balance = balance_of(
+            &self.provider,
+            asset.address,
+            self.account.account_address(),
+        )
+        .await?;
+        Ok(Decimal::from_str(&balance.low.to_string())? / Decimal::TEN.pow(asset.decimals))
+    }
+
+    /// Builds the liquidation of the intent: from the inventory if its debt asset
+    /// is repaid from it & `balances` - the balances of the signer, read once
+    /// per batch - cover the debt, otherwise with the contract of its pool.
+    async fn prepare_liquidation(
+        &self,
+        intent: &LiquidationIntent,
+        balances: &mut HashMap<Currency, Decimal>,
+    ) -> anyhow::Result<PreparedLiquidation> {
+        let position = &intent.position;
+        if let Some(debt_to_cover) = self
+            .config
+            .inventory
+            .debt_to_cover(position, intent.debt_to_repay)
+        {
+            let debt = position.debt.currency;
+            let balance = match balances.get(&debt) {
+                Some(balance) => Some(*balance),
+                None => match self.signer_balance(&position.debt).await {
+                    Ok(balance) => Some(*balances.entry(debt).or_insert(balance)),
+                    Err(e) => {
+                        tracing::warn!(
+                            "[🔭 Monitoring] Could not read the {debt} inventory, swapping the collateral of {position}: {e}"
+                        );
+                        None
+                    }
+                },
+            };
+
+            if let Some(balance) = balance {
+                if balance >= debt_to_cover {
+                    balances.insert(debt, balance - debt_to_cover);
+                    let (call, allowance) = inventory_liquidation_call(position, debt_to_cover)?;
+                    return Ok(PreparedLiquidation {
+                        intent_id: intent.id,
+                        position: position.clone(),
+                        calls: vec![call],
+                        debt_to_repay: Some(debt_to_cover),
+                        allowance: Some(allowance),
+                        approval: None,
+                        from_inventory: true,
+                        quote: None,
+                        expected_profit_usd: None,
+                        deadline: None,
+                        timings: StageTimings::default(),
+                    });
+                }
+                tracing::debug!(
+                    "[🔭 Monitoring] Not enough {debt} in the inventory to repay {position}, swapping its collateral"
+                );
+            }
+        }
+
+        let route_started_at = Instant::now();
+        // The debt to repay is capped by the depth of the swap route if configured.
+        let debt_to_repay = match &self.config.depth_cap {
+            Some(depth_cap) => depth_cap.apply(position, intent.debt_to_repay).await?,
+            None => intent.debt_to_repay,
+        };
+        // A cap leaving dust is resized to leave the floor of the pool instead,
+        // within the depth: all the debt only if the depth allows it.
+        let debt_to_repay = match debt_to_repay {
+            Some(debt_to_repay) => {
+                let max_debt_usd = debt_to_repay * position.debt.currency.price();
+                match apply_debt_floor(
+                    position,
+                    LiquidationDecision::Partial { debt_to_repay },
+                    POOL_RATES.debt_floor_usd(position),
+                    Some(max_debt_usd),
+                ) {
+                    LiquidationDecision::Full => None,
+                    LiquidationDecision::Partial { debt_to_repay } => Some(debt_to_repay),
+                    LiquidationDecision::Skip { reason } => anyhow::bail!(
+                        "repaying {} would leave less debt than the floor of the pool: {}",
+                        position.debt.currency.format_amount(debt_to_repay),
+                        reason.unwrap_or_default()
+                    ),
+                }
+            }
+            None => None,
+        };
+        let (call, quote) = position
+            .get_vesu_liquidate_tx(
+                self.liquidate_contracts.for_pool(position.pool_name),
+                &self.recipient(),
+                debt_to_repay,
+            )
+            .await?;
+
+        let mut timings = StageTimings::default();
+        timings.record(Stage::Route, route_started_at.elapsed());
+        Ok(PreparedLiquidation {
+            intent_id: intent.id,
+            position: position.clone(),
+            calls: vec![call],
+            debt_to_repay,
+            allowance: None,
+            approval: None,
+            from_inventory: false,
+            quote: Some(quote),
+            expected_profit_usd: None,
+            deadline: None,
+            timings,
+        })
+    }
+
+    /// Returns the queued intents, keeping only the latest one per position &
+    /// dropping the positions with a liquidation already in flight.
+    fn drain_intents(&mut self, first: Vec<LiquidationIntent>) -> Vec<LiquidationIntent> {
+        let mut by_position: HashMap<String, LiquidationIntent> = HashMap::new();
+        let mut order = Vec::new();
+
+        let mut next = Some(first);
+        while let Some(intents) = next {
+            for intent in intents {
+                let position_id = intent.position.position_id();
+                if self.in_flight.is_pending(&position_id) {
+                    tracing::debug!(
+                        intent_id = %intent.id,
+                        "[🔭 Monitoring] ⏳ Liquidation of {} already in flight, skipping",
+                        intent.position
+                    );
+                } else if by_position.insert(position_id.clone(), intent).is_none() {
+                    order.push(position_id);
+                }
+            }
+            next = self.rx_intents.try_recv().ok();
+        }
+
+        order
+            .into_iter()
+            .filter_map(|position_id| by_position.remove(&position_id))
+            .collect()
+    }
+
+    async fn execute(&mut self, mut intents: Vec<LiquidationIntent>) {
+        if intents.is_empty() {
+            return;
+        }
+
+        if self.calibration.is_some() {
+            self.simulate_liquidations(intents).await;
+            return;
+        }
+
+        if self.config.kill_switch.is_engaged() {
+            tracing::warn!(
+                "[🔭 Monitoring] 🛑 Kill switch engaged, not liquidating {} positions",
+                intents.len()
+            );
+            return;
+        }
+
+        if let Some(slots) = self.in_flight_slots() {
+            // The others wait for the next check of their position.
+            let sendable = slots * self.config.max_liquidations_per_tx.max(1);
+            if intents.len() > sendable {
+                tracing::warn!(
+                    "[🔭 Monitoring] 🚦 {} transactions already in flight, deferring {} liquidations",
+                    self.in_flight.pending_txs(),
+                    intents.len() - sendable
+                );
+                intents.truncate(sendable);
+            }
+            if intents.is_empty() {
+                return;
+            }
+        }
+
+        let timed_out = self.liquidate_positions(intents).await;
+        if !timed_out.is_empty() {
+            // Once more right away, the stages they completed being reused.
+            // The ones running out of their budget again wait for the next
+            // check of their position.
+            tracing::info!(
+                "[🔭 Monitoring] ⏱️ Retrying the {} liquidations that ran out of their budget",
+                timed_out.len()
+            );
+            self.liquidate_positions(timed_out).await;
+        }
+    }
+
+    /// How many more transactions can be sent before reaching the in-flight
+    /// limit, None without a limit.
+    fn in_flight_slots(&self) -> Option<usize> {
+        self.config
+            .max_inflight_txs
+            .map(|max| max.saturating_sub(self.in_flight.pending_txs()))
+    }
+
+    /// Simulates the liquidations one by one & records their outcome in the
+    /// calibration report, without sending anything.
+    async fn simulate_liquidations(&mut self, intents: Vec<LiquidationIntent>) {
+        if self.calibration.is_none() {
+            return;
+        }
+
+        let mut balances = HashMap::new();
+        let mut simulations = Vec::with_capacity(intents.len());
+        for intent in intents {
+            let simulation = match self.prepare_liquidation(&intent, &mut balances).await {
+                Ok(liquidation) => match liquidation.simulation_calls() {
+                    Ok(calls) => self.account.simulate_txs(&calls).await,
+                    Err(e) => Err(e),
+                },
+                Err(e) => Err(e),
+            };
+            simulations.push((intent.position, simulation));
+        }
+
+        let Some(calibration) = self.calibration.as_mut() else {
+            return;
+        };
+        for (position, simulation) in simulations {
+            calibration.record(
+                &position,
+                self.liquidate_contracts
+                    .for_pool(position.pool_name)
+                    .address(),
+                simulation,
+            );
+        }
+
+        if let Err(e) = calibration.write() {
+            tracing::error!("[🔭 Monitoring] Could not write the calibration report: {e}");
+        }
+    }
+
+    /// Simulates the liquidations to check that their pairs can be liquidated -
+    /// their routes, the liquidate contracts & the account - & reports the
+    /// ones that would fail.
+    async fn check_capacity(&mut self, intents: Vec<LiquidationIntent>) {
+        let mut balances = HashMap::new();
+        let mut outcomes = Vec::with_capacity(intents.len());
+        for intent in intents {
+            let outcome = match self.prepare_liquidation(&intent, &mut balances).await {
+                Ok(liquidation) => match liquidation.simulation_calls() {
+                    Ok(calls) => simulated_capacity(
+                        route_capacity(&intent.position).await,
+                        self.account.simulate_txs(&calls).await,
+                        self.liquidate_contracts
+                            .for_pool(intent.position.pool_name)
+                            .address(),
+                    ),
+                    Err(e) => Err(e),
+                },
+                Err(e) => Err(e),
+            };
+            outcomes.push((intent.position, outcome));
+        }
+        report_capacity(&outcomes);
+    }
+
+    /// Checks the receipts of the in-flight liquidations and drops the ones that
+    /// are resolved or expired.
+    async fn resolve_in_flight_liquidations(&mut self) {
+        for in_flight in self.in_flight.pending() {
+            let (intent_id, position, tx_hash) = (
+                in_flight.intent_id,
+                in_flight.position.clone(),
+                in_flight.tx_hash,
+            );
+            let position_id = position.position_id();
+            let receipt =
+                guarded_starknet(&self.provider, RpcPath::Liquidation, |node| async move {
+                    match node.get_transaction_receipt(tx_hash).await {
+                        Ok(tx) => Ok(Some(tx)),
+                        Err(ProviderError::StarknetError(
+                            StarknetError::TransactionHashNotFound,
+                        )) => Ok(None),
+                        Err(e) => Err(e),
+                    }
+                })
+                .await;
+            match receipt {
+                Ok(Some(tx)) => {
+                    let mut timings = in_flight.timings.clone();
+                    timings.record(Stage::Confirmation, in_flight.sent_at.elapsed());
+                    tracing::info!(
+                        intent_id = %intent_id,
+                        "[🔭 Monitoring] ⏱️ Liquidation of position #{position_id} took {:?}: {timings}",
+                        timings.total()
+                    );
+                    LIQUIDATION_LATENCY.record(
+                        intent_id,
+                        position_id.clone(),
+                        Some(format!("{tx_hash:#064x}")),
+                        &timings,
+                    );
+                    let status = match tx.receipt.execution_result() {
+                        ExecutionResult::Succeeded => {
+                            let repayment = if in_flight.from_inventory {
+                                Repayment::Inventory
+                            } else {
+                                Repayment::Swap {
+                                    liquidate_contract: self
+                                        .liquidate_contracts
+                                        .for_pool(position.pool_name)
+                                        .address(),
+                                    recipient: self.recipient(),
+                                }
+                            };
+                            self.fee_cache.forget(&intent_id);
+                            let realized = realized_liquidation(&tx.receipt, &position, repayment);
+                            self.record_confirmed_liquidation(
+                                intent_id,
+                                &position,
+                                tx_hash,
+                                realized.as_ref(),
+                            );
+                            let _ = self.tx_confirmations.send(ConfirmedLiquidation {
+                                position_id: position_id.clone(),
+                                tx_hash,
+                                block_number: tx.block.block_number(),
+                            });
+                            LiquidationStatus::Confirmed
+                        }
+                        ExecutionResult::Reverted { reason } => {
+                            tracing::warn!(
+                                intent_id = %intent_id,
+                                "[🔭 Monitoring] Liquidation of position #{position_id} reverted (tx {tx_hash:#064x}): {reason}"
+                            );
+                            if matches!(
+                                LiquidationError::revert(reason.clone()),
+                                LiquidationError::NotUndercollateralized
+                            ) {
+                                report_lost_race_in_background(
+                                    self.provider.clone(),
+                                    in_flight.clone(),
+                                    timings.clone(),
+                                    Some(tx.block.block_number()),
+                                    reason.clone(),
+                                );
+                            }
+                            self.fee_cache.forget(&intent_id);
+                            LiquidationStatus::Reverted
+                        }
+                    };
+                    AuditLog::record(AuditEvent::Resolved {
+                        tx_hash: format!("{tx_hash:#064x}"),
+                        position_id: Some(position_id.clone()),
+                        status,
+                        actual_fee: Some(tx.receipt.actual_fee().amount.to_string()),
+                    });
+                    WATCHLIST.resolve_liquidation(tx_hash, status);
+                    self.in_flight.resolve(&position_id);
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    tracing::debug!(
+                        "[🔭 Monitoring] Could not fetch receipt of tx {tx_hash:#064x}: {e:?}"
+                    );
+                }
+            }
+        }
+
+        let expired = self.in_flight.prune_expired();
+        for tx_hash in &expired {
+            AuditLog::record(AuditEvent::Resolved {
+                tx_hash: format!("{tx_hash:#064x}"),
+                position_id: None,
+                status: LiquidationStatus::Expired,
+                actual_fee: None,
+            });
+            WATCHLIST.resolve_liquidation(*tx_hash, LiquidationStatus::Expired);
+        }
+        // The nonce of the expired txs may not have been consumed.
+        if !expired.is_empty() {
+            self.account.reset_nonce().await;
+        }
+    }
+
+    /// Liquidates the positions, batching up to `max_liquidations_per_tx` of them
+    /// per transaction. A batch that fails its simulation is sent one by one.
+    /// Returns the intents whose attempt ran out of its budget before the send.
+    async fn liquidate_positions(
+        &mut self,
+        intents: Vec<LiquidationIntent>,
+    ) -> Vec<LiquidationIntent> {
+        let started_at = Instant::now();
+        self.artifacts
+            .retain(|_, cached| cached.cached_at.elapsed() < Self::ARTIFACTS_MAX_AGE);
+
+        let mut balances = HashMap::new();
+        let mut prepared_liquidations = Vec::with_capacity(intents.len());
+        let mut timed_out = TimedOutAttempts::default();
+        for intent in &intents {
+            tracing::info!(
+                intent_id = %intent.id,
+                "[🔭 Monitoring] 🔫 Liquidating {} ({})",
+                intent.position,
+                intent.context
+            );
+            let deadline = self
+                .config
+                .attempt_budget
+                .map(|budget| tokio::time::Instant::now() + budget);
+
+            let liquidation = match self.cached_liquidation(intent) {
+                Some(liquidation) => liquidation,
+                None => {
+                    let prepared = within_budget(
+                        deadline,
+                        Stage::Route,
+                        self.prepare_liquidation(intent, &mut balances),
+                    )
+                    .await;
+                    match prepared {
+                        Ok(liquidation) => liquidation,
+                        Err(e) => {
+                            timed_out.record(&e, intent.id, None);
+                            Self::log_liquidation_error(&e, intent.id);
+                            continue;
+                        }
+                    }
+                }
+            };
+            prepared_liquidations.push(PreparedLiquidation {
+                deadline,
+                ..liquidation
+            });
+        }
+
+        // The profits are simulated concurrently, each within its own budget.
+        // The route is kept for the next attempt if one runs out of it.
+        let routes: Vec<_> = prepared_liquidations
+            .iter()
+            .map(|liquidation| {
+                (
+                    liquidation.intent_id,
+                    (!liquidation.from_inventory).then(|| liquidation.clone()),
+                )
+            })
+            .collect();
+        let simulations = join_all(prepared_liquidations.into_iter().map(|liquidation| {
+            within_budget(
+                liquidation.deadline,
+                Stage::Simulation,
+                self.is_profitable(liquidation),
+            )
+        }))
+        .await;
+
+        let mut liquidations = Vec::with_capacity(simulations.len());
+        for ((intent_id, route), simulation) in routes.into_iter().zip(simulations) {
+            match simulation {
+                Ok(Some(liquidation)) => liquidations.push(liquidation),
+                Ok(None) => {}
+                Err(e) => {
+                    timed_out.record(&e, intent_id, route);
+                    Self::log_liquidation_error(&e, intent_id);
+                }
+            }
+        }
+
+        if !liquidations.is_empty() {
+            self.send_prepared_liquidations(&mut liquidations, started_at, &mut timed_out)
+                .await;
+        }
+
+        // The intents that got sent or failed start from scratch next time.
+        let mut retries = Vec::new();
+        for intent in intents {
+            match timed_out.routes.remove(&intent.id) {
+                Some(route) => {
+                    if let Some(liquidation) = route {
+                        // A reused route keeps its age.
+                        let cached_at = self
+                            .artifacts
+                            .get(&intent.id)
+                            .map_or_else(Instant::now, |cached| cached.cached_at);
+                        self.artifacts.insert(
+                            intent.id,
+                            CachedLiquidation {
+                                liquidation,
+                                debt_to_repay: intent.debt_to_repay,
+                                cached_at,
+                            },
+                        );
+                    }
+                    retries.push(intent);
+                }
+                None => {
+                    self.artifacts.remove(&intent.id);
+                }
+            }
+        }
+        retries
+    }
+
+    /// Sends the prepared liquidations in batches, recording the ones running
+    /// out of their budget before their send.
+    async fn send_prepared_liquidations(
+        &mut self,
+        liquidations: &mut [PreparedLiquidation],
+        started_at: Instant,
+        timed_out: &mut TimedOutAttempts,
+    ) {
+        let deadline = liquidations.iter().filter_map(|l| l.deadline).min();
+        let precheck_started_at = Instant::now();
+        let prechecked = within_budget(
+            deadline,
+            Stage::Precheck,
+            self.precheck_submission(liquidations),
+        )
+        .await;
+        for liquidation in liquidations.iter_mut() {
+            liquidation
+                .timings
+                .record(Stage::Precheck, precheck_started_at.elapsed());
+        }
+        if let Err(e) = prechecked {
+            self.prechecks.forget_allowances();
+            if is_attempt_timeout(&e) {
+                for liquidation in liquidations.iter() {
+                    timed_out.record(&e, liquidation.intent_id, Some(liquidation.clone()));
+                    Self::log_liquidation_error(&e, liquidation.intent_id);
+                }
+                return;
+            }
+            tracing::error!(
+                "[🔭 Monitoring] ⛔ Not sending {} liquidations: {e:#}",
+                liquidations.len()
+            );
+            return;
+        }
+
+        for batch in liquidations.chunks_mut(self.config.max_liquidations_per_tx.max(1)) {
+            if batch.len() > 1 {
+                let deadline = batch.iter().filter_map(|l| l.deadline).min();
+                let calls: Vec<Call> = batch.iter().flat_map(|l| l.sent_calls()).collect();
+                let simulation_started_at = Instant::now();
+                let estimate = within_budget(
+                    deadline,
+                    Stage::Simulation,
+                    self.account.estimate_txs(&calls),
+                )
+                .await;
+                for liquidation in batch.iter_mut() {
+                    liquidation
+                        .timings
+                        .record(Stage::Simulation, simulation_started_at.elapsed());
+                }
+                match estimate {
+                    // The fee of the batch is the one just estimated.
+                    Ok(estimate) => {
+                        let fee = Some(self.fee_cache.fee(&estimate));
+                        if let Err(e) = self.send_liquidations(batch, started_at, fee).await {
+                            for liquidation in batch.iter() {
+                                Self::log_liquidation_error(&e, liquidation.intent_id);
+                            }
+                        }
+                        continue;
+                    }
+                    Err(e) if is_attempt_timeout(&e) => {
+                        // The approvals they carry are not sent.
+                        self.prechecks.forget_allowances();
+                        for liquidation in batch.iter() {
+                            timed_out.record(&e, liquidation.intent_id, Some(liquidation.clone()));
+                            Self::log_liquidation_error(&e, liquidation.intent_id);
+                        }
+                        continue;
+                    }
+                    Err(e) => {
+                        tracing::warn!(
+                            intent_ids = %Self::intent_ids(batch),
+                            "[🔭 Monitoring] Batch of {} liquidations reverted in simulation, sending them one by one: {e}",
+                            batch.len()
+                        );
+                    }
+                }
+            }
+
+            for liquidation in batch.iter_mut() {
+                let fee = within_budget(
+                    liquidation.deadline,
+                    Stage::Simulation,
+                    self.liquidation_fee(liquidation),
+                )
+                .await;
+                let sent = match fee {
+                    Ok(fee) => {
+                        self.send_liquidations(
+                            std::slice::from_ref(liquidation),
+                            started_at,
+                            Some(fee),
+                        )
+                        .await
+                    }
+                    Err(e) => {
+                        // The approval it carries is not sent.
+                        if liquidation.approval.is_some() {
+                            self.prechecks.forget_allowances();
+                        }
+                        Err(e)
+                    }
+                };
+                if let Err(e) = sent {
+                    timed_out.record(&e, liquidation.intent_id, Some(liquidation.clone()));
+                    Self::log_liquidation_error(&e, liquidation.intent_id);
+                }
+            }
+        }
+    }
+
+    /// The liquidation built by the previous attempt of the intent if it ran
+    /// out of its budget, for the same debt to repay. Its route is simulated
+    /// again, the state of the chain having moved since.
+    fn cached_liquidation(&self, intent: &LiquidationIntent) -> Option<PreparedLiquidation> {
+        let cached = self.artifacts.get(&intent.id)?;
+        if cached.debt_to_repay != intent.debt_to_repay {
+            return None;
+        }
+        tracing::debug!(
+            intent_id = %intent.id,
+            "[🔭 Monitoring] ♻️ Reusing the route of the previous attempt of {}",
+            intent.position
+        );
+        Some(PreparedLiquidation {
+            approval: None,
+            expected_profit_usd: None,
+            timings: StageTimings::default(),
+            ..cached.liquidation.clone()
+        })
+    }
+
+    /// The minimum profit of the liquidation in USD, the highest of the floor &
+    /// of the bps of the debt it repays set for the class of its collateral.
+    /// None if the class has no minimum.
+    fn min_profit_usd(&self, liquidation: &PreparedLiquidation) -> Option<Decimal> {
+        let position = &liquidation.position;
+        let collateral_class = position.collateral.currency.class();
+        let floor = self.config.min_profit_usd.get(&collateral_class).copied();
+        let relative = self
+            .config
+            .min_profit_bps
+            .get(&collateral_class)
+            .map(|bps| {
+                let debt_repaid = liquidation.debt_to_repay.unwrap_or(position.debt.amount);
+                debt_repaid * position.debt.currency.price() * bps / dec!(10_000)
+            });
+        match (floor, relative) {
+            (Some(floor), Some(relative)) => Some(floor.max(relative)),
+            (floor, relative) => floor.or(relative),
+        }
+    }
+
+    /// Simulates the liquidation if the class of its collateral has a minimum
+    /// profit & returns it if its profit is above, None otherwise. The inventory
+    /// liquidations are kept: they hold the collateral instead of selling it.
+    async fn is_profitable(
+        &self,
+        mut liquidation: PreparedLiquidation,
+    ) -> anyhow::Result<Option<PreparedLiquidation>> {
+        let Some(min_profit_usd) = self.min_profit_usd(&liquidation) else {
+            return Ok(Some(liquidation));
+        };
+        let position = &liquidation.position;
+        let collateral_class = position.collateral.currency.class();
+        if liquidation.from_inventory {
+            return Ok(Some(liquidation));
+        }
+
+        let simulation_started_at = Instant::now();
+        let simulation = self
+            .account
+            .simulate_txs(&liquidation.simulation_calls()?)
+            .await?;
+        let (_, profit_usd, revert_reason) = simulation_outcome(
+            &simulation,
+            self.liquidate_contracts
+                .for_pool(position.pool_name)
+                .address(),
+        );
+        if let Some(reason) = revert_reason {
+            return Err(LiquidationError::revert(reason).in_simulation().into());
+        }
+        let profit_usd =
+            profit_usd.ok_or_else(|| anyhow::anyhow!("could not read the profit of {position}"))?;
+
+        if profit_usd < min_profit_usd {
+            tracing::info!(
+                intent_id = %liquidation.intent_id,
+                "[🔭 Monitoring] 💸 Not liquidating {position}: its profit of {} is below the {} minimum of the {collateral_class} collaterals",
+                format_usd(profit_usd),
+                format_usd(min_profit_usd)
+            );
+            return Ok(None);
+        }
+        liquidation
+            .timings
+            .record(Stage::Simulation, simulation_started_at.elapsed());
+        liquidation.expected_profit_usd = Some(profit_usd);
+        Ok(Some(liquidation))
+    }
+
+    /// Checks the fee balance of the account & adds the approvals the
+    /// liquidations miss to the first liquidation needing each of them: sent in
+    /// its transaction, the approval lands before the liquidation, which can
+    /// then be estimated.
+    async fn precheck_submission(
+        &mut self,
+        liquidations: &mut [PreparedLiquidation],
+    ) -> anyhow::Result<()> {
+        self.prechecks
+            .ensure_fee_balance(&self.account, &self.provider)
+            .await?;
+
+        let required: Vec<RequiredAllowance> =
+            liquidations.iter().filter_map(|l| l.allowance).collect();
+        let mut approvals = self
+            .prechecks
+            .missing_approvals(&self.provider, self.account.account_address(), &required)
+            .await?;
+        for liquidation in liquidations.iter_mut() {
+            if let Some(allowance) = liquidation.allowance {
+                liquidation.approval = approvals.remove(&allowance.key());
+            }
+        }
+        Ok(())
+    }
+
+    /// Sends the liquidations in a single transaction and tracks them as in-flight.
+    /// The account estimates their fee if not given.
+    async fn send_liquidations(
+        &mut self,
+        liquidations: &[PreparedLiquidation],
+        started_at: Instant,
+        fee: Option<TransactionFee>,
+    ) -> anyhow::Result<Felt> {
+        // A batch sent one by one may reach the limit.
+        if self.in_flight_slots() == Some(0) {
+            return Err(LiquidationError::InFlightLimit {
+                in_flight: self.in_flight.pending_txs(),
+            }
+            .into());
+        }
+
+        let calls: Vec<Call> = liquidations.iter().flat_map(|l| l.sent_calls()).collect();
+        let submission_started_at = Instant::now();
+        let sent = self
+            .send_calls(&calls, fee, TxPurpose::Liquidation)
+            .instrument(tracing::info_span!(
+                "liquidation",
+                intent_ids = %Self::intent_ids(liquidations)
+            ))
+            .await;
+        let submission = submission_started_at.elapsed();
+        let tx_hash = match sent {
+            Ok(tx_hash) => tx_hash,
+            Err(e) => {
+                // The allowances they would have used are read again.
+                self.prechecks.forget_allowances();
+                for liquidation in liquidations {
+                    let mut timings = liquidation.timings.clone();
+                    timings.record(Stage::Submission, submission);
+                    LIQUIDATION_LATENCY.record(
+                        liquidation.intent_id,
+                        liquidation.position.position_id(),
+                        None,
+                        &timings,
+                    );
+                }
+                return Err(e);
+            }
+        };
+
+        for liquidation in liquidations {
+            let position = &liquidation.position;
+            let mut timings = liquidation.timings.clone();
+            timings.record(Stage::Submission, submission);
+            self.in_flight.insert(
+                liquidation.intent_id,
+                position,
+                tx_hash,
+                liquidation.from_inventory,
+                timings,
+            );
+            WATCHLIST.record_liquidation(liquidation.intent_id, position.position_id(), tx_hash);
+            if !liquidation.from_inventory {
+                compare_routes_in_background(position.clone(), liquidation.debt_to_repay);
+            }
+            tracing::info!(
+                intent_id = %liquidation.intent_id,
+                "[🔭 Monitoring] ✅ Liquidated position #{}{}! (tx {tx_hash:#064x}) - ⌛ {:?}",
+                position.position_id(),
+                if liquidation.from_inventory {
+                    " from the inventory"
+                } else {
+                    ""
+                },
+                started_at.elapsed()
+            );
+            Self::report_submission(liquidation, tx_hash);
+        }
+        Ok(tx_hash)
+    }
+
+    /// Logs & notifies the route, the quote & the expected profit of a sent
+    /// liquidation, so the execution of every trade can be audited.
+    fn report_submission(liquidation: &PreparedLiquidation, tx_hash: Felt) {
+        let position = &liquidation.position;
+        let expected_profit = liquidation
+            .expected_profit_usd
+            .map_or_else(|| "not simulated".into(), format_usd);
+        let Some(quote) = &liquidation.quote else {
+            NOTIFIER.notify(format!(
+                "Liquidated {position} from the inventory (tx {tx_hash:#064x}) - expected profit {expected_profit}"
+            ));
+            return;
+        };
+
+        let route = serde_json::to_string(&quote.route).unwrap_or_default();
+        tracing::info!(
+            intent_id = %liquidation.intent_id,
+            tx_hash = %format!("{tx_hash:#064x}"),
+            route = %route,
+            collateral_in = %quote.collateral_in,
+            debt_out = %quote.debt_out,
+            expected_profit_usd = ?liquidation.expected_profit_usd,
+            "[🔭 Monitoring] 🛣️ Swapped {} for {} through {} - expected profit {expected_profit}",
+            position.collateral.currency.format_amount(quote.collateral_in),
+            position.debt.currency.format_amount(quote.debt_out),
+            quote.route_summary()
+        );
+        NOTIFIER.notify_with_details(
+            format!(
+                "Liquidated {position} (tx {tx_hash:#064x}): quoted {} for {} through {} - expected profit {expected_profit}",
+                position.collateral.currency.format_amount(quote.collateral_in),
+                position.debt.currency.format_amount(quote.debt_out),
+                quote.route_summary()
+            ),
+            json!({
+                "intent_id": liquidation.intent_id.to_string(),
+                "position_id": position.position_id(),
+                "tx_hash": format!("{tx_hash:#064x}"),
+                "quote": quote,
+                "expected_profit_usd": liquidation.expected_profit_usd.map(|profit| profit.to_string()),
+            }),
+        );
+    }
+
+    /// Sends the calls in a single transaction, with the nonce tracked by the
+    /// account. The send is retried, with a fresh nonce if needed, unless the
+    /// liquidation itself failed. Their fee is estimated at each attempt if not
+    /// given.
+    async fn send_calls(
+        &mut self,
+        calls: &[Call],
+        fee: Option<TransactionFee>,
+        purpose: TxPurpose,
+    ) -> anyhow::Result<Felt> {
+        let mut attempt = 1;
+        loop {
+            match self
+                .account
+                .execute_txs_with_next_nonce(calls, fee, purpose)
+                .await
+            {
+                Ok(tx_hash) => return Ok(tx_hash),
+                Err(e) => {
+                    let error = e.downcast_ref::<LiquidationError>();
+                    // The unclassified errors are timeouts & open breakers.
+                    let retryable = error.is_none_or(LiquidationError::is_retryable);
+                    if attempt >= Self::MAX_SEND_ATTEMPTS || !retryable {
+                        return Err(e);
+                    }
+                    tracing::warn!(
+                        "[🔭 Monitoring] Tx failed (attempt {attempt}/{}), retrying: {e}",
+                        Self::MAX_SEND_ATTEMPTS
+                    );
+                    attempt += 1;
+                    tokio::time::sleep(Self::RETRY_DELAY).await;
+                }
+            }
+        }
+    }
+
+    /// Repays the debt of one of our positions from the balance of the signer.
+    async fn deleverage(&mut self, intent: DeleverageIntent) {
+        let position = &intent.position;
+        let position_id = position.position_id();
+        if self
+            .deleveraged_at
+            .get(&position_id)
+            .is_some_and(|at| at.elapsed() < Self::DELEVERAGE_COOLDOWN)
+        {
+            return;
+        }
+        self.deleveraged_at.insert(position_id, Instant::now());
+
+        let debt = position.debt.currency;
+        if self.calibration.is_some() {
+            tracing::info!(
+                "[🔭 Monitoring] 🧪 Would deleverage {position} by repaying {}",
+                debt.format_amount(intent.debt_to_repay)
+            );
+            return;
+        }
+        if self.config.kill_switch.is_engaged() {
+            tracing::warn!("[🔭 Monitoring] 🛑 Kill switch engaged, not deleveraging {position}");
+            return;
+        }
+
+        let balance = match self.signer_balance(&position.debt).await {
+            Ok(balance) => balance,
+            Err(e) => {
+                tracing::error!(
+                    "[🔭 Monitoring] Could not read the {debt} balance to deleverage {position}: {e}"
+                );
+                return;
+            }
+        };
+        let debt_to_repay = intent.debt_to_repay.min(balance);
+        if debt_to_repay.is_zero() {
+            tracing::error!(
+                "[🔭 Monitoring] 🛡️ Cannot deleverage {position}: the signer has no {debt} to repay with"
+            );
+            return;
+        }
+
+        let sent = match deleverage_calls(position, debt_to_repay) {
+            Ok(calls) => self.send_calls(&calls, None, TxPurpose::Deleverage).await,
+            Err(e) => Err(e),
+        };
+        match sent {
+            Ok(tx_hash) => tracing::info!(
+                "[🔭 Monitoring] 🛡️ Deleveraged {position} by repaying {} (tx {tx_hash:#064x})",
+                debt.format_amount(debt_to_repay)
+            ),
+            Err(e) => tracing::error!("[🔭 Monitoring] 😨 Could not deleverage {position}: {e}"),
+        }
+    }
+
+    /// Logs & accounts the outcome of a confirmed liquidation.
+    fn record_confirmed_liquidation(
+        &mut self,
+        intent_id: Uuid,
+        position: &VesuPosition,
+        tx_hash: Felt,
+        realized: Option<&RealizedLiquidation>,
+    ) {
+        let Some(realized) = realized else {
+            tracing::info!(
+                intent_id = %intent_id,
+                "[🔭 Monitoring] 🎯 Liquidation of position #{} confirmed (tx {tx_hash:#064x})",
+                position.position_id()
+            );
+            return;
+        };
+
+        self.realized_profit_usd += realized.profit_usd();
+        WATCHLIST.realize_liquidation(tx_hash, &position.position_id(), realized);
+        tracing::info!(
+            intent_id = %intent_id,
+            "[🔭 Monitoring] 🎯 Liquidation of position #{} confirmed (tx {tx_hash:#064x}): seized {}, repaid {} - profit {} (total {})",
+            position.position_id(),
+            position
+                .collateral
+                .currency
+                .format_amount(realized.collateral_seized),
+            position.debt.currency.format_amount(realized.debt_repaid),
+            format_usd(realized.profit_usd()),
+            format_usd(self.realized_profit_usd),
+        );
+        if !realized.bad_debt.is_zero() {
+            tracing::warn!(
+                "[🔭 Monitoring] Liquidation of position #{} left a bad debt of {}",
+                position.position_id(),
+                position.debt.currency.format_amount(realized.bad_debt)
+            );
+        }
+    }
+
+    /// The ids of the intents of the liquidations, for the logs of a batch.
+    fn intent_ids(liquidations: &[PreparedLiquidation]) -> String {
+        liquidations
+            .iter()
+            .map(|l| l.intent_id.to_string())
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    fn log_liquidation_error(e: &anyhow::Error, intent_id: Uuid) {
+        LIQUIDATION_ERRORS.record(e);
+        match e.downcast_ref::<LiquidationError>() {
+            Some(LiquidationError::NotUndercollateralized) => {
+                tracing::warn!(
+                    intent_id = %intent_id,
+                    "[🔭 Monitoring] Position was not under collateralized!"
+                );
+            }
+            error => tracing::error!(
+                intent_id = %intent_id,
+                error = %e,
+                kind = error.map_or("other", LiquidationError::kind),
+                "[🔭 Monitoring] 😨 Could not liquidate position",
+            ),
+        }
+    }
+}
@@ -0,0 +1,183 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use dashmap::DashMap;
+use num_traits::Pow;
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use starknet::core::types::{FeeEstimate, MaybePreConfirmedBlockWithTxHashes, ResourcePrice};
+use uuid::Uuid;
+
+use crate::types::currency::Currency;
+
+/// Prices of the resources of a transaction, in FRI per unit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GasPrices {
+    pub l1_gas: u128,
+    pub l2_gas: u128,
+    pub l1_data_gas: u128,
+}
+
+impl GasPrices {
+    /// The prices of the block, i.e the ones its transactions paid.
+    pub fn of_block(block: &MaybePreConfirmedBlockWithTxHashes) -> Result<Self> {
+        let (l1_gas, l2_gas, l1_data_gas) = match block {
+            MaybePreConfirmedBlockWithTxHashes::Block(block) => (
+                &block.l1_gas_price,
+                &block.l2_gas_price,
+                &block.l1_data_gas_price,
+            ),
+            MaybePreConfirmedBlockWithTxHashes::PreConfirmedBlock(block) => (
+                &block.l1_gas_price,
+                &block.l2_gas_price,
+                &block.l1_data_gas_price,
+            ),
+        };
+        let in_fri =
+            |price: &ResourcePrice| -> Result<u128> { Ok(u128::try_from(price.price_in_fri)?) };
+        Ok(Self {
+            l1_gas: in_fri(l1_gas)?,
+            l2_gas: in_fri(l2_gas)?,
+            l1_data_gas: in_fri(l1_data_gas)?,
+        })
+    }
+
+    /// The largest relative change of a price since `previous`, in bps.
+    fn change_bps(&self, previous: &Self) -> Decimal {
+        let change = |current: u128, previous: u128| {
+            if previous == 0 {
+                return Decimal::ZERO;
+            }
+            let (current, previous) = (Decimal::from(current), Decimal::from(previous));
+            ((current - previous) / previous).abs() * dec!(10_000)
+        };
+        change(self.l1_gas, previous.l1_gas)
+            .max(change(self.l2_gas, previous.l2_gas))
+            .max(change(self.l1_data_gas, previous.l1_data_gas))
+    }
+}
+
+/// The resources a transaction consumes at the prices of its estimation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransactionFee {
+    pub l1_gas: u64,
+    pub l2_gas: u64,
+    pub l1_data_gas: u64,
+    pub prices: GasPrices,
+}
+
+impl From<&FeeEstimate> for TransactionFee {
+    fn from(estimate: &FeeEstimate) -> Self {
+        Self {
+            l1_gas: estimate.l1_gas_consumed,
+            l2_gas: estimate.l2_gas_consumed,
+            l1_data_gas: estimate.l1_data_gas_consumed,
+            prices: GasPrices {
+                l1_gas: estimate.l1_gas_price,
+                l2_gas: estimate.l2_gas_price,
+                l1_data_gas: estimate.l1_data_gas_price,
+            },
+        }
+    }
+}
+
+impl TransactionFee {
+    /// The fee in USD, paid in STRK by the v3 transactions.
+    pub fn usd(&self) -> Decimal {
+        let fri = Decimal::from(self.l1_gas) * Decimal::from(self.prices.l1_gas)
+            + Decimal::from(self.l2_gas) * Decimal::from(self.prices.l2_gas)
+            + Decimal::from(self.l1_data_gas) * Decimal::from(self.prices.l1_data_gas);
+        fri / Decimal::TEN.pow(Currency::STRK.d_decimals()) * Currency::STRK.price()
+    }
+}
+
+/// The fees of the cached intents in USD, shared with the monitoring to give
+/// them to the strategy.
+#[derive(Debug, Clone, Default)]
+pub struct FeeEstimates(Arc<DashMap<Uuid, Decimal>>);
+
+impl FeeEstimates {
+    /// The last fee estimated for the liquidation of the intent, in USD.
+    pub fn usd(&self, intent_id: &Uuid) -> Option<Decimal> {
+        self.0.get(intent_id).map(|fee_usd| *fee_usd)
+    }
+}
+
+/// The gas prices of the liquidations & the fee last estimated for every
+/// intent. The calls of every send are estimated - a fee reused by the next
+/// attempts would send them blind, e.g after a competitor liquidated the
+/// position or with a new route - but their price bounds come from the cache:
+/// they only follow the chain once a price moved by more than
+/// `refresh_threshold_bps`, so the sends of a busy period keep the same bounds.
+#[derive(Debug)]
+pub struct FeeCache {
+    /// intent => when its fee was last estimated.
+    estimated_at: HashMap<Uuid, Instant>,
+    estimates: FeeEstimates,
+    /// Gas prices of the sends, None until read once.
+    prices: Option<GasPrices>,
+    refresh_threshold_bps: Decimal,
+}
+
+impl FeeCache {
+    /// How long the fee of an intent is kept, its intent being long gone after.
+    const MAX_AGE: Duration = Duration::from_secs(3600);
+
+    pub fn new(refresh_threshold_bps: Decimal) -> Self {
+        Self {
+            estimated_at: HashMap::new(),
+            estimates: FeeEstimates::default(),
+            prices: None,
+            refresh_threshold_bps,
+        }
+    }
+
+    /// The fee of a send from the estimation of its calls, at the cached prices
+    /// unless the estimated ones moved too much since.
+    pub fn fee(&mut self, estimate: &FeeEstimate) -> TransactionFee {
+        let mut fee = TransactionFee::from(estimate);
+        match self.prices {
+            Some(prices) if fee.prices.change_bps(&prices) <= self.refresh_threshold_bps => {
+                fee.prices = prices;
+            }
+            _ => self.prices = Some(fee.prices),
+        }
+        fee
+    }
+
+    /// The fees of the cached intents, readable from another task.
+    pub fn estimates(&self) -> FeeEstimates {
+        self.estimates.clone()
+    }
+
+    /// Records the fee last estimated for the liquidation of the intent.
+    pub fn record(&mut self, intent_id: Uuid, fee: &TransactionFee) {
+        self.estimates.0.insert(intent_id, fee.usd());
+        self.estimated_at.insert(intent_id, Instant::now());
+    }
+
+    /// Drops the fee of an intent that got liquidated or reverted.
+    pub fn forget(&mut self, intent_id: &Uuid) {
+        self.estimated_at.remove(intent_id);
+        self.estimates.0.remove(intent_id);
+    }
+
+    /// Moves the cached prices to the latest ones of the chain once one of them
+    /// moved by more than the threshold, & drops the fees estimated more than
+    /// `MAX_AGE` ago.
+    pub fn update_prices(&mut self, prices: GasPrices) {
+        let is_stale = self
+            .prices
+            .is_none_or(|cached| prices.change_bps(&cached) > self.refresh_threshold_bps);
+        if is_stale {
+            self.prices = Some(prices);
+        }
+        self.estimated_at
+            .retain(|_, estimated_at| estimated_at.elapsed() < Self::MAX_AGE);
+        self.estimates
+            .0
+            .retain(|intent_id, _| self.estimated_at.contains_key(intent_id));
+    }
+}
@@ -0,0 +1,84 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+
+/// A LTV measured at a given time.
+#[derive(Debug, Clone, Copy)]
+pub struct HealthSample {
+    pub at: Instant,
+    pub ltv: Decimal,
+}
+
+/// Ring buffer of the latest LTVs of a position, used to show its trajectory
+/// rather than a single snapshot.
+#[derive(Debug, Clone, Default)]
+pub struct HealthHistory {
+    samples: VecDeque<HealthSample>,
+}
+
+impl HealthHistory {
+    /// With a check every 10s, covers the last ~5 minutes.
+    pub const CAPACITY: usize = 32;
+
+    pub fn record(&mut self, ltv: Decimal) {
+        if self.samples.len() == Self::CAPACITY {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(HealthSample {
+            at: Instant::now(),
+            ltv,
+        });
+    }
+
+    pub fn samples(&self) -> impl Iterator<Item = &HealthSample> {
+        self.samples.iter()
+    }
+
+    pub fn latest(&self) -> Option<&HealthSample> {
+        self.samples.back()
+    }
+
+    /// Returns the number of latest consecutive samples with a LTV at or above
+    /// the threshold.
+    pub fn consecutive_at_or_above(&self, threshold: Decimal) -> usize {
+        self.samples
+            .iter()
+            .rev()
+            .take_while(|s| s.ltv >= threshold)
+            .count()
+    }
+
+    /// Returns the LTV change in bps between the oldest and the latest sample,
+    /// along with the time elapsed between both.
+    pub fn trend(&self) -> Option<(Decimal, Duration)> {
+        let (oldest, latest) = (self.samples.front()?, self.samples.back()?);
+        let change_bps = (latest.ltv - oldest.ltv) * dec!(10_000);
+        Some((change_bps, latest.at.duration_since(oldest.at)))
+    }
+
+    /// Describes the trend, cf: "LTV rose 40bps in 5m".
+    pub fn describe_trend(&self) -> Option<String> {
+        let (change_bps, elapsed) = self.trend()?;
+        if elapsed.is_zero() {
+            return None;
+        }
+
+        let direction = if change_bps.is_sign_negative() {
+            "fell"
+        } else {
+            "rose"
+        };
+        let elapsed = if elapsed.as_secs() >= 60 {
+            format!("{}m", elapsed.as_secs() / 60)
+        } else {
+            format!("{}s", elapsed.as_secs())
+        };
+
+        Some(format!(
+            "LTV {direction} {}bps in {elapsed}",
+            change_bps.abs().round_dp(0)
+        ))
+    }
+}
@@ -0,0 +1,75 @@
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+
+use crate::services::monitoring::evaluation::HealthEvaluation;
+
+/// How the healthy positions are logged at each check. The positions at risk -
+/// liquidable or almost - are always logged in detail.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum HealthyPositionsLog {
+    /// A single line per check, counting the healthy positions by health factor.
+    #[default]
+    Summary,
+    /// The summary & a debug line per healthy position.
+    Detailed,
+    /// Nothing.
+    Off,
+}
+
+/// Upper bounds of the health factor buckets of the summary, the last one being
+/// unbounded.
+const BUCKETS: [Decimal; 4] = [dec!(1.25), dec!(1.5), dec!(2), dec!(5)];
+
+/// Counts the evaluated positions of a check by health factor.
+#[derive(Debug, Default)]
+pub struct HealthSummary {
+    at_risk: usize,
+    /// Healthy positions per bucket, the last one for the health factors above
+    /// the last bound.
+    healthy: [usize; BUCKETS.len() + 1],
+    without_debt: usize,
+}
+
+impl HealthSummary {
+    pub fn record(&mut self, evaluation: &HealthEvaluation) {
+        if evaluation.is_at_risk {
+            self.at_risk += 1;
+        } else if evaluation.health_factor == Decimal::MAX {
+            self.without_debt += 1;
+        } else {
+            let bucket = BUCKETS
+                .iter()
+                .position(|bound| evaluation.health_factor < *bound)
+                .unwrap_or(BUCKETS.len());
+            self.healthy[bucket] += 1;
+        }
+    }
+
+    /// Logs the summary, with the number of hibernating positions if they
+    /// hibernate - they are not checked.
+    pub fn log(&self, hibernating: Option<usize>) {
+        let mut buckets = Vec::with_capacity(self.healthy.len());
+        let mut lower = None;
+        for (index, count) in self.healthy.iter().enumerate() {
+            let label = match (lower, BUCKETS.get(index)) {
+                (None, Some(upper)) => format!("<{upper}"),
+                (Some(lower), Some(upper)) => format!("{lower}-{upper}"),
+                (Some(lower), None) => format!("≥{lower}"),
+                (None, None) => unreachable!("there is at least one bound"),
+            };
+            buckets.push(format!("{label}: {count}"));
+            lower = BUCKETS.get(index);
+        }
+
+        let hibernating = hibernating
+            .map(|hibernating| format!(", hibernating: {hibernating}"))
+            .unwrap_or_default();
+        tracing::info!(
+            "[🔭 Monitoring] 🩺 {} positions checked, {} at risk - healthy by health factor {}, without debt: {}{hibernating}",
+            self.at_risk + self.healthy.iter().sum::<usize>() + self.without_debt,
+            self.at_risk,
+            buckets.join(", "),
+            self.without_debt
+        );
+    }
+}
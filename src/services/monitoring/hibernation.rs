@@ -0,0 +1,134 @@
+use std::collections::HashMap;
+
+use rust_decimal::Decimal;
+
+use crate::services::monitoring::evaluation::HealthEvaluation;
+use crate::types::currency::Currency;
+use crate::types::position::VesuPosition;
+
+/// When the positions far from liquidable stop being evaluated at every check.
+#[derive(Debug, Clone, Copy)]
+pub struct HibernationConfig {
+    /// A position with a LTV below this % of its LLTV hibernates.
+    pub ltv_pct: Decimal,
+    /// A hibernating position wakes up once the price of its collateral or of
+    /// its debt moved by more than this %, since it hibernated.
+    pub wakeup_move_pct: Decimal,
+}
+
+/// A hibernating position, as it was when it fell asleep.
+#[derive(Debug, Clone)]
+struct HibernatedPosition {
+    collateral: Currency,
+    debt: Currency,
+    collateral_price: Decimal,
+    debt_price: Decimal,
+    collateral_amount: Decimal,
+    debt_amount: Decimal,
+    lltv: Decimal,
+}
+
+impl HibernatedPosition {
+    fn new(position: &VesuPosition) -> Self {
+        Self {
+            collateral: position.collateral.currency,
+            debt: position.debt.currency,
+            collateral_price: position.collateral.currency.price(),
+            debt_price: position.debt.currency.price(),
+            collateral_amount: position.collateral.amount,
+            debt_amount: position.debt.amount,
+            lltv: position.lltv,
+        }
+    }
+
+    /// Whether an event or an LLTV change modified the position since.
+    fn changed(&self, position: &VesuPosition) -> bool {
+        self.collateral_amount != position.collateral.amount
+            || self.debt_amount != position.debt.amount
+            || self.lltv != position.lltv
+    }
+}
+
+/// The positions skipped by the checks until a price move or an event could
+/// bring them closer to liquidable - cutting the evaluations & the logs of the
+/// large position sets, mostly made of very healthy positions.
+#[derive(Debug)]
+pub struct Hibernation {
+    config: HibernationConfig,
+    /// position id => the position when it hibernated
+    hibernating: HashMap<String, HibernatedPosition>,
+}
+
+impl Hibernation {
+    pub fn new(config: HibernationConfig) -> Self {
+        Self {
+            config,
+            hibernating: HashMap::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.hibernating.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hibernating.is_empty()
+    }
+
+    /// Wakes up the positions whose assets moved by more than the wakeup move,
+    /// the prices being read once per asset.
+    pub fn wake_on_price_moves(&mut self) {
+        if self.hibernating.is_empty() {
+            return;
+        }
+
+        let mut prices: HashMap<Currency, Decimal> = HashMap::new();
+        let mut price =
+            |currency: Currency| *prices.entry(currency).or_insert_with(|| currency.price());
+        let threshold = self.config.wakeup_move_pct / Decimal::ONE_HUNDRED;
+        let moved =
+            |from: Decimal, to: Decimal| !from.is_zero() && ((to - from) / from).abs() > threshold;
+
+        let before = self.hibernating.len();
+        self.hibernating.retain(|_, hibernated| {
+            !moved(hibernated.collateral_price, price(hibernated.collateral))
+                && !moved(hibernated.debt_price, price(hibernated.debt))
+        });
+        let woken = before - self.hibernating.len();
+        if woken > 0 {
+            tracing::debug!("[🔭 Monitoring] 💤 {woken} positions woke up on a price move");
+        }
+    }
+
+    /// Returns whether the position can skip its evaluation, waking it up if it
+    /// changed since it hibernated.
+    pub fn is_hibernating(&mut self, position: &VesuPosition) -> bool {
+        let position_id = position.position_id();
+        match self.hibernating.get(&position_id) {
+            Some(hibernated) if hibernated.changed(position) => {
+                self.hibernating.remove(&position_id);
+                false
+            }
+            Some(_) => true,
+            None => false,
+        }
+    }
+
+    /// Drops a position evicted from memory.
+    pub fn forget(&mut self, position_id: &str) {
+        self.hibernating.remove(position_id);
+    }
+
+    /// Puts the evaluated position to sleep if it is far enough from its LLTV.
+    pub fn record(&mut self, position: &VesuPosition, evaluation: &HealthEvaluation) {
+        let asleep = !evaluation.is_at_risk
+            && evaluation.deleverage.is_none()
+            && evaluation.ltv < position.lltv * self.config.ltv_pct / Decimal::ONE_HUNDRED;
+        if asleep {
+            self.hibernating
+                .insert(position.position_id(), HibernatedPosition::new(position));
+        } else {
+            self.hibernating.remove(&position.position_id());
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+use starknet::core::types::Felt;
+use uuid::Uuid;
+
+use crate::services::monitoring::latency::StageTimings;
+use crate::types::position::VesuPosition;
+
+/// A liquidation that has been sent but not yet resolved.
+#[derive(Debug, Clone)]
+pub struct InFlightLiquidation {
+    /// Id of the intent that got sent.
+    pub intent_id: Uuid,
+    pub position: VesuPosition,
+    pub tx_hash: Felt,
+    /// Repaid from the inventory instead of a swap of the collateral.
+    pub from_inventory: bool,
+    /// Time spent in the stages before the send.
+    pub timings: StageTimings,
+    pub sent_at: Instant,
+    pub expires_at: Instant,
+}
+
+/// Tracks the liquidations sent per position so that we don't re-send the same
+/// liquidation while the previous attempt is still pending.
+#[derive(Debug, Default)]
+pub struct InFlightLiquidations {
+    by_position: HashMap<String, InFlightLiquidation>,
+}
+
+impl InFlightLiquidations {
+    pub const DEFAULT_EXPIRY: Duration = Duration::from_secs(60);
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a new pending liquidation for the position.
+    pub fn insert(
+        &mut self,
+        intent_id: Uuid,
+        position: &VesuPosition,
+        tx_hash: Felt,
+        from_inventory: bool,
+        timings: StageTimings,
+    ) {
+        self.by_position.insert(
+            position.position_id(),
+            InFlightLiquidation {
+                intent_id,
+                position: position.clone(),
+                tx_hash,
+                from_inventory,
+                timings,
+                sent_at: Instant::now(),
+                expires_at: Instant::now() + Self::DEFAULT_EXPIRY,
+            },
+        );
+    }
+
+    /// Returns true if a liquidation is still pending for the position.
+    pub fn is_pending(&self, position_id: &str) -> bool {
+        self.by_position
+            .get(position_id)
+            .is_some_and(|l| l.expires_at > Instant::now())
+    }
+
+    /// Returns the number of transactions still pending, a batch counting once.
+    pub fn pending_txs(&self) -> usize {
+        let now = Instant::now();
+        self.by_position
+            .values()
+            .filter(|l| l.expires_at > now)
+            .map(|l| l.tx_hash)
+            .collect::<HashSet<_>>()
+            .len()
+    }
+
+    /// Marks the liquidation of the position as resolved.
+    pub fn resolve(&mut self, position_id: &str) -> Option<InFlightLiquidation> {
+        self.by_position.remove(position_id)
+    }
+
+    /// Removes all the expired liquidations & returns their tx hashes.
+    pub fn prune_expired(&mut self) -> Vec<Felt> {
+        let now = Instant::now();
+        let mut expired = Vec::new();
+        self.by_position.retain(|position_id, l| {
+            let keep = l.expires_at > now;
+            if !keep {
+                expired.push(l.tx_hash);
+                tracing::warn!(
+                    intent_id = %l.intent_id,
+                    "[🔭 Monitoring] ⌛ Liquidation of position #{position_id} (tx {:#064x}) timed out",
+                    l.tx_hash
+                );
+            }
+            keep
+        });
+        expired
+    }
+
+    /// Returns the pending liquidations.
+    pub fn pending(&self) -> Vec<InFlightLiquidation> {
+        self.by_position.values().cloned().collect()
+    }
+}
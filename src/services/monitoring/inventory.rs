@@ -0,0 +1,105 @@
+use std::collections::HashSet;
+
+use num_traits::Pow;
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use starknet::core::types::{Call, Felt};
+use starknet::macros::selector;
+
+use crate::services::monitoring::prechecks::RequiredAllowance;
+use crate::types::currency::Currency;
+use crate::types::position::VesuPosition;
+
+/// Debt covered by a full repayment from the inventory, over the known debt of
+/// the position, for the interest accrued since its last event. The pool only
+/// takes the debt of the position.
+const FULL_REPAYMENT_MARGIN: Decimal = dec!(1.001);
+
+/// Share of the expected collateral an inventory liquidation may receive less
+/// of, for the gap between our prices & the ones of the oracle of the pool.
+const MIN_COLLATERAL_TOLERANCE: Decimal = dec!(0.02);
+
+/// Liquidates the positions borrowing these assets from the balance of the
+/// signer when it covers their debt: the debt is repaid directly to the pool &
+/// the seized collateral kept, without the swap of the liquidate contract. The
+/// collateral is settled later by the treasury. It stays with the signer: the
+/// `--recipient` only receives the collateral of the swapped liquidations.
+#[derive(Debug, Clone, Default)]
+pub struct InventoryConfig {
+    pub assets: HashSet<Currency>,
+}
+
+impl InventoryConfig {
+    /// Returns the debt the inventory must cover to liquidate the position, None
+    /// if its debt asset is not repaid from the inventory.
+    pub fn debt_to_cover(
+        &self,
+        position: &VesuPosition,
+        debt_to_repay: Option<Decimal>,
+    ) -> Option<Decimal> {
+        if !self.assets.contains(&position.debt.currency) {
+            return None;
+        }
+        Some(debt_to_repay.unwrap_or(position.debt.amount * FULL_REPAYMENT_MARGIN))
+    }
+}
+
+/// The collateral the liquidation must at least seize: the value of the debt
+/// it repays - the pool seizes more, its liquidation bonus on top - up to the
+/// collateral of the position, minus `MIN_COLLATERAL_TOLERANCE`.
+fn min_collateral_to_receive(
+    position: &VesuPosition,
+    debt_to_repay: Decimal,
+) -> anyhow::Result<Decimal> {
+    let collateral_price = position.collateral.currency.price();
+    anyhow::ensure!(
+        !collateral_price.is_zero(),
+        "no price for {}, the collateral to receive cannot be bounded",
+        position.collateral.currency
+    );
+    let debt_value = debt_to_repay.min(position.debt.amount) * position.debt.currency.price();
+    let expected = (debt_value / collateral_price).min(position.collateral.amount);
+    Ok(expected * (Decimal::ONE - MIN_COLLATERAL_TOLERANCE))
+}
+
+/// Returns the `liquidate_position` of the pool liquidating the position from
+/// the balance of the signer, along with the allowance of the debt asset it
+/// needs toward the pool.
+pub fn inventory_liquidation_call(
+    position: &VesuPosition,
+    debt_to_repay: Decimal,
+) -> anyhow::Result<(Call, RequiredAllowance)> {
+    let raw_amount: u128 = (debt_to_repay * Decimal::TEN.pow(position.debt.decimals))
+        .trunc()
+        .try_into()?;
+    let raw_min_collateral: u128 = (min_collateral_to_receive(position, debt_to_repay)?
+        * Decimal::TEN.pow(position.collateral.decimals))
+    .trunc()
+    .try_into()?;
+    let pool = position.pool_name.pool_address();
+
+    // LiquidatePositionParams { collateral_asset, debt_asset, user,
+    // min_collateral_to_receive: u256, debt_to_repay: u256 }.
+    let liquidate_position = Call {
+        to: pool,
+        selector: selector!("liquidate_position"),
+        calldata: vec![
+            position.collateral.address,
+            position.debt.address,
+            position.user_address,
+            raw_min_collateral.into(),
+            Felt::ZERO,
+            raw_amount.into(),
+            Felt::ZERO,
+        ],
+    };
+
+    Ok((
+        liquidate_position,
+        RequiredAllowance {
+            currency: position.debt.currency,
+            spender: pool,
+            amount: debt_to_repay,
+        },
+    ))
+}
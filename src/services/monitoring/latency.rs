@@ -0,0 +1,195 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::Duration;
+
+use serde::Serialize;
+use uuid::Uuid;
+
+/// Upper bounds of the buckets of the latency histograms, in seconds.
+const BUCKET_BOUNDS: [f64; 10] = [0.05, 0.1, 0.25, 0.5, 1.0, 2.0, 5.0, 10.0, 30.0, 60.0];
+
+// Latency of the stages of the liquidation attempts, readable from the API.
+pub static LIQUIDATION_LATENCY: LazyLock<Arc<LiquidationLatency>> =
+    LazyLock::new(|| Arc::new(LiquidationLatency::default()));
+
+/// The stages a liquidation goes through, from its intent to its receipt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, strum::Display)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum Stage {
+    /// Building the liquidation: the swap route & the depth cap.
+    Route,
+    /// Simulating the batch of liquidations before sending it.
+    Simulation,
+    /// The checks of the account before sending: its fee balance & allowances.
+    Precheck,
+    /// Sending the transaction. The account estimates its fee, signs it &
+    /// broadcasts it in one go, so the signing is part of it.
+    Submission,
+    /// From the send to the receipt of the transaction, as seen by the polling
+    /// of the receipts.
+    Confirmation,
+}
+
+/// Time spent in each stage of a liquidation attempt.
+#[derive(Debug, Clone, Default)]
+pub struct StageTimings(BTreeMap<Stage, Duration>);
+
+impl StageTimings {
+    pub fn record(&mut self, stage: Stage, elapsed: Duration) {
+        self.0.insert(stage, elapsed);
+    }
+
+    pub fn total(&self) -> Duration {
+        self.0.values().sum()
+    }
+
+    /// stage => milliseconds spent in it
+    pub fn stages_ms(&self) -> BTreeMap<Stage, u64> {
+        self.0
+            .iter()
+            .map(|(stage, elapsed)| (*stage, elapsed.as_millis() as u64))
+            .collect()
+    }
+}
+
+impl fmt::Display for StageTimings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let stages: Vec<String> = self
+            .0
+            .iter()
+            .map(|(stage, elapsed)| format!("{stage} {elapsed:.2?}"))
+            .collect();
+        write!(f, "{}", stages.join(", "))
+    }
+}
+
+/// The latency budget of one liquidation attempt.
+#[derive(Debug, Clone, Serialize)]
+pub struct AttemptLatency {
+    pub intent_id: String,
+    pub position_id: String,
+    /// None if the send failed.
+    pub tx_hash: Option<String>,
+    /// stage => milliseconds spent in it
+    pub stages_ms: BTreeMap<Stage, u64>,
+    pub total_ms: u64,
+}
+
+/// Prometheus histogram of the seconds spent in a stage.
+#[derive(Debug, Default)]
+struct Histogram {
+    /// Counts per bucket, not cumulative.
+    buckets: [u64; BUCKET_BOUNDS.len()],
+    sum_secs: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, elapsed: Duration) {
+        let secs = elapsed.as_secs_f64();
+        if let Some(bucket) = BUCKET_BOUNDS.iter().position(|bound| secs <= *bound) {
+            self.buckets[bucket] += 1;
+        }
+        self.sum_secs += secs;
+        self.count += 1;
+    }
+}
+
+/// Keeps the latency histograms of every stage & the latest attempts, so the
+/// time lost to e.g the route building is visible.
+#[derive(Debug, Default)]
+pub struct LiquidationLatency {
+    histograms: Mutex<BTreeMap<Stage, Histogram>>,
+    recent: Mutex<VecDeque<AttemptLatency>>,
+    /// stage => attempts that ran out of their budget in it
+    budget_exceeded: Mutex<BTreeMap<Stage, u64>>,
+}
+
+impl LiquidationLatency {
+    /// Number of attempts kept.
+    const CAPACITY: usize = 100;
+
+    /// Records the stages reached by a liquidation attempt.
+    pub fn record(
+        &self,
+        intent_id: Uuid,
+        position_id: String,
+        tx_hash: Option<String>,
+        timings: &StageTimings,
+    ) {
+        let mut histograms = self.histograms.lock().expect("poisoned latency histograms");
+        for (stage, elapsed) in &timings.0 {
+            histograms.entry(*stage).or_default().observe(*elapsed);
+        }
+        drop(histograms);
+
+        let attempt = AttemptLatency {
+            intent_id: intent_id.to_string(),
+            position_id,
+            tx_hash,
+            stages_ms: timings.stages_ms(),
+            total_ms: timings.total().as_millis() as u64,
+        };
+        let mut recent = self.recent.lock().expect("poisoned latency attempts");
+        if recent.len() == Self::CAPACITY {
+            recent.pop_front();
+        }
+        recent.push_back(attempt);
+    }
+
+    /// Records an attempt aborted for running out of its budget in the stage.
+    pub fn record_budget_exceeded(&self, stage: Stage) {
+        let mut budget_exceeded = self
+            .budget_exceeded
+            .lock()
+            .expect("poisoned latency budgets");
+        *budget_exceeded.entry(stage).or_default() += 1;
+    }
+
+    /// The latest attempts, the most recent first.
+    pub fn recent(&self) -> Vec<AttemptLatency> {
+        let recent = self.recent.lock().expect("poisoned latency attempts");
+        recent.iter().rev().cloned().collect()
+    }
+
+    pub fn prometheus_metric(&self) -> String {
+        let mut metric = String::from(
+            "# HELP liquidation_stage_seconds Seconds spent in each stage of the liquidations.\n\
+             # TYPE liquidation_stage_seconds histogram\n",
+        );
+        let histograms = self.histograms.lock().expect("poisoned latency histograms");
+        for (stage, histogram) in histograms.iter() {
+            let mut cumulative = 0;
+            for (bound, count) in BUCKET_BOUNDS.iter().zip(histogram.buckets) {
+                cumulative += count;
+                metric.push_str(&format!(
+                    "liquidation_stage_seconds_bucket{{stage=\"{stage}\",le=\"{bound}\"}} {cumulative}\n"
+                ));
+            }
+            metric.push_str(&format!(
+                "liquidation_stage_seconds_bucket{{stage=\"{stage}\",le=\"+Inf\"}} {}\n\
+                 liquidation_stage_seconds_sum{{stage=\"{stage}\"}} {}\n\
+                 liquidation_stage_seconds_count{{stage=\"{stage}\"}} {}\n",
+                histogram.count, histogram.sum_secs, histogram.count
+            ));
+        }
+        drop(histograms);
+
+        metric.push_str(
+            "# HELP liquidation_budget_exceeded_total Liquidation attempts aborted for running out of their budget, per stage.\n\
+             # TYPE liquidation_budget_exceeded_total counter\n",
+        );
+        let budget_exceeded = self
+            .budget_exceeded
+            .lock()
+            .expect("poisoned latency budgets");
+        for (stage, count) in budget_exceeded.iter() {
+            metric.push_str(&format!(
+                "liquidation_budget_exceeded_total{{stage=\"{stage}\"}} {count}\n"
+            ));
+        }
+        metric
+    }
+}
@@ -0,0 +1,83 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use starknet::core::types::Felt;
+
+use crate::types::pool::PoolName;
+use crate::types::position::VesuPosition;
+
+/// How long a position must stay liquidable before it gets liquidated, e.g to
+/// give its user the time to react instead of racing them.
+#[derive(Debug, Clone, Default)]
+pub struct LiquidationDelayConfig {
+    /// Delay of the positions without a more specific one.
+    pub default: Duration,
+    /// Delay of the positions of a pool.
+    pub per_pool: HashMap<PoolName, Duration>,
+    /// Delay of the positions of a user, over the one of its pool.
+    pub per_user: HashMap<Felt, Duration>,
+}
+
+impl LiquidationDelayConfig {
+    pub fn delay_for(&self, position: &VesuPosition) -> Duration {
+        self.per_user
+            .get(&position.user_address)
+            .or_else(|| self.per_pool.get(&position.pool_name))
+            .copied()
+            .unwrap_or(self.default)
+    }
+}
+
+/// Tracks since when the positions are liquidable & holds their liquidation
+/// until their delay elapsed.
+#[derive(Debug)]
+pub struct LiquidationDelay {
+    config: LiquidationDelayConfig,
+    liquidable_since: HashMap<String, Instant>,
+}
+
+impl LiquidationDelay {
+    pub fn new(config: LiquidationDelayConfig) -> Self {
+        Self {
+            config,
+            liquidable_since: HashMap::new(),
+        }
+    }
+
+    /// Records whether the position is liquidable: its delay runs from when it
+    /// became liquidable, whatever the strategy decides of it meanwhile, &
+    /// restarts the next time it is once it no longer is.
+    pub fn track(&mut self, position: &VesuPosition, is_liquidable: bool) {
+        if is_liquidable {
+            self.liquidable_since
+                .entry(position.position_id())
+                .or_insert_with(Instant::now);
+        } else {
+            self.clear(&position.position_id());
+        }
+    }
+
+    /// Returns the remaining time before the position can be liquidated, None if
+    /// its delay elapsed. The whole delay remains if it is not liquidable yet,
+    /// e.g when the strategy acts ahead of its LLTV.
+    pub fn remaining(&self, position: &VesuPosition) -> Option<Duration> {
+        let delay = self.config.delay_for(position);
+        if delay.is_zero() {
+            return None;
+        }
+
+        let elapsed = self
+            .liquidable_since
+            .get(&position.position_id())
+            .map_or(Duration::ZERO, Instant::elapsed);
+        delay
+            .checked_sub(elapsed)
+            .filter(|remaining| !remaining.is_zero())
+    }
+
+    /// Forgets the position, e.g once it is no longer liquidable or no longer
+    /// in memory.
+    pub fn clear(&mut self, position_id: &str) {
+        self.liquidable_since.remove(position_id);
+    }
+}
@@ -0,0 +1,170 @@
+use std::fmt;
+use std::sync::{Arc, LazyLock};
+
+use dashmap::DashMap;
+use starknet::accounts::AccountError;
+use starknet::core::types::StarknetError;
+use starknet::providers::ProviderError;
+
+use crate::services::monitoring::latency::Stage;
+
+// Failed liquidations per kind of error, readable from the API.
+pub static LIQUIDATION_ERRORS: LazyLock<Arc<LiquidationErrors>> =
+    LazyLock::new(|| Arc::new(LiquidationErrors::default()));
+
+/// Why a liquidation failed. Classified once, where the error happens, so the
+/// retries, the logs & the metrics match on it instead of on the messages.
+#[derive(Debug)]
+pub enum LiquidationError {
+    /// The position is not liquidable anymore, e.g already liquidated.
+    NotUndercollateralized,
+    /// The transaction got rejected when sent.
+    Revert { reason: String },
+    /// The transaction reverted in its simulation or its fee estimation.
+    Simulation { reason: String },
+    /// The nonce of the transaction is not the next one of the account.
+    InvalidNonce,
+    /// The account cannot pay the fee of the transaction.
+    FeeTooHigh { reason: String },
+    /// No swap route for the collateral to seize.
+    RouteNotFound { reason: String },
+    /// The RPC itself failed: transport error, rate limit...
+    Rpc { reason: String },
+    /// The account could not build or sign the transaction.
+    Account { reason: String },
+    /// The attempt ran out of its budget in this stage, before its send.
+    AttemptTimeout { stage: Stage },
+    /// The account already has the maximum number of transactions in flight.
+    InFlightLimit { in_flight: usize },
+}
+
+impl LiquidationError {
+    pub fn from_account_error<S: fmt::Debug>(e: AccountError<S>) -> Self {
+        match e {
+            AccountError::Provider(e) => Self::from_provider_error(e),
+            AccountError::FeeOutOfRange => Self::FeeTooHigh {
+                reason: "the fee is out of range".into(),
+            },
+            e => Self::Account {
+                reason: format!("{e:?}"),
+            },
+        }
+    }
+
+    pub fn from_provider_error(e: ProviderError) -> Self {
+        match e {
+            ProviderError::StarknetError(e) => match e {
+                StarknetError::InvalidTransactionNonce { .. } => Self::InvalidNonce,
+                e @ (StarknetError::InsufficientAccountBalance { .. }
+                | StarknetError::InsufficientResourcesForValidate { .. }) => Self::FeeTooHigh {
+                    reason: format!("{e:?}"),
+                },
+                e => Self::revert(format!("{e:?}")),
+            },
+            e => Self::Rpc {
+                reason: format!("{e:?}"),
+            },
+        }
+    }
+
+    /// The position being healthy is only known from the panic of the Vesu
+    /// contract, hence the one match on the revert reason.
+    pub fn revert(reason: String) -> Self {
+        if reason.contains("not-undercollateralized") {
+            Self::NotUndercollateralized
+        } else {
+            Self::Revert { reason }
+        }
+    }
+
+    /// The same error, raised by a simulation instead of a send.
+    pub fn in_simulation(self) -> Self {
+        match self {
+            Self::Revert { reason } => Self::Simulation { reason },
+            e => e,
+        }
+    }
+
+    /// Whether sending the same transaction again may succeed.
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, Self::InvalidNonce | Self::Rpc { .. })
+    }
+
+    /// Whether the provider failed rather than the call, for its circuit breaker.
+    pub fn is_provider_failure(&self) -> bool {
+        matches!(self, Self::Rpc { .. })
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::NotUndercollateralized => "not_undercollateralized",
+            Self::Revert { .. } => "revert",
+            Self::Simulation { .. } => "simulation",
+            Self::InvalidNonce => "invalid_nonce",
+            Self::FeeTooHigh { .. } => "fee_too_high",
+            Self::RouteNotFound { .. } => "route_not_found",
+            Self::Rpc { .. } => "rpc",
+            Self::Account { .. } => "account",
+            Self::AttemptTimeout { .. } => "attempt_timeout",
+            Self::InFlightLimit { .. } => "in_flight_limit",
+        }
+    }
+}
+
+impl fmt::Display for LiquidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotUndercollateralized => write!(f, "the position is not undercollateralized"),
+            Self::Revert { reason } => write!(f, "the transaction got rejected: {reason}"),
+            Self::Simulation { reason } => write!(f, "the simulation reverted: {reason}"),
+            Self::InvalidNonce => write!(f, "invalid nonce"),
+            Self::FeeTooHigh { reason } => write!(f, "the account cannot pay the fee: {reason}"),
+            Self::RouteNotFound { reason } => write!(f, "no swap route: {reason}"),
+            Self::Rpc { reason } => write!(f, "RPC error: {reason}"),
+            Self::Account { reason } => write!(f, "account error: {reason}"),
+            Self::AttemptTimeout { stage } => {
+                write!(f, "the attempt ran out of its budget in the {stage} stage")
+            }
+            Self::InFlightLimit { in_flight } => {
+                write!(
+                    f,
+                    "{in_flight} transactions of the account already in flight"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for LiquidationError {}
+
+/// Counts the failed liquidations per kind of error.
+#[derive(Debug, Default)]
+pub struct LiquidationErrors {
+    /// kind => failed liquidations
+    by_kind: DashMap<&'static str, u64>,
+}
+
+impl LiquidationErrors {
+    /// Records a failed liquidation, as `other` if its error is not classified.
+    pub fn record(&self, e: &anyhow::Error) {
+        let kind = e
+            .downcast_ref::<LiquidationError>()
+            .map_or("other", LiquidationError::kind);
+        *self.by_kind.entry(kind).or_default() += 1;
+    }
+
+    pub fn prometheus_metric(&self) -> String {
+        let mut metric = String::from(
+            "# HELP liquidation_errors_total Failed liquidations per kind of error.\n\
+             # TYPE liquidation_errors_total counter\n",
+        );
+        for entry in self.by_kind.iter() {
+            metric.push_str(&format!(
+                "liquidation_errors_total{{kind=\"{}\"}} {}\n",
+                entry.key(),
+                entry.value()
+            ));
+        }
+        metric
+    }
+}
@@ -0,0 +1,110 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::Result;
+use evian::vesu::v2::data::VesuDataClient;
+use futures_util::future::join_all;
+use pragma_common::starknet::FallbackProvider;
+use rust_decimal::Decimal;
+
+use crate::services::indexer::IndexerService;
+use crate::types::currency::Currency;
+use crate::types::pair_config::PAIR_CONFIGS;
+use crate::types::pool::PoolName;
+use crate::utils::rpc::{RpcPath, RpcProvider, guarded};
+
+/// A monitored pair: (pool, collateral, debt).
+pub type Pair = (PoolName, Currency, Currency);
+
+/// Reads the current LLTV of every monitored pair.
+pub async fn fetch_pair_lltvs(
+    vesu_client: &Arc<VesuDataClient<FallbackProvider>>,
+) -> Vec<(Pair, Result<Decimal>)> {
+    let fetches =
+        IndexerService::monitored_pairs()
+            .into_iter()
+            .map(|(pool, collateral, debt)| async move {
+                let lltv = guarded(
+                    RpcProvider::Starknet,
+                    RpcPath::Background,
+                    vesu_client.pair_config(
+                        pool.pool_address(),
+                        collateral.address(),
+                        debt.address(),
+                        None,
+                    ),
+                )
+                .await
+                .map(|pair_config| pair_config.max_ltv);
+                ((pool, collateral, debt), lltv)
+            });
+
+    join_all(fetches).await
+}
+
+/// Returns the monitored pairs with a zero LLTV: their positions can never be
+/// liquidated.
+pub fn zero_lltv_pairs(lltvs: &[(Pair, Result<Decimal>)]) -> Vec<Pair> {
+    lltvs
+        .iter()
+        .filter(|(_, lltv)| lltv.as_ref().is_ok_and(|lltv| lltv.is_zero()))
+        .map(|(pair, _)| *pair)
+        .collect()
+}
+
+/// Tracks the LLTV of the monitored pairs to alert when one is zero or changes.
+#[derive(Debug, Default)]
+pub struct LltvWatcher {
+    known: HashMap<Pair, Decimal>,
+}
+
+impl LltvWatcher {
+    /// Reads the LLTVs, alerts on the zero & changed ones and returns the pairs
+    /// whose LLTV changed since the previous check.
+    pub async fn check(
+        &mut self,
+        vesu_client: &Arc<VesuDataClient<FallbackProvider>>,
+    ) -> Vec<(Pair, Decimal)> {
+        let lltvs = fetch_pair_lltvs(vesu_client).await;
+
+        let zero_pairs = zero_lltv_pairs(&lltvs);
+        if !zero_pairs.is_empty() {
+            tracing::error!(
+                "[🔭 Monitoring] 🚫 {} monitored pairs have a zero LLTV & will never be liquidated: {}",
+                zero_pairs.len(),
+                zero_pairs
+                    .iter()
+                    .map(|(pool, collateral, debt)| format!("{pool} {collateral}/{debt}"))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+
+        let mut changed = Vec::new();
+        for (pair, lltv) in lltvs {
+            let lltv = match lltv {
+                Ok(lltv) => lltv,
+                Err(e) => {
+                    let (pool, collateral, debt) = pair;
+                    tracing::debug!(
+                        "[🔭 Monitoring] Could not read the LLTV of {pool} {collateral}/{debt}: {e}"
+                    );
+                    continue;
+                }
+            };
+
+            match self.known.insert(pair, lltv) {
+                Some(previous) if previous != lltv => {
+                    let (pool, collateral, debt) = pair;
+                    tracing::warn!(
+                        "[🔭 Monitoring] 🔀 LLTV of {pool} {collateral}/{debt} changed from {previous} to {lltv}"
+                    );
+                    PAIR_CONFIGS.invalidate_pair(pool, collateral.address(), debt.address());
+                    changed.push((pair, lltv));
+                }
+                _ => {}
+            }
+        }
+        changed
+    }
+}
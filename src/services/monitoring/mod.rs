@@ -1,56 +1,250 @@
+pub mod avnu;
+pub mod calibration;
+pub mod capacity;
+pub mod collateralization;
+pub mod competitors;
+pub mod depeg;
+pub mod depth;
 pub mod ekubo;
+pub mod evaluation;
+pub mod executor;
+pub mod fee_cache;
+pub mod health_history;
+pub mod health_summary;
+pub mod hibernation;
+pub mod in_flight;
+pub mod inventory;
+pub mod latency;
+pub mod liquidation_delay;
+pub mod liquidation_error;
+pub mod lltv_check;
+pub mod opportunities;
+pub mod pool_pause;
+pub mod post_mortem;
+pub mod prechecks;
+pub mod protect;
+pub mod quarantine;
+pub mod receipt;
+pub mod rollback;
+pub mod route_preflight;
+pub mod route_quotes;
+pub mod strategy;
 pub mod task;
+pub mod unknown_pools;
+pub mod user_scope;
+pub mod utilization;
+pub mod value_at_risk;
+pub mod wal;
+pub mod watchlist;
 
-use std::collections::HashMap;
+use std::cmp::Reverse;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
 use std::hash::{Hash, Hasher};
+use std::str::FromStr;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-use evian::{utils::indexer::handler::StarknetEventMetadata, vesu::v2::data::VesuDataClient};
-use pragma_common::starknet::{FallbackProvider, StarknetNetwork};
-use starknet::core::types::Felt;
-use starknet::macros::felt_hex;
+use evian::vesu::v2::data::VesuDataClient;
+use futures_util::future::join_all;
+use num_traits::Pow;
+use pragma_common::starknet::FallbackProvider;
+use rust_decimal::Decimal;
+use starknet::core::types::{BlockId, Felt};
+use starknet::providers::Provider;
 use tokio::sync::{mpsc, oneshot};
+use uuid::Uuid;
 
-use crate::bindings::liquidate::Liquidate;
-use crate::services::indexer::PositionDelta;
+use crate::config::addresses::NETWORK;
+use crate::config::onchain_assets::{ONCHAIN_ASSETS, UNLISTED_ASSETS};
+use crate::services::chain_head::CHAIN_HEAD;
+use crate::services::indexer::lag::INDEXER_LAG;
+use crate::services::indexer::{EventId, EventMetadata, IndexedEvent, PositionDelta};
+use crate::services::monitoring::capacity::capacity_check_positions;
+use crate::services::monitoring::collateralization::{
+    COLLATERALIZATION_CHECKS, CollateralizationCheckConfig,
+};
+use crate::services::monitoring::competitors::CompetitorTracker;
+use crate::services::monitoring::depeg::{DepegConfig, DepegGuard};
+use crate::services::monitoring::evaluation::evaluate_health;
+use crate::services::monitoring::executor::{
+    ConfirmedLiquidation, ExecutorConfig, ExecutorHandle, LiquidationIntent,
+};
+use crate::services::monitoring::health_history::HealthHistory;
+use crate::services::monitoring::health_summary::{HealthSummary, HealthyPositionsLog};
+use crate::services::monitoring::hibernation::{Hibernation, HibernationConfig};
+use crate::services::monitoring::liquidation_delay::{LiquidationDelay, LiquidationDelayConfig};
+use crate::services::monitoring::lltv_check::{LltvWatcher, Pair};
+use crate::services::monitoring::opportunities::{
+    LiquidationOpportunity, OpportunityBroadcastConfig, OpportunityBroadcaster,
+};
+use crate::services::monitoring::pool_pause::POOL_PAUSES;
+use crate::services::monitoring::protect::{DeleverageIntent, ProtectConfig};
+use crate::services::monitoring::quarantine::{QUARANTINE, QuarantinedPosition};
+use crate::services::monitoring::rollback::{PositionChange, RollbackBuffer};
+use crate::services::monitoring::route_preflight::check_routes;
+use crate::services::monitoring::strategy::{
+    DebtCap, LiquidationDecision, LiquidationStrategy, StrategyInputs, apply_debt_floor,
+};
+use crate::services::monitoring::unknown_pools::UNKNOWN_POOLS;
+use crate::services::monitoring::user_scope::{ProtectedUsersAction, UserScope};
+use crate::services::monitoring::utilization::POOL_RATES;
+use crate::services::monitoring::value_at_risk::VALUE_AT_RISK;
+use crate::services::monitoring::wal::{
+    EventCursor, EvictedPosition, RecoveredState, WriteAheadLog,
+};
+use crate::services::monitoring::watchlist::WATCHLIST;
+use crate::services::notifier::{AlertKind, NOTIFIER};
 use crate::services::oracle::vesu_prices::VESU_PRICES;
-use crate::types::account::StarknetSingleOwnerAccount;
+use crate::types::currency::Currency;
 use crate::types::pool::PoolName;
-use crate::types::{account::StarknetAccount, position::VesuPosition};
+use crate::types::position::{Asset, VesuPosition};
+use crate::utils::erc20::{balance_of, token_metadata};
+use crate::utils::format::{format_amount, format_usd};
+use crate::utils::rpc::{RpcPath, guarded_starknet};
 
 pub struct MonitoringService {
     pub vesu_client: Arc<VesuDataClient<FallbackProvider>>,
-    pub rx_from_indexer: mpsc::UnboundedReceiver<(StarknetEventMetadata, PositionDelta)>,
+    pub rx_from_indexer: mpsc::UnboundedReceiver<IndexedEvent>,
     pub current_positions: HashMap<(PoolName, String), VesuPosition>,
+    /// Positions evicted from memory, re-hydrated from the chain on their next
+    /// event & dropped once closed.
+    evicted: HashMap<(PoolName, String), EvictedPosition>,
     wait_for_indexer: Option<oneshot::Receiver<()>>,
-    liquidate_contract: Arc<Liquidate<StarknetSingleOwnerAccount>>,
-    account: StarknetAccount,
+    executor: ExecutorHandle,
+    /// Positions we liquidated, skipped until their liquidation event arrives.
+    pending_close: HashMap<String, Instant>,
+    /// Liquidable positions of the protected users already alerted on.
+    alerted: HashSet<String>,
+    provider: FallbackProvider,
+    account_address: Felt,
+    health_history: HashMap<String, HealthHistory>,
+    depeg_guard: DepegGuard,
+    liquidation_delay: LiquidationDelay,
+    /// The very healthy positions skipped by the checks, None to check them all.
+    hibernation: Option<Hibernation>,
+    /// Id of the liquidation intent of every liquidable position, kept while it
+    /// stays liquidable so all the attempts at liquidating it share it.
+    intent_ids: HashMap<String, Uuid>,
+    /// Balances of the liquidator account, given to the strategy.
+    inventory: HashMap<Currency, Decimal>,
+    /// Unlisted assets whose metadata could not be read & when, read again
+    /// after `UNLISTED_METADATA_RETRY_DELAY`.
+    unlisted_metadata_failures: HashMap<Felt, Instant>,
+    wal: Option<WriteAheadLog>,
+    recovered_state: Option<RecoveredState>,
+    /// Last event applied to the positions.
+    cursor: EventCursor,
+    /// The events applied on top of the finalized block.
+    rollback: RollbackBuffer,
+    competitors: CompetitorTracker,
+    lltv_watcher: LltvWatcher,
+    /// Publishes the liquidable positions, if configured.
+    broadcaster: Option<OpportunityBroadcaster>,
+    config: MonitoringConfig,
+}
+
+#[derive(Debug, Clone)]
+pub struct MonitoringConfig {
+    pub executor: ExecutorConfig,
+    /// Decides if and how the positions get liquidated.
+    pub strategy: Arc<dyn LiquidationStrategy>,
+    /// If set, caps the debt repaid by the decisions of the strategy.
+    pub debt_cap: Option<DebtCap>,
+    pub depeg: DepegConfig,
+    /// How long the positions must stay liquidable before being liquidated.
+    pub liquidation_delay: LiquidationDelayConfig,
+    /// A position within this % of its LLTV counts in the value at risk.
+    pub value_at_risk_threshold_pct: Decimal,
+    /// Maximum number of positions kept in memory per pair before evicting the
+    /// least recently updated dust positions.
+    pub max_positions_per_pair: usize,
+    /// Positions with less debt than this are dust & can get evicted.
+    pub dust_position_usd: Decimal,
+    /// If set, all the known positions get re-read from the chain state at this
+    /// interval - a safety net against missed events.
+    pub full_scan_interval: Option<Duration>,
+    /// If set, only the positions of these users are monitored.
+    pub user_scope: Option<UserScope>,
+    /// If set, our own positions get deleveraged before being liquidable.
+    pub protect: Option<ProtectConfig>,
+    /// How the healthy positions get logged at each check.
+    pub healthy_positions_log: HealthyPositionsLog,
+    /// If set, samples of the positions get checked against the
+    /// collateralization check of their pool.
+    pub collateralization_check: Option<CollateralizationCheckConfig>,
+    /// Where the liquidable positions get published, for other systems to
+    /// liquidate them.
+    pub opportunities: OpportunityBroadcastConfig,
+    /// Only detects the liquidable positions, without liquidating them.
+    pub watch_only: bool,
+    /// If set, the positions far from their LLTV are only evaluated again once
+    /// a price moved or an event changed them.
+    pub hibernation: Option<HibernationConfig>,
+    /// Number of pairs whose riskiest position gets a simulated liquidation
+    /// once the positions are synced, to catch a pair that could not be
+    /// liquidated before a real opportunity. 0 to skip it.
+    pub capacity_check_pairs: usize,
 }
 
 impl MonitoringService {
     pub fn new(
         provider: FallbackProvider,
-        account: StarknetAccount,
-        rx_from_indexer: mpsc::UnboundedReceiver<(StarknetEventMetadata, PositionDelta)>,
+        account_address: Felt,
+        executor: ExecutorHandle,
+        rx_from_indexer: mpsc::UnboundedReceiver<IndexedEvent>,
         wait_for_indexer: oneshot::Receiver<()>,
+        wal: Option<(WriteAheadLog, RecoveredState)>,
+        config: MonitoringConfig,
     ) -> Self {
-        const LIQUIDATE_CONTRACT_ADDRESS: Felt =
-            felt_hex!("0x6b895ba904fb8f02ed0d74e343161de48e611e9e771be4cc2c997501dbfb418");
+        let (wal, recovered_state) = wal.unzip();
 
         Self {
-            vesu_client: Arc::new(VesuDataClient::new(StarknetNetwork::Mainnet, provider)),
+            vesu_client: Arc::new(VesuDataClient::new(NETWORK, provider.clone())),
             rx_from_indexer,
             current_positions: HashMap::new(),
+            evicted: HashMap::new(),
             wait_for_indexer: Some(wait_for_indexer),
-            liquidate_contract: Arc::new(Liquidate::new(
-                LIQUIDATE_CONTRACT_ADDRESS,
-                account.0.clone(),
-            )),
-            account,
+            executor,
+            pending_close: HashMap::new(),
+            alerted: HashSet::new(),
+            provider,
+            account_address,
+            health_history: HashMap::new(),
+            depeg_guard: DepegGuard::new(config.depeg.clone()),
+            liquidation_delay: LiquidationDelay::new(config.liquidation_delay.clone()),
+            hibernation: config.hibernation.map(Hibernation::new),
+            intent_ids: HashMap::new(),
+            inventory: HashMap::new(),
+            unlisted_metadata_failures: HashMap::new(),
+            wal,
+            recovered_state,
+            cursor: EventCursor::default(),
+            rollback: RollbackBuffer::default(),
+            competitors: CompetitorTracker::new(account_address),
+            lltv_watcher: LltvWatcher::default(),
+            broadcaster: config
+                .opportunities
+                .is_enabled()
+                .then(|| OpportunityBroadcaster::spawn(config.opportunities.clone())),
+            config,
         }
     }
 
+    const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(300);
+    const ROUTE_PREFLIGHT_INTERVAL: Duration = Duration::from_secs(3600);
+    const LLTV_CHECK_INTERVAL: Duration = Duration::from_secs(3600);
+    const COLLATERALIZATION_CHECK_INTERVAL: Duration = Duration::from_secs(600);
+    const POOL_RATES_INTERVAL: Duration = Duration::from_secs(300);
+    const POOL_PAUSE_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+    const INVENTORY_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+    const UNLISTED_METADATA_RETRY_DELAY: Duration = Duration::from_secs(600);
+    /// Number of the riskiest positions re-checked at each new block.
+    const NEW_BLOCK_CHECK_LIMIT: usize = 100;
+    /// How long a liquidated position waits for its event before being re-read
+    /// from the chain.
+    const PENDING_CLOSE_TIMEOUT: Duration = Duration::from_secs(300);
+
     pub async fn run_forever(mut self) -> anyhow::Result<()> {
         tracing::info!("[🔭 Monitoring] Waiting for first vesu prices");
         VESU_PRICES.wait_for_first_prices().await;
@@ -60,7 +254,28 @@ impl MonitoringService {
             .take()
             .expect("wait_for_indexer should be present in the Option. The task is ran only once!");
 
+        self.recover_state().await?;
+
         let mut interval = tokio::time::interval(Duration::from_secs(10));
+        let mut checkpoint_interval = tokio::time::interval(Self::CHECKPOINT_INTERVAL);
+        let mut route_preflight_interval = tokio::time::interval(Self::ROUTE_PREFLIGHT_INTERVAL);
+        let mut lltv_check_interval = tokio::time::interval(Self::LLTV_CHECK_INTERVAL);
+        let mut collateralization_check_interval =
+            tokio::time::interval(Self::COLLATERALIZATION_CHECK_INTERVAL);
+        let mut pool_rates_interval = tokio::time::interval(Self::POOL_RATES_INTERVAL);
+        let mut pool_pause_interval = tokio::time::interval(Self::POOL_PAUSE_CHECK_INTERVAL);
+        let mut inventory_interval = tokio::time::interval(Self::INVENTORY_REFRESH_INTERVAL);
+        let full_scan_period = self
+            .config
+            .full_scan_interval
+            .unwrap_or(Self::CHECKPOINT_INTERVAL);
+        let mut full_scan_interval = tokio::time::interval_at(
+            tokio::time::Instant::now() + full_scan_period,
+            full_scan_period,
+        );
+        let mut refreshed_prices = CHAIN_HEAD.subscribe_refreshed_prices();
+        let mut capacity_check_pending =
+            self.config.capacity_check_pairs > 0 && !self.config.watch_only;
 
         loop {
             tokio::select! {
@@ -68,33 +283,71 @@ impl MonitoringService {
                     if let Some((metadata, event)) = maybe_msg {
                         tracing::info!("[🔭 Monitoring] Processing new event from block #{}", metadata.block_number);
 
-                        let pool = PoolName::try_from(&metadata.from_address)?;
-                        let position_key = Self::compute_position_key(metadata.from_address, &event);
-
-                        if let Some(position) = self.current_positions.get_mut(&(pool, position_key.clone())) {
-                            position.update_from_delta(event);
-                        } else {
-                            match VesuPosition::new(&metadata, &self.vesu_client, event).await {
-                                Ok(position) => {
-                                    self.current_positions.insert((pool, position.position_id()), position);
-                                }
-                                Err(e) => {
-                                    tracing::error!("[🔭 Monitoring] Could not new create position: {e}");
-                                }
-                            };
+                        if self.is_applied(&metadata) {
+                            tracing::debug!("[🔭 Monitoring] Skipping event of block #{} already applied before restart", metadata.block_number);
+                            continue;
                         }
 
-                        let to_close = if let Some(position) = self.current_positions.get(&(pool, position_key.clone())) {
-                            position.is_closed()
-                        } else {
-                            false
-                        };
-
-                        if to_close {
-                            self.current_positions.remove(&(pool, position_key));
+                        if let Some(wal) = self.wal.as_mut() {
+                            wal.append(&(metadata.clone(), event.clone()))?;
                         }
 
+                        self.apply_event(metadata, event).await?;
+                    }
+                },
+                Some(confirmed) = self.executor.rx_confirmations.recv() => {
+                    self.intent_ids.remove(&confirmed.position_id);
+                    if self.is_liquidation_applied(&confirmed) {
+                        continue;
+                    }
+                    tracing::debug!(
+                        "[🔭 Monitoring] Position #{} pending close after tx {:#064x}",
+                        confirmed.position_id,
+                        confirmed.tx_hash
+                    );
+                    self.pending_close.insert(confirmed.position_id, Instant::now());
+                },
+                _ = checkpoint_interval.tick() => {
+                    self.evict_dormant_positions();
+                    self.checkpoint();
+                    Self::log_value_at_risk();
+                    self.log_tip_recommendation();
+                },
+                _ = route_preflight_interval.tick() => {
+                    Self::route_preflight().await;
+                },
+                _ = lltv_check_interval.tick() => {
+                    let changed = self.lltv_watcher.check(&self.vesu_client).await;
+                    self.apply_lltv_changes(&changed);
+                },
+                _ = collateralization_check_interval.tick() => {
+                    let Some(config) = self.config.collateralization_check else {
+                        continue;
+                    };
+                    if wait_for_indexer.is_empty() || !self.rx_from_indexer.is_empty() {
+                        continue;
+                    }
 
+                    COLLATERALIZATION_CHECKS
+                        .check(&self.provider, self.current_positions.values(), config)
+                        .await;
+                },
+                _ = pool_rates_interval.tick() => {
+                    POOL_RATES.refresh(&self.provider).await;
+                },
+                _ = pool_pause_interval.tick() => {
+                    POOL_PAUSES.refresh(&self.provider).await;
+                },
+                _ = inventory_interval.tick() => {
+                    self.refresh_inventory().await;
+                },
+                _ = full_scan_interval.tick(), if self.config.full_scan_interval.is_some() => {
+                    if wait_for_indexer.is_empty() {
+                        continue;
+                    }
+
+                    if let Err(e) = self.full_scan().await {
+                        tracing::error!("[🔭 Monitoring] Full scan failed: {e}");
                     }
                 },
                 _ = interval.tick() => {
@@ -102,32 +355,1026 @@ impl MonitoringService {
                         continue;
                     }
 
-                    for p in self.current_positions.values() {
-                        if p.is_closed() {
+                    self.check_positions().await;
+                    if capacity_check_pending {
+                        capacity_check_pending = false;
+                        self.check_liquidation_capacity();
+                    }
+                }
+                Ok(()) = refreshed_prices.changed() => {
+                    if wait_for_indexer.is_empty() || !self.rx_from_indexer.is_empty() {
+                        continue;
+                    }
+
+                    self.check_watchlist_positions().await;
+                }
+            }
+        }
+    }
+
+    /// Applies an indexed event to the current positions.
+    async fn apply_event(
+        &mut self,
+        metadata: EventMetadata,
+        event: PositionDelta,
+    ) -> anyhow::Result<()> {
+        // Without a WAL, nothing gets persisted to roll back.
+        if self.wal.is_some() {
+            let change = PoolName::try_from(&metadata.from_address).ok().map(|pool| {
+                let key = (
+                    pool,
+                    Self::compute_position_key(metadata.from_address, &event),
+                );
+                PositionChange {
+                    previous: self.current_positions.get(&key).cloned(),
+                    evicted: self.evicted.get(&key).cloned(),
+                    key,
+                }
+            });
+            self.rollback
+                .push((metadata.clone(), event.clone()), self.cursor, change);
+        }
+        // A backfilled event is behind the cursor, which stays at the stream.
+        if !metadata.is_backfill {
+            self.cursor.advance(metadata.block_number);
+        }
+
+        if metadata.is_liquidation
+            && let Some(tx_hash) = metadata.transaction_hash
+        {
+            self.competitors.queue(tx_hash, metadata.block_number);
+        }
+
+        let Ok(pool) = PoolName::try_from(&metadata.from_address) else {
+            // Warns once per pool, the metric counts the following events.
+            if UNKNOWN_POOLS.record_skipped(metadata.from_address) == 1 {
+                tracing::warn!(
+                    "[🔭 Monitoring] ⚠️ Skipping the events of the unknown pool {:#x}",
+                    metadata.from_address
+                );
+            }
+            return Ok(());
+        };
+        let position_key = Self::compute_position_key(metadata.from_address, &event);
+
+        if let Some(position) = self
+            .current_positions
+            .get_mut(&(pool, position_key.clone()))
+        {
+            let event_id = metadata.event_id();
+            let already_applied = event_id
+                .zip(position.last_event)
+                .is_some_and(|(event_id, last_event)| event_id <= last_event);
+            if already_applied {
+                tracing::debug!(
+                    "[🔭 Monitoring] Skipping event of block #{} already applied to {position}",
+                    metadata.block_number
+                );
+                return Ok(());
+            }
+            position.update_from_delta(event);
+            position.last_event = event_id.or(position.last_event);
+            // Our liquidation, or any other update, reconciled the position.
+            self.pending_close.remove(&position_key);
+        } else if self.evicted.contains_key(&(pool, position_key.clone())) {
+            self.rehydrate_position(&metadata, pool, position_key.clone(), &event)
+                .await;
+        } else if QUARANTINE.contains(pool, &position_key) {
+            // Read whole from the chain once its asset is listed.
+        } else if let Some(unlisted) = [event.collateral_address, event.debt_address]
+            .into_iter()
+            .find(|address| !Asset::is_listed(address))
+        {
+            self.register_unlisted_asset(unlisted).await;
+            self.quarantine_position(pool, position_key.clone(), &event, unlisted);
+        } else {
+            match VesuPosition::new(&metadata, &self.vesu_client, event).await {
+                Ok(position) => {
+                    self.current_positions
+                        .insert((pool, position.position_id()), position);
+                }
+                Err(e) => {
+                    tracing::error!("[🔭 Monitoring] Could not new create position: {e}");
+                }
+            };
+        }
+
+        let to_close =
+            if let Some(position) = self.current_positions.get(&(pool, position_key.clone())) {
+                position.is_closed()
+            } else {
+                false
+            };
+
+        if to_close {
+            self.current_positions.remove(&(pool, position_key.clone()));
+            self.forget_position(&position_key);
+        }
+
+        Ok(())
+    }
+
+    /// Sets aside a position of an unlisted asset instead of dropping it, alerting
+    /// once per asset.
+    fn quarantine_position(
+        &self,
+        pool: PoolName,
+        position_key: String,
+        event: &PositionDelta,
+        unlisted: Felt,
+    ) {
+        let is_new_asset = QUARANTINE.insert(QuarantinedPosition {
+            pool,
+            position_key,
+            collateral_address: event.collateral_address,
+            debt_address: event.debt_address,
+            user_address: event.user_address,
+        });
+        if !is_new_asset {
+            return;
+        }
+
+        let asset = UNLISTED_ASSETS
+            .get(&unlisted)
+            .map(|asset| format!("{} ({unlisted:#x})", asset.ticker))
+            .unwrap_or_else(|| format!("{unlisted:#x}"));
+        NOTIFIER.notify(format!(
+            "⚠️ {asset} is not in assets.toml: its positions in {pool} are quarantined until it gets listed & the bot restarted"
+        ));
+    }
+
+    /// Reads from the chain the quarantined positions whose assets are listed now,
+    /// at the block of the cursor like a re-hydrated position.
+    async fn release_quarantined_positions(&mut self) {
+        let released = QUARANTINE.release_listed();
+        if released.is_empty() {
+            return;
+        }
+
+        let block_number = self.cursor.block_number;
+        for quarantined in &released {
+            let position = VesuPosition::from_onchain(
+                &self.vesu_client,
+                &self.provider,
+                quarantined.pool,
+                quarantined.collateral_address,
+                quarantined.debt_address,
+                quarantined.user_address,
+                BlockId::Number(block_number),
+            )
+            .await;
+
+            match position {
+                Ok(Some(mut position)) => {
+                    position.last_event = Some(EventId {
+                        block_number,
+                        event_index: u64::MAX,
+                    });
+                    self.current_positions.insert(
+                        (quarantined.pool, quarantined.position_key.clone()),
+                        position,
+                    );
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    tracing::error!(
+                        "[🔭 Monitoring] Could not read the quarantined position #{}, quarantining it again: {e}",
+                        quarantined.position_key
+                    );
+                    QUARANTINE.insert(quarantined.clone());
+                }
+            }
+        }
+        tracing::info!(
+            "[🔭 Monitoring] 🩺 Released {} quarantined positions whose assets are listed now ({} left)",
+            released.len(),
+            QUARANTINE.len()
+        );
+    }
+
+    /// Reads an evicted position back from the chain, at the block of its new event.
+    async fn rehydrate_position(
+        &mut self,
+        metadata: &EventMetadata,
+        pool: PoolName,
+        position_key: String,
+        event: &PositionDelta,
+    ) {
+        let position = VesuPosition::from_onchain(
+            &self.vesu_client,
+            &self.provider,
+            pool,
+            event.collateral_address,
+            event.debt_address,
+            event.user_address,
+            BlockId::Number(metadata.block_number),
+        )
+        .await;
+
+        match position {
+            Ok(position) => {
+                self.evicted.remove(&(pool, position_key.clone()));
+                if let Some(mut position) = position {
+                    // The state read includes all the events of the block.
+                    position.last_event = metadata.event_id().map(|_| EventId {
+                        block_number: metadata.block_number,
+                        event_index: u64::MAX,
+                    });
+                    tracing::debug!("[🔭 Monitoring] ♻️ Re-hydrated {position}");
+                    self.current_positions
+                        .insert((pool, position_key), position);
+                }
+            }
+            Err(e) => {
+                tracing::error!(
+                    "[🔭 Monitoring] Could not re-hydrate position #{position_key}: {e}"
+                );
+            }
+        }
+    }
+
+    /// Whether the indexer already applied an event of the position from the
+    /// block of the liquidation or later - or removed it - the confirmation
+    /// coming after it.
+    fn is_liquidation_applied(&self, confirmed: &ConfirmedLiquidation) -> bool {
+        let Some(position) = self
+            .current_positions
+            .iter()
+            .find(|((_, key), _)| *key == confirmed.position_id)
+            .map(|(_, position)| position)
+        else {
+            return true;
+        };
+        position
+            .last_event
+            .is_some_and(|last_event| last_event.block_number >= confirmed.block_number)
+    }
+
+    /// Re-reads from the chain the liquidated positions whose event never came.
+    async fn reconcile_stale_pending_closes(&mut self) {
+        let stale: Vec<String> = self
+            .pending_close
+            .iter()
+            .filter(|(_, since)| since.elapsed() >= Self::PENDING_CLOSE_TIMEOUT)
+            .map(|(position_id, _)| position_id.clone())
+            .collect();
+        if stale.is_empty() {
+            return;
+        }
+
+        let head_block = match guarded_starknet(&self.provider, RpcPath::Background, |node| {
+            node.block_number()
+        })
+        .await
+        {
+            Ok(head_block) => head_block,
+            Err(e) => {
+                tracing::debug!("[🔭 Monitoring] Could not fetch the head block: {e}");
+                return;
+            }
+        };
+
+        for position_id in stale {
+            self.pending_close.remove(&position_id);
+
+            let Some((key, known)) = self
+                .current_positions
+                .iter()
+                .find(|((_, key), _)| *key == position_id)
+                .map(|(key, position)| (key.clone(), position.clone()))
+            else {
+                continue;
+            };
+
+            let onchain = VesuPosition::from_onchain(
+                &self.vesu_client,
+                &self.provider,
+                known.pool_name,
+                known.collateral.address,
+                known.debt.address,
+                known.user_address,
+                BlockId::Number(head_block),
+            )
+            .await;
+
+            match onchain {
+                Ok(Some(mut position)) => {
+                    tracing::warn!(
+                        "[🔭 Monitoring] No event after the liquidation of {known}, re-read as {position}"
+                    );
+                    // The state read includes all the events up to the head.
+                    position.last_event = Some(EventId {
+                        block_number: head_block,
+                        event_index: u64::MAX,
+                    });
+                    self.current_positions.insert(key, position);
+                }
+                Ok(None) => {
+                    self.current_positions.remove(&key);
+                    self.forget_position(&position_id);
+                }
+                Err(e) => {
+                    tracing::error!(
+                        "[🔭 Monitoring] Could not re-read the liquidated position #{position_id}: {e}"
+                    );
+                }
+            }
+        }
+    }
+
+    /// Updates the LLTV of the positions of the pairs whose LLTV changed.
+    fn apply_lltv_changes(&mut self, changed: &[(Pair, Decimal)]) {
+        for ((pool, collateral, debt), lltv) in changed {
+            for position in self.current_positions.values_mut().filter(|p| {
+                p.pool_name == *pool
+                    && p.collateral.currency == *collateral
+                    && p.debt.currency == *debt
+            }) {
+                position.lltv = *lltv;
+            }
+        }
+    }
+
+    /// Re-reads all the known positions from the chain state & fixes the ones that
+    /// drifted, e.g because of a missed event.
+    async fn full_scan(&mut self) -> anyhow::Result<()> {
+        const SCAN_CHUNK_SIZE: usize = 50;
+
+        let head_block = guarded_starknet(&self.provider, RpcPath::Background, |node| {
+            node.block_number()
+        })
+        .await?;
+        let keys: Vec<(PoolName, String, VesuPosition)> = self
+            .current_positions
+            .iter()
+            .map(|((pool, key), position)| (*pool, key.clone(), position.clone()))
+            .collect();
+
+        tracing::info!(
+            "[🔭 Monitoring] 🔁 Full scan of {} positions at block #{head_block}",
+            keys.len()
+        );
+
+        let mut fixed = 0;
+        let mut closed = 0;
+        for chunk in keys.chunks(SCAN_CHUNK_SIZE) {
+            let reads = chunk.iter().map(|(_, _, known)| {
+                VesuPosition::from_onchain(
+                    &self.vesu_client,
+                    &self.provider,
+                    known.pool_name,
+                    known.collateral.address,
+                    known.debt.address,
+                    known.user_address,
+                    BlockId::Number(head_block),
+                )
+            });
+
+            for ((pool, key, known), onchain) in chunk.iter().zip(join_all(reads).await) {
+                match onchain {
+                    Ok(Some(mut onchain)) => {
+                        if onchain.collateral.amount == known.collateral.amount
+                            && onchain.debt.amount == known.debt.amount
+                        {
                             continue;
                         }
+                        tracing::warn!(
+                            "[🔭 Monitoring] 🩹 {known} drifted from the chain state, now {onchain}"
+                        );
+                        // The state read includes all the events up to the head.
+                        onchain.last_event = Some(EventId {
+                            block_number: head_block,
+                            event_index: u64::MAX,
+                        });
+                        self.current_positions.insert((*pool, key.clone()), onchain);
+                        fixed += 1;
+                    }
+                    Ok(None) => {
+                        self.current_positions.remove(&(*pool, key.clone()));
+                        self.forget_position(key);
+                        closed += 1;
+                    }
+                    Err(e) => {
+                        tracing::debug!("[🔭 Monitoring] Could not scan position #{key}: {e}");
+                    }
+                }
+            }
+        }
 
-                        if p.is_liquidable() {
-                            tracing::info!(
-                                "[🔭 Monitoring] 🔫 Liquidating {p}",
-                            );
-
-                            if let Err(e) = self.liquidate_position(p).await {
-                                if e.to_string().contains("not-undercollateralized") {
-                                    tracing::warn!("[🔭 Monitoring] Position was not under collateralized!");
-                                } else {
-                                    tracing::error!(
-                                        error = %e,
-                                        "[🔭 Monitoring] 😨 Could not liquidate position",
-                                    );
-                                }
-                            }
-                        }
+        let pruned = self.prune_closed_evicted_positions(head_block).await;
+        tracing::info!(
+            "[🔭 Monitoring] 🔁 Full scan done: {fixed} positions fixed, {closed} closed, {pruned} evicted ones closed"
+        );
+        Ok(())
+    }
+
+    /// Reads the evicted positions from the chain & drops the closed ones, which
+    /// no event would re-hydrate. Returns how many got dropped.
+    async fn prune_closed_evicted_positions(&mut self, head_block: u64) -> usize {
+        const SCAN_CHUNK_SIZE: usize = 50;
+
+        let evicted: Vec<((PoolName, String), (Felt, Felt, Felt))> = self
+            .evicted
+            .iter()
+            .filter_map(|(key, evicted)| Some((key.clone(), evicted.addresses?)))
+            .collect();
+
+        let mut pruned = 0;
+        for chunk in evicted.chunks(SCAN_CHUNK_SIZE) {
+            let reads = chunk.iter().map(|((pool, _), (collateral, debt, user))| {
+                VesuPosition::from_onchain(
+                    &self.vesu_client,
+                    &self.provider,
+                    *pool,
+                    *collateral,
+                    *debt,
+                    *user,
+                    BlockId::Number(head_block),
+                )
+            });
+
+            for ((key, _), onchain) in chunk.iter().zip(join_all(reads).await) {
+                match onchain {
+                    Ok(None) => {
+                        self.evicted.remove(key);
+                        pruned += 1;
+                    }
+                    Ok(Some(_)) => {}
+                    Err(e) => {
+                        tracing::debug!(
+                            "[🔭 Monitoring] Could not scan evicted position #{}: {e}",
+                            key.1
+                        );
+                    }
+                }
+            }
+        }
+        pruned
+    }
+
+    /// Drops what is tracked of a position once it is closed or evicted.
+    fn forget_position(&mut self, position_id: &str) {
+        self.health_history.remove(position_id);
+        self.liquidation_delay.clear(position_id);
+        self.intent_ids.remove(position_id);
+        NOTIFIER.resolve_all(position_id, || {
+            format!("position #{position_id} is no longer monitored")
+        });
+    }
+
+    /// Evicts the least recently updated dust positions of the pairs holding more
+    /// than `max_positions_per_pair` positions.
+    fn evict_dormant_positions(&mut self) {
+        let mut by_pair: HashMap<(PoolName, Currency, Currency), Vec<&VesuPosition>> =
+            HashMap::new();
+        for position in self.current_positions.values() {
+            by_pair
+                .entry((
+                    position.pool_name,
+                    position.collateral.currency,
+                    position.debt.currency,
+                ))
+                .or_default()
+                .push(position);
+        }
+
+        let mut to_evict = Vec::new();
+        for positions in by_pair.into_values() {
+            let excess = positions
+                .len()
+                .saturating_sub(self.config.max_positions_per_pair);
+            if excess == 0 {
+                continue;
+            }
+
+            // Below the debt floor of its pool, a position can only be fully
+            // liquidated: dust as well.
+            let mut dust: Vec<&VesuPosition> = positions
+                .into_iter()
+                .filter(|p| {
+                    p.debt_value_in_usd()
+                        < self
+                            .config
+                            .dust_position_usd
+                            .max(POOL_RATES.debt_floor_usd(p))
+                })
+                .collect();
+            dust.sort_by_key(|p| p.last_event);
+            to_evict.extend(dust.into_iter().take(excess).map(|p| EvictedPosition {
+                pool: p.pool_name,
+                position_key: p.position_id(),
+                addresses: Some((p.collateral.address, p.debt.address, p.user_address)),
+            }));
+        }
+
+        if to_evict.is_empty() {
+            return;
+        }
+
+        tracing::info!(
+            "[🔭 Monitoring] 🧹 Evicting {} dormant dust positions",
+            to_evict.len()
+        );
+        for evicted in to_evict {
+            let key = (evicted.pool, evicted.position_key.clone());
+            self.current_positions.remove(&key);
+            self.forget_position(&key.1);
+            if let Some(hibernation) = self.hibernation.as_mut() {
+                hibernation.forget(&key.1);
+            }
+            self.evicted.insert(key, evicted);
+        }
+    }
+
+    /// Loads the last snapshot & replays the WAL on top of it.
+    async fn recover_state(&mut self) -> anyhow::Result<()> {
+        let Some(recovered_state) = self.recovered_state.take() else {
+            return Ok(());
+        };
+
+        let RecoveredState { snapshot, events } = recovered_state;
+
+        self.cursor = snapshot.cursor;
+        for position in snapshot.positions {
+            self.current_positions
+                .insert((position.pool_name, position.position_id()), position);
+        }
+        self.evicted.extend(
+            snapshot
+                .evicted
+                .into_iter()
+                .map(|evicted| ((evicted.pool, evicted.position_key.clone()), evicted)),
+        );
+        for quarantined in snapshot.quarantined {
+            QUARANTINE.insert(quarantined);
+        }
+
+        let replayed = events.len();
+        for (metadata, event) in events {
+            self.apply_event(metadata, event).await?;
+        }
+        self.release_quarantined_positions().await;
+
+        tracing::info!(
+            "[🔭 Monitoring] 💾 Recovered {} positions ({replayed} events replayed from the WAL)",
+            self.current_positions.len()
+        );
+        Ok(())
+    }
 
+    /// Returns true if the event was already applied, i.e it is re-delivered by
+    /// the indexer restarting from the checkpoint block. The backfilled events
+    /// are deduplicated per position instead.
+    fn is_applied(&self, metadata: &EventMetadata) -> bool {
+        !metadata.is_backfill
+            && metadata
+                .event_id()
+                .is_some_and(|event_id| self.cursor.contains(event_id))
+    }
+
+    fn log_value_at_risk() {
+        let total = VALUE_AT_RISK.total();
+        if total.is_zero() {
+            return;
+        }
+
+        let per_pool = VALUE_AT_RISK
+            .per_pool()
+            .into_iter()
+            .map(|(pool, value)| format!("{pool}: {}", format_usd(value)))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let per_debt_asset = VALUE_AT_RISK
+            .debt_at_risk()
+            .into_iter()
+            .map(|debt| format!("{} {}", format_amount(debt.amount), debt.asset))
+            .collect::<Vec<_>>()
+            .join(", ");
+        tracing::info!(
+            "[🔭 Monitoring] 💰 Value at risk: {} ({per_pool}) - debt to repay: {per_debt_asset}",
+            format_usd(total)
+        );
+    }
+
+    /// Checks that every monitored pair can be swapped, so an unroutable pair is
+    /// known before a liquidation fails on it.
+    async fn route_preflight() {
+        let unroutable = check_routes().await;
+        if unroutable.is_empty() {
+            tracing::info!("[🔭 Monitoring] 🛣️ Swap routes available for all the monitored pairs");
+            return;
+        }
+
+        let pairs = unroutable
+            .iter()
+            .map(|(debt, collateral)| format!("{debt}→{collateral}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        tracing::error!(
+            "[🔭 Monitoring] 🚧 {} monitored pairs are currently unroutable: {pairs}",
+            unroutable.len()
+        );
+    }
 
+    /// Simulates the liquidation of the riskiest positions in the executor,
+    /// once they are synced.
+    fn check_liquidation_capacity(&self) {
+        let user_scope = self.config.user_scope.as_ref();
+        let positions = capacity_check_positions(
+            self.current_positions
+                .values()
+                .filter(|p| user_scope.is_none_or(|scope| scope.includes(p))),
+            self.config.capacity_check_pairs,
+        );
+        if positions.is_empty() {
+            return;
+        }
+
+        tracing::info!(
+            "[🔭 Monitoring] 🧪 Simulating the liquidation of the riskiest position of {} pairs",
+            positions.len()
+        );
+        let intents = positions
+            .into_iter()
+            .map(|position| LiquidationIntent {
+                id: Uuid::new_v4(),
+                position,
+                debt_to_repay: None,
+                context: "capacity check".into(),
+            })
+            .collect();
+        if self.executor.tx_capacity_checks.send(intents).is_err() {
+            tracing::error!(
+                "[🔭 Monitoring] The executor stopped, could not simulate the liquidations"
+            );
+        }
+    }
+
+    fn log_tip_recommendation(&self) {
+        let Some(recommendation) = self.competitors.recommendation() else {
+            return;
+        };
+
+        tracing::info!(
+            "[🔭 Monitoring] 💡 Recommended tip: {} & L2 gas price: {} (from {} competitor liquidations)",
+            recommendation.tip,
+            recommendation.l2_gas_max_price,
+            recommendation.samples
+        );
+    }
+
+    /// Snapshots the positions in the WAL.
+    fn checkpoint(&mut self) {
+        let Some(wal) = self.wal.as_mut() else {
+            return;
+        };
+
+        if let Some(finalized_block) = INDEXER_LAG.finalized_block() {
+            self.rollback.prune(finalized_block);
+        }
+        // Only the finalized state is persisted, the later events stay in the WAL.
+        let (positions, evicted) = self
+            .rollback
+            .finalized_state(&self.current_positions, &self.evicted);
+        let cursor = self.rollback.finalized_cursor(self.cursor);
+        match wal.checkpoint(
+            cursor,
+            positions.iter(),
+            evicted.iter(),
+            QUARANTINE.all(),
+            self.rollback.events(),
+        ) {
+            Ok(()) => tracing::debug!(
+                "[🔭 Monitoring] 💾 Checkpointed the positions at block #{} ({} events not finalized kept in the WAL)",
+                cursor.block_number,
+                self.rollback.len()
+            ),
+            Err(e) => tracing::error!("[🔭 Monitoring] Could not checkpoint the positions: {e}"),
+        }
+    }
+
+    /// Checks all the current positions & queues the liquidation of the liquidable
+    /// ones to the executor.
+    async fn check_positions(&mut self) {
+        self.reconcile_stale_pending_closes().await;
+        self.competitors.analyze_pending(&self.provider).await;
+        self.depeg_guard.update();
+        let user_scope = self.config.user_scope.as_ref();
+        let in_scope = |p: &&VesuPosition| user_scope.is_none_or(|scope| scope.includes(p));
+        VALUE_AT_RISK.update(
+            self.current_positions.values().filter(in_scope),
+            self.config.value_at_risk_threshold_pct,
+        );
+        WATCHLIST.update(
+            self.current_positions.values().filter(in_scope),
+            &self.health_history,
+        );
+
+        let hibernation = &mut self.hibernation;
+        if let Some(hibernation) = hibernation.as_mut() {
+            hibernation.wake_on_price_moves();
+        }
+        let to_evaluate = self
+            .current_positions
+            .iter()
+            .filter(|(_, p)| in_scope(p))
+            .filter(|(_, p)| !p.is_closed() && !self.pending_close.contains_key(&p.position_id()))
+            .filter(|(_, p)| {
+                !hibernation
+                    .as_mut()
+                    .is_some_and(|hibernation| hibernation.is_hibernating(p))
+            })
+            .map(|(key, p)| (key.clone(), p.clone()))
+            .collect();
+        self.evaluate_positions(to_evaluate, true).await;
+    }
+
+    /// Re-checks only the riskiest positions of the watchlist, once the prices
+    /// got refreshed at a new block.
+    async fn check_watchlist_positions(&mut self) {
+        let watched: HashSet<String> = WATCHLIST
+            .positions(Self::NEW_BLOCK_CHECK_LIMIT)
+            .into_iter()
+            .map(|watched| watched.position_id)
+            .collect();
+
+        let to_evaluate = self
+            .current_positions
+            .iter()
+            .filter(|(_, p)| watched.contains(&p.position_id()))
+            .filter(|(_, p)| !p.is_closed() && !self.pending_close.contains_key(&p.position_id()))
+            .map(|(key, p)| (key.clone(), p.clone()))
+            .collect();
+        self.evaluate_positions(to_evaluate, false).await;
+    }
+
+    /// Evaluates the health of the positions & queues the liquidation of the
+    /// liquidable ones to the executor.
+    async fn evaluate_positions(
+        &mut self,
+        to_evaluate: Vec<((PoolName, String), VesuPosition)>,
+        log_summary: bool,
+    ) {
+        let user_scope = self.config.user_scope.as_ref();
+        let protect = self.config.protect.clone().map(Arc::new);
+        let evaluations = evaluate_health(to_evaluate, protect).await;
+
+        let mut intents = Vec::new();
+        let mut summary = HealthSummary::default();
+
+        for evaluation in evaluations {
+            let Some(p) = self.current_positions.get(&evaluation.key) else {
+                continue;
+            };
+            summary.record(&evaluation);
+            if let Some(hibernation) = self.hibernation.as_mut() {
+                hibernation.record(p, &evaluation);
+            }
+            if evaluation.is_at_risk {
+                NOTIFIER.alert(AlertKind::PositionAtRisk, &p.position_id(), || {
+                    format!(
+                        "{p} is at risk (health factor {:.3})",
+                        evaluation.health_factor
+                    )
+                });
+            } else {
+                NOTIFIER.resolve(AlertKind::PositionAtRisk, &p.position_id(), || {
+                    format!(
+                        "{p} is healthy again (health factor {:.3})",
+                        evaluation.health_factor
+                    )
+                });
+            }
+            if !evaluation.is_at_risk
+                && self.config.healthy_positions_log == HealthyPositionsLog::Detailed
+            {
+                tracing::debug!(
+                    "[🔭 Monitoring] {p} is healthy (health factor {:.3})",
+                    evaluation.health_factor
+                );
+            }
+
+            let history = self.health_history.entry(p.position_id()).or_default();
+            history.record(evaluation.ltv);
+            self.liquidation_delay.track(p, evaluation.is_liquidable);
+
+            if let Some(debt_to_repay) = evaluation.deleverage {
+                tracing::warn!(
+                    "[🔭 Monitoring] 🛡️ Our {p} crossed its deleverage threshold (health factor {:.3})",
+                    p.health_factor()
+                );
+                let _ = self.executor.tx_deleverages.send(DeleverageIntent {
+                    position: p.clone(),
+                    debt_to_repay,
+                });
+            }
+
+            // The almost liquidable positions go through the strategy too, so it
+            // can act ahead of the LLTV.
+            if !evaluation.is_at_risk {
+                Self::resolve_protected_alert(&mut self.alerted, p);
+                self.intent_ids.remove(&p.position_id());
+                continue;
+            }
+
+            let decision = self.config.strategy.decide(&StrategyInputs {
+                position: p,
+                history,
+                prices: &VESU_PRICES,
+                estimated_fee_usd: self
+                    .intent_ids
+                    .get(&p.position_id())
+                    .and_then(|id| self.executor.fee_estimates.usd(id)),
+                inventory: &self.inventory,
+            });
+            let decision = match &self.config.debt_cap {
+                Some(debt_cap) => debt_cap.apply(p, decision),
+                None => decision,
+            };
+            let decision = apply_debt_floor(
+                p,
+                decision,
+                POOL_RATES.debt_floor_usd(p),
+                self.config.debt_cap.map(|debt_cap| debt_cap.max_debt_usd),
+            );
+
+            let debt_to_repay = match decision {
+                LiquidationDecision::Skip { reason } => {
+                    Self::resolve_protected_alert(&mut self.alerted, p);
+                    self.intent_ids.remove(&p.position_id());
+                    if let Some(reason) = reason {
+                        tracing::info!("[🔭 Monitoring] ⏸️ Not liquidating {p}: {reason}");
                     }
+                    continue;
+                }
+                LiquidationDecision::Full => None,
+                LiquidationDecision::Partial { debt_to_repay, .. } => Some(debt_to_repay),
+            };
+
+            if self.depeg_guard.is_paused(p) {
+                tracing::warn!(
+                    "[🔭 Monitoring] ⏸️ Not liquidating {p}: one of its assets depegged"
+                );
+                continue;
+            }
+            if POOL_PAUSES.is_paused(p.pool_name) {
+                tracing::warn!("[🔭 Monitoring] ⏸️ Not liquidating {p}: its pool is paused");
+                continue;
+            }
 
+            let mut context = format!(
+                "health factor {:.3}, {}",
+                p.health_factor(),
+                history
+                    .describe_trend()
+                    .unwrap_or_else(|| "no LTV history".into()),
+            );
+            if let Some(rates) = POOL_RATES.of_debt(p) {
+                context.push_str(&format!(", {} {rates}", p.debt.currency));
+            }
+            if user_scope.is_some_and(|scope| scope.action == ProtectedUsersAction::Alert) {
+                if self.alerted.insert(p.position_id()) {
+                    tracing::error!(
+                        "[🔭 Monitoring] 🚨 Protected {p} is liquidable ({context}), not liquidating it"
+                    );
                 }
+                NOTIFIER.alert(AlertKind::ProtectedUser, &p.position_id(), || {
+                    format!("Protected {p} is liquidable ({context}), not liquidating it")
+                });
+                continue;
+            }
+
+            if let Some(remaining) = self.liquidation_delay.remaining(p) {
+                tracing::debug!(
+                    "[🔭 Monitoring] ⏳ {p} is liquidable ({context}), liquidating it in {}s",
+                    remaining.as_secs()
+                );
+                continue;
+            }
+
+            let id = match self.intent_ids.entry(p.position_id()) {
+                Entry::Occupied(entry) => *entry.get(),
+                // Broadcasted once per intent, not at every check it stays liquidable.
+                Entry::Vacant(entry) => {
+                    let id = *entry.insert(Uuid::new_v4());
+                    if let Some(broadcaster) = &self.broadcaster {
+                        broadcaster.broadcast(LiquidationOpportunity::new(id, p, debt_to_repay), p);
+                    }
+                    if self.config.watch_only {
+                        tracing::info!(
+                            intent_id = %id,
+                            "[🔭 Monitoring] 👀 {p} is liquidable ({context}), watching only"
+                        );
+                    }
+                    id
+                }
+            };
+            if self.config.watch_only {
+                continue;
+            }
+            tracing::debug!(
+                intent_id = %id,
+                "[🔭 Monitoring] 🔫 Queuing the liquidation of {p} ({context})"
+            );
+            intents.push(LiquidationIntent {
+                id,
+                position: p.clone(),
+                debt_to_repay,
+                context,
+            });
+        }
+
+        if log_summary && self.config.healthy_positions_log != HealthyPositionsLog::Off {
+            summary.log(self.hibernation.as_ref().map(Hibernation::len));
+        }
+
+        // The executor liquidates the most utilized debt assets first - their
+        // positions accrue interest the fastest & their liquidity is the
+        // scarcest - then the largest debts.
+        intents.sort_by_key(|intent| {
+            let position = &intent.position;
+            let utilization = POOL_RATES
+                .of_debt(position)
+                .map(|rates| rates.utilization)
+                .unwrap_or_default();
+            Reverse((utilization, position.debt_value_in_usd()))
+        });
+        if !intents.is_empty() && self.executor.tx_intents.send(intents).is_err() {
+            tracing::error!(
+                "[🔭 Monitoring] The executor stopped, could not liquidate the positions"
+            );
+        }
+    }
+
+    /// Reads the balances of the liquidator account, given to the strategy. A
+    /// balance that could not be read keeps its last value.
+    async fn refresh_inventory(&mut self) {
+        for asset in ONCHAIN_ASSETS.all() {
+            let Ok(currency) = Currency::from_str(&asset.ticker) else {
+                continue;
+            };
+            let balance = guarded_starknet(&self.provider, RpcPath::Background, |node| {
+                balance_of(node, asset.address, self.account_address)
+            })
+            .await
+            .and_then(|balance| Ok(Decimal::from_str(&balance.low.to_string())?));
+            match balance {
+                Ok(balance) => {
+                    let amount = balance / Decimal::TEN.pow(currency.d_decimals());
+                    self.inventory.insert(currency, amount);
+                }
+                Err(e) => {
+                    tracing::debug!("[🔭 Monitoring] Could not read the {currency} balance: {e}");
+                }
+            }
+        }
+    }
+
+    /// Clears the alert of a protected position that is not liquidable anymore.
+    fn resolve_protected_alert(alerted: &mut HashSet<String>, position: &VesuPosition) {
+        if alerted.remove(&position.position_id()) {
+            NOTIFIER.resolve(AlertKind::ProtectedUser, &position.position_id(), || {
+                format!("Protected {position} is not liquidable anymore")
+            });
+        }
+    }
+
+    /// Reads the metadata of an asset missing from assets.toml & warns once: its
+    /// positions cannot be priced, so they are quarantined until it gets listed.
+    /// A failed read is only retried after `UNLISTED_METADATA_RETRY_DELAY`.
+    async fn register_unlisted_asset(&mut self, address: Felt) {
+        if UNLISTED_ASSETS.contains_key(&address) {
+            return;
+        }
+        if self
+            .unlisted_metadata_failures
+            .get(&address)
+            .is_some_and(|failed_at| failed_at.elapsed() < Self::UNLISTED_METADATA_RETRY_DELAY)
+        {
+            return;
+        }
+
+        let metadata = guarded_starknet(&self.provider, RpcPath::Background, |node| {
+            token_metadata(node, address)
+        })
+        .await;
+        match metadata {
+            Ok(asset) => {
+                self.unlisted_metadata_failures.remove(&address);
+                tracing::warn!(
+                    "[🔭 Monitoring] ⚠️ {} ({}, {} decimals, {address:#x}) is not in assets.toml, quarantining its positions",
+                    asset.ticker,
+                    asset.name,
+                    asset.decimals
+                );
+                UNLISTED_ASSETS.insert(address, asset);
+            }
+            Err(e) => {
+                tracing::warn!(
+                    "[🔭 Monitoring] ⚠️ Asset {address:#x} is not in assets.toml & its metadata could not be read, quarantining its positions: {e}"
+                );
+                self.unlisted_metadata_failures
+                    .insert(address, Instant::now());
             }
         }
     }
@@ -143,21 +1390,4 @@ impl MonitoringService {
         .hash(&mut hasher);
         hasher.finish().to_string()
     }
-
-    async fn liquidate_position(&self, position: &VesuPosition) -> anyhow::Result<()> {
-        let started_at = std::time::Instant::now();
-
-        let liquidation_tx = position
-            .get_vesu_liquidate_tx(&self.liquidate_contract, &self.account.account_address())
-            .await?;
-
-        let tx_hash = self.account.execute_txs(&[liquidation_tx]).await?;
-
-        tracing::info!(
-            "[🔭 Monitoring] ✅ Liquidated position #{}! (tx {tx_hash:#064x}) - ⌛ {:?}",
-            position.position_id(),
-            started_at.elapsed()
-        );
-        Ok(())
-    }
 }
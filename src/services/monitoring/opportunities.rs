@@ -0,0 +1,367 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use redis::AsyncCommands;
+use redis::aio::MultiplexedConnection;
+use redis::streams::StreamMaxlen;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use tokio::sync::Notify;
+use tokio::task::JoinSet;
+use tokio::time::Instant;
+use url::Url;
+use uuid::Uuid;
+
+use crate::services::monitoring::ekubo::{RouteSplit, describe_route, get_ekubo_route};
+use crate::services::stream::EventStreamConfig;
+use crate::types::decimal;
+use crate::types::position::{Asset, VesuPosition};
+use crate::utils::rpc::{RpcPath, RpcProvider, guarded};
+
+/// Field of the stream entries holding an opportunity, as JSON.
+const OPPORTUNITY_FIELD: &str = "opportunity";
+/// Number of opportunities waiting to be broadcasted, past which the oldest
+/// ones get dropped.
+const MAX_QUEUED_OPPORTUNITIES: usize = 1_000;
+/// Number of opportunities whose route & publishing run at once.
+const BROADCAST_CONCURRENCY: usize = 8;
+/// Delay before connecting again to the stream, doubling after each failure.
+const MIN_RECONNECT_DELAY: Duration = Duration::from_secs(1);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(60);
+
+/// Where the liquidable positions get published.
+#[derive(Debug, Clone, Default)]
+pub struct OpportunityBroadcastConfig {
+    /// Receives every opportunity as a JSON POST.
+    pub webhook_url: Option<Url>,
+    /// The Redis stream every opportunity is added to.
+    pub stream: Option<EventStreamConfig>,
+}
+
+impl OpportunityBroadcastConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.webhook_url.is_some() || self.stream.is_some()
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OpportunityAsset {
+    pub symbol: String,
+    pub address: String,
+    #[serde(with = "decimal::string")]
+    pub decimals: Decimal,
+    #[serde(with = "decimal::string")]
+    pub amount: Decimal,
+    #[serde(with = "decimal::string")]
+    pub value_usd: Decimal,
+}
+
+impl OpportunityAsset {
+    fn new(asset: &Asset) -> Self {
+        Self {
+            symbol: asset.currency.to_string(),
+            address: asset.address.to_fixed_hex_string(),
+            decimals: asset.decimals,
+            amount: asset.amount,
+            value_usd: asset.amount * asset.currency.price(),
+        }
+    }
+}
+
+/// A liquidable position, with all it takes to liquidate it.
+#[derive(Debug, Clone, Serialize)]
+pub struct LiquidationOpportunity {
+    pub intent_id: String,
+    pub position_id: String,
+    pub pool: String,
+    pub pool_address: String,
+    pub user: String,
+    pub collateral: OpportunityAsset,
+    pub debt: OpportunityAsset,
+    /// Debt to repay, in units of the debt asset: all of it unless the
+    /// strategy or the debt cap decided a partial liquidation.
+    #[serde(with = "decimal::string")]
+    pub debt_to_repay: Decimal,
+    #[serde(with = "decimal::string")]
+    pub ltv: Decimal,
+    #[serde(with = "decimal::string")]
+    pub lltv: Decimal,
+    #[serde(with = "decimal::string")]
+    pub health_factor: Decimal,
+    /// Block of the last event applied to the position.
+    pub block_number: Option<u64>,
+    pub detected_at: u64,
+    /// The Ekubo swap of the seized collateral into the debt to repay, None if
+    /// no route was found.
+    pub route: Option<Vec<RouteSplit>>,
+}
+
+impl LiquidationOpportunity {
+    pub fn new(intent_id: Uuid, position: &VesuPosition, debt_to_repay: Option<Decimal>) -> Self {
+        Self {
+            intent_id: intent_id.to_string(),
+            position_id: position.position_id(),
+            pool: position.pool_name.to_string(),
+            pool_address: position.pool_name.pool_address().to_fixed_hex_string(),
+            user: position.user_address.to_fixed_hex_string(),
+            collateral: OpportunityAsset::new(&position.collateral),
+            debt: OpportunityAsset::new(&position.debt),
+            debt_to_repay: debt_to_repay.unwrap_or(position.debt.amount),
+            ltv: position.ltv(),
+            lltv: position.lltv,
+            health_factor: position.health_factor(),
+            block_number: position.last_event.map(|event_id| event_id.block_number),
+            detected_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            route: None,
+        }
+    }
+}
+
+/// The opportunities waiting to be broadcasted, dropping the oldest ones once
+/// full: a stuck webhook or stream never grows it unbounded.
+#[derive(Debug, Default)]
+struct OpportunityQueue {
+    opportunities: Mutex<VecDeque<(LiquidationOpportunity, VesuPosition)>>,
+    notify: Notify,
+}
+
+impl OpportunityQueue {
+    /// Queues the opportunity & returns the oldest one if dropped for it.
+    fn push(
+        &self,
+        opportunity: LiquidationOpportunity,
+        position: VesuPosition,
+    ) -> Option<VesuPosition> {
+        let mut opportunities = self.opportunities.lock().expect("poisoned opportunities");
+        let dropped = if opportunities.len() >= MAX_QUEUED_OPPORTUNITIES {
+            opportunities.pop_front().map(|(_, position)| position)
+        } else {
+            None
+        };
+        opportunities.push_back((opportunity, position));
+        self.notify.notify_one();
+        dropped
+    }
+
+    /// Waits for the next opportunity.
+    async fn pop(&self) -> (LiquidationOpportunity, VesuPosition) {
+        loop {
+            if let Some(next) = self
+                .opportunities
+                .lock()
+                .expect("poisoned opportunities")
+                .pop_front()
+            {
+                return next;
+            }
+            self.notify.notified().await;
+        }
+    }
+}
+
+/// The connection to the Redis stream, connecting again with a backoff once
+/// lost: the opportunities are only posted to the webhook meanwhile.
+struct StreamConnection {
+    config: EventStreamConfig,
+    connection: Option<MultiplexedConnection>,
+    retry_at: Instant,
+    retry_delay: Duration,
+}
+
+impl StreamConnection {
+    fn new(config: EventStreamConfig) -> Self {
+        Self {
+            config,
+            connection: None,
+            retry_at: Instant::now(),
+            retry_delay: MIN_RECONNECT_DELAY,
+        }
+    }
+
+    /// The connection, connecting first if lost & the backoff elapsed.
+    async fn get(&mut self) -> Option<MultiplexedConnection> {
+        if self.connection.is_none() && Instant::now() >= self.retry_at {
+            match self.config.connect().await {
+                Ok(connection) => {
+                    tracing::info!("[🔭 Monitoring] 📡 Connected to the opportunity stream");
+                    self.connection = Some(connection);
+                    self.retry_delay = MIN_RECONNECT_DELAY;
+                }
+                Err(e) => {
+                    tracing::warn!(
+                        "[🔭 Monitoring] 📡 Could not connect to the opportunity stream, retrying in {}s: {e:#}",
+                        self.retry_delay.as_secs()
+                    );
+                    self.retry_at = Instant::now() + self.retry_delay;
+                    self.retry_delay = (self.retry_delay * 2).min(MAX_RECONNECT_DELAY);
+                }
+            }
+        }
+        self.connection.clone()
+    }
+
+    /// Drops the connection after a failed publish, to connect again.
+    fn lost(&mut self) {
+        self.connection = None;
+    }
+}
+
+/// Publishes the liquidable positions to a webhook & a Redis stream, so that
+/// other systems can execute them - e.g from a `--watch-only` bot. Runs in the
+/// background: reading the routes & publishing never delay the monitoring.
+#[derive(Debug, Clone)]
+pub struct OpportunityBroadcaster {
+    queue: Arc<OpportunityQueue>,
+}
+
+impl OpportunityBroadcaster {
+    /// Starts the publishing task.
+    pub fn spawn(config: OpportunityBroadcastConfig) -> Self {
+        let queue = Arc::new(OpportunityQueue::default());
+        tokio::spawn(Self::run_forever(config, queue.clone()));
+        Self { queue }
+    }
+
+    /// Queues the opportunity, its route being read before publishing it.
+    pub fn broadcast(&self, opportunity: LiquidationOpportunity, position: &VesuPosition) {
+        if let Some(dropped) = self.queue.push(opportunity, position.clone()) {
+            tracing::warn!(
+                "[🔭 Monitoring] 📡 Too many opportunities queued, dropped the oldest one of {dropped}"
+            );
+        }
+    }
+
+    /// Broadcasts `BROADCAST_CONCURRENCY` opportunities at once.
+    async fn run_forever(config: OpportunityBroadcastConfig, queue: Arc<OpportunityQueue>) {
+        let http_client = reqwest::Client::new();
+        let mut stream = config.stream.clone().map(StreamConnection::new);
+        let mut broadcasts = JoinSet::new();
+
+        loop {
+            tokio::select! {
+                (opportunity, position) = queue.pop(), if broadcasts.len() < BROADCAST_CONCURRENCY => {
+                    let connection = match stream.as_mut() {
+                        Some(stream) => stream.get().await,
+                        None => None,
+                    };
+                    broadcasts.spawn(Self::broadcast_one(
+                        config.clone(),
+                        http_client.clone(),
+                        connection,
+                        opportunity,
+                        position,
+                    ));
+                }
+                Some(published) = broadcasts.join_next() => {
+                    if let Ok(Err(e)) = published {
+                        tracing::warn!(
+                            "[🔭 Monitoring] 📡 Lost the opportunity stream, connecting again: {e:#}"
+                        );
+                        if let Some(stream) = stream.as_mut() {
+                            stream.lost();
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Reads the route of the opportunity, posts it to the webhook & adds it to
+    /// the stream. Fails if the stream does, to connect again.
+    async fn broadcast_one(
+        config: OpportunityBroadcastConfig,
+        http_client: reqwest::Client,
+        connection: Option<MultiplexedConnection>,
+        mut opportunity: LiquidationOpportunity,
+        position: VesuPosition,
+    ) -> Result<()> {
+        opportunity.route = Self::suggested_route(&position, opportunity.debt_to_repay).await;
+
+        if let Some(webhook_url) = &config.webhook_url {
+            let posted = http_client
+                .post(webhook_url.clone())
+                .json(&opportunity)
+                .timeout(Duration::from_secs(10))
+                .send()
+                .await
+                .and_then(|response| response.error_for_status());
+            if let Err(e) = posted {
+                tracing::warn!(
+                    "[🔭 Monitoring] 📡 Could not post the opportunity of {position}: {e}"
+                );
+            }
+        }
+
+        let published = match (&config.stream, connection) {
+            (Some(stream), Some(mut connection)) => {
+                Self::publish(stream, &mut connection, &opportunity).await
+            }
+            (Some(_), None) => {
+                tracing::warn!(
+                    "[🔭 Monitoring] 📡 Not connected to the opportunity stream, could not publish the opportunity of {position}"
+                );
+                Ok(())
+            }
+            (None, _) => Ok(()),
+        };
+        if let Err(e) = &published {
+            tracing::warn!(
+                "[🔭 Monitoring] 📡 Could not publish the opportunity of {position}: {e:#}"
+            );
+        }
+        tracing::debug!(
+            intent_id = %opportunity.intent_id,
+            "[🔭 Monitoring] 📡 Broadcasted the liquidation of {position}"
+        );
+        published
+    }
+
+    /// The swap route of the seized collateral into the debt repaid, as used by
+    /// the liquidations of the bot.
+    async fn suggested_route(
+        position: &VesuPosition,
+        debt_to_repay: Decimal,
+    ) -> Option<Vec<RouteSplit>> {
+        let route = guarded(
+            RpcProvider::Ekubo,
+            RpcPath::Background,
+            get_ekubo_route(
+                position.debt.address,
+                position.collateral.address,
+                &debt_to_repay,
+                position.debt.decimals,
+            ),
+        )
+        .await;
+        match route {
+            Ok((swaps, weights)) => Some(describe_route(&swaps, &weights)),
+            Err(e) => {
+                tracing::debug!("[🔭 Monitoring] 📡 No route for {position}: {e}");
+                None
+            }
+        }
+    }
+
+    async fn publish(
+        stream: &EventStreamConfig,
+        connection: &mut MultiplexedConnection,
+        opportunity: &LiquidationOpportunity,
+    ) -> Result<()> {
+        let payload = serde_json::to_string(opportunity)?;
+        let _: String = connection
+            .xadd_maxlen(
+                &stream.key,
+                StreamMaxlen::Approx(stream.max_len),
+                "*",
+                &[(OPPORTUNITY_FIELD, payload)],
+            )
+            .await
+            .context("Could not add the opportunity to the stream")?;
+        Ok(())
+    }
+}
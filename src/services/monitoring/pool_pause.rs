@@ -0,0 +1,106 @@
+use std::collections::HashSet;
+use std::sync::{Arc, LazyLock};
+
+use anyhow::Result;
+use dashmap::DashMap;
+use futures_util::future::join_all;
+use pragma_common::starknet::FallbackProvider;
+use starknet::core::types::{BlockId, BlockTag, Felt, FunctionCall};
+use starknet::macros::selector;
+use starknet::providers::Provider;
+
+use crate::services::indexer::IndexerService;
+use crate::services::notifier::{AlertKind, NOTIFIER};
+use crate::types::pool::PoolName;
+use crate::utils::rpc::{RpcPath, guarded_starknet};
+
+// Whether the monitored pools are paused, refreshed by the monitoring.
+pub static POOL_PAUSES: LazyLock<Arc<PoolPauses>> =
+    LazyLock::new(|| Arc::new(PoolPauses::default()));
+
+/// The paused state of the pools. A paused pool reverts every liquidation, so
+/// its positions are not liquidated until it gets unpaused.
+#[derive(Debug, Default)]
+pub struct PoolPauses {
+    /// pool => paused, for the pools read at least once
+    paused: DashMap<PoolName, bool>,
+}
+
+impl PoolPauses {
+    /// Reads the paused state of the monitored pools & notifies the changes. The
+    /// pools failing to be read keep their previous state.
+    pub async fn refresh(&self, provider: &FallbackProvider) {
+        let pools: HashSet<PoolName> = IndexerService::monitored_pairs()
+            .into_iter()
+            .map(|(pool, _, _)| pool)
+            .collect();
+
+        let fetches = pools
+            .into_iter()
+            .map(|pool| async move { (pool, fetch_is_paused(provider, pool).await) });
+        for (pool, is_paused) in join_all(fetches).await {
+            match is_paused {
+                Ok(is_paused) => self.update(pool, is_paused),
+                Err(e) => {
+                    tracing::debug!("[🔭 Monitoring] Could not read whether {pool} is paused: {e}")
+                }
+            }
+        }
+    }
+
+    fn update(&self, pool: PoolName, is_paused: bool) {
+        let was_paused = self.paused.insert(pool, is_paused).unwrap_or_default();
+
+        if is_paused && !was_paused {
+            tracing::error!("[🔭 Monitoring] 🚨 {pool} is paused - pausing its liquidations");
+        } else if !is_paused && was_paused {
+            tracing::info!("[🔭 Monitoring] {pool} is unpaused - resuming its liquidations");
+            NOTIFIER.resolve(AlertKind::PoolPaused, &pool.to_string(), || {
+                format!("{pool} is unpaused, its liquidations resumed")
+            });
+        }
+
+        if is_paused {
+            NOTIFIER.alert(AlertKind::PoolPaused, &pool.to_string(), || {
+                format!("{pool} is paused, its liquidations are paused until it gets unpaused")
+            });
+        }
+    }
+
+    /// Returns true if the pool was paused when last read.
+    pub fn is_paused(&self, pool: PoolName) -> bool {
+        self.paused.get(&pool).is_some_and(|paused| *paused)
+    }
+
+    pub fn prometheus_metric(&self) -> String {
+        let mut metric = String::from(
+            "# HELP pool_paused Whether the pool is paused, its liquidations paused meanwhile.\n\
+             # TYPE pool_paused gauge\n",
+        );
+        for entry in self.paused.iter() {
+            metric.push_str(&format!(
+                "pool_paused{{pool=\"{}\"}} {}\n",
+                entry.key(),
+                u8::from(*entry.value())
+            ));
+        }
+        metric
+    }
+}
+
+/// Reads from the pool whether it is paused.
+pub async fn fetch_is_paused(provider: &FallbackProvider, pool: PoolName) -> Result<bool> {
+    let request = FunctionCall {
+        contract_address: pool.pool_address(),
+        entry_point_selector: selector!("is_paused"),
+        calldata: vec![],
+    };
+    let result = guarded_starknet(provider, RpcPath::Background, |node| {
+        node.call(request.clone(), BlockId::Tag(BlockTag::Latest))
+    })
+    .await?;
+    let is_paused = result
+        .first()
+        .ok_or_else(|| anyhow::anyhow!("Empty is_paused result for {pool}"))?;
+    Ok(*is_paused != Felt::ZERO)
+}
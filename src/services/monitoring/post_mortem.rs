@@ -0,0 +1,268 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::Result;
+use pragma_common::starknet::FallbackProvider;
+use serde::Serialize;
+use starknet::core::types::{
+    BlockId, EventFilter, InvokeTransaction, MaybePreConfirmedBlockWithTxHashes, Transaction,
+};
+use starknet::macros::selector;
+use starknet::providers::Provider;
+
+use crate::services::monitoring::in_flight::InFlightLiquidation;
+use crate::services::monitoring::latency::{Stage, StageTimings};
+use crate::services::monitoring::route_quotes::ROUTE_QUOTES;
+use crate::utils::rpc::{RpcPath, guarded_starknet};
+
+// Post-mortems of the latest liquidations lost to a competitor, readable from the API.
+pub static RACE_REPORTS: LazyLock<Arc<RaceReports>> =
+    LazyLock::new(|| Arc::new(RaceReports::default()));
+
+/// Blocks before ours searched for the liquidation that won the race.
+const WINNER_LOOKBACK_BLOCKS: u64 = 20;
+const EVENTS_CHUNK_SIZE: u64 = 100;
+
+/// The liquidation that won the race against ours.
+#[derive(Debug, Clone, Serialize)]
+pub struct WinningLiquidation {
+    pub tx_hash: String,
+    pub block_number: u64,
+    /// Unix timestamp of its block, in seconds.
+    pub block_timestamp: u64,
+    /// None if it is not a V3 invoke.
+    pub liquidator: Option<String>,
+    pub tip: Option<u64>,
+    pub l2_gas_max_price: Option<u128>,
+}
+
+/// What each venue quoted for the swap of the lost liquidation.
+#[derive(Debug, Clone, Serialize)]
+pub struct RouteReport {
+    pub chosen: String,
+    /// venue => collateral sold
+    pub quotes: BTreeMap<String, String>,
+    pub spread_bps: Option<String>,
+}
+
+/// Everything known about a liquidation that reverted because the position got
+/// liquidated first, to tune the latency & the fees against the competitors.
+#[derive(Debug, Clone, Serialize)]
+pub struct RaceReport {
+    pub intent_id: String,
+    pub position_id: String,
+    pub pool: String,
+    pub tx_hash: String,
+    /// Block our liquidation reverted in.
+    pub block_number: Option<u64>,
+    /// Unix timestamp of our send, in seconds.
+    pub sent_at: u64,
+    pub revert_reason: String,
+    /// None if its event was not found in the blocks before ours.
+    pub winner: Option<WinningLiquidation>,
+    /// Seconds from the block of the winner to our send: positive if we sent
+    /// once the position was already liquidated.
+    pub send_delay_secs: Option<i64>,
+    /// Blocks between the one of the winner & ours.
+    pub blocks_behind: Option<u64>,
+    /// stage => milliseconds our attempt spent in it
+    pub stages_ms: BTreeMap<Stage, u64>,
+    /// The latest route comparison of the pair, if quoted.
+    pub routes: Option<RouteReport>,
+}
+
+/// The post-mortems of the latest lost races.
+#[derive(Debug, Default)]
+pub struct RaceReports {
+    recent: Mutex<VecDeque<RaceReport>>,
+}
+
+impl RaceReports {
+    /// Number of reports kept.
+    const CAPACITY: usize = 50;
+
+    fn record(&self, report: RaceReport) {
+        let mut recent = self.recent.lock().expect("poisoned race reports");
+        if recent.len() == Self::CAPACITY {
+            recent.pop_front();
+        }
+        recent.push_back(report);
+    }
+
+    /// The latest reports, the most recent first.
+    pub fn recent(&self) -> Vec<RaceReport> {
+        let recent = self.recent.lock().expect("poisoned race reports");
+        recent.iter().rev().cloned().collect()
+    }
+}
+
+/// Builds the post-mortem of a liquidation lost to a competitor in a background
+/// task, so the polling of the receipts is never slowed down by it.
+pub fn report_lost_race_in_background(
+    provider: FallbackProvider,
+    in_flight: InFlightLiquidation,
+    timings: StageTimings,
+    block_number: Option<u64>,
+    revert_reason: String,
+) {
+    tokio::spawn(async move {
+        let report =
+            build_report(&provider, &in_flight, &timings, block_number, revert_reason).await;
+        match serde_json::to_string(&report) {
+            Ok(json) => tracing::info!(
+                "[🔭 Monitoring] 🏁 Lost the race for position #{}: {json}",
+                report.position_id
+            ),
+            Err(e) => tracing::debug!("[🔭 Monitoring] Could not serialize the race report: {e}"),
+        }
+        RACE_REPORTS.record(report);
+    });
+}
+
+async fn build_report(
+    provider: &FallbackProvider,
+    in_flight: &InFlightLiquidation,
+    timings: &StageTimings,
+    block_number: Option<u64>,
+    revert_reason: String,
+) -> RaceReport {
+    let position = &in_flight.position;
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let sent_at = now.saturating_sub(in_flight.sent_at.elapsed().as_secs());
+
+    let winner = match block_number {
+        Some(block_number) => match find_winner(provider, in_flight, block_number).await {
+            Ok(winner) => winner,
+            Err(e) => {
+                tracing::debug!(
+                    "[🔭 Monitoring] Could not find the liquidation that won against {:#064x}: {e}",
+                    in_flight.tx_hash
+                );
+                None
+            }
+        },
+        None => None,
+    };
+    let send_delay_secs = winner
+        .as_ref()
+        .map(|winner| sent_at as i64 - winner.block_timestamp as i64);
+    let blocks_behind = block_number
+        .zip(winner.as_ref())
+        .map(|(block_number, winner)| block_number.saturating_sub(winner.block_number));
+
+    let routes = ROUTE_QUOTES
+        .latest(position.collateral.currency, position.debt.currency)
+        .map(|comparison| RouteReport {
+            chosen: comparison.chosen.to_string(),
+            spread_bps: comparison
+                .spread_bps()
+                .map(|bps| bps.round_dp(2).to_string()),
+            quotes: comparison
+                .quotes
+                .iter()
+                .map(|(venue, collateral)| (venue.to_string(), collateral.to_string()))
+                .collect(),
+        });
+
+    RaceReport {
+        intent_id: in_flight.intent_id.to_string(),
+        position_id: position.position_id(),
+        pool: position.pool_name.to_string(),
+        tx_hash: format!("{:#064x}", in_flight.tx_hash),
+        block_number,
+        sent_at,
+        revert_reason,
+        winner,
+        send_delay_secs,
+        blocks_behind,
+        stages_ms: timings.stages_ms(),
+        routes,
+    }
+}
+
+/// Finds the `LiquidatePosition` event of the position, keyed by (collateral,
+/// debt, user), in the blocks up to ours & reads its transaction.
+async fn find_winner(
+    provider: &FallbackProvider,
+    in_flight: &InFlightLiquidation,
+    block_number: u64,
+) -> Result<Option<WinningLiquidation>> {
+    let position = &in_flight.position;
+    let filter = EventFilter {
+        from_block: Some(BlockId::Number(
+            block_number.saturating_sub(WINNER_LOOKBACK_BLOCKS),
+        )),
+        to_block: Some(BlockId::Number(block_number)),
+        address: Some(position.pool_name.pool_address()),
+        keys: Some(vec![
+            vec![selector!("LiquidatePosition")],
+            vec![position.collateral.address],
+            vec![position.debt.address],
+            vec![position.user_address],
+        ]),
+    };
+
+    let mut winner = None;
+    let mut continuation_token = None;
+    loop {
+        let page = guarded_starknet(provider, RpcPath::Background, |node| {
+            node.get_events(
+                filter.clone(),
+                continuation_token.clone(),
+                EVENTS_CHUNK_SIZE,
+            )
+        })
+        .await?;
+        // The latest liquidation of the position before ours won.
+        if let Some(event) = page
+            .events
+            .into_iter()
+            .filter(|event| event.transaction_hash != in_flight.tx_hash)
+            .next_back()
+        {
+            winner = Some((event.transaction_hash, event.block_number));
+        }
+        continuation_token = page.continuation_token;
+        if continuation_token.is_none() {
+            break;
+        }
+    }
+    let Some((tx_hash, winner_block)) = winner else {
+        return Ok(None);
+    };
+    let winner_block = winner_block.unwrap_or(block_number);
+
+    let block_timestamp = match guarded_starknet(provider, RpcPath::Background, |node| {
+        node.get_block_with_tx_hashes(BlockId::Number(winner_block))
+    })
+    .await?
+    {
+        MaybePreConfirmedBlockWithTxHashes::Block(block) => block.timestamp,
+        MaybePreConfirmedBlockWithTxHashes::PreConfirmedBlock(block) => block.timestamp,
+    };
+    let transaction = guarded_starknet(provider, RpcPath::Background, |node| {
+        node.get_transaction_by_hash(tx_hash)
+    })
+    .await?;
+    let (liquidator, tip, l2_gas_max_price) = match transaction {
+        Transaction::Invoke(InvokeTransaction::V3(tx)) => (
+            Some(format!("{:#064x}", tx.sender_address)),
+            Some(tx.tip),
+            Some(tx.resource_bounds.l2_gas.max_price_per_unit),
+        ),
+        _ => (None, None, None),
+    };
+
+    Ok(Some(WinningLiquidation {
+        tx_hash: format!("{tx_hash:#064x}"),
+        block_number: winner_block,
+        block_timestamp,
+        liquidator,
+        tip,
+        l2_gas_max_price,
+    }))
+}
@@ -0,0 +1,192 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use cainome::cairo_serde::U256;
+use num_traits::Pow;
+use pragma_common::starknet::FallbackProvider;
+use rust_decimal::Decimal;
+use starknet::core::types::{Call, Felt};
+
+use crate::types::account::{FeeToken, StarknetAccount};
+use crate::types::currency::Currency;
+use crate::utils::erc20::{allowance, approve_call};
+use crate::utils::format::format_usd;
+use crate::utils::rpc::{RpcPath, guarded_starknet};
+
+/// Checks of the account before the liquidations get sent, so an empty fee
+/// balance or a missing allowance fails clearly instead of as a rejected tx.
+#[derive(Debug, Clone)]
+pub struct PrecheckConfig {
+    pub fee_token: FeeToken,
+    /// Minimum balance of the fee token to send liquidations.
+    pub min_fee_token_balance: Decimal,
+    /// Approvals sent for the inventory liquidations cover this much, in USD, so
+    /// they are sent once in a while instead of with every liquidation. The
+    /// liquidations needing more are not sent.
+    pub max_approval_usd: Decimal,
+}
+
+/// An allowance of the signer a liquidation needs.
+#[derive(Debug, Clone, Copy)]
+pub struct RequiredAllowance {
+    pub currency: Currency,
+    pub spender: Felt,
+    pub amount: Decimal,
+}
+
+impl RequiredAllowance {
+    /// The (token, spender) of the allowance.
+    pub fn key(&self) -> (Felt, Felt) {
+        (self.currency.address(), self.spender)
+    }
+
+    fn raw(&self, amount: Decimal) -> anyhow::Result<u128> {
+        Ok((amount * Decimal::TEN.pow(self.currency.d_decimals()))
+            .trunc()
+            .try_into()?)
+    }
+
+    /// The call approving exactly the required amount.
+    pub fn approve_call(&self) -> anyhow::Result<Call> {
+        Ok(approve_call(
+            self.currency.address(),
+            self.spender,
+            U256 {
+                low: self.raw(self.amount)?,
+                high: 0,
+            },
+        ))
+    }
+}
+
+#[derive(Debug)]
+pub struct AccountPrechecks {
+    config: PrecheckConfig,
+    /// Latest balance of the fee token read & when.
+    fee_balance: Option<(Instant, Decimal)>,
+    /// (token, spender) => raw allowance left, tracked locally since its read.
+    allowances: HashMap<(Felt, Felt), u128>,
+}
+
+impl AccountPrechecks {
+    /// How long a read balance of the fee token is trusted.
+    const FEE_BALANCE_TTL: Duration = Duration::from_secs(60);
+
+    pub fn new(config: PrecheckConfig) -> Self {
+        Self {
+            config,
+            fee_balance: None,
+            allowances: HashMap::new(),
+        }
+    }
+
+    /// Fails if the balance of the fee token is below the minimum.
+    pub async fn ensure_fee_balance(
+        &mut self,
+        account: &StarknetAccount,
+        provider: &FallbackProvider,
+    ) -> anyhow::Result<()> {
+        let balance = match self.fee_balance {
+            Some((read_at, balance)) if read_at.elapsed() < Self::FEE_BALANCE_TTL => balance,
+            _ => {
+                let balance = guarded_starknet(provider, RpcPath::Liquidation, |node| {
+                    account.ensure_fee_token_balance(node, self.config.fee_token)
+                })
+                .await?;
+                self.fee_balance = Some((Instant::now(), balance));
+                balance
+            }
+        };
+
+        let currency = self.config.fee_token.currency();
+        anyhow::ensure!(
+            balance >= self.config.min_fee_token_balance,
+            "the account only has {} to pay the fees, below the {} minimum",
+            currency.format_amount(balance),
+            currency.format_amount(self.config.min_fee_token_balance)
+        );
+        Ok(())
+    }
+
+    /// Returns the approvals the liquidations need & the signer does not give
+    /// yet, per (token, spender), to send in the transaction of the liquidations.
+    /// Each approval covers `max_approval_usd`, so it is only sent once in a
+    /// while. Fails if the liquidations need more than that.
+    pub async fn missing_approvals(
+        &mut self,
+        provider: &FallbackProvider,
+        owner: Felt,
+        required: &[RequiredAllowance],
+    ) -> anyhow::Result<HashMap<(Felt, Felt), Call>> {
+        let mut needed: HashMap<(Felt, Felt), RequiredAllowance> = HashMap::new();
+        for allowance in required {
+            needed
+                .entry(allowance.key())
+                .and_modify(|needed| needed.amount += allowance.amount)
+                .or_insert(*allowance);
+        }
+
+        let mut approvals = HashMap::new();
+        for (key, needed) in needed {
+            let (token, spender) = key;
+            let left = match self.allowances.get(&key) {
+                Some(left) => *left,
+                None => {
+                    let current = guarded_starknet(provider, RpcPath::Liquidation, |node| {
+                        allowance(node, token, owner, spender)
+                    })
+                    .await?;
+                    if current.high > 0 {
+                        u128::MAX
+                    } else {
+                        current.low
+                    }
+                }
+            };
+
+            let raw_needed = needed.raw(needed.amount)?;
+            if left >= raw_needed {
+                self.allowances.insert(key, left - raw_needed);
+                continue;
+            }
+
+            let price = needed.currency.price();
+            anyhow::ensure!(
+                !price.is_zero(),
+                "the price of {} is unknown, its approval cannot be capped",
+                needed.currency
+            );
+            let cap = self.config.max_approval_usd / price;
+            anyhow::ensure!(
+                needed.amount <= cap,
+                "the inventory liquidations need an approval of {}, above the {} maximum",
+                needed.currency.format_amount(needed.amount),
+                format_usd(self.config.max_approval_usd)
+            );
+            let raw_cap = needed.raw(cap)?;
+            tracing::info!(
+                "[🔭 Monitoring] 🔓 Approving {} to {spender:#x} for the inventory liquidations",
+                needed.currency.format_amount(cap)
+            );
+            approvals.insert(
+                key,
+                approve_call(
+                    token,
+                    spender,
+                    U256 {
+                        low: raw_cap,
+                        high: 0,
+                    },
+                ),
+            );
+            self.allowances.insert(key, raw_cap - raw_needed);
+        }
+        Ok(approvals)
+    }
+
+    /// Re-reads the allowances at the next check, e.g after a failed send.
+    pub fn forget_allowances(&mut self) {
+        self.allowances.clear();
+    }
+}
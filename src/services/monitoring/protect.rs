@@ -0,0 +1,103 @@
+use std::collections::HashSet;
+
+use cainome::cairo_serde::U256;
+use num_traits::Pow;
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use starknet::core::types::{Call, Felt};
+use starknet::macros::selector;
+
+use crate::services::oracle::pricing;
+use crate::types::position::VesuPosition;
+use crate::utils::erc20::approve_call;
+
+/// `AmountDenomination::Assets` of the Vesu pool: the amounts are in assets,
+/// not in shares.
+const ASSETS_DENOMINATION: Felt = Felt::ONE;
+
+/// Deleverages the positions of our own users before they get liquidable, by
+/// repaying their debt from the balance of the signer.
+#[derive(Debug, Clone)]
+pub struct ProtectConfig {
+    /// The signer must own the positions of these users or be their delegatee.
+    pub users: HashSet<Felt>,
+    /// A position gets deleveraged once its LTV reaches this % of its LLTV.
+    pub trigger_pct: Decimal,
+    /// The debt is repaid until the LTV gets back to this % of the LLTV.
+    pub target_pct: Decimal,
+}
+
+impl ProtectConfig {
+    /// Returns the debt to repay to bring the position back to the target LTV,
+    /// None if it doesn't need to be deleveraged.
+    pub fn debt_to_repay(&self, position: &VesuPosition) -> Option<Decimal> {
+        if !self.users.contains(&position.user_address) || position.debt.amount.is_zero() {
+            return None;
+        }
+
+        let trigger_ltv = position.lltv * self.trigger_pct / dec!(100);
+        if position.ltv() < trigger_ltv {
+            return None;
+        }
+
+        let target_ltv = position.lltv * self.target_pct / dec!(100);
+        let collateral_in_debt = pricing::convert(
+            position.collateral.amount,
+            position.collateral.currency,
+            position.debt.currency,
+        );
+        let debt_to_repay = position.debt.amount - collateral_in_debt * target_ltv;
+        (debt_to_repay > Decimal::ZERO).then_some(debt_to_repay.min(position.debt.amount))
+    }
+}
+
+/// A position of ours the monitoring wants deleveraged.
+#[derive(Debug, Clone)]
+pub struct DeleverageIntent {
+    pub position: VesuPosition,
+    pub debt_to_repay: Decimal,
+}
+
+/// Returns the calls repaying `debt_to_repay` of the position: the approval of
+/// the debt asset & the `modify_position` of the pool.
+pub fn deleverage_calls(
+    position: &VesuPosition,
+    debt_to_repay: Decimal,
+) -> anyhow::Result<Vec<Call>> {
+    let raw_amount: u128 = (debt_to_repay * Decimal::TEN.pow(position.debt.decimals))
+        .trunc()
+        .try_into()?;
+    let pool = position.pool_name.pool_address();
+
+    // ModifyPositionParams { collateral_asset, debt_asset, user, collateral: Amount,
+    // debt: Amount } with Amount { denomination, value: i257 { abs: u256, is_negative } }.
+    let modify_position = Call {
+        to: pool,
+        selector: selector!("modify_position"),
+        calldata: vec![
+            position.collateral.address,
+            position.debt.address,
+            position.user_address,
+            ASSETS_DENOMINATION,
+            Felt::ZERO,
+            Felt::ZERO,
+            Felt::ZERO,
+            ASSETS_DENOMINATION,
+            raw_amount.into(),
+            Felt::ZERO,
+            Felt::ONE,
+        ],
+    };
+
+    Ok(vec![
+        approve_call(
+            position.debt.address,
+            pool,
+            U256 {
+                low: raw_amount,
+                high: 0,
+            },
+        ),
+        modify_position,
+    ])
+}
@@ -0,0 +1,114 @@
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock};
+
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use starknet::core::types::Felt;
+
+use crate::types::pool::PoolName;
+use crate::types::position::Asset;
+
+// Positions set aside because of an asset missing from assets.toml, readable
+// from the API.
+pub static QUARANTINE: LazyLock<Arc<Quarantine>> =
+    LazyLock::new(|| Arc::new(Quarantine::default()));
+
+/// A position whose collateral or debt asset is not listed: it cannot be
+/// priced, so only its pair & user are kept to read it from the chain once the
+/// asset gets listed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuarantinedPosition {
+    pub pool: PoolName,
+    pub position_key: String,
+    pub collateral_address: Felt,
+    pub debt_address: Felt,
+    pub user_address: Felt,
+}
+
+impl QuarantinedPosition {
+    /// The first asset of the position missing from assets.toml, if any.
+    pub fn unlisted_asset(&self) -> Option<Felt> {
+        [self.collateral_address, self.debt_address]
+            .into_iter()
+            .find(|address| !Asset::is_listed(address))
+    }
+}
+
+/// The quarantined positions, instead of dropping their events: the monitoring
+/// keeps running on the listed assets & re-evaluates them on the next startup,
+/// i.e once assets.toml is updated.
+#[derive(Debug, Default)]
+pub struct Quarantine {
+    positions: DashMap<(PoolName, String), QuarantinedPosition>,
+}
+
+impl Quarantine {
+    /// Quarantines the position & returns true if it is the first one of its
+    /// unlisted asset, to alert once per asset.
+    pub fn insert(&self, position: QuarantinedPosition) -> bool {
+        let unlisted = position.unlisted_asset();
+        let is_new_asset = unlisted.is_some_and(|unlisted| {
+            !self
+                .positions
+                .iter()
+                .any(|entry| entry.unlisted_asset() == Some(unlisted))
+        });
+        self.positions
+            .insert((position.pool, position.position_key.clone()), position);
+        is_new_asset
+    }
+
+    pub fn contains(&self, pool: PoolName, position_key: &str) -> bool {
+        self.positions
+            .contains_key(&(pool, position_key.to_string()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    pub fn all(&self) -> Vec<QuarantinedPosition> {
+        self.positions
+            .iter()
+            .map(|entry| entry.value().clone())
+            .collect()
+    }
+
+    /// Removes & returns the positions whose assets are all listed now.
+    pub fn release_listed(&self) -> Vec<QuarantinedPosition> {
+        let listed: Vec<(PoolName, String)> = self
+            .positions
+            .iter()
+            .filter(|entry| entry.unlisted_asset().is_none())
+            .map(|entry| entry.key().clone())
+            .collect();
+        listed
+            .into_iter()
+            .filter_map(|key| self.positions.remove(&key).map(|(_, position)| position))
+            .collect()
+    }
+
+    pub fn prometheus_metric(&self) -> String {
+        let mut per_asset: HashMap<Felt, usize> = HashMap::new();
+        for entry in self.positions.iter() {
+            if let Some(unlisted) = entry.unlisted_asset() {
+                *per_asset.entry(unlisted).or_default() += 1;
+            }
+        }
+
+        let mut metric = String::from(
+            "# HELP quarantined_positions Positions set aside because their asset is not in assets.toml.\n\
+             # TYPE quarantined_positions gauge\n",
+        );
+        for (asset, positions) in per_asset {
+            metric.push_str(&format!(
+                "quarantined_positions{{asset=\"{asset:#x}\"}} {positions}\n"
+            ));
+        }
+        metric
+    }
+}
@@ -0,0 +1,163 @@
+use std::str::FromStr;
+
+use num_traits::Pow;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use starknet::core::types::{Event, Felt, TransactionReceipt};
+use starknet::macros::selector;
+
+use crate::config::onchain_assets::ONCHAIN_ASSETS;
+use crate::types::currency::Currency;
+use crate::types::decimal;
+use crate::types::position::VesuPosition;
+
+/// What a confirmed liquidation actually did, read from its receipt events.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct RealizedLiquidation {
+    /// Collateral removed from the position, in collateral units.
+    #[serde(with = "decimal::string")]
+    pub collateral_seized: Decimal,
+    /// Debt repaid, in debt units.
+    #[serde(with = "decimal::string")]
+    pub debt_repaid: Decimal,
+    /// Debt left unbacked by the position, in debt units.
+    #[serde(with = "decimal::string")]
+    pub bad_debt: Decimal,
+    /// USD value of the collateral left to the recipient once the debt is repaid.
+    #[serde(with = "decimal::string")]
+    pub residual_usd: Decimal,
+    /// Share of the transaction fee of this liquidation, in USD.
+    #[serde(with = "decimal::string")]
+    pub fee_usd: Decimal,
+}
+
+impl RealizedLiquidation {
+    pub fn profit_usd(&self) -> Decimal {
+        self.residual_usd - self.fee_usd
+    }
+}
+
+/// How the debt of a liquidation got repaid.
+#[derive(Debug, Clone, Copy)]
+pub enum Repayment {
+    /// The liquidate contract swapped the seized collateral into the debt asset
+    /// & sent the residual to the recipient.
+    Swap {
+        liquidate_contract: Felt,
+        recipient: Felt,
+    },
+    /// The signer repaid the debt from its inventory & kept all the collateral.
+    Inventory,
+}
+
+/// Reads the liquidation of `position` from the receipt of its transaction.
+///
+/// The amounts come from the `LiquidatePosition` event of the pool, keyed by
+/// (collateral, debt, user) with the data (liquidator, collateral_delta,
+/// collateral_shares_delta, debt_delta, nominal_debt_delta, bad_debt) - all u256.
+/// For a swap, the residual is the sum of the transfers from the liquidate
+/// contract to the recipient following that event. From the inventory, it's the
+/// value of the seized collateral minus the one of the repaid debt.
+/// The fee is split evenly between the liquidations of the transaction.
+/// Returns None if the receipt has no liquidation of the position.
+pub fn realized_liquidation(
+    receipt: &TransactionReceipt,
+    position: &VesuPosition,
+    repayment: Repayment,
+) -> Option<RealizedLiquidation> {
+    let TransactionReceipt::Invoke(receipt) = receipt else {
+        return None;
+    };
+
+    let liquidations_in_tx = receipt
+        .events
+        .iter()
+        .filter(|event| event.keys.first() == Some(&selector!("LiquidatePosition")))
+        .count()
+        .max(1);
+
+    let mut realized: Option<RealizedLiquidation> = None;
+    // Whether the last liquidation event of the tx is the one of the position.
+    let mut in_position_liquidation = false;
+    for event in &receipt.events {
+        if event.keys.first() == Some(&selector!("LiquidatePosition")) {
+            in_position_liquidation = is_position_liquidation(event, position);
+            if in_position_liquidation && realized.is_none() {
+                realized = Some(RealizedLiquidation {
+                    collateral_seized: u256_amount(&event.data, 1, position.collateral.decimals)?,
+                    debt_repaid: u256_amount(&event.data, 5, position.debt.decimals)?,
+                    bad_debt: u256_amount(&event.data, 9, position.debt.decimals)?,
+                    residual_usd: Decimal::ZERO,
+                    fee_usd: Decimal::ZERO,
+                });
+            }
+            continue;
+        }
+
+        if in_position_liquidation
+            && let Repayment::Swap {
+                liquidate_contract,
+                recipient,
+            } = repayment
+            && let Some(realized) = realized.as_mut()
+            && let Some((from, to, amount_low)) = parse_transfer(event)
+            && from == liquidate_contract
+            && to == recipient
+        {
+            realized.residual_usd += transfer_value_usd(event.from_address, amount_low);
+        }
+    }
+
+    let mut realized = realized?;
+    if let Repayment::Inventory = repayment {
+        realized.residual_usd = realized.collateral_seized * position.collateral.currency.price()
+            - realized.debt_repaid * position.debt.currency.price();
+    }
+    let fee = Decimal::from_str(&receipt.actual_fee.amount.to_string()).ok()?
+        / Decimal::TEN.pow(Currency::STRK.d_decimals());
+    realized.fee_usd = fee * Currency::STRK.price() / Decimal::from(liquidations_in_tx);
+    Some(realized)
+}
+
+fn is_position_liquidation(event: &Event, position: &VesuPosition) -> bool {
+    event.from_address == position.pool_name.pool_address()
+        && event.keys.get(1) == Some(&position.collateral.address)
+        && event.keys.get(2) == Some(&position.debt.address)
+        && event.keys.get(3) == Some(&position.user_address)
+}
+
+/// Returns the (from, to, amount low) of an ERC20 `Transfer` event, with its
+/// addresses either as keys or, for the legacy tokens, as data.
+fn parse_transfer(event: &Event) -> Option<(Felt, Felt, Felt)> {
+    if event.keys.first() != Some(&selector!("Transfer")) {
+        return None;
+    }
+    match event.keys.len() {
+        3 => Some((event.keys[1], event.keys[2], *event.data.first()?)),
+        1 => Some((
+            *event.data.first()?,
+            *event.data.get(1)?,
+            *event.data.get(2)?,
+        )),
+        _ => None,
+    }
+}
+
+fn transfer_value_usd(token: Felt, amount_low: Felt) -> Decimal {
+    let Some(asset) = ONCHAIN_ASSETS.get_by_address(&token) else {
+        return Decimal::ZERO;
+    };
+    let Ok(currency) = Currency::from_str(&asset.ticker) else {
+        return Decimal::ZERO;
+    };
+    let Ok(amount) = Decimal::from_str(&amount_low.to_string()) else {
+        return Decimal::ZERO;
+    };
+    amount / Decimal::TEN.pow(currency.d_decimals()) * currency.price()
+}
+
+/// Reads the low part of the u256 at `index` as an amount with `decimals`.
+fn u256_amount(data: &[Felt], index: usize, decimals: Decimal) -> Option<Decimal> {
+    let low = Decimal::from_str(&data.get(index)?.to_string()).ok()?;
+    Some(low / Decimal::TEN.pow(decimals))
+}
@@ -0,0 +1,124 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::services::indexer::IndexedEvent;
+use crate::services::monitoring::wal::{EventCursor, EvictedPosition};
+use crate::types::pool::PoolName;
+use crate::types::position::VesuPosition;
+
+/// The key of a position in the monitoring: its pool & its id.
+type PositionKey = (PoolName, String);
+
+/// The state of a position before an event changed it.
+#[derive(Debug, Clone)]
+pub struct PositionChange {
+    pub key: PositionKey,
+    /// None if the event created the position.
+    pub previous: Option<VesuPosition>,
+    /// The position, if it was evicted from memory before the event.
+    pub evicted: Option<EvictedPosition>,
+}
+
+/// An event applied on top of the finalized block.
+#[derive(Debug, Clone)]
+struct UnfinalizedEvent {
+    event: IndexedEvent,
+    /// The cursor before the event got applied.
+    cursor: EventCursor,
+    /// None if the event did not concern a monitored pool.
+    change: Option<PositionChange>,
+}
+
+/// The events applied on top of the finalized block, with the state of the
+/// positions they changed. Lets the checkpoints persist only the finalized state,
+/// the events not finalized yet staying in the WAL, where a re-org can drop them.
+#[derive(Debug, Default)]
+pub struct RollbackBuffer {
+    events: VecDeque<UnfinalizedEvent>,
+}
+
+impl RollbackBuffer {
+    /// Bounds the buffer if no finalized block gets notified: the oldest events
+    /// are then considered final.
+    const MAX_EVENTS: usize = 50_000;
+
+    pub fn push(
+        &mut self,
+        event: IndexedEvent,
+        cursor: EventCursor,
+        change: Option<PositionChange>,
+    ) {
+        if self.events.len() == Self::MAX_EVENTS {
+            self.events.pop_front();
+        }
+        self.events.push_back(UnfinalizedEvent {
+            event,
+            cursor,
+            change,
+        });
+    }
+
+    /// Drops the events of the blocks up to the finalized one & returns how many.
+    pub fn prune(&mut self, finalized_block: u64) -> usize {
+        let finalized = self
+            .events
+            .iter()
+            .take_while(|e| e.event.0.block_number <= finalized_block)
+            .count();
+        self.events.drain(..finalized);
+        finalized
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// The events not finalized yet, in their order.
+    pub fn events(&self) -> impl Iterator<Item = &IndexedEvent> {
+        self.events.iter().map(|e| &e.event)
+    }
+
+    /// The cursor of the finalized state, before the first event not finalized.
+    pub fn finalized_cursor(&self, current: EventCursor) -> EventCursor {
+        self.events.front().map_or(current, |e| e.cursor)
+    }
+
+    /// The positions & the evicted keys at the finalized block: the current ones,
+    /// with the changes of the events not finalized undone.
+    pub fn finalized_state(
+        &self,
+        positions: &HashMap<PositionKey, VesuPosition>,
+        evicted: &HashMap<PositionKey, EvictedPosition>,
+    ) -> (Vec<VesuPosition>, Vec<EvictedPosition>) {
+        // The earliest change of a position holds its finalized state.
+        let mut finalized: HashMap<&PositionKey, &PositionChange> = HashMap::new();
+        for change in self.events.iter().filter_map(|e| e.change.as_ref()) {
+            finalized.entry(&change.key).or_insert(change);
+        }
+
+        let mut finalized_evicted = evicted.clone();
+        let mut finalized_positions: Vec<VesuPosition> = positions
+            .iter()
+            .filter(|(key, _)| !finalized.contains_key(key))
+            .map(|(_, position)| position.clone())
+            .collect();
+        for change in finalized.into_values() {
+            // Evicted since, it gets re-read from the chain on its next event.
+            if evicted.contains_key(&change.key) {
+                continue;
+            }
+            if let Some(was_evicted) = &change.evicted {
+                finalized_evicted.insert(change.key.clone(), was_evicted.clone());
+            } else if let Some(previous) = &change.previous {
+                finalized_positions.push(previous.clone());
+            }
+        }
+        (
+            finalized_positions,
+            finalized_evicted.into_values().collect(),
+        )
+    }
+}
@@ -0,0 +1,42 @@
+use std::collections::BTreeSet;
+
+use futures_util::future::join_all;
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+
+use crate::services::indexer::IndexerService;
+use crate::services::monitoring::ekubo::get_ekubo_route;
+use crate::types::currency::Currency;
+
+/// USD value of the debt used to quote the routes.
+const PREFLIGHT_AMOUNT_USD: Decimal = dec!(100);
+
+/// Checks that a debt => collateral swap route exists for every monitored pair
+/// and returns the unroutable ones.
+pub async fn check_routes() -> Vec<(Currency, Currency)> {
+    let pairs: BTreeSet<(Currency, Currency)> = IndexerService::monitored_pairs()
+        .into_iter()
+        .map(|(_, collateral, debt)| (debt, collateral))
+        .collect();
+
+    let checks = pairs.into_iter().map(|(debt, collateral)| async move {
+        let amount = PREFLIGHT_AMOUNT_USD / debt.price();
+        let route = get_ekubo_route(
+            debt.address(),
+            collateral.address(),
+            &amount,
+            debt.d_decimals(),
+        )
+        .await;
+        (debt, collateral, route)
+    });
+
+    let mut unroutable = Vec::new();
+    for (debt, collateral, route) in join_all(checks).await {
+        if let Err(e) = route {
+            tracing::warn!("[🔭 Monitoring] 🚧 No swap route from {debt} to {collateral}: {e}");
+            unroutable.push((debt, collateral));
+        }
+    }
+    unroutable
+}
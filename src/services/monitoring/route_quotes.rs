@@ -0,0 +1,212 @@
+use std::str::FromStr;
+use std::sync::{Arc, LazyLock};
+
+use dashmap::DashMap;
+use futures_util::future::join_all;
+use num_traits::Pow;
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+
+use crate::services::monitoring::avnu::get_avnu_exact_output_quote;
+use crate::services::monitoring::ekubo::get_ekubo_exact_output_quote;
+use crate::services::monitoring::strategy::RouteVenue;
+use crate::types::currency::Currency;
+use crate::types::position::VesuPosition;
+use crate::utils::rpc::{RpcPath, RpcProvider, guarded};
+
+// Latest route comparison of every pair, readable from the API.
+pub static ROUTE_QUOTES: LazyLock<Arc<RouteQuotes>> =
+    LazyLock::new(|| Arc::new(RouteQuotes::default()));
+
+/// Venues quoted for every liquidation.
+const QUOTED_VENUES: [RouteVenue; 2] = [RouteVenue::Ekubo, RouteVenue::Avnu];
+
+/// What each venue takes of collateral to buy the debt repaid by a liquidation.
+#[derive(Debug, Clone)]
+pub struct RouteComparison {
+    /// Venue the liquidation swapped on.
+    pub chosen: RouteVenue,
+    /// Collateral sold per venue that could quote the swap.
+    pub quotes: Vec<(RouteVenue, Decimal)>,
+}
+
+impl RouteComparison {
+    pub fn best(&self) -> Option<(RouteVenue, Decimal)> {
+        self.quotes.iter().copied().min_by(|a, b| a.1.cmp(&b.1))
+    }
+
+    /// Extra collateral sold by the chosen venue compared to the best one, in bps.
+    pub fn spread_bps(&self) -> Option<Decimal> {
+        let (_, best) = self.best()?;
+        let (_, chosen) = self
+            .quotes
+            .iter()
+            .find(|(venue, _)| *venue == self.chosen)?;
+        if best.is_zero() {
+            return None;
+        }
+        Some((chosen - best) / best * dec!(10000))
+    }
+}
+
+/// The route comparisons of the liquidations, so a regression of the routing
+/// shows up in the metrics.
+#[derive(Debug, Default)]
+pub struct RouteQuotes {
+    /// (collateral, debt) pair => comparison of its latest liquidation
+    latest: DashMap<(Currency, Currency), RouteComparison>,
+    /// best venue => liquidations
+    best_venues: DashMap<RouteVenue, u64>,
+}
+
+impl RouteQuotes {
+    fn record(&self, position: &VesuPosition, comparison: RouteComparison) {
+        let quotes = comparison
+            .quotes
+            .iter()
+            .map(|(venue, collateral)| {
+                let chosen = if *venue == comparison.chosen {
+                    " (chosen)"
+                } else {
+                    ""
+                };
+                format!(
+                    "{venue} {collateral:.6} {}{chosen}",
+                    position.collateral.currency
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        let spread = comparison
+            .spread_bps()
+            .map_or_else(|| "no spread".into(), |bps| format!("spread {bps:.1}bps"));
+        tracing::info!("[🔭 Monitoring] 🧭 Routes of {position}: {quotes} - {spread}");
+
+        if let Some((best, _)) = comparison.best() {
+            *self.best_venues.entry(best).or_default() += 1;
+        }
+        self.latest.insert(
+            (position.collateral.currency, position.debt.currency),
+            comparison,
+        );
+    }
+
+    /// The comparison of the latest liquidation of the pair, if quoted.
+    pub fn latest(&self, collateral: Currency, debt: Currency) -> Option<RouteComparison> {
+        self.latest
+            .get(&(collateral, debt))
+            .map(|comparison| comparison.clone())
+    }
+
+    pub fn prometheus_metric(&self) -> String {
+        let mut metric = String::from(
+            "# HELP route_spread_bps Extra collateral sold by the chosen route of the latest liquidation of the pair, against the best venue.\n\
+             # TYPE route_spread_bps gauge\n",
+        );
+        for entry in self.latest.iter() {
+            let (collateral, debt) = entry.key();
+            if let Some(spread_bps) = entry.value().spread_bps() {
+                metric.push_str(&format!(
+                    "route_spread_bps{{collateral=\"{collateral}\",debt=\"{debt}\",chosen=\"{}\"}} {}\n",
+                    entry.value().chosen,
+                    spread_bps.round_dp(2)
+                ));
+            }
+        }
+
+        metric.push_str(
+            "# HELP route_quote_collateral Collateral each venue takes to repay the latest liquidation of the pair.\n\
+             # TYPE route_quote_collateral gauge\n",
+        );
+        for entry in self.latest.iter() {
+            let (collateral, debt) = entry.key();
+            for (venue, amount) in &entry.value().quotes {
+                metric.push_str(&format!(
+                    "route_quote_collateral{{collateral=\"{collateral}\",debt=\"{debt}\",venue=\"{venue}\"}} {amount}\n"
+                ));
+            }
+        }
+
+        metric.push_str(
+            "# HELP route_best_venue_total Liquidations whose swap was quoted best by the venue.\n\
+             # TYPE route_best_venue_total counter\n",
+        );
+        for entry in self.best_venues.iter() {
+            metric.push_str(&format!(
+                "route_best_venue_total{{venue=\"{}\"}} {}\n",
+                entry.key(),
+                entry.value()
+            ));
+        }
+        metric
+    }
+}
+
+/// Quotes every venue for the swap of a sent liquidation in a background task,
+/// so the liquidations are never slowed down by the comparison.
+pub fn compare_routes_in_background(position: VesuPosition, debt_to_repay: Option<Decimal>) {
+    tokio::spawn(async move {
+        match quote_venues(&position, debt_to_repay).await {
+            Ok(comparison) => ROUTE_QUOTES.record(&position, comparison),
+            Err(e) => {
+                tracing::debug!("[🔭 Monitoring] Could not quote the routes of {position}: {e}");
+            }
+        }
+    });
+}
+
+/// Quotes the collateral every venue takes to buy the debt repaid by the
+/// liquidation. The venues failing to quote are left out.
+async fn quote_venues(
+    position: &VesuPosition,
+    debt_to_repay: Option<Decimal>,
+) -> anyhow::Result<RouteComparison> {
+    let debt_to_repay = debt_to_repay.unwrap_or(position.debt.amount);
+    let raw_debt: u128 = (debt_to_repay * Decimal::TEN.pow(position.debt.decimals))
+        .trunc()
+        .try_into()?;
+    let (from, to) = (position.collateral.address, position.debt.address);
+
+    let quotes = join_all(QUOTED_VENUES.map(|venue| async move {
+        let quote = match venue {
+            RouteVenue::Ekubo => {
+                guarded(
+                    RpcProvider::Ekubo,
+                    RpcPath::Background,
+                    get_ekubo_exact_output_quote(from, to, raw_debt),
+                )
+                .await
+            }
+            RouteVenue::Avnu => {
+                guarded(
+                    RpcProvider::Avnu,
+                    RpcPath::Background,
+                    get_avnu_exact_output_quote(from, to, raw_debt),
+                )
+                .await
+            }
+        };
+        (venue, quote)
+    }))
+    .await;
+
+    let scale = Decimal::TEN.pow(position.collateral.decimals);
+    let mut comparison = RouteComparison {
+        // The liquidate contracts swap on Ekubo.
+        chosen: RouteVenue::Ekubo,
+        quotes: Vec::with_capacity(quotes.len()),
+    };
+    for (venue, quote) in quotes {
+        match quote.and_then(|raw| Ok(Decimal::from_str(&raw.to_string())? / scale)) {
+            Ok(collateral) => comparison.quotes.push((venue, collateral)),
+            Err(e) => {
+                tracing::debug!(
+                    "[🔭 Monitoring] {venue} could not quote the swap of {position}: {e}"
+                );
+            }
+        }
+    }
+
+    anyhow::ensure!(!comparison.quotes.is_empty(), "no venue could quote it");
+    Ok(comparison)
+}
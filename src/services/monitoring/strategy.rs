@@ -0,0 +1,194 @@
+use std::collections::HashMap;
+use std::fmt::Debug;
+
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+
+use crate::services::monitoring::health_history::HealthHistory;
+use crate::services::oracle::vesu_prices::VesuOraclePrices;
+use crate::types::currency::Currency;
+use crate::types::position::VesuPosition;
+use crate::utils::format::format_usd;
+
+/// Everything a strategy can use to take its decision.
+pub struct StrategyInputs<'a> {
+    pub position: &'a VesuPosition,
+    pub history: &'a HealthHistory,
+    pub prices: &'a VesuOraclePrices,
+    /// Fee estimated for the liquidation in USD, known once its intent got a
+    /// first attempt.
+    pub estimated_fee_usd: Option<Decimal>,
+    /// Balances of the liquidator account, refreshed every minute.
+    pub inventory: &'a HashMap<Currency, Decimal>,
+}
+
+/// Venue used to swap the collateral into the debt asset.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, strum::Display)]
+pub enum RouteVenue {
+    #[default]
+    Ekubo,
+    /// Only quoted, to compare the routes: the liquidate contracts swap on Ekubo.
+    Avnu,
+}
+
+#[derive(Debug, Clone)]
+pub enum LiquidationDecision {
+    /// Don't liquidate the position. The reason, if any, gets logged.
+    Skip { reason: Option<String> },
+    /// Repay all the debt of the position.
+    Full,
+    /// Only repay `debt_to_repay` of the debt asset.
+    Partial { debt_to_repay: Decimal },
+}
+
+impl LiquidationDecision {
+    pub fn skip() -> Self {
+        Self::Skip { reason: None }
+    }
+
+    pub fn skip_because(reason: impl Into<String>) -> Self {
+        Self::Skip {
+            reason: Some(reason.into()),
+        }
+    }
+}
+
+/// What to do with a liquidation repaying more than the maximum debt.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum OversizedLiquidation {
+    /// Only repay the maximum debt.
+    #[default]
+    Partial,
+    /// Don't liquidate the position & alert.
+    Alert,
+}
+
+/// Caps the debt repaid by a liquidation, so a bot with a limited inventory
+/// doesn't attempt whale liquidations it can't fill without a massive slippage.
+#[derive(Debug, Clone, Copy)]
+pub struct DebtCap {
+    pub max_debt_usd: Decimal,
+    pub on_oversized: OversizedLiquidation,
+}
+
+impl DebtCap {
+    /// Applies the cap to the decision taken for the position.
+    pub fn apply(
+        &self,
+        position: &VesuPosition,
+        decision: LiquidationDecision,
+    ) -> LiquidationDecision {
+        let debt_to_repay = match &decision {
+            LiquidationDecision::Skip { .. } => return decision,
+            LiquidationDecision::Full => position.debt.amount,
+            LiquidationDecision::Partial { debt_to_repay } => *debt_to_repay,
+        };
+
+        let debt_price = position.debt.currency.price();
+        if debt_price.is_zero() || debt_to_repay * debt_price <= self.max_debt_usd {
+            return decision;
+        }
+
+        match self.on_oversized {
+            OversizedLiquidation::Partial => LiquidationDecision::Partial {
+                debt_to_repay: self.max_debt_usd / debt_price,
+            },
+            OversizedLiquidation::Alert => {
+                tracing::error!(
+                    "[🔭 Monitoring] 🐋 {position} needs to repay {} of debt, more than the {} maximum",
+                    format_usd(debt_to_repay * debt_price),
+                    format_usd(self.max_debt_usd)
+                );
+                LiquidationDecision::skip_because("its debt exceeds the maximum debt to repay")
+            }
+        }
+    }
+}
+
+/// Margin kept above the debt floor by a partial liquidation, the debt value
+/// moving with the price until the transaction lands.
+const DEBT_FLOOR_MARGIN: Decimal = dec!(1.02);
+
+/// Returns true if repaying `debt_to_repay` leaves the position with a debt
+/// below the floor of its pool, which reverts the repayment.
+pub fn leaves_dust(position: &VesuPosition, debt_to_repay: Decimal, floor_usd: Decimal) -> bool {
+    let left_usd = (position.debt.amount - debt_to_repay) * position.debt.currency.price();
+    left_usd > Decimal::ZERO && left_usd < floor_usd
+}
+
+/// Sizes a partial liquidation so that the debt left is either zero or above
+/// the debt floor of the pool: all the debt gets repaid if within `max_debt_usd`,
+/// else the repayment leaves the floor & a margin.
+pub fn apply_debt_floor(
+    position: &VesuPosition,
+    decision: LiquidationDecision,
+    floor_usd: Decimal,
+    max_debt_usd: Option<Decimal>,
+) -> LiquidationDecision {
+    let LiquidationDecision::Partial { debt_to_repay } = &decision else {
+        return decision;
+    };
+    let debt_price = position.debt.currency.price();
+    if debt_price.is_zero() || !leaves_dust(position, *debt_to_repay, floor_usd) {
+        return decision;
+    }
+
+    if max_debt_usd.is_none_or(|max_debt_usd| position.debt.amount * debt_price <= max_debt_usd) {
+        return LiquidationDecision::Full;
+    }
+    let debt_to_repay = position.debt.amount - floor_usd * DEBT_FLOOR_MARGIN / debt_price;
+    if debt_to_repay <= Decimal::ZERO {
+        return LiquidationDecision::skip_because(
+            "its debt cannot be partially repaid above the debt floor of the pool",
+        );
+    }
+    LiquidationDecision::Partial { debt_to_repay }
+}
+
+/// Decides if and how a position should be liquidated.
+/// Implement this trait to plug a custom strategy in the monitoring loop. Only
+/// the liquidable & almost liquidable positions are given to the strategy: it
+/// must check `VesuPosition::is_liquidable` itself before liquidating one.
+pub trait LiquidationStrategy: Debug + Send + Sync {
+    fn decide(&self, inputs: &StrategyInputs<'_>) -> LiquidationDecision;
+}
+
+/// Liquidates fully every liquidable position, once the hysteresis rule is met:
+/// the position must be liquidable for K consecutive checks, or by more than X bps.
+#[derive(Debug, Clone)]
+pub struct DefaultStrategy {
+    /// Number of consecutive checks a position must be liquidable before we
+    /// attempt to liquidate it.
+    pub liquidation_confirmations: usize,
+    /// If set, a position exceeding its LLTV by more than this many bps gets
+    /// liquidated without waiting for the confirmations.
+    pub liquidation_margin_bps: Option<Decimal>,
+}
+
+impl DefaultStrategy {
+    fn is_liquidation_confirmed(&self, position: &VesuPosition, history: &HealthHistory) -> bool {
+        if history.consecutive_at_or_above(position.lltv) >= self.liquidation_confirmations {
+            return true;
+        }
+
+        let excess_bps = (position.ltv() - position.lltv) * dec!(10_000);
+        self.liquidation_margin_bps
+            .is_some_and(|margin_bps| excess_bps > margin_bps)
+    }
+}
+
+impl LiquidationStrategy for DefaultStrategy {
+    fn decide(&self, inputs: &StrategyInputs<'_>) -> LiquidationDecision {
+        if !inputs.position.is_liquidable() {
+            return LiquidationDecision::skip();
+        }
+
+        if !self.is_liquidation_confirmed(inputs.position, inputs.history) {
+            return LiquidationDecision::skip_because(
+                "waiting for it to stay liquidable before liquidating it",
+            );
+        }
+
+        LiquidationDecision::Full
+    }
+}
@@ -1,4 +1,3 @@
-use evian::utils::indexer::handler::StarknetEventMetadata;
 use pragma_common::{
     services::{Service, ServiceRunner},
     starknet::FallbackProvider,
@@ -6,29 +5,45 @@ use pragma_common::{
 use tokio::sync::{mpsc, oneshot};
 
 use crate::{
-    services::{indexer::PositionDelta, monitoring::MonitoringService},
-    types::account::StarknetAccount,
+    services::{
+        indexer::IndexedEvent,
+        monitoring::{
+            MonitoringConfig, MonitoringService,
+            executor::LiquidationExecutor,
+            wal::{RecoveredState, WriteAheadLog},
+        },
+    },
+    types::{account::StarknetAccount, liquidate_contract::LiquidateContracts},
 };
 
 pub struct MonitoringTask {
     account: StarknetAccount,
     provider: FallbackProvider,
-    rx_from_indexer: Option<mpsc::UnboundedReceiver<(StarknetEventMetadata, PositionDelta)>>,
+    liquidate_contracts: LiquidateContracts,
+    rx_from_indexer: Option<mpsc::UnboundedReceiver<IndexedEvent>>,
     wait_for_indexer: Option<oneshot::Receiver<()>>,
+    wal: Option<(WriteAheadLog, RecoveredState)>,
+    config: MonitoringConfig,
 }
 
 impl MonitoringTask {
     pub fn new(
         account: StarknetAccount,
         provider: FallbackProvider,
-        rx_from_indexer: mpsc::UnboundedReceiver<(StarknetEventMetadata, PositionDelta)>,
+        liquidate_contracts: LiquidateContracts,
+        rx_from_indexer: mpsc::UnboundedReceiver<IndexedEvent>,
         wait_for_indexer: oneshot::Receiver<()>,
+        wal: Option<(WriteAheadLog, RecoveredState)>,
+        config: MonitoringConfig,
     ) -> Self {
         Self {
             account,
             provider,
+            liquidate_contracts,
             rx_from_indexer: Some(rx_from_indexer),
             wait_for_indexer: Some(wait_for_indexer),
+            wal,
+            config,
         }
     }
 }
@@ -38,6 +53,9 @@ impl Service for MonitoringTask {
     async fn start<'a>(&mut self, mut runner: ServiceRunner<'a>) -> anyhow::Result<()> {
         let account = self.account.clone();
         let provider = self.provider.clone();
+        let liquidate_contracts = self.liquidate_contracts.clone();
+        let config = self.config.clone();
+        let wal = self.wal.take();
         let rx_from_indexer = self
             .rx_from_indexer
             .take()
@@ -47,9 +65,31 @@ impl Service for MonitoringTask {
             .take()
             .expect("MonitoringTask cannot be launched twice");
 
+        let account_address = account.account_address();
+        let (executor, executor_handle) = LiquidationExecutor::new(
+            account,
+            provider.clone(),
+            liquidate_contracts,
+            config.executor.clone(),
+        );
+        runner.spawn_loop(move |ctx| async move {
+            if let Some(result) = ctx.run_until_cancelled(executor.run_forever()).await {
+                result?;
+            }
+
+            anyhow::Ok(())
+        });
+
         runner.spawn_loop(move |ctx| async move {
-            let monitoring_service =
-                MonitoringService::new(provider, account, rx_from_indexer, wait_for_indexer);
+            let monitoring_service = MonitoringService::new(
+                provider,
+                account_address,
+                executor_handle,
+                rx_from_indexer,
+                wait_for_indexer,
+                wal,
+                config,
+            );
             if let Some(result) = ctx
                 .run_until_cancelled(monitoring_service.run_forever())
                 .await
@@ -0,0 +1,41 @@
+use std::sync::{Arc, LazyLock};
+
+use dashmap::DashMap;
+use starknet::core::types::Felt;
+
+// Events skipped because their pool is not a `PoolName`, readable from the API.
+pub static UNKNOWN_POOLS: LazyLock<Arc<UnknownPools>> =
+    LazyLock::new(|| Arc::new(UnknownPools::default()));
+
+/// Counts the events of the pools the bot does not know, skipped instead of
+/// stopping the monitoring.
+#[derive(Debug, Default)]
+pub struct UnknownPools {
+    /// pool address => skipped events
+    skipped_events: DashMap<Felt, u64>,
+}
+
+impl UnknownPools {
+    /// Records a skipped event of the pool & returns the number of events
+    /// skipped for it so far.
+    pub fn record_skipped(&self, pool: Felt) -> u64 {
+        let mut skipped = self.skipped_events.entry(pool).or_default();
+        *skipped += 1;
+        *skipped
+    }
+
+    pub fn prometheus_metric(&self) -> String {
+        let mut metric = String::from(
+            "# HELP unknown_pool_events_total Events skipped because their pool is unknown.\n\
+             # TYPE unknown_pool_events_total counter\n",
+        );
+        for entry in self.skipped_events.iter() {
+            metric.push_str(&format!(
+                "unknown_pool_events_total{{pool=\"{:#x}\"}} {}\n",
+                entry.key(),
+                entry.value()
+            ));
+        }
+        metric
+    }
+}
@@ -0,0 +1,29 @@
+use std::collections::HashSet;
+
+use starknet::core::types::Felt;
+
+use crate::types::position::VesuPosition;
+
+/// What to do with the liquidable positions of the protected users.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ProtectedUsersAction {
+    /// Liquidate them, as for any other position.
+    #[default]
+    Liquidate,
+    /// Don't liquidate them & alert.
+    Alert,
+}
+
+/// Restricts the bot to the positions of a set of users, e.g the vaults of the
+/// operator, instead of all the positions of the monitored pairs.
+#[derive(Debug, Clone)]
+pub struct UserScope {
+    pub users: HashSet<Felt>,
+    pub action: ProtectedUsersAction,
+}
+
+impl UserScope {
+    pub fn includes(&self, position: &VesuPosition) -> bool {
+        self.users.contains(&position.user_address)
+    }
+}
@@ -0,0 +1,207 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, LazyLock};
+
+use anyhow::Result;
+use dashmap::DashMap;
+use futures_util::future::join_all;
+use pragma_common::starknet::FallbackProvider;
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use starknet::core::types::{BlockId, BlockTag, Felt, FunctionCall};
+use starknet::macros::selector;
+use starknet::providers::Provider;
+
+use crate::services::indexer::IndexerService;
+use crate::types::currency::Currency;
+use crate::types::pool::PoolName;
+use crate::types::position::VesuPosition;
+use crate::utils::rpc::{RpcPath, guarded_starknet};
+
+// Latest utilization & borrow rate of the debt assets of the monitored pairs.
+pub static POOL_RATES: LazyLock<Arc<PoolRates>> = LazyLock::new(|| Arc::new(PoolRates::default()));
+
+/// Scale of the utilizations & rates of the Vesu pools.
+const RATE_SCALE: Decimal = dec!(1_000_000_000_000_000_000);
+const SECONDS_PER_YEAR: Decimal = dec!(31_536_000);
+
+/// Layout of the `AssetConfig` returned by the pools, in felts: {
+/// total_collateral_shares, total_nominal_debt, reserve, max_utilization,
+/// floor, scale, is_legacy, last_updated, last_rate_accumulator,
+/// last_full_utilization_rate, fee_rate, fee_shares }, the amounts being u256s
+/// of two felts (low, high).
+const ASSET_CONFIG_LEN: usize = 22;
+const MAX_UTILIZATION: usize = 6;
+const FLOOR: usize = 8;
+const LAST_UPDATED: usize = 13;
+const LAST_FULL_UTILIZATION_RATE: usize = 16;
+
+/// The utilization & borrow rate of an asset of a pool.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AssetRates {
+    /// Share of the supplied asset that is borrowed, in [0, 1].
+    pub utilization: Decimal,
+    /// Utilization above which the asset cannot be borrowed nor withdrawn.
+    pub max_utilization: Decimal,
+    /// Yearly borrow rate, not compounded.
+    pub borrow_apr: Decimal,
+    /// Minimum USD value of the debt of a position: a repayment leaving less,
+    /// but not zero, reverts.
+    pub debt_floor_usd: Decimal,
+}
+
+impl fmt::Display for AssetRates {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "utilization {:.1}% (max {:.1}%), borrow APR {:.2}%, debt floor ${:.2}",
+            self.utilization * Decimal::ONE_HUNDRED,
+            self.max_utilization * Decimal::ONE_HUNDRED,
+            self.borrow_apr * Decimal::ONE_HUNDRED,
+            self.debt_floor_usd
+        )
+    }
+}
+
+/// The rates of the assets of the pools, refreshed periodically by the
+/// monitoring. The utilization of the debt asset tells how fast a position
+/// accrues interest & how hard the liquidity is to find.
+#[derive(Debug, Default)]
+pub struct PoolRates {
+    rates: DashMap<(PoolName, Currency), AssetRates>,
+}
+
+impl PoolRates {
+    /// Reads the rates of the debt assets of the monitored pairs. The assets
+    /// failing to be read keep their previous rates.
+    pub async fn refresh(&self, provider: &FallbackProvider) {
+        let assets: HashSet<(PoolName, Currency)> = IndexerService::monitored_pairs()
+            .into_iter()
+            .map(|(pool, _, debt)| (pool, debt))
+            .collect();
+
+        let fetches = assets.into_iter().map(|(pool, asset)| async move {
+            (
+                (pool, asset),
+                fetch_asset_rates(provider, pool, asset).await,
+            )
+        });
+        for ((pool, asset), rates) in join_all(fetches).await {
+            match rates {
+                Ok(rates) => {
+                    self.rates.insert((pool, asset), rates);
+                }
+                Err(e) => tracing::debug!(
+                    "[🔭 Monitoring] Could not read the utilization of {asset} in {pool}: {e}"
+                ),
+            }
+        }
+    }
+
+    pub fn of(&self, pool: PoolName, asset: Currency) -> Option<AssetRates> {
+        self.rates.get(&(pool, asset)).map(|rates| *rates)
+    }
+
+    /// The rates of the debt asset of the position, if read.
+    pub fn of_debt(&self, position: &VesuPosition) -> Option<AssetRates> {
+        self.of(position.pool_name, position.debt.currency)
+    }
+
+    /// The debt floor of the debt asset of the position, zero if not read.
+    pub fn debt_floor_usd(&self, position: &VesuPosition) -> Decimal {
+        self.of_debt(position)
+            .map(|rates| rates.debt_floor_usd)
+            .unwrap_or_default()
+    }
+
+    pub fn prometheus_metric(&self) -> String {
+        let mut utilization = String::from(
+            "# HELP pool_utilization Share of the supplied asset that is borrowed.\n\
+             # TYPE pool_utilization gauge\n",
+        );
+        let mut borrow_apr = String::from(
+            "# HELP pool_borrow_apr Yearly borrow rate of the asset, not compounded.\n\
+             # TYPE pool_borrow_apr gauge\n",
+        );
+        for entry in self.rates.iter() {
+            let ((pool, asset), rates) = (entry.key(), entry.value());
+            utilization.push_str(&format!(
+                "pool_utilization{{pool=\"{pool}\",asset=\"{asset}\"}} {}\n",
+                rates.utilization
+            ));
+            borrow_apr.push_str(&format!(
+                "pool_borrow_apr{{pool=\"{pool}\",asset=\"{asset}\"}} {}\n",
+                rates.borrow_apr
+            ));
+        }
+        utilization + &borrow_apr
+    }
+}
+
+/// Reads the utilization & the borrow rate of the asset from its pool.
+pub async fn fetch_asset_rates(
+    provider: &FallbackProvider,
+    pool: PoolName,
+    asset: Currency,
+) -> Result<AssetRates> {
+    let call = |entry_point_selector: Felt, calldata: Vec<Felt>| {
+        let request = FunctionCall {
+            contract_address: pool.pool_address(),
+            entry_point_selector,
+            calldata,
+        };
+        async move {
+            guarded_starknet(provider, RpcPath::Background, |node| {
+                node.call(request.clone(), BlockId::Tag(BlockTag::Latest))
+            })
+            .await
+        }
+    };
+
+    // Any other length is another layout, whose felts cannot be read by index.
+    let asset_config = call(selector!("asset_config"), vec![asset.address()]).await?;
+    anyhow::ensure!(
+        asset_config.len() == ASSET_CONFIG_LEN,
+        "Unexpected asset config result for {asset} in {pool}: {} felts instead of {ASSET_CONFIG_LEN}",
+        asset_config.len()
+    );
+    // A u256.
+    let utilization = call(selector!("utilization"), vec![asset.address()]).await?;
+    let [utilization_low, utilization_high] = utilization[..] else {
+        anyhow::bail!(
+            "Unexpected utilization result for {asset} in {pool}: {} felts instead of 2",
+            utilization.len()
+        );
+    };
+
+    // The rate per second at the current utilization, which the pool accrues
+    // the debt with.
+    let interest_rate = call(
+        selector!("interest_rate"),
+        vec![
+            asset.address(),
+            utilization_low,
+            utilization_high,
+            asset_config[LAST_UPDATED],
+            asset_config[LAST_FULL_UTILIZATION_RATE],
+            asset_config[LAST_FULL_UTILIZATION_RATE + 1],
+        ],
+    )
+    .await?;
+    let rate_per_second = *interest_rate
+        .first()
+        .ok_or_else(|| anyhow::anyhow!("Empty interest rate result for {asset} in {pool}"))?;
+
+    Ok(AssetRates {
+        utilization: scaled(utilization_low)?,
+        max_utilization: scaled(asset_config[MAX_UTILIZATION])?,
+        borrow_apr: scaled(rate_per_second)? * SECONDS_PER_YEAR,
+        debt_floor_usd: scaled(asset_config[FLOOR])?,
+    })
+}
+
+/// The low part of a u256 scaled by the pool, as a decimal.
+fn scaled(low: Felt) -> Result<Decimal> {
+    Ok(Decimal::from_str(&low.to_string())? / RATE_SCALE)
+}
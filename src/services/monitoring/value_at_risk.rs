@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock};
+
+use dashmap::DashMap;
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use serde::Serialize;
+
+use crate::types::currency::Currency;
+use crate::types::decimal;
+use crate::types::pool::PoolName;
+use crate::types::position::VesuPosition;
+
+// Latest value at risk of every pool, readable from anywhere in the code.
+pub static VALUE_AT_RISK: LazyLock<Arc<ValueAtRisk>> =
+    LazyLock::new(|| Arc::new(ValueAtRisk::default()));
+
+/// Debt of the positions close to liquidation, in USD per pool & in units per
+/// debt asset. Tells how much liquidity must be ready and where, e.g the
+/// inventory to hold for the inventory liquidations.
+#[derive(Debug, Default)]
+pub struct ValueAtRisk {
+    per_pool: DashMap<PoolName, Decimal>,
+    per_debt_asset: DashMap<Currency, Decimal>,
+}
+
+/// The debt of an asset that may need repaying at the next price shock.
+#[derive(Debug, Clone, Serialize)]
+pub struct DebtAtRisk {
+    pub asset: String,
+    #[serde(with = "decimal::string")]
+    pub amount: Decimal,
+    #[serde(with = "decimal::string")]
+    pub value_usd: Decimal,
+}
+
+impl ValueAtRisk {
+    /// Recomputes the value at risk from the positions. A position is at risk when
+    /// its LTV is within `threshold_pct`% of its LLTV, i.e its health factor is
+    /// below 1 / (1 - threshold_pct%).
+    pub fn update<'a>(
+        &self,
+        positions: impl Iterator<Item = &'a VesuPosition>,
+        threshold_pct: Decimal,
+    ) {
+        let ratio_at_risk = Decimal::ONE - threshold_pct / dec!(100);
+
+        let mut per_pool: HashMap<PoolName, Decimal> = HashMap::new();
+        let mut per_debt_asset: HashMap<Currency, Decimal> = HashMap::new();
+        for position in positions {
+            if position.is_closed() || position.lltv.is_zero() {
+                continue;
+            }
+            if position.health_factor() * ratio_at_risk <= Decimal::ONE {
+                *per_pool.entry(position.pool_name).or_default() += position.debt_value_in_usd();
+                *per_debt_asset.entry(position.debt.currency).or_default() += position.debt.amount;
+            }
+        }
+
+        self.per_pool.retain(|pool, _| per_pool.contains_key(pool));
+        for (pool, value) in per_pool {
+            self.per_pool.insert(pool, value);
+        }
+        self.per_debt_asset
+            .retain(|asset, _| per_debt_asset.contains_key(asset));
+        for (asset, amount) in per_debt_asset {
+            self.per_debt_asset.insert(asset, amount);
+        }
+    }
+
+    pub fn of(&self, pool: PoolName) -> Decimal {
+        self.per_pool.get(&pool).map(|v| *v).unwrap_or_default()
+    }
+
+    pub fn total(&self) -> Decimal {
+        self.per_pool.iter().map(|entry| *entry.value()).sum()
+    }
+
+    /// USD value at risk per pool.
+    pub fn per_pool(&self) -> Vec<(PoolName, Decimal)> {
+        self.per_pool
+            .iter()
+            .map(|entry| (*entry.key(), *entry.value()))
+            .collect()
+    }
+
+    /// The debt at risk per asset, by decreasing USD value.
+    pub fn debt_at_risk(&self) -> Vec<DebtAtRisk> {
+        let mut debts: Vec<DebtAtRisk> = self
+            .per_debt_asset
+            .iter()
+            .map(|entry| DebtAtRisk {
+                asset: entry.key().to_string(),
+                amount: *entry.value(),
+                value_usd: *entry.value() * entry.key().price(),
+            })
+            .collect();
+        debts.sort_by(|a, b| b.value_usd.cmp(&a.value_usd));
+        debts
+    }
+
+    pub fn prometheus_metric(&self) -> String {
+        let mut metric = String::from(
+            "# HELP value_at_risk_usd USD debt of the positions close to liquidation.\n\
+             # TYPE value_at_risk_usd gauge\n",
+        );
+        for (pool, value) in self.per_pool() {
+            metric.push_str(&format!("value_at_risk_usd{{pool=\"{pool}\"}} {value}\n"));
+        }
+        metric.push_str(
+            "# HELP debt_at_risk Debt of the positions close to liquidation, in units of the debt asset.\n\
+             # TYPE debt_at_risk gauge\n",
+        );
+        for debt in self.debt_at_risk() {
+            metric.push_str(&format!(
+                "debt_at_risk{{asset=\"{}\"}} {}\n",
+                debt.asset, debt.amount
+            ));
+        }
+        metric
+    }
+}
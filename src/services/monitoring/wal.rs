@@ -0,0 +1,307 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use starknet::core::types::Felt;
+
+use crate::services::indexer::{EventId, IndexedEvent};
+use crate::services::monitoring::quarantine::QuarantinedPosition;
+use crate::services::replay::RecordedEvent;
+use crate::types::pool::PoolName;
+use crate::types::position::VesuPosition;
+
+const SNAPSHOT_FILE: &str = "snapshot.json";
+const WAL_FILE: &str = "wal.jsonl";
+
+/// Position in the events stream: the last block applied & how many of its
+/// events were applied.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EventCursor {
+    pub block_number: u64,
+    pub events_in_block: usize,
+}
+
+impl EventCursor {
+    pub fn advance(&mut self, block_number: u64) {
+        if block_number == self.block_number {
+            self.events_in_block += 1;
+        } else {
+            self.block_number = block_number;
+            self.events_in_block = 1;
+        }
+    }
+
+    /// Returns true if the event is before this cursor in the stream.
+    pub fn contains(&self, event_id: EventId) -> bool {
+        event_id.block_number < self.block_number
+            || (event_id.block_number == self.block_number
+                && event_id.event_index < self.events_in_block as u64)
+    }
+}
+
+/// A position evicted from memory: re-hydrated from the chain on its next event,
+/// or dropped once a full scan finds it closed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EvictedPosition {
+    pub pool: PoolName,
+    pub position_key: String,
+    /// Its (collateral, debt, user) addresses, to read it from the chain. None
+    /// for the ones persisted before they were kept: only re-hydrated then.
+    #[serde(default)]
+    pub addresses: Option<(Felt, Felt, Felt)>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub cursor: EventCursor,
+    pub positions: Vec<VesuPosition>,
+    /// Positions evicted from memory, to re-hydrate on their next event.
+    #[serde(default)]
+    pub evicted: Vec<EvictedPosition>,
+    /// Positions of unlisted assets, read from the chain once they get listed.
+    #[serde(default)]
+    pub quarantined: Vec<QuarantinedPosition>,
+}
+
+/// The state of a state directory in a single file, to move it between hosts.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StateExport {
+    #[serde(flatten)]
+    pub snapshot: Snapshot,
+    /// Events of the WAL not checkpointed yet, replayed on top of the snapshot.
+    #[serde(default)]
+    pub pending_events: Vec<RecordedEvent>,
+}
+
+/// State recovered from the disk on startup.
+#[derive(Debug, Default)]
+pub struct RecoveredState {
+    pub snapshot: Snapshot,
+    /// Events appended to the WAL after the snapshot, to replay on top of it.
+    pub events: Vec<IndexedEvent>,
+}
+
+impl RecoveredState {
+    /// The cursor once all the recovered events are applied.
+    pub fn cursor(&self) -> Option<EventCursor> {
+        let mut cursor = self.snapshot.cursor;
+        for (metadata, _) in &self.events {
+            cursor.advance(metadata.block_number);
+        }
+        (cursor != EventCursor::default()).then_some(cursor)
+    }
+}
+
+/// Crash-safe write-ahead log of the events applied to the positions.
+/// Every event is appended before being applied, and the positions are
+/// periodically checkpointed into a snapshot that truncates the log.
+pub struct WriteAheadLog {
+    dir: PathBuf,
+    writer: BufWriter<File>,
+}
+
+impl WriteAheadLog {
+    /// Opens the WAL of the directory & recovers the state it contains.
+    pub fn open(dir: impl AsRef<Path>) -> Result<(Self, RecoveredState)> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)
+            .with_context(|| format!("Could not create state directory {}", dir.display()))?;
+
+        let recovered_state = Self::read(&dir)?;
+
+        let wal_path = dir.join(WAL_FILE);
+        if wal_path.exists() {
+            Self::truncate_torn_tail(&wal_path)?;
+        }
+
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&wal_path)?;
+
+        Ok((
+            Self {
+                dir,
+                writer: BufWriter::new(file),
+            },
+            recovered_state,
+        ))
+    }
+
+    /// Reads the state of the directory without opening its WAL.
+    pub fn read(dir: &Path) -> Result<RecoveredState> {
+        let snapshot_path = dir.join(SNAPSHOT_FILE);
+        let snapshot = if snapshot_path.exists() {
+            serde_json::from_str(&fs::read_to_string(&snapshot_path)?)
+                .context("Invalid WAL snapshot")?
+        } else {
+            Snapshot::default()
+        };
+
+        let wal_path = dir.join(WAL_FILE);
+        let events = if wal_path.exists() {
+            Self::read_events(&wal_path)?.0
+        } else {
+            vec![]
+        };
+
+        Ok(RecoveredState { snapshot, events })
+    }
+
+    /// Reads the events of the log, with the length of its valid part. A crash
+    /// during an append leaves a torn last line: it is skipped, while an
+    /// invalid line in the middle of the log is a corruption & an error.
+    fn read_events(wal_path: &Path) -> Result<(Vec<IndexedEvent>, usize)> {
+        let bytes = fs::read(wal_path)
+            .with_context(|| format!("Could not read the WAL {}", wal_path.display()))?;
+
+        let mut events = Vec::new();
+        let mut valid_len = 0;
+        let mut lines = bytes
+            .split_inclusive(|byte| *byte == b'\n')
+            .enumerate()
+            .peekable();
+        while let Some((i, line)) = lines.next() {
+            if line.trim_ascii().is_empty() {
+                valid_len += line.len();
+                continue;
+            }
+            match serde_json::from_slice::<RecordedEvent>(line) {
+                Ok(event) => {
+                    events.push(event.into());
+                    valid_len += line.len();
+                }
+                Err(e) if lines.peek().is_none() => {
+                    tracing::warn!(
+                        "[🔭 Monitoring] 💾 Skipping the torn last line {} of {}: {e}",
+                        i + 1,
+                        wal_path.display()
+                    );
+                }
+                Err(e) => {
+                    return Err(e).with_context(|| {
+                        format!(
+                            "Invalid event at line {} of the WAL {}",
+                            i + 1,
+                            wal_path.display()
+                        )
+                    });
+                }
+            }
+        }
+
+        Ok((events, valid_len))
+    }
+
+    /// Truncates the torn last line of the log, if any, so that the next
+    /// appends start on a line of their own.
+    fn truncate_torn_tail(wal_path: &Path) -> Result<()> {
+        let (_, valid_len) = Self::read_events(wal_path)?;
+        let file = OpenOptions::new().write(true).open(wal_path)?;
+        let len = file.metadata()?.len();
+        if (valid_len as u64) < len {
+            tracing::warn!(
+                "[🔭 Monitoring] 💾 Truncating {} torn bytes at the end of {}",
+                len - valid_len as u64,
+                wal_path.display()
+            );
+            file.set_len(valid_len as u64)?;
+            file.sync_all()?;
+        }
+
+        // A complete last event without its newline would be merged with the next one.
+        if valid_len > 0 && !fs::read(wal_path)?.ends_with(b"\n") {
+            let mut file = OpenOptions::new().append(true).open(wal_path)?;
+            file.write_all(b"\n")?;
+            file.sync_all()?;
+        }
+        Ok(())
+    }
+
+    /// Returns true if the directory holds a state.
+    pub fn has_state(dir: &Path) -> bool {
+        dir.join(SNAPSHOT_FILE).exists()
+            || fs::metadata(dir.join(WAL_FILE)).is_ok_and(|wal| wal.len() > 0)
+    }
+
+    /// Writes an exported state to the directory, replacing its state.
+    pub fn import(dir: &Path, state: &StateExport) -> Result<()> {
+        fs::create_dir_all(dir)
+            .with_context(|| format!("Could not create state directory {}", dir.display()))?;
+
+        let tmp_path = dir.join(format!("{SNAPSHOT_FILE}.tmp"));
+        fs::write(&tmp_path, serde_json::to_vec(&state.snapshot)?)?;
+        File::open(&tmp_path)?.sync_all()?;
+        fs::rename(&tmp_path, dir.join(SNAPSHOT_FILE))?;
+
+        let mut writer = BufWriter::new(File::create(dir.join(WAL_FILE))?);
+        for event in &state.pending_events {
+            serde_json::to_writer(&mut writer, event)?;
+            writer.write_all(b"\n")?;
+        }
+        writer.flush()?;
+        writer.get_ref().sync_all()?;
+        Ok(())
+    }
+
+    /// Appends an event to the log. Must be called before applying it.
+    pub fn append(&mut self, (metadata, delta): &IndexedEvent) -> Result<()> {
+        let recorded = RecordedEvent {
+            metadata: metadata.clone(),
+            delta: delta.clone(),
+        };
+        serde_json::to_writer(&mut self.writer, &recorded)?;
+        self.writer.write_all(b"\n")?;
+        self.writer.flush()?;
+        self.writer.get_ref().sync_data()?;
+        Ok(())
+    }
+
+    /// Writes a snapshot of the positions & truncates the log to the `pending`
+    /// events, the ones after the snapshot.
+    pub fn checkpoint<'a>(
+        &mut self,
+        cursor: EventCursor,
+        positions: impl Iterator<Item = &'a VesuPosition>,
+        evicted: impl Iterator<Item = &'a EvictedPosition>,
+        quarantined: Vec<QuarantinedPosition>,
+        pending: impl Iterator<Item = &'a IndexedEvent>,
+    ) -> Result<()> {
+        let snapshot = Snapshot {
+            cursor,
+            positions: positions.cloned().collect(),
+            evicted: evicted.cloned().collect(),
+            quarantined,
+        };
+
+        // Write & rename so that a crash never leaves a partial snapshot.
+        let tmp_path = self.dir.join(format!("{SNAPSHOT_FILE}.tmp"));
+        fs::write(&tmp_path, serde_json::to_vec(&snapshot)?)?;
+        File::open(&tmp_path)?.sync_all()?;
+        fs::rename(&tmp_path, self.dir.join(SNAPSHOT_FILE))?;
+
+        // Same, the pending events must still be in the log once it is replaced.
+        let tmp_path = self.dir.join(format!("{WAL_FILE}.tmp"));
+        let mut writer = BufWriter::new(File::create(&tmp_path)?);
+        for (metadata, delta) in pending {
+            let recorded = RecordedEvent {
+                metadata: metadata.clone(),
+                delta: delta.clone(),
+            };
+            serde_json::to_writer(&mut writer, &recorded)?;
+            writer.write_all(b"\n")?;
+        }
+        writer.flush()?;
+        writer.get_ref().sync_all()?;
+        fs::rename(&tmp_path, self.dir.join(WAL_FILE))?;
+
+        let file = OpenOptions::new()
+            .append(true)
+            .open(self.dir.join(WAL_FILE))?;
+        self.writer = BufWriter::new(file);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,274 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::{Arc, LazyLock, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use starknet::core::types::Felt;
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+use crate::services::indexer::lag::INDEXER_LAG;
+use crate::services::monitoring::health_history::HealthHistory;
+use crate::services::monitoring::receipt::RealizedLiquidation;
+use crate::services::oracle::vesu_prices::VESU_PRICES;
+use crate::services::oracle::volatility::pair_hourly_volatility;
+use crate::types::decimal;
+use crate::types::position::VesuPosition;
+
+pub static WATCHLIST: LazyLock<Arc<Watchlist>> = LazyLock::new(|| Arc::new(Watchlist::default()));
+
+/// A monitored position, as last evaluated by the monitoring.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchedPosition {
+    pub position_id: String,
+    pub pool: String,
+    pub user: String,
+    pub collateral: String,
+    pub debt: String,
+    #[serde(with = "decimal::string")]
+    pub collateral_value_usd: Decimal,
+    #[serde(with = "decimal::string")]
+    pub debt_value_usd: Decimal,
+    #[serde(with = "decimal::string")]
+    pub health_factor: Decimal,
+    #[serde(with = "decimal::string")]
+    pub ltv: Decimal,
+    #[serde(with = "decimal::string")]
+    pub lltv: Decimal,
+    /// Hourly standard deviations of the price ratio before the liquidation.
+    #[serde(default, with = "decimal::option_string")]
+    pub sigmas_to_liquidation: Option<Decimal>,
+    #[serde(default, with = "decimal::option_string")]
+    pub hours_to_liquidation: Option<Decimal>,
+    /// The latest LTVs of the position, the oldest first.
+    #[serde(default)]
+    pub ltv_history: Vec<LtvPoint>,
+}
+
+/// A LTV of a position at a given time.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LtvPoint {
+    /// Unix timestamp, in seconds.
+    pub at: u64,
+    #[serde(with = "decimal::string")]
+    pub ltv: Decimal,
+}
+
+impl LtvPoint {
+    fn history(history: &HealthHistory) -> Vec<Self> {
+        let now = unix_now();
+        history
+            .samples()
+            .map(|sample| Self {
+                at: now.saturating_sub(sample.at.elapsed().as_secs()),
+                ltv: sample.ltv,
+            })
+            .collect()
+    }
+}
+
+impl From<&VesuPosition> for WatchedPosition {
+    fn from(position: &VesuPosition) -> Self {
+        Self {
+            position_id: position.position_id(),
+            pool: position.pool_name.to_string(),
+            user: format!("{:#x}", position.user_address),
+            collateral: position.collateral.currency.to_string(),
+            debt: position.debt.currency.to_string(),
+            collateral_value_usd: position.collateral_value_in_usd(),
+            debt_value_usd: position.debt_value_in_usd(),
+            health_factor: position.health_factor(),
+            ltv: position.ltv(),
+            lltv: position.lltv,
+            sigmas_to_liquidation: None,
+            hours_to_liquidation: None,
+            ltv_history: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, strum::Display)]
+pub enum LiquidationStatus {
+    Pending,
+    Confirmed,
+    Reverted,
+    Expired,
+}
+
+/// A liquidation sent by the executor.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecentLiquidation {
+    /// Id of the liquidation intent, shared by all the attempts at liquidating
+    /// the position.
+    #[serde(default)]
+    pub intent_id: String,
+    pub position_id: String,
+    pub tx_hash: String,
+    /// Unix timestamp, in seconds.
+    pub sent_at: u64,
+    pub status: LiquidationStatus,
+    /// Set once confirmed, from the receipt of the transaction.
+    #[serde(default)]
+    pub realized: Option<RealizedLiquidation>,
+}
+
+/// Everything the operators need to follow the bot live.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchSnapshot {
+    pub positions: Vec<WatchedPosition>,
+    /// USD prices by ticker.
+    #[serde(with = "decimal::map_string")]
+    pub prices: BTreeMap<String, Decimal>,
+    pub indexer_lag_blocks: u64,
+    pub indexer_lag_seconds: u64,
+    pub liquidations: Vec<RecentLiquidation>,
+}
+
+impl WatchSnapshot {
+    /// Returns the current snapshot, with the `limit` riskiest positions.
+    pub fn current(limit: usize) -> Self {
+        Self {
+            positions: WATCHLIST.positions(limit),
+            prices: VESU_PRICES
+                .0
+                .iter()
+                .map(|entry| (entry.key().ticker.clone(), *entry.value()))
+                .collect(),
+            indexer_lag_blocks: INDEXER_LAG.blocks(),
+            indexer_lag_seconds: INDEXER_LAG.seconds(),
+            liquidations: WATCHLIST.liquidations(),
+        }
+    }
+}
+
+/// What the bot currently sees: the positions by health factor & the recent
+/// liquidations - shared with the operators through the API.
+#[derive(Debug)]
+pub struct Watchlist {
+    positions: RwLock<Vec<WatchedPosition>>,
+    liquidations: RwLock<VecDeque<RecentLiquidation>>,
+    /// Every change of a recent liquidation, for the API subscribers.
+    liquidation_updates: broadcast::Sender<RecentLiquidation>,
+}
+
+impl Default for Watchlist {
+    fn default() -> Self {
+        Self {
+            positions: RwLock::default(),
+            liquidations: RwLock::default(),
+            liquidation_updates: broadcast::channel(Self::MAX_RECENT_LIQUIDATIONS).0,
+        }
+    }
+}
+
+impl Watchlist {
+    /// Number of liquidations kept.
+    const MAX_RECENT_LIQUIDATIONS: usize = 50;
+
+    /// Replaces the watched positions, sorted by how close they are to their
+    /// liquidation: in sigmas when the volatility is known, else by health factor.
+    pub fn update<'a>(
+        &self,
+        positions: impl Iterator<Item = &'a VesuPosition>,
+        health_history: &HashMap<String, HealthHistory>,
+    ) {
+        let mut watched: Vec<WatchedPosition> = positions
+            .filter(|p| !p.is_closed())
+            .map(|p| {
+                let volatility = pair_hourly_volatility(p.collateral.currency, p.debt.currency);
+                WatchedPosition {
+                    sigmas_to_liquidation: volatility.and_then(|v| p.sigmas_to_liquidation(v)),
+                    hours_to_liquidation: volatility.and_then(|v| p.hours_to_liquidation(v)),
+                    ltv_history: health_history
+                        .get(&p.position_id())
+                        .map(LtvPoint::history)
+                        .unwrap_or_default(),
+                    ..WatchedPosition::from(p)
+                }
+            })
+            .collect();
+        watched.sort_by(
+            |a, b| match (a.sigmas_to_liquidation, b.sigmas_to_liquidation) {
+                (Some(a), Some(b)) => a.cmp(&b),
+                (Some(_), None) => std::cmp::Ordering::Less,
+                (None, Some(_)) => std::cmp::Ordering::Greater,
+                (None, None) => a.health_factor.cmp(&b.health_factor),
+            },
+        );
+
+        *self.positions.write().expect("poisoned watchlist") = watched;
+    }
+
+    /// Returns the `limit` riskiest positions.
+    pub fn positions(&self, limit: usize) -> Vec<WatchedPosition> {
+        let positions = self.positions.read().expect("poisoned watchlist");
+        positions.iter().take(limit).cloned().collect()
+    }
+
+    pub fn record_liquidation(&self, intent_id: Uuid, position_id: String, tx_hash: Felt) {
+        let mut liquidations = self.liquidations.write().expect("poisoned watchlist");
+        if liquidations.len() == Self::MAX_RECENT_LIQUIDATIONS {
+            liquidations.pop_back();
+        }
+        let liquidation = RecentLiquidation {
+            intent_id: intent_id.to_string(),
+            position_id,
+            tx_hash: format!("{tx_hash:#064x}"),
+            sent_at: unix_now(),
+            status: LiquidationStatus::Pending,
+            realized: None,
+        };
+        self.publish(&liquidation);
+        liquidations.push_front(liquidation);
+    }
+
+    pub fn resolve_liquidation(&self, tx_hash: Felt, status: LiquidationStatus) {
+        let tx_hash = format!("{tx_hash:#064x}");
+        let mut liquidations = self.liquidations.write().expect("poisoned watchlist");
+        for liquidation in liquidations.iter_mut().filter(|l| l.tx_hash == tx_hash) {
+            liquidation.status = status;
+            self.publish(liquidation);
+        }
+    }
+
+    pub fn realize_liquidation(
+        &self,
+        tx_hash: Felt,
+        position_id: &str,
+        realized: &RealizedLiquidation,
+    ) {
+        let tx_hash = format!("{tx_hash:#064x}");
+        let mut liquidations = self.liquidations.write().expect("poisoned watchlist");
+        for liquidation in liquidations
+            .iter_mut()
+            .filter(|l| l.tx_hash == tx_hash && l.position_id == position_id)
+        {
+            liquidation.realized = Some(*realized);
+            self.publish(liquidation);
+        }
+    }
+
+    /// Returns the recent liquidations, the most recent first.
+    pub fn liquidations(&self) -> Vec<RecentLiquidation> {
+        let liquidations = self.liquidations.read().expect("poisoned watchlist");
+        liquidations.iter().cloned().collect()
+    }
+
+    /// Returns a receiver of every new or updated recent liquidation.
+    pub fn subscribe(&self) -> broadcast::Receiver<RecentLiquidation> {
+        self.liquidation_updates.subscribe()
+    }
+
+    fn publish(&self, liquidation: &RecentLiquidation) {
+        // No subscriber is not an error.
+        let _ = self.liquidation_updates.send(liquidation.clone());
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
@@ -0,0 +1,213 @@
+use std::collections::HashMap;
+use std::sync::{LazyLock, OnceLock};
+use std::time::{Duration, Instant};
+
+use dashmap::DashMap;
+use serde_json::{Value, json};
+use url::Url;
+
+static NOTIFIER_CONFIG: OnceLock<NotifierConfig> = OnceLock::new();
+
+pub static NOTIFIER: LazyLock<Notifier> = LazyLock::new(|| Notifier::new(NotifierConfig::get()));
+
+/// The conditions the operators get notified about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, strum::Display, strum::EnumString)]
+#[strum(serialize_all = "kebab-case")]
+pub enum AlertKind {
+    /// A position is liquidable or almost.
+    PositionAtRisk,
+    /// A position of a protected user is liquidable.
+    ProtectedUser,
+    /// A stable asset is off its peg.
+    Depeg,
+    /// The indexer lags behind the chain head.
+    IndexerLag,
+    /// The local LTV of a position disagrees with the collateralization check
+    /// of its pool.
+    LtvDrift,
+    /// A service made no progress for a while & got restarted.
+    ServiceStalled,
+    /// A monitored pool is paused, reverting its liquidations.
+    PoolPaused,
+}
+
+/// When to notify about the conditions of every kind.
+#[derive(Debug, Clone, Default)]
+pub struct NotifierConfig {
+    /// Receives the notifications as a JSON `{"text", "content"}` POST, the
+    /// format of the Slack & Discord webhooks. They are only logged if not set.
+    pub webhook_url: Option<Url>,
+    /// kind => how long its condition must last before the first notification.
+    pub delays: HashMap<AlertKind, Duration>,
+    /// kind => interval of the reminders while its condition lasts, zero for no
+    /// reminder. `DEFAULT_REMINDER` if not set.
+    pub reminders: HashMap<AlertKind, Duration>,
+}
+
+impl NotifierConfig {
+    pub const DEFAULT_REMINDER: Duration = Duration::from_secs(30 * 60);
+
+    /// Sets the notifier config of the whole process. Only the first call has an
+    /// effect.
+    pub fn install(self) {
+        let _ = NOTIFIER_CONFIG.set(self);
+    }
+
+    pub fn get() -> Self {
+        NOTIFIER_CONFIG.get().cloned().unwrap_or_default()
+    }
+
+    fn delay(&self, kind: AlertKind) -> Duration {
+        self.delays.get(&kind).copied().unwrap_or_default()
+    }
+
+    fn reminder(&self, kind: AlertKind) -> Option<Duration> {
+        let reminder = self
+            .reminders
+            .get(&kind)
+            .copied()
+            .unwrap_or(Self::DEFAULT_REMINDER);
+        (!reminder.is_zero()).then_some(reminder)
+    }
+}
+
+/// A condition currently raised.
+#[derive(Debug, Clone, Copy)]
+struct ActiveAlert {
+    since: Instant,
+    /// None until the condition lasted long enough to be notified.
+    notified_at: Option<Instant>,
+}
+
+/// Notifies the operators once when a condition is raised, reminds them while it
+/// lasts & tells them when it is resolved - instead of at every check.
+#[derive(Debug)]
+pub struct Notifier {
+    config: NotifierConfig,
+    /// (kind, key of the condition, e.g a position id) => alert
+    active: DashMap<(AlertKind, String), ActiveAlert>,
+    http_client: reqwest::Client,
+}
+
+impl Notifier {
+    pub fn new(config: NotifierConfig) -> Self {
+        Self {
+            config,
+            active: DashMap::new(),
+            http_client: reqwest::Client::new(),
+        }
+    }
+
+    /// Raises the condition `key` of the kind. Called at every check it holds;
+    /// only notifies after its delay, then at every reminder.
+    pub fn alert(&self, kind: AlertKind, key: &str, message: impl FnOnce() -> String) {
+        let now = Instant::now();
+        let mut alert = self
+            .active
+            .entry((kind, key.to_string()))
+            .or_insert(ActiveAlert {
+                since: now,
+                notified_at: None,
+            });
+
+        let is_reminder = alert.notified_at.is_some();
+        let should_notify = match alert.notified_at {
+            None => now.duration_since(alert.since) >= self.config.delay(kind),
+            Some(notified_at) => self
+                .config
+                .reminder(kind)
+                .is_some_and(|reminder| now.duration_since(notified_at) >= reminder),
+        };
+        if !should_notify {
+            return;
+        }
+        alert.notified_at = Some(now);
+        let since = alert.since;
+        drop(alert);
+
+        let text = if is_reminder {
+            format!(
+                "⏰ Still ongoing after {}m: {}",
+                since.elapsed().as_secs() / 60,
+                message()
+            )
+        } else {
+            format!("🚨 {}", message())
+        };
+        tracing::warn!("[📣 Notifier] {text}");
+        self.send(text, None);
+    }
+
+    /// Resolves the condition `key` of the kind, notifying it if it got notified
+    /// when raised.
+    pub fn resolve(&self, kind: AlertKind, key: &str, message: impl FnOnce() -> String) {
+        let Some((_, alert)) = self.active.remove(&(kind, key.to_string())) else {
+            return;
+        };
+        if alert.notified_at.is_none() {
+            return;
+        }
+
+        let text = format!(
+            "✅ Resolved after {}m: {}",
+            alert.since.elapsed().as_secs() / 60,
+            message()
+        );
+        tracing::info!("[📣 Notifier] {text}");
+        self.send(text, None);
+    }
+
+    /// Resolves all the conditions of `key`, whatever their kind, e.g once the
+    /// position they are about is closed or evicted & never checked again.
+    pub fn resolve_all(&self, key: &str, message: impl Fn() -> String) {
+        let kinds: Vec<AlertKind> = self
+            .active
+            .iter()
+            .filter(|entry| entry.key().1 == key)
+            .map(|entry| entry.key().0)
+            .collect();
+        for kind in kinds {
+            self.resolve(kind, key, &message);
+        }
+    }
+
+    /// Notifies an event once, e.g a pair getting onboarded - unlike the
+    /// conditions, it is neither reminded nor resolved.
+    pub fn notify(&self, message: String) {
+        let text = format!("📢 {message}");
+        tracing::info!("[📣 Notifier] {text}");
+        self.send(text, None);
+    }
+
+    /// Same as `notify`, the webhook also receiving the details as a `details`
+    /// JSON field, e.g for the systems auditing the liquidations.
+    pub fn notify_with_details(&self, message: String, details: Value) {
+        let text = format!("📢 {message}");
+        tracing::info!("[📣 Notifier] {text}");
+        self.send(text, Some(details));
+    }
+
+    /// Posts the notification to the webhook in the background.
+    fn send(&self, text: String, details: Option<Value>) {
+        let Some(webhook_url) = self.config.webhook_url.clone() else {
+            return;
+        };
+        let http_client = self.http_client.clone();
+        tokio::spawn(async move {
+            let mut body = json!({ "text": text, "content": text });
+            if let Some(details) = details {
+                body["details"] = details;
+            }
+            let sent = http_client
+                .post(webhook_url)
+                .json(&body)
+                .timeout(Duration::from_secs(10))
+                .send()
+                .await
+                .and_then(|response| response.error_for_status());
+            if let Err(e) = sent {
+                tracing::warn!("[📣 Notifier] Could not post the notification: {e}");
+            }
+        });
+    }
+}
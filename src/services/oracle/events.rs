@@ -0,0 +1,107 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Result;
+use pragma_common::starknet::fallback_provider::FallbackProvider;
+use starknet::core::types::{BlockId, EventFilter, Felt};
+use starknet::core::utils::cairo_short_string_to_felt;
+use starknet::macros::selector;
+use starknet::providers::Provider;
+
+use crate::config::addresses::AddressBook;
+use crate::config::onchain_assets::OnchainAssetConfig;
+use crate::services::oracle::vesu_prices::VESU_PRICES;
+use crate::utils::rpc::{RpcPath, guarded_starknet};
+
+/// Index of the `pair_id` in the data of a `SubmittedSpotEntry` event:
+/// (timestamp, source, publisher, price, pair_id, volume).
+const PAIR_ID_INDEX: usize = 4;
+
+const EVENTS_CHUNK_SIZE: u64 = 1_000;
+
+/// Watches the price update events of the oracle to know which assets actually
+/// changed since the last check.
+pub struct OracleEventsWatcher {
+    last_block: Option<u64>,
+    /// `{TICKER}/USD` pair ids => asset
+    assets_by_pair_id: HashMap<Felt, OnchainAssetConfig>,
+}
+
+impl OracleEventsWatcher {
+    pub fn new() -> Self {
+        let assets_by_pair_id = VESU_PRICES
+            .0
+            .iter()
+            .filter_map(|entry| {
+                let asset = entry.key();
+                let pair_id = format!("{}/USD", asset.ticker.to_uppercase());
+                cairo_short_string_to_felt(&pair_id)
+                    .ok()
+                    .map(|pair_id| (pair_id, asset.clone()))
+            })
+            .collect();
+
+        Self {
+            last_block: None,
+            assets_by_pair_id,
+        }
+    }
+
+    /// Returns the assets with a price update since the last call.
+    /// The first call only sets the starting block and returns nothing.
+    pub async fn changed_assets(
+        &mut self,
+        provider: &FallbackProvider,
+    ) -> Result<Vec<OnchainAssetConfig>> {
+        let head_block =
+            guarded_starknet(provider, RpcPath::Background, |node| node.block_number()).await?;
+
+        let Some(last_block) = self.last_block.replace(head_block) else {
+            return Ok(vec![]);
+        };
+        if head_block <= last_block {
+            return Ok(vec![]);
+        }
+
+        let filter = EventFilter {
+            from_block: Some(BlockId::Number(last_block + 1)),
+            to_block: Some(BlockId::Number(head_block)),
+            address: Some(AddressBook::get().pragma_oracle),
+            keys: Some(vec![vec![selector!("SubmittedSpotEntry")]]),
+        };
+
+        let mut changed_pairs = HashSet::new();
+        let mut continuation_token = None;
+        loop {
+            let page = guarded_starknet(provider, RpcPath::Background, |node| {
+                node.get_events(
+                    filter.clone(),
+                    continuation_token.clone(),
+                    EVENTS_CHUNK_SIZE,
+                )
+            })
+            .await?;
+
+            for event in page.events {
+                if let Some(pair_id) = event.data.get(PAIR_ID_INDEX) {
+                    changed_pairs.insert(*pair_id);
+                }
+            }
+
+            continuation_token = page.continuation_token;
+            if continuation_token.is_none() {
+                break;
+            }
+        }
+
+        Ok(changed_pairs
+            .iter()
+            .filter_map(|pair_id| self.assets_by_pair_id.get(pair_id).cloned())
+            .collect())
+    }
+}
+
+impl Default for OracleEventsWatcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
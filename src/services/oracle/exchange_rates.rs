@@ -0,0 +1,77 @@
+use std::sync::{Arc, LazyLock};
+
+use dashmap::DashMap;
+use futures_util::future::join_all;
+use pragma_common::starknet::fallback_provider::FallbackProvider;
+use rust_decimal::Decimal;
+
+use crate::config::onchain_assets::ONCHAIN_ASSETS;
+use crate::services::oracle::pricing::fetch_exchange_rate;
+
+pub static EXCHANGE_RATES: LazyLock<Arc<ExchangeRates>> =
+    LazyLock::new(|| Arc::new(ExchangeRates::default()));
+
+#[derive(Debug, Clone, Copy)]
+pub struct ExchangeRate {
+    /// Latest rate read on-chain.
+    pub latest: Decimal,
+    /// Rate when the oracle price of the wrapper was last updated.
+    pub at_price_update: Option<Decimal>,
+}
+
+/// Exchange rates of the yield bearing assets against their underlying, by
+/// wrapper ticker.
+#[derive(Default, Debug, Clone)]
+pub struct ExchangeRates(pub DashMap<String, ExchangeRate>);
+
+impl ExchangeRates {
+    pub fn update(&self, ticker: &str, rate: Decimal) {
+        self.0
+            .entry(ticker.to_string())
+            .and_modify(|r| r.latest = rate)
+            .or_insert(ExchangeRate {
+                latest: rate,
+                at_price_update: None,
+            });
+    }
+
+    /// Marks the oracle price of the wrapper as updated with the latest rate.
+    pub fn mark_price_update(&self, ticker: &str) {
+        if let Some(mut rate) = self.0.get_mut(ticker) {
+            rate.at_price_update = Some(rate.latest);
+        }
+    }
+
+    /// How much the wrapper appreciated since its last oracle price update.
+    /// One for the assets that are not wrappers.
+    pub fn drift(&self, ticker: &str) -> Decimal {
+        self.0
+            .get(ticker)
+            .and_then(|r| {
+                r.at_price_update
+                    .filter(|at_update| !at_update.is_zero())
+                    .map(|at_update| r.latest / at_update)
+            })
+            .unwrap_or(Decimal::ONE)
+    }
+}
+
+/// Reads the exchange rate of every configured wrapper.
+pub async fn refresh_exchange_rates(provider: &FallbackProvider) {
+    let fetch_tasks = ONCHAIN_ASSETS
+        .wrappers()
+        .map(|(wrapper, underlying)| async move {
+            let rate = fetch_exchange_rate(provider, wrapper, underlying).await;
+            (wrapper, rate)
+        });
+
+    for (wrapper, rate) in join_all(fetch_tasks).await {
+        match rate {
+            Ok(rate) => EXCHANGE_RATES.update(&wrapper.ticker, rate),
+            Err(e) => tracing::debug!(
+                "[🔮 Oracle] Could not read the exchange rate of {}: {e}",
+                wrapper.ticker
+            ),
+        }
+    }
+}
@@ -0,0 +1,57 @@
+use std::sync::{Arc, LazyLock};
+
+use dashmap::DashMap;
+
+pub static ORACLE_FAILURES: LazyLock<Arc<OracleFailures>> =
+    LazyLock::new(|| Arc::new(OracleFailures::default()));
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FailureCounter {
+    /// Failures since the last successful fetch.
+    pub consecutive: u64,
+    pub total: u64,
+}
+
+/// Failure counters of the price fetches, per asset ticker.
+#[derive(Debug, Default)]
+pub struct OracleFailures(pub DashMap<String, FailureCounter>);
+
+impl OracleFailures {
+    /// Records a failure and returns the updated counter.
+    pub fn record_failure(&self, ticker: &str) -> FailureCounter {
+        let mut counter = self.0.entry(ticker.to_string()).or_default();
+        counter.consecutive += 1;
+        counter.total += 1;
+        *counter
+    }
+
+    pub fn record_success(&self, ticker: &str) {
+        if let Some(mut counter) = self.0.get_mut(ticker) {
+            counter.consecutive = 0;
+        }
+    }
+
+    pub fn prometheus_metric(&self) -> String {
+        let mut consecutive = String::from(
+            "# HELP oracle_consecutive_failures Failed price fetches of the asset since its last successful one.\n\
+             # TYPE oracle_consecutive_failures gauge\n",
+        );
+        let mut total = String::from(
+            "# HELP oracle_failures_total Failed price fetches of the asset.\n\
+             # TYPE oracle_failures_total counter\n",
+        );
+        for entry in self.0.iter() {
+            consecutive.push_str(&format!(
+                "oracle_consecutive_failures{{asset=\"{}\"}} {}\n",
+                entry.key(),
+                entry.consecutive
+            ));
+            total.push_str(&format!(
+                "oracle_failures_total{{asset=\"{}\"}} {}\n",
+                entry.key(),
+                entry.total
+            ));
+        }
+        consecutive + &total
+    }
+}
@@ -1,94 +1,319 @@
+pub mod events;
+pub mod exchange_rates;
+pub mod failures;
+pub mod price_history;
+pub mod pricing;
+pub mod sources;
 pub mod task;
 pub mod vesu_prices;
+pub mod volatility;
 
-use std::str::FromStr;
-use std::time::Duration;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use anyhow::Result;
 use futures_util::future::join_all;
-use num_traits::pow::Pow;
 use pragma_common::starknet::fallback_provider::FallbackProvider;
 use rust_decimal::Decimal;
-use rust_decimal_macros::dec;
-use starknet::core::types::{BlockId, BlockTag, Felt, FunctionCall};
-use starknet::macros::{felt_hex, selector};
-use starknet::providers::Provider;
+use tokio::sync::watch;
 
-use crate::config::onchain_assets::OnchainAssetConfig;
+use crate::config::onchain_assets::{ONCHAIN_ASSETS, OnchainAssetConfig};
+use crate::services::chain_head::CHAIN_HEAD;
+use crate::services::oracle::events::OracleEventsWatcher;
+use crate::services::oracle::exchange_rates::{EXCHANGE_RATES, refresh_exchange_rates};
+use crate::services::oracle::failures::ORACLE_FAILURES;
+use crate::services::oracle::price_history::PRICE_HISTORY;
+use crate::services::oracle::pricing::{
+    CROSS_RATES, DIRECT_PAIRS, fetch_direct_rate, fetch_exchange_rate,
+};
+use crate::services::oracle::sources::{PriceSource, PriceSources, VesuSource};
 use crate::services::oracle::vesu_prices::VESU_PRICES;
+use crate::services::watchdog::{WATCHDOG, WatchedService};
+
+/// How the oracle prices get refreshed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum OracleMode {
+    /// Fetches every asset price every update.
+    #[default]
+    Polling,
+    /// Only fetches the assets with a price update event since the last
+    /// update, with a periodic full refresh for the derived prices.
+    Events,
+}
 
 #[derive(Clone)]
 pub struct OracleService {
     starknet_provider: FallbackProvider,
+    mode: OracleMode,
+    /// If set, the price history is persisted to this file.
+    history_path: Option<PathBuf>,
+    sources: Arc<PriceSources>,
 }
 
 impl OracleService {
     const PRICES_UPDATE_INTERVAL: Duration = Duration::from_secs(10);
+    const MAX_FETCH_RETRIES: u32 = 3;
+    const RETRY_BASE_DELAY: Duration = Duration::from_millis(200);
+    /// Number of consecutive failed updates of an asset before escalating.
+    const MAX_CONSECUTIVE_FAILURES: u64 = 5;
+
+    /// In events mode, interval between two full refreshes - needed for the assets
+    /// that are not directly fed by the oracle (yield bearing assets...).
+    const FULL_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+    const HISTORY_PERSIST_INTERVAL: Duration = Duration::from_secs(60);
 
     pub fn new(starknet_provider: FallbackProvider) -> Self {
-        Self { starknet_provider }
+        Self {
+            starknet_provider,
+            mode: OracleMode::default(),
+            history_path: None,
+            sources: Arc::new(PriceSources::default()),
+        }
+    }
+
+    pub fn with_mode(mut self, mode: OracleMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    pub fn with_history_path(mut self, history_path: Option<PathBuf>) -> Self {
+        self.history_path = history_path;
+        self
+    }
+
+    pub fn with_sources(mut self, sources: Arc<PriceSources>) -> Self {
+        self.sources = sources;
+        self
     }
 
     /// Starts the oracle service that will fetch the latest oracle prices every
-    /// PRICES_UPDATE_INTERVAL seconds.
+    /// PRICES_UPDATE_INTERVAL seconds, or at each new block when the chain head
+    /// is followed.
     pub async fn run_forever(self) -> Result<()> {
+        let Some(history_path) = self.history_path.clone() else {
+            return self.update_prices_forever().await;
+        };
+
+        if let Err(e) = PRICE_HISTORY.load(&history_path) {
+            tracing::warn!(
+                "[🔮 Oracle] Could not load the price history {}: {e}",
+                history_path.display()
+            );
+        }
+
+        tokio::select! {
+            result = self.update_prices_forever() => result,
+            result = Self::persist_history_forever(history_path) => result,
+        }
+    }
+
+    async fn update_prices_forever(&self) -> Result<()> {
+        match self.mode {
+            OracleMode::Polling => {
+                let mut heads = CHAIN_HEAD.subscribe_heads();
+                loop {
+                    let head = *heads.borrow_and_update();
+                    refresh_exchange_rates(&self.starknet_provider).await;
+                    self.update_prices().await;
+                    CHAIN_HEAD.publish_refreshed_prices(head);
+                    Self::wait_for_next_update(&mut heads).await;
+                }
+            }
+            OracleMode::Events => self.run_on_events().await,
+        }
+    }
+
+    async fn persist_history_forever(history_path: PathBuf) -> Result<()> {
+        let mut interval = tokio::time::interval(Self::HISTORY_PERSIST_INTERVAL);
+        interval.tick().await;
+
         loop {
-            self.update_prices().await?;
-            tokio::time::sleep(Self::PRICES_UPDATE_INTERVAL).await;
+            interval.tick().await;
+            if let Err(e) = PRICE_HISTORY.save(&history_path) {
+                tracing::warn!("[🔮 Oracle] Could not persist the price history: {e}");
+            }
+        }
+    }
+
+    /// Refreshes only the assets that changed, according to the oracle events.
+    async fn run_on_events(&self) -> Result<()> {
+        let mut watcher = OracleEventsWatcher::new();
+        let mut last_full_refresh: Option<Instant> = None;
+        let mut heads = CHAIN_HEAD.subscribe_heads();
+
+        loop {
+            let head = *heads.borrow_and_update();
+            // Keeps the yield bearing assets accurate between their price updates.
+            refresh_exchange_rates(&self.starknet_provider).await;
+            let changed_assets = watcher.changed_assets(&self.starknet_provider).await;
+
+            let needs_full_refresh =
+                last_full_refresh.is_none_or(|at| at.elapsed() >= Self::FULL_REFRESH_INTERVAL);
+
+            match changed_assets {
+                _ if needs_full_refresh => {
+                    self.update_prices().await;
+                    last_full_refresh = Some(Instant::now());
+                }
+                Ok(changed_assets) => self.update_prices_of(changed_assets).await,
+                Err(e) => {
+                    tracing::warn!("[🔮 Oracle] Could not fetch the oracle events: {e}");
+                    self.update_prices().await;
+                }
+            }
+
+            CHAIN_HEAD.publish_refreshed_prices(head);
+            Self::wait_for_next_update(&mut heads).await;
+        }
+    }
+
+    /// Waits PRICES_UPDATE_INTERVAL, or less if a new block comes before.
+    async fn wait_for_next_update(heads: &mut watch::Receiver<u64>) {
+        tokio::select! {
+            _ = tokio::time::sleep(Self::PRICES_UPDATE_INTERVAL) => {}
+            _ = heads.changed() => {}
         }
     }
 
     /// Update all the monitored assets with their latest USD price asynchronously.
-    async fn update_prices(&self) -> Result<()> {
+    pub async fn update_prices(&self) {
         let assets: Vec<OnchainAssetConfig> = VESU_PRICES
             .0
             .iter()
             .map(|entry| entry.key().clone())
             .collect();
 
+        self.update_prices_of(assets).await;
+        self.update_cross_rates().await;
+    }
+
+    /// Update the direct rates of the `DIRECT_PAIRS`.
+    /// A pair without a direct rate gets priced through USD.
+    async fn update_cross_rates(&self) {
+        let fetch_tasks = DIRECT_PAIRS.iter().map(|&(base, quote)| async move {
+            let rate = fetch_direct_rate(&self.starknet_provider, base, quote).await;
+            (base, quote, rate)
+        });
+
+        for (base, quote, rate) in join_all(fetch_tasks).await {
+            match rate {
+                Ok(rate) => {
+                    CROSS_RATES.set(base, quote, rate);
+                }
+                Err(e) => {
+                    CROSS_RATES.expire(base, quote);
+                    tracing::debug!("[🔮 Oracle] No direct rate for {base}/{quote}: {e}");
+                }
+            }
+        }
+    }
+
+    /// Update the provided assets with their latest USD price asynchronously.
+    /// A failing asset keeps its previous price and doesn't impact the others.
+    async fn update_prices_of(&self, assets: Vec<OnchainAssetConfig>) {
+        if assets.is_empty() {
+            return;
+        }
+
         let fetch_tasks = assets.into_iter().map(|asset| async move {
-            let vesu_price = self.vesu_price_in_usd(&asset).await;
-            (asset, vesu_price)
+            let price = self.price_with_retries(&asset).await;
+            (asset, price)
         });
 
         let results = join_all(fetch_tasks).await;
 
-        for (asset, vesu_price_result) in results {
-            if let Ok(vesu_price) = vesu_price_result {
-                VESU_PRICES.0.insert(asset, vesu_price);
+        let mut failed = Vec::new();
+        for (asset, price_result) in results {
+            match price_result {
+                Ok(price) => {
+                    ORACLE_FAILURES.record_success(&asset.ticker);
+                    EXCHANGE_RATES.mark_price_update(&asset.ticker);
+                    PRICE_HISTORY.record(&asset.ticker, price);
+                    VESU_PRICES.0.insert(asset, price);
+                    WATCHDOG.beat(WatchedService::Oracle);
+                }
+                Err(e) => failed.push((asset, e)),
             }
         }
 
-        Ok(())
+        // Handled after the successes so the underlyings have their latest price.
+        for (asset, e) in failed {
+            match self.price_from_underlying(&asset).await {
+                Ok(Some(price)) => {
+                    ORACLE_FAILURES.record_failure(&asset.ticker);
+                    EXCHANGE_RATES.mark_price_update(&asset.ticker);
+                    tracing::debug!(
+                        "[🔮 Oracle] Priced {} through its underlying after: {e}",
+                        asset.ticker
+                    );
+                    PRICE_HISTORY.record(&asset.ticker, price);
+                    VESU_PRICES.0.insert(asset, price);
+                }
+                _ => {
+                    let failures = ORACLE_FAILURES.record_failure(&asset.ticker);
+                    if failures.consecutive >= Self::MAX_CONSECUTIVE_FAILURES {
+                        tracing::error!(
+                            "[🔮 Oracle] Could not fetch the price of {} for {} consecutive updates: {e}",
+                            asset.ticker,
+                            failures.consecutive
+                        );
+                    } else {
+                        tracing::debug!(
+                            "[🔮 Oracle] Could not fetch the price of {}: {e}",
+                            asset.ticker
+                        );
+                    }
+                }
+            }
+        }
     }
 
-    async fn vesu_price_in_usd(&self, base_asset: &OnchainAssetConfig) -> Result<Decimal> {
-        const VESU_ORACLE_ADDRESS: Felt =
-            felt_hex!("0xfe4bfb1b353ba51eb34dff963017f94af5a5cf8bdf3dfc191c504657f3c05");
-
-        const VESU_SCALE: Decimal = dec!(18);
-
-        let price_request = FunctionCall {
-            contract_address: VESU_ORACLE_ADDRESS,
-            entry_point_selector: selector!("price"),
-            calldata: vec![base_asset.address],
+    /// Prices a wrapper from its underlying price & the on-chain exchange rate.
+    /// Returns None if the asset is not a configured wrapper.
+    async fn price_from_underlying(&self, wrapper: &OnchainAssetConfig) -> Result<Option<Decimal>> {
+        let Some(underlying) = ONCHAIN_ASSETS.underlying_of(&wrapper.ticker) else {
+            return Ok(None);
         };
 
-        let call_result = self
-            .starknet_provider
-            .call(price_request, BlockId::Tag(BlockTag::Latest))
-            .await?;
+        let underlying_price = VESU_PRICES
+            .0
+            .get(underlying)
+            .map(|p| *p)
+            .unwrap_or_default();
+        if underlying_price.is_zero() {
+            anyhow::bail!("No price for the underlying {}", underlying.ticker);
+        }
 
-        // NOTE: Works for now since prices always fit in the low part.
-        let asset_price_low = Decimal::from_str(&call_result[0].to_string())?;
+        let rate = fetch_exchange_rate(&self.starknet_provider, wrapper, underlying).await?;
+        Ok(Some(underlying_price * rate))
+    }
 
-        let is_valid = u128::from_str(&call_result[2].to_string())?;
-        if is_valid == 0 {
-            anyhow::bail!("Vesu price is not valid");
+    /// Fetches the price of the asset, retrying with an exponential backoff.
+    async fn price_with_retries(&self, base_asset: &OnchainAssetConfig) -> Result<Decimal> {
+        let mut attempt = 0;
+        loop {
+            match self.price_in_usd(base_asset).await {
+                Ok(price) => return Ok(price),
+                Err(e) if attempt >= Self::MAX_FETCH_RETRIES => return Err(e),
+                Err(_) => {
+                    tokio::time::sleep(Self::RETRY_BASE_DELAY * 2u32.pow(attempt)).await;
+                    attempt += 1;
+                }
+            }
         }
+    }
 
-        let asset_price = asset_price_low / Decimal::TEN.pow(VESU_SCALE);
+    /// Fetches the price of the asset from its configured source.
+    async fn price_in_usd(&self, base_asset: &OnchainAssetConfig) -> Result<Decimal> {
+        self.sources
+            .source_of(base_asset)
+            .price(&self.starknet_provider, base_asset)
+            .await
+    }
 
-        Ok(asset_price)
+    pub async fn vesu_price_in_usd(&self, base_asset: &OnchainAssetConfig) -> Result<Decimal> {
+        VesuSource.price(&self.starknet_provider, base_asset).await
     }
 }
@@ -0,0 +1,119 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::fs::{self, File};
+use std::path::Path;
+use std::sync::{Arc, LazyLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::Result;
+use dashmap::DashMap;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::types::decimal;
+
+pub const PRICE_HISTORY_FILE: &str = "price_history.json";
+
+pub static PRICE_HISTORY: LazyLock<Arc<PriceHistory>> =
+    LazyLock::new(|| Arc::new(PriceHistory::default()));
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct PricePoint {
+    /// Unix timestamp, in seconds.
+    pub timestamp: u64,
+    #[serde(with = "decimal::string")]
+    pub price: Decimal,
+}
+
+/// Recent USD prices the bot used, by ticker, to reconstruct what it believed
+/// the prices were during a post-mortem.
+#[derive(Default, Debug, Clone)]
+pub struct PriceHistory(pub DashMap<String, VecDeque<PricePoint>>);
+
+impl PriceHistory {
+    /// How long the prices are kept.
+    pub const RETENTION_SECS: u64 = 24 * 3600;
+    /// Minimum time between two recorded prices of an asset.
+    pub const RESOLUTION_SECS: u64 = 10;
+
+    /// Records the latest price of the asset, dropping the expired ones.
+    pub fn record(&self, ticker: &str, price: Decimal) {
+        let now = now_secs();
+        let mut series = self.0.entry(ticker.to_string()).or_default();
+
+        match series.back_mut() {
+            // Too close to the previous point: keep the most recent price.
+            Some(last) if now < last.timestamp + Self::RESOLUTION_SECS => {
+                last.price = price;
+            }
+            _ => series.push_back(PricePoint {
+                timestamp: now,
+                price,
+            }),
+        }
+
+        while series
+            .front()
+            .is_some_and(|p| p.timestamp + Self::RETENTION_SECS < now)
+        {
+            series.pop_front();
+        }
+    }
+
+    /// Returns the recorded prices of the asset between the two timestamps.
+    pub fn query(
+        &self,
+        ticker: &str,
+        from: Option<u64>,
+        to: Option<u64>,
+    ) -> Option<Vec<PricePoint>> {
+        let series = self
+            .0
+            .iter()
+            .find(|entry| entry.key().eq_ignore_ascii_case(ticker))?;
+
+        Some(
+            series
+                .iter()
+                .filter(|p| from.is_none_or(|from| p.timestamp >= from))
+                .filter(|p| to.is_none_or(|to| p.timestamp <= to))
+                .copied()
+                .collect(),
+        )
+    }
+
+    /// Loads the history persisted at this path, if any.
+    pub fn load(&self, path: &Path) -> Result<()> {
+        if !path.exists() {
+            return Ok(());
+        }
+
+        let persisted: BTreeMap<String, VecDeque<PricePoint>> =
+            serde_json::from_reader(File::open(path)?)?;
+        for (ticker, series) in persisted {
+            self.0.insert(ticker, series);
+        }
+        Ok(())
+    }
+
+    /// Persists the history at this path.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let history: BTreeMap<String, VecDeque<PricePoint>> = self
+            .0
+            .iter()
+            .map(|entry| (entry.key().clone(), entry.value().clone()))
+            .collect();
+
+        // Write & rename so that a crash never leaves a partial history.
+        let tmp_path = path.with_extension("json.tmp");
+        fs::write(&tmp_path, serde_json::to_vec(&history)?)?;
+        fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
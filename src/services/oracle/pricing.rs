@@ -0,0 +1,240 @@
+use std::{
+    collections::HashSet,
+    str::FromStr,
+    sync::{Arc, LazyLock, OnceLock},
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use dashmap::DashMap;
+use num_traits::pow::Pow;
+use pragma_common::starknet::fallback_provider::FallbackProvider;
+use rust_decimal::Decimal;
+use starknet::core::types::{BlockId, BlockTag, Felt, FunctionCall};
+use starknet::core::utils::cairo_short_string_to_felt;
+use starknet::macros::selector;
+use starknet::providers::Provider;
+
+use crate::config::addresses::AddressBook;
+use crate::config::onchain_assets::OnchainAssetConfig;
+use crate::types::currency::Currency;
+use crate::types::pool::PoolName;
+use crate::utils::rpc::{RpcPath, guarded_starknet};
+
+pub static CROSS_RATES: LazyLock<Arc<CrossRates>> =
+    LazyLock::new(|| Arc::new(CrossRates::default()));
+
+/// Pairs for which we fetch a direct rate instead of going through USD.
+/// Mostly the BTC wrappers, all quoted in WBTC so they can be crossed together.
+pub const DIRECT_PAIRS: &[(Currency, Currency)] = &[
+    (Currency::tBTC, Currency::WBTC),
+    (Currency::LBTC, Currency::WBTC),
+    (Currency::solvBTC, Currency::WBTC),
+    (Currency::xtBTC, Currency::WBTC),
+    (Currency::xWBTC, Currency::WBTC),
+    (Currency::xLBTC, Currency::WBTC),
+    (Currency::xsBTC, Currency::WBTC),
+];
+
+static NATIVE_LTV_CONFIG: OnceLock<NativeLtvConfig> = OnceLock::new();
+
+/// The pools whose positions of two assets of the same underlying - e.g two BTC
+/// wrappers - get their LTV from the direct rate of the assets only, never from
+/// the ratio of their USD prices: the noise of two USD feeds would make them
+/// liquidable, or not, on their own.
+#[derive(Debug, Clone)]
+pub struct NativeLtvConfig {
+    pub pools: HashSet<PoolName>,
+    /// Age past which the last direct rate known is not used anymore: the pair
+    /// falls back to the ratio of the USD prices.
+    pub max_rate_age: Duration,
+}
+
+impl Default for NativeLtvConfig {
+    fn default() -> Self {
+        Self {
+            pools: HashSet::new(),
+            max_rate_age: Duration::from_secs(3600),
+        }
+    }
+}
+
+impl NativeLtvConfig {
+    /// Sets the pools of the whole process. Only the first call has an effect.
+    pub fn install(self) {
+        let _ = NATIVE_LTV_CONFIG.set(self);
+    }
+
+    pub fn get() -> &'static Self {
+        NATIVE_LTV_CONFIG.get_or_init(Self::default)
+    }
+
+    /// Whether the LTV of the pair of the pool is computed in native terms.
+    pub fn applies_to(&self, pool: PoolName, collateral: Currency, debt: Currency) -> bool {
+        self.pools.contains(&pool)
+            && collateral
+                .class()
+                .underlying()
+                .is_some_and(|underlying| Some(underlying) == debt.class().underlying())
+    }
+}
+
+/// Map containing the direct rates, i.e how many `quote` for one `base`.
+#[derive(Default, Debug, Clone)]
+pub struct CrossRates {
+    rates: DashMap<(Currency, Currency), Decimal>,
+    /// The last direct rate fetched per pair & when, kept when the pair has no
+    /// rate anymore, for the pools priced in native terms.
+    last_known: DashMap<(Currency, Currency), (Decimal, Instant)>,
+}
+
+impl CrossRates {
+    pub fn set(&self, base: Currency, quote: Currency, rate: Decimal) {
+        self.rates.insert((base, quote), rate);
+        self.last_known
+            .insert((base, quote), (rate, Instant::now()));
+    }
+
+    /// Drops the direct rate of the pair, which gets priced through USD.
+    pub fn expire(&self, base: Currency, quote: Currency) {
+        self.rates.remove(&(base, quote));
+    }
+
+    /// Returns the price of one `base` in `quote`.
+    /// Uses a direct rate when available (or crossed through a common quote),
+    /// and falls back to the ratio of the USD prices otherwise.
+    pub fn rate(&self, base: Currency, quote: Currency) -> Decimal {
+        if base == quote {
+            return Decimal::ONE;
+        }
+        self.direct(base, quote)
+            .unwrap_or_else(|| base.price() / quote.price())
+    }
+
+    /// Returns the rate between the two assets without going through USD, if known.
+    pub fn direct(&self, base: Currency, quote: Currency) -> Option<Decimal> {
+        Self::direct_in(&self.rates, |rate| Some(*rate), base, quote)
+    }
+
+    /// Returns the rate between the two assets in native terms: the direct one,
+    /// else the last one known if not older than the `max_rate_age` of the
+    /// `NativeLtvConfig` - None if there is none.
+    pub fn native(&self, base: Currency, quote: Currency) -> Option<Decimal> {
+        if base == quote {
+            return Some(Decimal::ONE);
+        }
+        let max_rate_age = NativeLtvConfig::get().max_rate_age;
+        self.direct(base, quote).or_else(|| {
+            Self::direct_in(
+                &self.last_known,
+                |(rate, fetched_at)| (fetched_at.elapsed() <= max_rate_age).then_some(*rate),
+                base,
+                quote,
+            )
+        })
+    }
+
+    fn direct_in<V>(
+        rates: &DashMap<(Currency, Currency), V>,
+        rate_of: impl Fn(&V) -> Option<Decimal>,
+        base: Currency,
+        quote: Currency,
+    ) -> Option<Decimal> {
+        let get = |base: Currency, quote: Currency| {
+            rates
+                .get(&(base, quote))
+                .and_then(|r| rate_of(&r))
+                .filter(|r| !r.is_zero())
+        };
+        if let Some(rate) = get(base, quote) {
+            return Some(rate);
+        }
+        if let Some(inverse) = get(quote, base) {
+            return Some(Decimal::ONE / inverse);
+        }
+        // Both assets quoted in the same asset, e.g xtBTC/WBTC & xWBTC/WBTC.
+        rates.iter().find_map(|entry| {
+            let (entry_base, common_quote) = *entry.key();
+            if entry_base != base {
+                return None;
+            }
+            let base_rate = rate_of(entry.value())?;
+            let quote_rate = get(quote, common_quote)?;
+            Some(base_rate / quote_rate)
+        })
+    }
+}
+
+/// Converts an amount of `from` into `to`.
+pub fn convert(amount: Decimal, from: Currency, to: Currency) -> Decimal {
+    amount * CROSS_RATES.rate(from, to)
+}
+
+/// Fetches the direct rate of the pair from the Pragma oracle.
+pub async fn fetch_direct_rate(
+    provider: &FallbackProvider,
+    base: Currency,
+    quote: Currency,
+) -> Result<Decimal> {
+    let pair_id = format!(
+        "{}/{}",
+        base.ticker().to_uppercase(),
+        quote.ticker().to_uppercase()
+    );
+    fetch_pragma_median(provider, &pair_id).await
+}
+
+/// Fetches the median spot price of a Pragma pair, cf: `ETH/USD`.
+pub async fn fetch_pragma_median(provider: &FallbackProvider, pair_id: &str) -> Result<Decimal> {
+    let pair_felt = cairo_short_string_to_felt(pair_id)?;
+
+    // DataType::SpotEntry(pair_id)
+    let median_request = FunctionCall {
+        contract_address: AddressBook::get().pragma_oracle,
+        entry_point_selector: selector!("get_data_median"),
+        calldata: vec![Felt::ZERO, pair_felt],
+    };
+
+    let call_result = guarded_starknet(provider, RpcPath::Background, |node| {
+        node.call(median_request.clone(), BlockId::Tag(BlockTag::Latest))
+    })
+    .await?;
+
+    // (price, decimals, last_updated_timestamp, num_sources_aggregated, ...)
+    let num_sources = u128::from_str(&call_result[3].to_string())?;
+    if num_sources == 0 {
+        anyhow::bail!("No sources for the pair {pair_id}");
+    }
+
+    let price = Decimal::from_str(&call_result[0].to_string())?;
+    let decimals = Decimal::from_str(&call_result[1].to_string())?;
+
+    Ok(price / Decimal::TEN.pow(decimals))
+}
+
+/// Reads the amount of `underlying` one `wrapper` share is worth from the
+/// ERC-4626 `convert_to_assets` of the wrapper.
+pub async fn fetch_exchange_rate(
+    provider: &FallbackProvider,
+    wrapper: &OnchainAssetConfig,
+    underlying: &OnchainAssetConfig,
+) -> Result<Decimal> {
+    let one_share: u128 = 10u128.pow(wrapper.decimals);
+
+    let convert_request = FunctionCall {
+        contract_address: wrapper.address,
+        entry_point_selector: selector!("convert_to_assets"),
+        calldata: vec![Felt::from(one_share), Felt::ZERO],
+    };
+
+    let call_result = guarded_starknet(provider, RpcPath::Background, |node| {
+        node.call(convert_request.clone(), BlockId::Tag(BlockTag::Latest))
+    })
+    .await?;
+
+    let assets = Decimal::from_str(&call_result[0].to_string())?;
+    let rate = assets / Decimal::TEN.pow(Decimal::from(underlying.decimals));
+    anyhow::ensure!(!rate.is_zero(), "Null exchange rate for {}", wrapper.ticker);
+
+    Ok(rate)
+}
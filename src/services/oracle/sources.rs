@@ -0,0 +1,540 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use anyhow::{Context, Result, anyhow};
+use futures_util::future::join_all;
+use num_traits::pow::Pow;
+use pragma_common::starknet::fallback_provider::FallbackProvider;
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use serde::Deserialize;
+use starknet::core::types::{BlockId, BlockTag, Felt, FunctionCall};
+use starknet::macros::selector;
+use starknet::providers::Provider;
+use url::Url;
+
+use crate::config::addresses::AddressBook;
+use crate::config::onchain_assets::{ONCHAIN_ASSETS, OnchainAssetConfig};
+use crate::services::oracle::pricing::fetch_pragma_median;
+use crate::utils::rpc::{RpcPath, RpcProvider, guarded, guarded_starknet};
+
+pub const DEFAULT_PRAGMA_API_URL: &str = "https://api.production.pragma.build";
+
+/// A source of USD prices. Implement it to price the assets from another
+/// oracle, & combine the sources of an asset with `MedianSource` or
+/// `PrioritySource`.
+#[async_trait::async_trait]
+pub trait PriceSource: fmt::Debug + fmt::Display + Send + Sync {
+    async fn price(
+        &self,
+        provider: &FallbackProvider,
+        asset: &OnchainAssetConfig,
+    ) -> Result<Decimal>;
+}
+
+/// The Vesu oracle, as used by the pools.
+#[derive(Debug, Clone, Copy)]
+pub struct VesuSource;
+
+impl VesuSource {
+    /// The oracle of the address book, e.g a mock oracle fed by `test-feeder`
+    /// with `--vesu-oracle-address`.
+    pub fn oracle() -> Felt {
+        AddressBook::get().vesu_oracle
+    }
+}
+
+impl fmt::Display for VesuSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "vesu")
+    }
+}
+
+#[async_trait::async_trait]
+impl PriceSource for VesuSource {
+    async fn price(
+        &self,
+        provider: &FallbackProvider,
+        asset: &OnchainAssetConfig,
+    ) -> Result<Decimal> {
+        const VESU_SCALE: Decimal = dec!(18);
+
+        let price_request = FunctionCall {
+            contract_address: Self::oracle(),
+            entry_point_selector: selector!("price"),
+            calldata: vec![asset.address],
+        };
+
+        let call_result = guarded_starknet(provider, RpcPath::Background, |node| {
+            node.call(price_request.clone(), BlockId::Tag(BlockTag::Latest))
+        })
+        .await?;
+
+        // NOTE: Works for now since prices always fit in the low part.
+        let asset_price_low = Decimal::from_str(&call_result[0].to_string())?;
+
+        let is_valid = u128::from_str(&call_result[2].to_string())?;
+        if is_valid == 0 {
+            anyhow::bail!("Vesu price is not valid");
+        }
+
+        Ok(asset_price_low / Decimal::TEN.pow(VESU_SCALE))
+    }
+}
+
+/// The median of the Pragma oracle contract for `<TICKER>/USD`.
+#[derive(Debug, Clone, Copy)]
+pub struct PragmaOnchainSource;
+
+impl fmt::Display for PragmaOnchainSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "pragma-onchain")
+    }
+}
+
+#[async_trait::async_trait]
+impl PriceSource for PragmaOnchainSource {
+    async fn price(
+        &self,
+        provider: &FallbackProvider,
+        asset: &OnchainAssetConfig,
+    ) -> Result<Decimal> {
+        fetch_pragma_median(provider, &format!("{}/USD", usd_pair_base(asset))).await
+    }
+}
+
+/// The Pragma API, for the assets priced from it.
+#[derive(Debug, Clone)]
+pub struct PragmaApiConfig {
+    pub url: Url,
+    pub api_key: String,
+}
+
+/// The median of the Pragma API for `<TICKER>/USD`.
+#[derive(Debug, Clone)]
+pub struct PragmaApiSource {
+    config: PragmaApiConfig,
+    http_client: reqwest::Client,
+}
+
+#[derive(Debug, Deserialize)]
+struct PragmaApiPrice {
+    /// Hex encoded.
+    price: String,
+    decimals: u32,
+    num_sources_aggregated: u32,
+}
+
+impl PragmaApiSource {
+    pub fn new(config: PragmaApiConfig) -> Self {
+        Self {
+            config,
+            http_client: reqwest::Client::new(),
+        }
+    }
+}
+
+impl fmt::Display for PragmaApiSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "pragma-api")
+    }
+}
+
+#[async_trait::async_trait]
+impl PriceSource for PragmaApiSource {
+    async fn price(
+        &self,
+        _provider: &FallbackProvider,
+        asset: &OnchainAssetConfig,
+    ) -> Result<Decimal> {
+        let endpoint = self.config.url.join(&format!(
+            "node/v1/data/{}/usd?aggregation=median",
+            usd_pair_base(asset)
+        ))?;
+
+        let response = guarded(
+            RpcProvider::PragmaApi,
+            RpcPath::Background,
+            self.http_client
+                .get(endpoint)
+                .header("x-api-key", &self.config.api_key)
+                .send(),
+        )
+        .await?;
+        if !response.status().is_success() {
+            anyhow::bail!(
+                "Pragma API request failed with status: {}",
+                response.status()
+            );
+        }
+
+        let price: PragmaApiPrice = response.json().await?;
+        if price.num_sources_aggregated == 0 {
+            anyhow::bail!("No sources for {}/USD", asset.ticker);
+        }
+        let raw_price = Felt::from_hex(&price.price)?;
+        Ok(Decimal::from_str(&raw_price.to_string())?
+            / Decimal::TEN.pow(Decimal::from(price.decimals)))
+    }
+}
+
+/// A fixed USD price, e.g for a pegged wrapper.
+#[derive(Debug, Clone, Copy)]
+pub struct PegSource(pub Decimal);
+
+impl fmt::Display for PegSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "peg:{}", self.0)
+    }
+}
+
+#[async_trait::async_trait]
+impl PriceSource for PegSource {
+    async fn price(
+        &self,
+        _provider: &FallbackProvider,
+        _asset: &OnchainAssetConfig,
+    ) -> Result<Decimal> {
+        Ok(self.0)
+    }
+}
+
+/// The median of the prices of the sources answering, fetched concurrently.
+/// Fails unless at least `quorum` of them answer, so a single source left
+/// answering - e.g the others being down or stale - cannot set the price alone.
+#[derive(Debug, Clone)]
+pub struct MedianSource {
+    pub sources: Vec<Arc<dyn PriceSource>>,
+    pub quorum: usize,
+}
+
+impl MedianSource {
+    /// A median needing the majority of its sources.
+    pub fn new(sources: Vec<Arc<dyn PriceSource>>) -> Self {
+        let quorum = majority(sources.len());
+        Self { sources, quorum }
+    }
+}
+
+impl fmt::Display for MedianSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "median({}; quorum {})",
+            join_sources(&self.sources),
+            self.quorum
+        )
+    }
+}
+
+#[async_trait::async_trait]
+impl PriceSource for MedianSource {
+    async fn price(
+        &self,
+        provider: &FallbackProvider,
+        asset: &OnchainAssetConfig,
+    ) -> Result<Decimal> {
+        let results = join_all(
+            self.sources
+                .iter()
+                .map(|source| source.price(provider, asset)),
+        )
+        .await;
+
+        let mut prices = Vec::with_capacity(results.len());
+        for (source, result) in self.sources.iter().zip(results) {
+            match result {
+                Ok(price) => prices.push(price),
+                Err(e) => {
+                    tracing::debug!("[🔮 Oracle] {source} could not price {}: {e}", asset.ticker);
+                }
+            }
+        }
+        anyhow::ensure!(
+            prices.len() >= self.quorum.max(1),
+            "Only {} of {self} could price it",
+            prices.len()
+        );
+
+        prices.sort_unstable();
+        let middle = prices.len() / 2;
+        Ok(if prices.len().is_multiple_of(2) {
+            (prices[middle - 1] + prices[middle]) / Decimal::TWO
+        } else {
+            prices[middle]
+        })
+    }
+}
+
+/// The price of the first source answering, by order of priority.
+#[derive(Debug, Clone)]
+pub struct PrioritySource(pub Vec<Arc<dyn PriceSource>>);
+
+impl fmt::Display for PrioritySource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "priority({})", join_sources(&self.0))
+    }
+}
+
+#[async_trait::async_trait]
+impl PriceSource for PrioritySource {
+    async fn price(
+        &self,
+        provider: &FallbackProvider,
+        asset: &OnchainAssetConfig,
+    ) -> Result<Decimal> {
+        let mut errors = Vec::with_capacity(self.0.len());
+        for source in &self.0 {
+            match source.price(provider, asset).await {
+                Ok(price) => return Ok(price),
+                Err(e) => errors.push(format!("{source}: {e}")),
+            }
+        }
+        anyhow::bail!("None of {self} could price it ({})", errors.join(", "))
+    }
+}
+
+/// The default quorum of a median: more than half of its sources.
+fn majority(sources: usize) -> usize {
+    sources / 2 + 1
+}
+
+fn join_sources(sources: &[Arc<dyn PriceSource>]) -> String {
+    sources
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Where the USD price of an asset comes from, as configured: a source, or a
+/// combination of sources.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "RawPriceSourceConfig")]
+pub enum PriceSourceConfig {
+    Vesu,
+    PragmaOnchain,
+    PragmaApi,
+    Peg(Decimal),
+    Median {
+        sources: Vec<PriceSourceConfig>,
+        quorum: usize,
+    },
+    Priority(Vec<PriceSourceConfig>),
+}
+
+/// A source in the TOML file: its name, or a table combining sources, e.g
+/// `{ median = ["vesu", "pragma-onchain"] }` or
+/// `{ median = ["vesu", "pragma-onchain", "pragma-api"], quorum = 2 }`.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawPriceSourceConfig {
+    Named(String),
+    Median {
+        median: Vec<PriceSourceConfig>,
+        quorum: Option<usize>,
+    },
+    Priority {
+        priority: Vec<PriceSourceConfig>,
+    },
+}
+
+impl TryFrom<RawPriceSourceConfig> for PriceSourceConfig {
+    type Error = anyhow::Error;
+
+    fn try_from(raw: RawPriceSourceConfig) -> Result<Self> {
+        match raw {
+            RawPriceSourceConfig::Named(name) => name.parse(),
+            RawPriceSourceConfig::Median { median, quorum } => {
+                anyhow::ensure!(!median.is_empty(), "A median needs at least one source");
+                let quorum = quorum.unwrap_or_else(|| majority(median.len()));
+                anyhow::ensure!(
+                    (1..=median.len()).contains(&quorum),
+                    "The quorum of a median must be between 1 & its {} sources",
+                    median.len()
+                );
+                Ok(Self::Median {
+                    sources: median,
+                    quorum,
+                })
+            }
+            RawPriceSourceConfig::Priority { priority } => {
+                anyhow::ensure!(!priority.is_empty(), "A priority needs at least one source");
+                Ok(Self::Priority(priority))
+            }
+        }
+    }
+}
+
+impl fmt::Display for PriceSourceConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let join = |sources: &[Self]| {
+            sources
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        match self {
+            Self::Vesu => write!(f, "vesu"),
+            Self::PragmaOnchain => write!(f, "pragma-onchain"),
+            Self::PragmaApi => write!(f, "pragma-api"),
+            Self::Peg(price) => write!(f, "peg:{price}"),
+            Self::Median { sources, quorum } => {
+                write!(f, "median({}; quorum {quorum})", join(sources))
+            }
+            Self::Priority(sources) => write!(f, "priority({})", join(sources)),
+        }
+    }
+}
+
+impl FromStr for PriceSourceConfig {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "vesu" => Ok(Self::Vesu),
+            "pragma-onchain" => Ok(Self::PragmaOnchain),
+            "pragma-api" => Ok(Self::PragmaApi),
+            _ => {
+                let price = s.strip_prefix("peg:").ok_or_else(|| {
+                    anyhow!("Unknown price source {s}, expected vesu, pragma-onchain, pragma-api or peg:<USD PRICE>")
+                })?;
+                let price = Decimal::from_str(price)?;
+                anyhow::ensure!(price > Decimal::ZERO, "The peg price must be positive");
+                Ok(Self::Peg(price))
+            }
+        }
+    }
+}
+
+impl PriceSourceConfig {
+    fn build(&self, pragma_api: Option<&PragmaApiConfig>) -> Result<Arc<dyn PriceSource>> {
+        let build_all = |sources: &[Self]| {
+            sources
+                .iter()
+                .map(|source| source.build(pragma_api))
+                .collect::<Result<Vec<_>>>()
+        };
+        Ok(match self {
+            Self::Vesu => Arc::new(VesuSource),
+            Self::PragmaOnchain => Arc::new(PragmaOnchainSource),
+            Self::PragmaApi => Arc::new(PragmaApiSource::new(
+                pragma_api
+                    .context("The Pragma API needs --pragma-api-key")?
+                    .clone(),
+            )),
+            Self::Peg(price) => Arc::new(PegSource(*price)),
+            Self::Median { sources, quorum } => Arc::new(MedianSource {
+                sources: build_all(sources)?,
+                quorum: *quorum,
+            }),
+            Self::Priority(sources) => Arc::new(PrioritySource(build_all(sources)?)),
+        })
+    }
+}
+
+/// Parses a `<TICKER>=<SOURCE>` price source override.
+pub fn parse_price_source(s: &str) -> Result<(String, PriceSourceConfig)> {
+    let (ticker, source) = s
+        .split_once('=')
+        .ok_or_else(|| anyhow!("Expected TICKER=SOURCE, got {s}"))?;
+    anyhow::ensure!(
+        ONCHAIN_ASSETS.get_by_ticker(ticker).is_some(),
+        "Unknown asset {ticker}"
+    );
+    Ok((ticker.to_string(), source.parse()?))
+}
+
+/// The TOML file of the `--price-sources-config`, e.g:
+///
+/// ```toml
+/// [sources]
+/// ETH = { median = ["vesu", "pragma-onchain", "pragma-api"] }
+/// xSTRK = { priority = ["pragma-onchain", "vesu"] }
+/// "USDC.E" = "peg:1"
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PriceSourcesFile {
+    /// ticker => source
+    #[serde(default)]
+    pub sources: HashMap<String, PriceSourceConfig>,
+}
+
+impl PriceSourcesFile {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Could not read {}", path.display()))?;
+        let file: Self = toml::from_str(&content)
+            .with_context(|| format!("Invalid price sources config {}", path.display()))?;
+
+        if let Some(ticker) = file
+            .sources
+            .keys()
+            .find(|ticker| ONCHAIN_ASSETS.get_by_ticker(ticker).is_none())
+        {
+            anyhow::bail!("Unknown asset {ticker} in {}", path.display());
+        }
+        Ok(file)
+    }
+}
+
+/// The price source of every asset: the Vesu oracle unless configured.
+#[derive(Debug, Clone)]
+pub struct PriceSources {
+    /// ticker => source
+    sources: HashMap<String, Arc<dyn PriceSource>>,
+    default: Arc<dyn PriceSource>,
+}
+
+impl Default for PriceSources {
+    fn default() -> Self {
+        Self {
+            sources: HashMap::new(),
+            default: Arc::new(VesuSource),
+        }
+    }
+}
+
+impl PriceSources {
+    pub fn new(
+        configs: HashMap<String, PriceSourceConfig>,
+        pragma_api: Option<PragmaApiConfig>,
+    ) -> Result<Self> {
+        let sources = configs
+            .into_iter()
+            .map(|(ticker, config)| {
+                let source = config
+                    .build(pragma_api.as_ref())
+                    .with_context(|| format!("Invalid price source {config} of {ticker}"))?;
+                Ok((ticker, source))
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self {
+            sources,
+            ..Self::default()
+        })
+    }
+
+    /// Plugs a custom source for an asset.
+    pub fn with_source(mut self, ticker: impl Into<String>, source: Arc<dyn PriceSource>) -> Self {
+        self.sources.insert(ticker.into(), source);
+        self
+    }
+
+    pub fn source_of(&self, asset: &OnchainAssetConfig) -> &dyn PriceSource {
+        self.sources
+            .get(&asset.ticker)
+            .unwrap_or(&self.default)
+            .as_ref()
+    }
+}
+
+fn usd_pair_base(asset: &OnchainAssetConfig) -> String {
+    asset.ticker.to_uppercase()
+}
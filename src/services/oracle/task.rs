@@ -1,17 +1,35 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
 use pragma_common::{
     services::{Service, ServiceRunner},
     starknet::FallbackProvider,
 };
 
-use crate::services::oracle::OracleService;
+use crate::services::oracle::sources::PriceSources;
+use crate::services::oracle::{OracleMode, OracleService};
+use crate::services::watchdog::{WATCHDOG, WatchedService};
 
 pub struct OracleTask {
     starknet_provider: FallbackProvider,
+    mode: OracleMode,
+    history_path: Option<PathBuf>,
+    sources: Arc<PriceSources>,
 }
 
 impl OracleTask {
-    pub const fn new(starknet_provider: FallbackProvider) -> Self {
-        Self { starknet_provider }
+    pub fn new(
+        starknet_provider: FallbackProvider,
+        mode: OracleMode,
+        history_path: Option<PathBuf>,
+        sources: PriceSources,
+    ) -> Self {
+        Self {
+            starknet_provider,
+            mode,
+            history_path,
+            sources: Arc::new(sources),
+        }
     }
 }
 
@@ -19,10 +37,20 @@ impl OracleTask {
 impl Service for OracleTask {
     async fn start<'a>(&mut self, mut runner: ServiceRunner<'a>) -> anyhow::Result<()> {
         let starknet_provider = self.starknet_provider.clone();
+        let mode = self.mode;
+        let history_path = self.history_path.clone();
+        let sources = self.sources.clone();
 
         runner.spawn_loop(move |ctx| async move {
-            let oracle_service = OracleService::new(starknet_provider);
-            if let Some(result) = ctx.run_until_cancelled(oracle_service.run_forever()).await {
+            // Restarted from scratch when stalled, the prices being global.
+            let oracle_service = WATCHDOG.supervise(WatchedService::Oracle, || {
+                OracleService::new(starknet_provider.clone())
+                    .with_mode(mode)
+                    .with_history_path(history_path.clone())
+                    .with_sources(sources.clone())
+                    .run_forever()
+            });
+            if let Some(result) = ctx.run_until_cancelled(oracle_service).await {
                 result?;
             }
 
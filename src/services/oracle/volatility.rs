@@ -0,0 +1,68 @@
+use std::sync::LazyLock;
+use std::time::{Duration, Instant};
+
+use dashmap::DashMap;
+use rust_decimal::{Decimal, MathematicalOps};
+
+use crate::services::oracle::price_history::PRICE_HISTORY;
+use crate::types::currency::Currency;
+
+/// Minimum number of recorded prices before estimating a volatility.
+const MIN_SAMPLES: usize = 30;
+const SECS_PER_HOUR: u64 = 3600;
+/// How long an estimated volatility is reused before being computed again.
+const CACHE_TTL: Duration = Duration::from_secs(60);
+
+static VOLATILITY_CACHE: LazyLock<DashMap<Currency, (Instant, Option<Decimal>)>> =
+    LazyLock::new(DashMap::new);
+
+/// Hourly volatility of the asset USD price (standard deviation of its hourly
+/// log returns), estimated from the recorded price history.
+/// None if the history is too short. Zero for the USD pegged assets without
+/// any recorded move.
+pub fn hourly_volatility(currency: Currency) -> Option<Decimal> {
+    if let Some(cached) = VOLATILITY_CACHE.get(&currency)
+        && cached.0.elapsed() < CACHE_TTL
+    {
+        return cached.1;
+    }
+
+    let volatility = estimate_hourly_volatility(currency);
+    VOLATILITY_CACHE.insert(currency, (Instant::now(), volatility));
+    volatility
+}
+
+fn estimate_hourly_volatility(currency: Currency) -> Option<Decimal> {
+    let prices = PRICE_HISTORY.query(currency.as_ref(), None, None)?;
+    if prices.len() < MIN_SAMPLES {
+        return None;
+    }
+
+    let mut squared_returns = Decimal::ZERO;
+    let mut elapsed_secs: u64 = 0;
+    for window in prices.windows(2) {
+        let (previous, current) = (window[0], window[1]);
+        if previous.price <= Decimal::ZERO || current.price <= Decimal::ZERO {
+            continue;
+        }
+        let log_return = (current.price / previous.price).checked_ln()?;
+        squared_returns += log_return * log_return;
+        elapsed_secs += current.timestamp.saturating_sub(previous.timestamp);
+    }
+
+    if elapsed_secs == 0 {
+        return None;
+    }
+
+    let hourly_variance =
+        squared_returns * Decimal::from(SECS_PER_HOUR) / Decimal::from(elapsed_secs);
+    hourly_variance.sqrt()
+}
+
+/// Hourly volatility of the collateral/debt price ratio, assuming independent
+/// assets - an upper bound for the correlated ones.
+pub fn pair_hourly_volatility(collateral: Currency, debt: Currency) -> Option<Decimal> {
+    let collateral_volatility = hourly_volatility(collateral)?;
+    let debt_volatility = hourly_volatility(debt)?;
+    (collateral_volatility * collateral_volatility + debt_volatility * debt_volatility).sqrt()
+}
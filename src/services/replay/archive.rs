@@ -0,0 +1,358 @@
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use evian::{
+    utils::indexer::handler::StarknetEventMetadata, vesu::v2::data::indexer::events::VesuEvent,
+};
+use rusqlite::types::{Type, Value};
+use rusqlite::{Connection, OpenFlags, Row, params, params_from_iter};
+use rust_decimal::Decimal;
+use starknet::core::types::Felt;
+use tokio::sync::mpsc;
+
+use crate::services::indexer::IndexedEvent;
+use crate::services::replay::EventSink;
+use crate::types::pool::PoolName;
+
+/// The SQLite archive of the indexed events, in the state directory.
+pub const EVENT_ARCHIVE_FILE: &str = "events.sqlite";
+
+/// Maximum number of writes of a single transaction.
+const MAX_BATCH_WRITES: usize = 1_000;
+
+/// The raw Vesu events, Felts as hex & decimals as strings, to not lose any
+/// precision. `event_index` is the index of the event among the Vesu events
+/// of its block: an event indexed again, e.g after a failover, replaces the
+/// archived one. The context events - e.g the parameter changes of a pool -
+/// have no user, assets nor deltas.
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS vesu_events (
+    block_number INTEGER NOT NULL,
+    event_index INTEGER NOT NULL,
+    transaction_hash TEXT NOT NULL,
+    pool TEXT NOT NULL,
+    pool_address TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    user TEXT,
+    collateral TEXT,
+    debt TEXT,
+    collateral_delta TEXT,
+    debt_delta TEXT,
+    raw TEXT NOT NULL,
+    received_at_ms INTEGER NOT NULL,
+    PRIMARY KEY (block_number, event_index)
+);
+CREATE INDEX IF NOT EXISTS vesu_events_by_user ON vesu_events (user, block_number);
+";
+
+/// The events of the archive to read.
+#[derive(Debug, Clone, Default)]
+pub struct EventFilter {
+    pub pool: Option<PoolName>,
+    pub user: Option<Felt>,
+    /// First block of the events.
+    pub since_block: Option<u64>,
+    pub limit: usize,
+}
+
+/// The kind of an archived Vesu event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, strum::Display, strum::EnumString)]
+#[strum(serialize_all = "snake_case")]
+pub enum ArchivedEventKind {
+    Position,
+    Liquidation,
+    /// E.g a change of the parameters of a pool.
+    Context,
+}
+
+/// The position changed by an archived event, its deltas in the scale of Vesu.
+#[derive(Debug, Clone)]
+pub struct ArchivedDelta {
+    pub user: String,
+    pub collateral: String,
+    pub debt: String,
+    pub collateral_delta: Decimal,
+    pub debt_delta: Decimal,
+}
+
+/// An event read from the archive.
+#[derive(Debug, Clone)]
+pub struct ArchivedEvent {
+    pub block_number: u64,
+    pub event_index: u64,
+    pub transaction_hash: String,
+    /// The pool name, or its address if unknown when archived.
+    pub pool: String,
+    pub kind: ArchivedEventKind,
+    /// None for the context events.
+    pub delta: Option<ArchivedDelta>,
+    /// The event as decoded from the chain.
+    pub raw: String,
+}
+
+/// A write of the archive, batched in a transaction by the writer.
+enum ArchiveWrite {
+    Event(ArchivedRow),
+    /// Drops the events of the blocks after this one, re-orged.
+    Invalidate(u64),
+}
+
+struct ArchivedRow {
+    block_number: u64,
+    transaction_hash: Felt,
+    pool_address: Felt,
+    kind: ArchivedEventKind,
+    /// (user, collateral, debt, collateral delta, debt delta).
+    delta: Option<(Felt, Felt, Felt, Decimal, Decimal)>,
+    raw: String,
+    received_at_ms: i64,
+}
+
+/// Archives every raw Vesu event into a SQLite database, so the history of a
+/// position can be queried with `events query` or any SQLite client. The
+/// events are sent to a writer thread, inserting them in batches.
+pub struct ArchiveSink {
+    tx_writes: mpsc::UnboundedSender<ArchiveWrite>,
+}
+
+impl ArchiveSink {
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Could not create directory {}", dir.display()))?;
+        }
+        let connection = Connection::open(path)
+            .with_context(|| format!("Could not open the event archive {}", path.display()))?;
+        // Readers, e.g `events query`, don't block the indexer.
+        connection.pragma_update(None, "journal_mode", "WAL")?;
+        connection.pragma_update(None, "synchronous", "NORMAL")?;
+        connection.execute_batch(SCHEMA)?;
+
+        let (tx_writes, rx_writes) = mpsc::unbounded_channel();
+        std::thread::Builder::new()
+            .name("event-archive".into())
+            .spawn(move || write_forever(connection, rx_writes))?;
+        Ok(Self { tx_writes })
+    }
+
+    fn send(&self, write: ArchiveWrite) -> Result<()> {
+        self.tx_writes
+            .send(write)
+            .map_err(|_| anyhow::anyhow!("The event archive writer stopped"))
+    }
+}
+
+impl EventSink for ArchiveSink {
+    /// The raw events are archived instead.
+    fn record(&mut self, _event: &IndexedEvent) -> Result<()> {
+        Ok(())
+    }
+
+    fn record_raw(&mut self, metadata: &StarknetEventMetadata, event: &VesuEvent) -> Result<()> {
+        let received_at_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as i64)
+            .unwrap_or_default();
+        let (kind, delta) = match event {
+            VesuEvent::Position(position) => (
+                ArchivedEventKind::Position,
+                Some((
+                    position.event_metadata.user_address.0,
+                    position.event_metadata.collateral_address,
+                    position.event_metadata.debt_address,
+                    position.collateral_delta,
+                    position.debt_delta,
+                )),
+            ),
+            VesuEvent::Liquidation(liquidation) => (
+                ArchivedEventKind::Liquidation,
+                Some((
+                    liquidation.event_metadata.user_address.0,
+                    liquidation.event_metadata.collateral_address,
+                    liquidation.event_metadata.debt_address,
+                    liquidation.collateral_delta,
+                    liquidation.debt_delta,
+                )),
+            ),
+            VesuEvent::Context(_) => (ArchivedEventKind::Context, None),
+        };
+
+        self.send(ArchiveWrite::Event(ArchivedRow {
+            block_number: metadata.block_number,
+            transaction_hash: metadata.transaction_hash,
+            pool_address: metadata.from_address,
+            kind,
+            delta,
+            raw: format!("{event:?}"),
+            received_at_ms,
+        }))
+    }
+
+    fn invalidate(&mut self, last_valid_block: u64) -> Result<()> {
+        self.send(ArchiveWrite::Invalidate(last_valid_block))
+    }
+}
+
+/// Writes the archive until the sink is dropped, each batch of the pending
+/// writes in a single transaction.
+fn write_forever(mut connection: Connection, mut rx_writes: mpsc::UnboundedReceiver<ArchiveWrite>) {
+    // Index of the next event of the last block written.
+    let mut next_index: Option<(u64, u64)> = None;
+    while let Some(first) = rx_writes.blocking_recv() {
+        let mut batch = vec![first];
+        while batch.len() < MAX_BATCH_WRITES
+            && let Ok(write) = rx_writes.try_recv()
+        {
+            batch.push(write);
+        }
+        if let Err(e) = write_batch(&mut connection, &batch, &mut next_index) {
+            tracing::error!(
+                "[📼 Replay] Could not archive {} events: {e:#}",
+                batch.len()
+            );
+        }
+    }
+}
+
+fn write_batch(
+    connection: &mut Connection,
+    batch: &[ArchiveWrite],
+    next_index: &mut Option<(u64, u64)>,
+) -> Result<()> {
+    let transaction = connection.transaction()?;
+    {
+        let mut insert = transaction.prepare_cached(
+            "INSERT OR REPLACE INTO vesu_events (block_number, event_index, transaction_hash, \
+             pool, pool_address, kind, user, collateral, debt, collateral_delta, debt_delta, raw, \
+             received_at_ms) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+        )?;
+        for write in batch {
+            match write {
+                ArchiveWrite::Event(row) => {
+                    // The indexer always restarts from the beginning of a block.
+                    let event_index = match *next_index {
+                        Some((block_number, index)) if block_number == row.block_number => index,
+                        _ => 0,
+                    };
+                    *next_index = Some((row.block_number, event_index + 1));
+
+                    let pool = PoolName::try_from(&row.pool_address)
+                        .map(|pool| pool.to_string())
+                        .unwrap_or_else(|_| format!("{:#x}", row.pool_address));
+                    let hex = |felt: Felt| format!("{felt:#x}");
+                    insert.execute(params![
+                        row.block_number as i64,
+                        event_index as i64,
+                        format!("{:#064x}", row.transaction_hash),
+                        pool,
+                        hex(row.pool_address),
+                        row.kind.to_string(),
+                        row.delta.map(|(user, ..)| hex(user)),
+                        row.delta.map(|(_, collateral, ..)| hex(collateral)),
+                        row.delta.map(|(_, _, debt, ..)| hex(debt)),
+                        row.delta
+                            .map(|(.., collateral_delta, _)| collateral_delta.to_string()),
+                        row.delta.map(|(.., debt_delta)| debt_delta.to_string()),
+                        &row.raw,
+                        row.received_at_ms,
+                    ])?;
+                }
+                ArchiveWrite::Invalidate(last_valid_block) => {
+                    let removed = transaction.execute(
+                        "DELETE FROM vesu_events WHERE block_number > ?1",
+                        params![*last_valid_block as i64],
+                    )?;
+                    *next_index = None;
+                    if removed > 0 {
+                        tracing::info!(
+                            "[📼 Replay] Removed {removed} re-orged events after block #{last_valid_block} from the archive"
+                        );
+                    }
+                }
+            }
+        }
+    }
+    transaction.commit()?;
+    Ok(())
+}
+
+/// Reads the archive, e.g from `events query`.
+pub struct EventArchive {
+    connection: Connection,
+}
+
+impl EventArchive {
+    /// Opens the archive of a running bot without writing to it.
+    pub fn open_read_only(path: &Path) -> Result<Self> {
+        let connection = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .with_context(|| format!("Could not open the event archive {}", path.display()))?;
+        Ok(Self { connection })
+    }
+
+    /// Returns the archived events matching the filter, by block.
+    pub fn query(&self, filter: &EventFilter) -> Result<Vec<ArchivedEvent>> {
+        let mut conditions = Vec::new();
+        let mut values = Vec::new();
+        if let Some(pool) = filter.pool {
+            conditions.push("pool = ?");
+            values.push(Value::Text(pool.to_string()));
+        }
+        if let Some(user) = filter.user {
+            conditions.push("user = ?");
+            values.push(Value::Text(format!("{user:#x}")));
+        }
+        if let Some(since_block) = filter.since_block {
+            conditions.push("block_number >= ?");
+            values.push(Value::Integer(since_block as i64));
+        }
+        let conditions = if conditions.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", conditions.join(" AND "))
+        };
+        values.push(Value::Integer(filter.limit as i64));
+
+        let mut statement = self.connection.prepare(&format!(
+            "SELECT block_number, event_index, transaction_hash, pool, kind, user, collateral, \
+             debt, collateral_delta, debt_delta, raw FROM vesu_events {conditions} \
+             ORDER BY block_number, event_index LIMIT ?"
+        ))?;
+        let events = statement
+            .query_map(params_from_iter(values), |row| {
+                let kind: String = row.get(4)?;
+                let user: Option<String> = row.get(5)?;
+                let delta = match user {
+                    Some(user) => Some(ArchivedDelta {
+                        user,
+                        collateral: row.get(6)?,
+                        debt: row.get(7)?,
+                        collateral_delta: decimal_column(row, 8)?,
+                        debt_delta: decimal_column(row, 9)?,
+                    }),
+                    None => None,
+                };
+                Ok(ArchivedEvent {
+                    block_number: row.get::<_, i64>(0)? as u64,
+                    event_index: row.get::<_, i64>(1)? as u64,
+                    transaction_hash: row.get(2)?,
+                    pool: row.get(3)?,
+                    kind: ArchivedEventKind::from_str(&kind).map_err(|e| {
+                        rusqlite::Error::FromSqlConversionFailure(4, Type::Text, Box::new(e))
+                    })?,
+                    delta,
+                    raw: row.get(10)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(events)
+    }
+}
+
+fn decimal_column(row: &Row, index: usize) -> rusqlite::Result<Decimal> {
+    let value: String = row.get(index)?;
+    Decimal::from_str(&value)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(index, Type::Text, Box::new(e)))
+}
@@ -0,0 +1,176 @@
+pub mod archive;
+pub mod parquet;
+pub mod task;
+
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use evian::{
+    utils::indexer::handler::StarknetEventMetadata, vesu::v2::data::indexer::events::VesuEvent,
+};
+use pragma_common::starknet::FallbackProvider;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{mpsc, oneshot};
+
+use crate::services::indexer::{EventMetadata, IndexedEvent, PositionDelta};
+use crate::services::replay::archive::ArchiveSink;
+use crate::services::replay::parquet::ParquetSink;
+
+/// A line of a recording file (JSON lines format).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedEvent {
+    pub metadata: EventMetadata,
+    pub delta: PositionDelta,
+}
+
+impl From<RecordedEvent> for IndexedEvent {
+    fn from(value: RecordedEvent) -> Self {
+        (value.metadata, value.delta)
+    }
+}
+
+/// Receives every indexed event, e.g to record it.
+pub trait EventSink: Send {
+    fn record(&mut self, event: &IndexedEvent) -> Result<()>;
+
+    /// Receives every Vesu event streamed, as decoded from the chain - including
+    /// the ones not sent to the monitoring, e.g the context events.
+    fn record_raw(&mut self, _metadata: &StarknetEventMetadata, _event: &VesuEvent) -> Result<()> {
+        Ok(())
+    }
+
+    /// The blocks after `last_valid_block` got re-orged: their events are not
+    /// part of the chain anymore.
+    fn invalidate(&mut self, _last_valid_block: u64) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Where the indexed events get recorded.
+#[derive(Debug, Clone, Default)]
+pub struct RecordingConfig {
+    /// JSON lines file, replayable with `--replay`.
+    pub events_path: Option<PathBuf>,
+    /// Directory of the Parquet files, for offline analytics.
+    pub parquet_dir: Option<PathBuf>,
+    /// SQLite archive of the raw Vesu events, queried with `events query`.
+    pub archive_path: Option<PathBuf>,
+}
+
+impl RecordingConfig {
+    pub fn open_sinks(&self, provider: &FallbackProvider) -> Result<Vec<Box<dyn EventSink>>> {
+        let mut sinks: Vec<Box<dyn EventSink>> = Vec::new();
+        if let Some(events_path) = &self.events_path {
+            sinks.push(Box::new(EventRecorder::open(events_path)?));
+        }
+        if let Some(parquet_dir) = &self.parquet_dir {
+            sinks.push(Box::new(ParquetSink::open(parquet_dir.clone(), provider)?));
+        }
+        if let Some(archive_path) = &self.archive_path {
+            sinks.push(Box::new(ArchiveSink::open(archive_path)?));
+        }
+        Ok(sinks)
+    }
+}
+
+/// Appends every indexed event to a JSON lines file so that it can be replayed
+/// later using `--replay`.
+pub struct EventRecorder {
+    writer: BufWriter<File>,
+}
+
+impl EventRecorder {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Could not open recording file {}", path.display()))?;
+
+        Ok(Self {
+            writer: BufWriter::new(file),
+        })
+    }
+}
+
+impl EventSink for EventRecorder {
+    fn record(&mut self, (metadata, delta): &IndexedEvent) -> Result<()> {
+        let recorded = RecordedEvent {
+            metadata: metadata.clone(),
+            delta: delta.clone(),
+        };
+        serde_json::to_writer(&mut self.writer, &recorded)?;
+        self.writer.write_all(b"\n")?;
+        // Flush every event: a recording is mostly useful when the bot crashed.
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Reads a recording file and sends its events to the monitoring service, as the
+/// indexer would do.
+pub struct ReplayService {
+    path: PathBuf,
+    tx_to_monitoring: mpsc::UnboundedSender<IndexedEvent>,
+    meet_with_monitoring: Option<oneshot::Sender<()>>,
+}
+
+impl ReplayService {
+    pub fn new(
+        path: PathBuf,
+        tx_to_monitoring: mpsc::UnboundedSender<IndexedEvent>,
+        meet_with_monitoring: oneshot::Sender<()>,
+    ) -> Self {
+        Self {
+            path,
+            tx_to_monitoring,
+            meet_with_monitoring: Some(meet_with_monitoring),
+        }
+    }
+
+    pub async fn run_forever(mut self) -> Result<()> {
+        let events = Self::read_events(&self.path)?;
+
+        tracing::info!(
+            "[📼 Replay] Replaying {} events from {}",
+            events.len(),
+            self.path.display()
+        );
+
+        for event in events {
+            self.tx_to_monitoring.send(event.into())?;
+        }
+
+        tracing::info!("[📼 Replay] 🥳 All events replayed!");
+
+        if let Some(meet_with_monitoring) = self.meet_with_monitoring.take() {
+            meet_with_monitoring
+                .send(())
+                .expect("Rendezvous from Replay dropped?");
+        }
+
+        // Keep the monitoring running on the replayed state.
+        std::future::pending::<()>().await;
+        Ok(())
+    }
+
+    /// Reads all the events of a recording file.
+    pub fn read_events(path: &Path) -> Result<Vec<RecordedEvent>> {
+        let file = File::open(path)
+            .with_context(|| format!("Could not open replay file {}", path.display()))?;
+
+        BufReader::new(file)
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.as_ref().is_ok_and(|l| l.trim().is_empty()))
+            .map(|(i, line)| {
+                let line = line?;
+                serde_json::from_str(&line)
+                    .with_context(|| format!("Invalid event at line {} of the replay file", i + 1))
+            })
+            .collect()
+    }
+}
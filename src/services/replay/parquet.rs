@@ -0,0 +1,299 @@
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use arrow_array::{ArrayRef, BooleanArray, RecordBatch, StringArray, UInt64Array};
+use arrow_schema::{DataType, Field, Schema};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use pragma_common::starknet::FallbackProvider;
+use starknet::core::types::{BlockId, MaybePreConfirmedBlockWithTxHashes};
+use starknet::providers::Provider;
+use tokio::sync::mpsc;
+
+use crate::services::indexer::IndexedEvent;
+use crate::services::replay::EventSink;
+use crate::utils::rpc::{RpcPath, guarded_starknet};
+
+/// Events buffered before writing the files.
+const MAX_BUFFERED_EVENTS: usize = 10_000;
+/// Maximum age of the buffered events before writing the files.
+const MAX_BUFFER_AGE: Duration = Duration::from_secs(10 * 60);
+/// Number of block timestamps kept, the events coming in block order.
+const MAX_CACHED_TIMESTAMPS: usize = 10_000;
+
+/// Writes the indexed events - position deltas & liquidations, flagged by
+/// `is_liquidation` - to Parquet files partitioned by day of their block:
+/// `<dir>/date=YYYY-MM-DD/events-<first block>-<received at ms>.parquet`.
+///
+/// The events are sent to a writer task, reading the timestamps of their
+/// blocks & writing the files on the blocking threads, so the indexer never
+/// waits for the disk. Each file is complete once written, so a crash loses
+/// the buffered events only: at most `MAX_BUFFERED_EVENTS` or
+/// `MAX_BUFFER_AGE` of them.
+pub struct ParquetSink {
+    tx_rows: mpsc::UnboundedSender<EventRow>,
+}
+
+struct EventRow {
+    /// Unix timestamp, in milliseconds.
+    received_at: u64,
+    /// Unix timestamp of the block, in seconds - read by the writer.
+    block_timestamp: u64,
+    block_number: u64,
+    event_index: Option<u64>,
+    transaction_hash: Option<String>,
+    pool: String,
+    is_liquidation: bool,
+    collateral: String,
+    debt: String,
+    user: String,
+    collateral_delta: String,
+    debt_delta: String,
+}
+
+impl ParquetSink {
+    pub fn open(dir: PathBuf, provider: &FallbackProvider) -> Result<Self> {
+        fs::create_dir_all(&dir)
+            .with_context(|| format!("Could not create the Parquet directory {}", dir.display()))?;
+
+        let (tx_rows, rx_rows) = mpsc::unbounded_channel();
+        let writer = ParquetWriter {
+            dir,
+            provider: provider.clone(),
+            rows: Vec::new(),
+            buffered_since: None,
+            block_timestamps: BTreeMap::new(),
+        };
+        tokio::spawn(writer.run(rx_rows));
+        Ok(Self { tx_rows })
+    }
+}
+
+/// Buffers the events & writes them, until the sink is dropped.
+struct ParquetWriter {
+    dir: PathBuf,
+    provider: FallbackProvider,
+    rows: Vec<EventRow>,
+    buffered_since: Option<Instant>,
+    block_timestamps: BTreeMap<u64, u64>,
+}
+
+impl ParquetWriter {
+    /// How often the age of the buffered events is checked.
+    const FLUSH_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+    async fn run(mut self, mut rx_rows: mpsc::UnboundedReceiver<EventRow>) {
+        let mut flush_interval = tokio::time::interval(Self::FLUSH_CHECK_INTERVAL);
+        loop {
+            tokio::select! {
+                row = rx_rows.recv() => {
+                    let Some(row) = row else {
+                        break;
+                    };
+                    self.rows.push(row);
+                    self.buffered_since.get_or_insert_with(Instant::now);
+                    if self.rows.len() >= MAX_BUFFERED_EVENTS {
+                        self.flush().await;
+                    }
+                }
+                _ = flush_interval.tick() => {
+                    if self.buffered_since.is_some_and(|since| since.elapsed() >= MAX_BUFFER_AGE) {
+                        self.flush().await;
+                    }
+                }
+            }
+        }
+        self.flush().await;
+    }
+
+    /// Writes the buffered events to a new file per day of their blocks.
+    async fn flush(&mut self) {
+        if self.rows.is_empty() {
+            return;
+        }
+        let rows = std::mem::take(&mut self.rows);
+        self.buffered_since = None;
+
+        let mut days: BTreeMap<u64, Vec<EventRow>> = BTreeMap::new();
+        for mut row in rows {
+            row.block_timestamp = self.block_timestamp(&row).await;
+            days.entry(row.block_timestamp / 86_400)
+                .or_default()
+                .push(row);
+        }
+
+        for rows in days.into_values() {
+            let dir = self.dir.clone();
+            let written = tokio::task::spawn_blocking(move || write_file(&dir, &rows)).await;
+            match written {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => {
+                    tracing::error!("[📼 Replay] Could not write the events to Parquet: {e:#}")
+                }
+                Err(e) => tracing::error!("[📼 Replay] The Parquet writer panicked: {e}"),
+            }
+        }
+    }
+
+    /// The timestamp of the block of the event, the time it was received if
+    /// it could not be read.
+    async fn block_timestamp(&mut self, row: &EventRow) -> u64 {
+        if let Some(timestamp) = self.block_timestamps.get(&row.block_number) {
+            return *timestamp;
+        }
+        let block = guarded_starknet(&self.provider, RpcPath::Background, |node| {
+            node.get_block_with_tx_hashes(BlockId::Number(row.block_number))
+        })
+        .await;
+        let timestamp = match block {
+            Ok(MaybePreConfirmedBlockWithTxHashes::Block(block)) => block.timestamp,
+            Ok(MaybePreConfirmedBlockWithTxHashes::PreConfirmedBlock(block)) => block.timestamp,
+            Err(e) => {
+                tracing::warn!(
+                    "[📼 Replay] Could not read the timestamp of block #{}, partitioning its events by reception: {e:#}",
+                    row.block_number
+                );
+                return row.received_at / 1_000;
+            }
+        };
+        self.block_timestamps.insert(row.block_number, timestamp);
+        if self.block_timestamps.len() > MAX_CACHED_TIMESTAMPS {
+            self.block_timestamps.pop_first();
+        }
+        timestamp
+    }
+}
+
+/// Writes the events of a single day to a new file.
+fn write_file(dir: &Path, rows: &[EventRow]) -> Result<()> {
+    let Some(first) = rows.first() else {
+        return Ok(());
+    };
+
+    let day_dir = dir.join(format!("date={}", utc_date(first.block_timestamp)));
+    fs::create_dir_all(&day_dir)?;
+    let path = day_dir.join(format!(
+        "events-{}-{}.parquet",
+        first.block_number, first.received_at
+    ));
+
+    let batch = record_batch(rows)?;
+    let properties = WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .build();
+    let file =
+        File::create(&path).with_context(|| format!("Could not create {}", path.display()))?;
+    let mut writer = ArrowWriter::try_new(file, batch.schema(), Some(properties))?;
+    writer.write(&batch)?;
+    writer.close()?;
+
+    tracing::debug!(
+        "[📼 Replay] Wrote {} events to {}",
+        rows.len(),
+        path.display()
+    );
+    Ok(())
+}
+
+fn record_batch(rows: &[EventRow]) -> Result<RecordBatch> {
+    // Felts as hex & decimals as strings, to not lose any precision.
+    let schema = Schema::new(vec![
+        Field::new("received_at_ms", DataType::UInt64, false),
+        Field::new("block_timestamp", DataType::UInt64, false),
+        Field::new("block_number", DataType::UInt64, false),
+        Field::new("event_index", DataType::UInt64, true),
+        Field::new("transaction_hash", DataType::Utf8, true),
+        Field::new("pool", DataType::Utf8, false),
+        Field::new("is_liquidation", DataType::Boolean, false),
+        Field::new("collateral", DataType::Utf8, false),
+        Field::new("debt", DataType::Utf8, false),
+        Field::new("user", DataType::Utf8, false),
+        Field::new("collateral_delta", DataType::Utf8, false),
+        Field::new("debt_delta", DataType::Utf8, false),
+    ]);
+
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(UInt64Array::from_iter_values(
+            rows.iter().map(|r| r.received_at),
+        )),
+        Arc::new(UInt64Array::from_iter_values(
+            rows.iter().map(|r| r.block_timestamp),
+        )),
+        Arc::new(UInt64Array::from_iter_values(
+            rows.iter().map(|r| r.block_number),
+        )),
+        Arc::new(UInt64Array::from_iter(rows.iter().map(|r| r.event_index))),
+        Arc::new(StringArray::from_iter(
+            rows.iter().map(|r| r.transaction_hash.as_deref()),
+        )),
+        Arc::new(StringArray::from_iter_values(rows.iter().map(|r| &r.pool))),
+        Arc::new(BooleanArray::from_iter(
+            rows.iter().map(|r| Some(r.is_liquidation)),
+        )),
+        Arc::new(StringArray::from_iter_values(
+            rows.iter().map(|r| &r.collateral),
+        )),
+        Arc::new(StringArray::from_iter_values(rows.iter().map(|r| &r.debt))),
+        Arc::new(StringArray::from_iter_values(rows.iter().map(|r| &r.user))),
+        Arc::new(StringArray::from_iter_values(
+            rows.iter().map(|r| &r.collateral_delta),
+        )),
+        Arc::new(StringArray::from_iter_values(
+            rows.iter().map(|r| &r.debt_delta),
+        )),
+    ];
+
+    Ok(RecordBatch::try_new(Arc::new(schema), columns)?)
+}
+
+impl EventSink for ParquetSink {
+    fn record(&mut self, (metadata, delta): &IndexedEvent) -> Result<()> {
+        let received_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or_default();
+
+        self.tx_rows
+            .send(EventRow {
+                received_at,
+                block_timestamp: 0,
+                block_number: metadata.block_number,
+                event_index: metadata.event_index,
+                transaction_hash: metadata.transaction_hash.map(|tx| format!("{tx:#064x}")),
+                pool: format!("{:#x}", metadata.from_address),
+                is_liquidation: metadata.is_liquidation,
+                collateral: format!("{:#x}", delta.collateral_address),
+                debt: format!("{:#x}", delta.debt_address),
+                user: format!("{:#x}", delta.user_address),
+                collateral_delta: delta.collateral_delta.to_string(),
+                debt_delta: delta.debt_delta.to_string(),
+            })
+            .context("The Parquet writer stopped")?;
+        Ok(())
+    }
+}
+
+/// Returns the UTC date of a unix timestamp, cf: `2025-01-31`.
+fn utc_date(unix_secs: u64) -> String {
+    // Civil from days, from http://howardhinnant.github.io/date_algorithms.html
+    let days = (unix_secs / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day:02}")
+}
@@ -0,0 +1,49 @@
+use std::path::PathBuf;
+
+use pragma_common::services::{Service, ServiceRunner};
+use tokio::sync::{mpsc, oneshot};
+
+use crate::services::{indexer::IndexedEvent, replay::ReplayService};
+
+pub struct ReplayTask {
+    path: PathBuf,
+    tx_to_monitoring: mpsc::UnboundedSender<IndexedEvent>,
+    meet_with_monitoring: Option<oneshot::Sender<()>>,
+}
+
+impl ReplayTask {
+    pub fn new(
+        path: PathBuf,
+        tx_to_monitoring: mpsc::UnboundedSender<IndexedEvent>,
+        meet_with_monitoring: oneshot::Sender<()>,
+    ) -> Self {
+        Self {
+            path,
+            tx_to_monitoring,
+            meet_with_monitoring: Some(meet_with_monitoring),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Service for ReplayTask {
+    async fn start<'a>(&mut self, mut runner: ServiceRunner<'a>) -> anyhow::Result<()> {
+        let path = self.path.clone();
+        let tx_to_monitoring = self.tx_to_monitoring.clone();
+        let meet_with_monitoring = self
+            .meet_with_monitoring
+            .take()
+            .expect("ReplayTask cannot be launched twice");
+
+        runner.spawn_loop(move |ctx| async move {
+            let replay_service = ReplayService::new(path, tx_to_monitoring, meet_with_monitoring);
+            if let Some(result) = ctx.run_until_cancelled(replay_service.run_forever()).await {
+                result?;
+            }
+
+            anyhow::Ok(())
+        });
+
+        Ok(())
+    }
+}
@@ -0,0 +1,397 @@
+pub mod task;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::{Context, Result, anyhow};
+use redis::aio::MultiplexedConnection;
+use redis::streams::{StreamMaxlen, StreamRangeReply, StreamReadOptions, StreamReadReply};
+use redis::{AsyncCommands, ToRedisArgs};
+use tokio::sync::{mpsc, oneshot};
+use url::Url;
+
+use crate::services::indexer::lag::INDEXER_LAG;
+use crate::services::indexer::{EventId, EventMetadata, IndexedEvent};
+use crate::services::replay::RecordedEvent;
+
+/// Field of the stream entries holding an indexed event, as a JSON `RecordedEvent`.
+const EVENT_FIELD: &str = "event";
+/// Field of the entry published once the indexer caught up with the chain.
+const SYNCED_FIELD: &str = "synced";
+/// Field of the entries holding the last block finalized by the indexer.
+const FINALIZED_FIELD: &str = "finalized";
+
+/// Which services the process runs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ProcessRole {
+    /// The indexer & the monitoring, connected in-process.
+    #[default]
+    All,
+    /// Only the indexer, publishing the events to the event stream.
+    Indexer,
+    /// Only the monitoring, consuming the events from the event stream.
+    Monitor,
+}
+
+/// The id of an entry of the event stream, `<block>-<sequence>`: the entries
+/// of the events of a block get its number, so a monitoring can resume reading
+/// the stream from the block of its last applied event.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+struct StreamEntryId {
+    block_number: u64,
+    sequence: u64,
+}
+
+impl StreamEntryId {
+    /// The id of the entry following this one, for an event of `block_number`.
+    /// The ids only grow: an entry of an older block, e.g after a reorg, or
+    /// without a block keeps the block of the previous entry.
+    fn next(self, block_number: u64) -> Self {
+        if block_number > self.block_number {
+            Self {
+                block_number,
+                sequence: 0,
+            }
+        } else {
+            Self {
+                sequence: self.sequence + 1,
+                ..self
+            }
+        }
+    }
+
+    /// The id right before the entries of the block.
+    fn before_block(block_number: u64) -> Self {
+        match block_number.checked_sub(1) {
+            Some(previous_block) => Self {
+                block_number: previous_block,
+                sequence: u64::MAX,
+            },
+            None => Self::default(),
+        }
+    }
+}
+
+impl fmt::Display for StreamEntryId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.block_number, self.sequence)
+    }
+}
+
+impl FromStr for StreamEntryId {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (block_number, sequence) = s
+            .split_once('-')
+            .ok_or_else(|| anyhow!("Invalid stream entry id {s}"))?;
+        Ok(Self {
+            block_number: block_number.parse()?,
+            sequence: sequence.parse()?,
+        })
+    }
+}
+
+/// The Redis stream carrying the indexed events from the indexer process to the
+/// monitoring ones.
+#[derive(Debug, Clone)]
+pub struct EventStreamConfig {
+    pub url: Url,
+    pub key: String,
+    /// Approximate number of entries kept in the stream, the oldest ones being
+    /// trimmed.
+    pub max_len: usize,
+}
+
+impl EventStreamConfig {
+    /// Number of entries read at once when looking for the last event.
+    const SCAN_COUNT: usize = 100;
+
+    pub(crate) async fn connect(&self) -> Result<MultiplexedConnection> {
+        let client = redis::Client::open(self.url.as_str())?;
+        client
+            .get_multiplexed_async_connection()
+            .await
+            .with_context(|| format!("Could not connect to the event stream {}", self.url))
+    }
+
+    /// The key set while the indexer publishing to the stream is synced.
+    fn synced_key(&self) -> String {
+        format!("{}:synced", self.key)
+    }
+
+    /// The last event published to the stream, the indexer resuming from its
+    /// block. None if the stream has no event.
+    pub async fn last_published_event(&self) -> Result<Option<EventMetadata>> {
+        let mut connection = self.connect().await?;
+        let mut end = String::from("+");
+        loop {
+            let reply: StreamRangeReply = connection
+                .xrevrange_count(&self.key, &end, "-", Self::SCAN_COUNT)
+                .await
+                .context("Could not read the event stream")?;
+            let Some(oldest) = reply.ids.last() else {
+                return Ok(None);
+            };
+            for entry in &reply.ids {
+                if let Some(payload) = entry.get::<String>(EVENT_FIELD) {
+                    let event: RecordedEvent = serde_json::from_str(&payload)
+                        .with_context(|| format!("Invalid event {} in the stream", entry.id))?;
+                    return Ok(Some(event.metadata));
+                }
+            }
+            end = format!("({}", oldest.id);
+        }
+    }
+}
+
+/// Publishes the events of the indexer to the event stream, then an entry
+/// marking that the indexer is synced. The finalized block is published as its
+/// own entry every time it advances. The events up to the last one already in
+/// the stream are skipped, the indexer resuming from its block.
+pub struct StreamPublisher {
+    config: EventStreamConfig,
+    rx_from_indexer: mpsc::UnboundedReceiver<IndexedEvent>,
+    wait_for_indexer: Option<oneshot::Receiver<()>>,
+    last_published: Option<EventId>,
+    /// Id of the last entry of the stream.
+    last_entry_id: StreamEntryId,
+}
+
+impl StreamPublisher {
+    /// How often the finalized block of the indexer is checked.
+    const FINALIZED_INTERVAL: Duration = Duration::from_secs(5);
+
+    pub fn new(
+        config: EventStreamConfig,
+        rx_from_indexer: mpsc::UnboundedReceiver<IndexedEvent>,
+        wait_for_indexer: oneshot::Receiver<()>,
+        last_published: Option<EventId>,
+    ) -> Self {
+        Self {
+            config,
+            rx_from_indexer,
+            wait_for_indexer: Some(wait_for_indexer),
+            last_published,
+            last_entry_id: StreamEntryId::default(),
+        }
+    }
+
+    pub async fn run_forever(mut self) -> Result<()> {
+        let mut connection = self.config.connect().await?;
+        let last_entry: StreamRangeReply = connection
+            .xrevrange_count(&self.config.key, "+", "-", 1)
+            .await
+            .context("Could not read the event stream")?;
+        if let Some(entry) = last_entry.ids.first() {
+            self.last_entry_id = entry.id.parse()?;
+        }
+        // The monitoring waits for this indexer to sync.
+        let _: () = connection.del(self.config.synced_key()).await?;
+
+        let mut wait_for_indexer = self
+            .wait_for_indexer
+            .take()
+            .expect("StreamPublisher cannot be launched twice");
+        let mut is_synced = false;
+        let mut finalized_interval = tokio::time::interval(Self::FINALIZED_INTERVAL);
+        let mut published_finalized_block = 0;
+
+        loop {
+            tokio::select! {
+                event = self.rx_from_indexer.recv() => {
+                    let event = event.context("The indexer stopped")?;
+                    self.publish(&mut connection, event).await?;
+                }
+                synced = &mut wait_for_indexer, if !is_synced => {
+                    synced.context("Rendezvous from Indexer dropped?")?;
+                    // The events indexed before the sync come first.
+                    while let Ok(event) = self.rx_from_indexer.try_recv() {
+                        self.publish(&mut connection, event).await?;
+                    }
+                    self.add(&mut connection, SYNCED_FIELD, 1, 0).await?;
+                    let _: () = connection.set(self.config.synced_key(), 1).await?;
+                    tracing::info!("[📡 Stream] Indexer synced, the monitoring can start");
+                    is_synced = true;
+                }
+                _ = finalized_interval.tick() => {
+                    let Some(finalized_block) = INDEXER_LAG.finalized_block() else {
+                        continue;
+                    };
+                    if finalized_block <= published_finalized_block {
+                        continue;
+                    }
+                    // The events of the finalized blocks come first.
+                    while let Ok(event) = self.rx_from_indexer.try_recv() {
+                        self.publish(&mut connection, event).await?;
+                    }
+                    self.add(&mut connection, FINALIZED_FIELD, finalized_block, 0)
+                        .await
+                        .context("Could not publish the finalized block to the event stream")?;
+                    published_finalized_block = finalized_block;
+                }
+            }
+        }
+    }
+
+    async fn publish(
+        &mut self,
+        connection: &mut MultiplexedConnection,
+        (metadata, delta): IndexedEvent,
+    ) -> Result<()> {
+        // The backfilled events are behind the last published one.
+        let already_published = !metadata.is_backfill
+            && metadata
+                .event_id()
+                .zip(self.last_published)
+                .is_some_and(|(event_id, last_published)| event_id <= last_published);
+        if already_published {
+            return Ok(());
+        }
+
+        let block_number = metadata.block_number;
+        let payload = serde_json::to_string(&RecordedEvent { metadata, delta })?;
+        self.add(connection, EVENT_FIELD, payload, block_number)
+            .await
+            .context("Could not publish an event to the event stream")
+    }
+
+    /// Adds an entry to the stream, trimming its oldest ones.
+    async fn add(
+        &mut self,
+        connection: &mut MultiplexedConnection,
+        field: &str,
+        value: impl ToRedisArgs,
+        block_number: u64,
+    ) -> Result<()> {
+        let id = self.last_entry_id.next(block_number);
+        let _: String = connection
+            .xadd_maxlen(
+                &self.config.key,
+                StreamMaxlen::Approx(self.config.max_len),
+                id.to_string(),
+                &[(field, value)],
+            )
+            .await?;
+        self.last_entry_id = id;
+        Ok(())
+    }
+}
+
+/// Consumes the events of the event stream & forwards them to the monitoring,
+/// from the block of its last applied event - the start of the stream without
+/// one. The events already applied are skipped by the monitoring.
+/// The finalized blocks of the stream are applied to the `INDEXER_LAG`, so the
+/// monitoring prunes & checkpoints its state as with an in-process indexer.
+pub struct StreamSubscriber {
+    config: EventStreamConfig,
+    tx_to_monitoring: mpsc::UnboundedSender<IndexedEvent>,
+    meet_with_monitoring: Option<oneshot::Sender<()>>,
+    /// Block of the last event applied by the monitoring.
+    resume_block: Option<u64>,
+}
+
+impl StreamSubscriber {
+    /// Maximum time an `XREAD` waits for new entries.
+    const BLOCK_MS: usize = 5_000;
+    const BATCH_SIZE: usize = 1_000;
+
+    pub fn new(
+        config: EventStreamConfig,
+        tx_to_monitoring: mpsc::UnboundedSender<IndexedEvent>,
+        meet_with_monitoring: oneshot::Sender<()>,
+        resume_block: Option<u64>,
+    ) -> Self {
+        Self {
+            config,
+            tx_to_monitoring,
+            meet_with_monitoring: Some(meet_with_monitoring),
+            resume_block,
+        }
+    }
+
+    pub async fn run_forever(mut self) -> Result<()> {
+        let mut connection = self.config.connect().await?;
+        let options = StreamReadOptions::default()
+            .block(Self::BLOCK_MS)
+            .count(Self::BATCH_SIZE);
+        let resume_id = self
+            .resume_block
+            .map_or_else(StreamEntryId::default, StreamEntryId::before_block);
+        self.ensure_not_trimmed(&mut connection, resume_id).await?;
+        if resume_id != StreamEntryId::default() {
+            tracing::info!(
+                "[📡 Stream] Resuming the event stream from block #{}",
+                resume_id.block_number + 1
+            );
+        }
+        let mut last_id = resume_id.to_string();
+
+        loop {
+            let reply: StreamReadReply = connection
+                .xread_options(&[&self.config.key], &[&last_id], &options)
+                .await
+                .context("Could not read the event stream")?;
+
+            // Caught up with the stream: the monitoring can start once the
+            // indexer is synced, its synced entry possibly being trimmed or
+            // before the resumed block.
+            if reply.keys.iter().all(|key| key.ids.is_empty())
+                && self.meet_with_monitoring.is_some()
+                && connection.exists(self.config.synced_key()).await?
+                && let Some(meet_with_monitoring) = self.meet_with_monitoring.take()
+            {
+                tracing::info!("[📡 Stream] Caught up with the synced indexer");
+                let _ = meet_with_monitoring.send(());
+            }
+
+            for entry in reply.keys.into_iter().flat_map(|key| key.ids) {
+                if let Some(payload) = entry.get::<String>(EVENT_FIELD) {
+                    let event: RecordedEvent = serde_json::from_str(&payload)
+                        .with_context(|| format!("Invalid event {} in the stream", entry.id))?;
+                    self.tx_to_monitoring.send(event.into())?;
+                } else if let Some(finalized_block) = entry.get::<u64>(FINALIZED_FIELD) {
+                    INDEXER_LAG.finalize(finalized_block);
+                } else if entry.get::<String>(SYNCED_FIELD).is_some()
+                    && let Some(meet_with_monitoring) = self.meet_with_monitoring.take()
+                {
+                    tracing::info!("[📡 Stream] Caught up with the synced indexer");
+                    let _ = meet_with_monitoring.send(());
+                }
+                last_id = entry.id;
+            }
+        }
+    }
+
+    /// Fails if entries past `resume_id` were trimmed from the stream: the
+    /// monitoring would miss their events.
+    async fn ensure_not_trimmed(
+        &self,
+        connection: &mut MultiplexedConnection,
+        resume_id: StreamEntryId,
+    ) -> Result<()> {
+        let exists: bool = connection.exists(&self.config.key).await?;
+        if !exists {
+            return Ok(());
+        }
+        let info: HashMap<String, redis::Value> = redis::cmd("XINFO")
+            .arg("STREAM")
+            .arg(&self.config.key)
+            .query_async(connection)
+            .await
+            .context("Could not read the event stream")?;
+        // Only reported since Redis 7.
+        let Some(max_deleted_id) = info.get("max-deleted-entry-id") else {
+            return Ok(());
+        };
+        let max_deleted_id: StreamEntryId =
+            redis::from_redis_value::<String>(max_deleted_id)?.parse()?;
+        anyhow::ensure!(
+            max_deleted_id <= resume_id,
+            "The event stream was trimmed up to {max_deleted_id}, past the state of the monitoring: restore a more recent --state-dir or raise --event-stream-max-len"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,106 @@
+use pragma_common::services::{Service, ServiceRunner};
+use tokio::sync::{mpsc, oneshot};
+
+use crate::services::{
+    indexer::{EventId, IndexedEvent},
+    stream::{EventStreamConfig, StreamPublisher, StreamSubscriber},
+};
+
+pub struct StreamPublisherTask {
+    config: EventStreamConfig,
+    rx_from_indexer: Option<mpsc::UnboundedReceiver<IndexedEvent>>,
+    wait_for_indexer: Option<oneshot::Receiver<()>>,
+    last_published: Option<EventId>,
+}
+
+impl StreamPublisherTask {
+    pub fn new(
+        config: EventStreamConfig,
+        rx_from_indexer: mpsc::UnboundedReceiver<IndexedEvent>,
+        wait_for_indexer: oneshot::Receiver<()>,
+        last_published: Option<EventId>,
+    ) -> Self {
+        Self {
+            config,
+            rx_from_indexer: Some(rx_from_indexer),
+            wait_for_indexer: Some(wait_for_indexer),
+            last_published,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Service for StreamPublisherTask {
+    async fn start<'a>(&mut self, mut runner: ServiceRunner<'a>) -> anyhow::Result<()> {
+        let config = self.config.clone();
+        let last_published = self.last_published;
+        let rx_from_indexer = self
+            .rx_from_indexer
+            .take()
+            .expect("StreamPublisherTask cannot be launched twice");
+        let wait_for_indexer = self
+            .wait_for_indexer
+            .take()
+            .expect("StreamPublisherTask cannot be launched twice");
+
+        runner.spawn_loop(move |ctx| async move {
+            let publisher =
+                StreamPublisher::new(config, rx_from_indexer, wait_for_indexer, last_published);
+            if let Some(result) = ctx.run_until_cancelled(publisher.run_forever()).await {
+                result?;
+            }
+
+            anyhow::Ok(())
+        });
+
+        Ok(())
+    }
+}
+
+pub struct StreamSubscriberTask {
+    config: EventStreamConfig,
+    tx_to_monitoring: mpsc::UnboundedSender<IndexedEvent>,
+    meet_with_monitoring: Option<oneshot::Sender<()>>,
+    resume_block: Option<u64>,
+}
+
+impl StreamSubscriberTask {
+    pub fn new(
+        config: EventStreamConfig,
+        tx_to_monitoring: mpsc::UnboundedSender<IndexedEvent>,
+        meet_with_monitoring: oneshot::Sender<()>,
+        resume_block: Option<u64>,
+    ) -> Self {
+        Self {
+            config,
+            tx_to_monitoring,
+            meet_with_monitoring: Some(meet_with_monitoring),
+            resume_block,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Service for StreamSubscriberTask {
+    async fn start<'a>(&mut self, mut runner: ServiceRunner<'a>) -> anyhow::Result<()> {
+        let config = self.config.clone();
+        let tx_to_monitoring = self.tx_to_monitoring.clone();
+        let resume_block = self.resume_block;
+        let meet_with_monitoring = self
+            .meet_with_monitoring
+            .take()
+            .expect("StreamSubscriberTask cannot be launched twice");
+
+        runner.spawn_loop(move |ctx| async move {
+            let subscriber =
+                StreamSubscriber::new(config, tx_to_monitoring, meet_with_monitoring, resume_block);
+            if let Some(result) = ctx.run_until_cancelled(subscriber.run_forever()).await {
+                result?;
+            }
+
+            anyhow::Ok(())
+        });
+
+        Ok(())
+    }
+}
@@ -0,0 +1,253 @@
+pub mod task;
+
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::Result;
+use cainome::cairo_serde::{CairoSerde, U256};
+use num_traits::Pow;
+use pragma_common::starknet::FallbackProvider;
+use rust_decimal::Decimal;
+use starknet::core::types::{Call, Felt};
+use starknet::macros::selector;
+
+use crate::bindings::liquidate_v1::Swap;
+use crate::config::addresses::AddressBook;
+use crate::config::onchain_assets::{AssetClass, ONCHAIN_ASSETS};
+use crate::services::audit::TxPurpose;
+use crate::services::monitoring::ekubo::get_ekubo_exact_input_swaps;
+use crate::services::oracle::vesu_prices::VESU_PRICES;
+use crate::types::account::StarknetAccount;
+use crate::types::currency::Currency;
+use crate::utils::erc20::{balance_of, transfer_call};
+use crate::utils::format::format_usd;
+use crate::utils::kill_switch::KillSwitch;
+use crate::utils::rpc::{RpcPath, RpcProvider, guarded};
+
+#[derive(Debug, Clone)]
+pub struct TreasuryConfig {
+    /// The asset every other collateral gets swept into.
+    pub settlement_asset: Currency,
+    /// The asset the collaterals of a class get swept into instead, e.g `btc`
+    /// into WBTC.
+    pub settlement_assets_per_class: HashMap<AssetClass, Currency>,
+    /// Minimum USD value of a balance before it gets swept.
+    pub sweep_threshold_usd: Decimal,
+    pub sweep_interval: Duration,
+    /// Kept to repay the liquidations from the inventory, never swept.
+    pub inventory_assets: HashSet<Currency>,
+    /// Pays the transaction fees, kept as the gas reserve & never swept.
+    pub fee_token: Currency,
+    pub kill_switch: KillSwitch,
+}
+
+/// Profit accounting of the sweeps, valued with the Vesu oracle prices.
+#[derive(Debug, Clone, Default)]
+pub struct TreasuryPnl {
+    /// USD value of all the collateral sold.
+    pub swept_usd: Decimal,
+    /// USD value of the settlement asset expected in return.
+    pub received_usd: Decimal,
+}
+
+impl TreasuryPnl {
+    pub fn pnl_usd(&self) -> Decimal {
+        self.received_usd - self.swept_usd
+    }
+}
+
+impl TreasuryConfig {
+    /// The asset the currency gets swept into.
+    pub fn settlement_asset_of(&self, currency: Currency) -> Currency {
+        self.settlement_assets_per_class
+            .get(&currency.class())
+            .copied()
+            .unwrap_or(self.settlement_asset)
+    }
+
+    /// Returns true if the currency is never swept: a settlement asset, an
+    /// inventory asset or the fee token.
+    pub fn is_kept(&self, currency: Currency) -> bool {
+        self.is_settlement_asset(currency)
+            || self.inventory_assets.contains(&currency)
+            || currency.is(self.fee_token)
+    }
+
+    /// Returns true if the currency is one of the settlement assets, never swept.
+    pub fn is_settlement_asset(&self, currency: Currency) -> bool {
+        currency.is(self.settlement_asset)
+            || self
+                .settlement_assets_per_class
+                .values()
+                .any(|settlement_asset| currency.is(*settlement_asset))
+    }
+}
+
+/// Periodically swaps the collateral received from liquidations into the
+/// settlement asset.
+pub struct TreasuryService {
+    account: StarknetAccount,
+    provider: FallbackProvider,
+    config: TreasuryConfig,
+    pnl: TreasuryPnl,
+}
+
+impl TreasuryService {
+    /// Max slippage accepted on the Ekubo quote, in bps.
+    const MAX_SLIPPAGE_BPS: u128 = 100;
+
+    pub fn new(
+        account: StarknetAccount,
+        provider: FallbackProvider,
+        config: TreasuryConfig,
+    ) -> Self {
+        Self {
+            account,
+            provider,
+            config,
+            pnl: TreasuryPnl::default(),
+        }
+    }
+
+    pub async fn run_forever(mut self) -> Result<()> {
+        VESU_PRICES.wait_for_first_prices().await;
+
+        loop {
+            if let Err(e) = self.sweep().await {
+                tracing::error!("[🏦 Treasury] Could not sweep the inventory: {e}");
+            }
+            tokio::time::sleep(self.config.sweep_interval).await;
+        }
+    }
+
+    /// Sweeps every balance above the threshold into its settlement asset.
+    async fn sweep(&mut self) -> Result<()> {
+        if self.config.kill_switch.is_engaged() {
+            tracing::warn!("[🏦 Treasury] 🛑 Kill switch engaged, skipping the sweep");
+            return Ok(());
+        }
+
+        for asset in ONCHAIN_ASSETS.all() {
+            let currency = Currency::from_str(&asset.ticker)?;
+            if self.config.is_kept(currency) {
+                continue;
+            }
+
+            let balance = match balance_of(
+                &self.provider,
+                asset.address,
+                self.account.account_address(),
+            )
+            .await
+            {
+                Ok(balance) => balance,
+                Err(e) => {
+                    tracing::error!("[🏦 Treasury] Could not read the {currency} balance: {e}");
+                    continue;
+                }
+            };
+            if balance.low == 0 {
+                continue;
+            }
+
+            let amount = Decimal::from_str(&balance.low.to_string())?
+                / Decimal::TEN.pow(currency.d_decimals());
+            let value_usd = amount * currency.price();
+            if value_usd < self.config.sweep_threshold_usd {
+                continue;
+            }
+
+            if let Err(e) = self.sweep_asset(currency, balance.low, value_usd).await {
+                tracing::error!("[🏦 Treasury] Could not sweep {amount} {currency}: {e}");
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn sweep_asset(
+        &mut self,
+        currency: Currency,
+        amount: u128,
+        value_usd: Decimal,
+    ) -> Result<()> {
+        let settlement_asset = self.config.settlement_asset_of(currency);
+
+        let (swaps, expected_output) = guarded(
+            RpcProvider::Ekubo,
+            RpcPath::Background,
+            get_ekubo_exact_input_swaps(currency.address(), settlement_asset.address(), amount),
+        )
+        .await?;
+        let min_output = expected_output - expected_output * Self::MAX_SLIPPAGE_BPS / 10_000;
+
+        let calls = Self::sweep_calls(
+            currency.address(),
+            settlement_asset.address(),
+            amount,
+            swaps,
+            min_output,
+        );
+        // The nonce is shared with the executor, signing with the same account.
+        let tx_hash = self
+            .account
+            .execute_txs_with_next_nonce(&calls, None, TxPurpose::TreasurySweep)
+            .await?;
+
+        let received_usd = Decimal::from_str(&expected_output.to_string())?
+            / Decimal::TEN.pow(settlement_asset.d_decimals())
+            * settlement_asset.price();
+        self.pnl.swept_usd += value_usd;
+        self.pnl.received_usd += received_usd;
+
+        tracing::info!(
+            "[🏦 Treasury] 🧹 Swept {currency} worth {} into {settlement_asset} (tx {tx_hash:#064x}) - total PnL {}",
+            format_usd(value_usd),
+            format_usd(self.pnl.pnl_usd()),
+        );
+
+        Ok(())
+    }
+
+    /// Calls for an exact input swap through the Ekubo router: transfer the tokens
+    /// to the router, swap them, then withdraw the output & any leftover input.
+    fn sweep_calls(
+        token_in: Felt,
+        token_out: Felt,
+        amount: u128,
+        swaps: Vec<Swap>,
+        min_output: u128,
+    ) -> Vec<Call> {
+        let ekubo_router = AddressBook::get().ekubo_router;
+        vec![
+            transfer_call(
+                token_in,
+                ekubo_router,
+                U256 {
+                    low: amount,
+                    high: 0,
+                },
+            ),
+            Call {
+                to: ekubo_router,
+                selector: selector!("multi_multihop_swap"),
+                calldata: Vec::<Swap>::cairo_serialize(&swaps),
+            },
+            Call {
+                to: ekubo_router,
+                selector: selector!("clear_minimum"),
+                calldata: vec![token_out, min_output.into(), Felt::ZERO],
+            },
+            Call {
+                to: ekubo_router,
+                selector: selector!("clear"),
+                calldata: vec![token_in],
+            },
+        ]
+    }
+
+    pub fn pnl(&self) -> &TreasuryPnl {
+        &self.pnl
+    }
+}
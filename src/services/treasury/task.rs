@@ -0,0 +1,52 @@
+use pragma_common::{
+    services::{Service, ServiceRunner},
+    starknet::FallbackProvider,
+};
+
+use crate::{
+    services::treasury::{TreasuryConfig, TreasuryService},
+    types::account::StarknetAccount,
+};
+
+pub struct TreasuryTask {
+    account: StarknetAccount,
+    provider: FallbackProvider,
+    config: TreasuryConfig,
+}
+
+impl TreasuryTask {
+    pub fn new(
+        account: StarknetAccount,
+        provider: FallbackProvider,
+        config: TreasuryConfig,
+    ) -> Self {
+        Self {
+            account,
+            provider,
+            config,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Service for TreasuryTask {
+    async fn start<'a>(&mut self, mut runner: ServiceRunner<'a>) -> anyhow::Result<()> {
+        let account = self.account.clone();
+        let provider = self.provider.clone();
+        let config = self.config.clone();
+
+        runner.spawn_loop(move |ctx| async move {
+            let treasury_service = TreasuryService::new(account, provider, config);
+            if let Some(result) = ctx
+                .run_until_cancelled(treasury_service.run_forever())
+                .await
+            {
+                result?;
+            }
+
+            anyhow::Ok(())
+        });
+
+        Ok(())
+    }
+}
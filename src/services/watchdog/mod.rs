@@ -0,0 +1,136 @@
+use std::sync::{Arc, LazyLock, OnceLock};
+use std::time::{Duration, Instant};
+
+use dashmap::DashMap;
+
+use crate::services::notifier::{AlertKind, NOTIFIER};
+
+static WATCHDOG_CONFIG: OnceLock<WatchdogConfig> = OnceLock::new();
+
+// Progress & restarts of the watched services, readable from the API.
+pub static WATCHDOG: LazyLock<Arc<Watchdog>> = LazyLock::new(|| Arc::new(Watchdog::default()));
+
+/// The services restarted when they stop making progress.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, strum::Display)]
+#[strum(serialize_all = "kebab-case")]
+pub enum WatchedService {
+    /// Progresses at every price updated.
+    Oracle,
+    /// Progresses at every message of its stream.
+    Indexer,
+}
+
+/// When the watched services are considered stalled.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WatchdogConfig {
+    /// A service making no progress for this long gets restarted. None to never
+    /// restart them.
+    pub stall_timeout: Option<Duration>,
+}
+
+impl WatchdogConfig {
+    /// Sets the watchdog config of the whole process. Only the first call has an
+    /// effect.
+    pub fn install(self) {
+        let _ = WATCHDOG_CONFIG.set(self);
+    }
+
+    pub fn get() -> Self {
+        WATCHDOG_CONFIG.get().copied().unwrap_or_default()
+    }
+}
+
+/// Tracks the last progress of the watched services, so that a stalled one
+/// gets restarted & notified instead of the bot silently running on stale data.
+#[derive(Debug, Default)]
+pub struct Watchdog {
+    last_progress: DashMap<WatchedService, Instant>,
+    restarts: DashMap<WatchedService, u64>,
+}
+
+impl Watchdog {
+    /// Interval of the checks of the services supervised by `supervise`.
+    const CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+    /// Records a progress of the service.
+    pub fn beat(&self, service: WatchedService) {
+        self.last_progress.insert(service, Instant::now());
+    }
+
+    /// Returns how long the service made no progress if it is stalled,
+    /// recording its restart & notifying it. Resolves the notification of a
+    /// restarted service making progress again.
+    pub fn check(&self, service: WatchedService) -> Option<Duration> {
+        let stall_timeout = WatchdogConfig::get().stall_timeout?;
+        let since_progress = self
+            .last_progress
+            .get(&service)
+            .map(|last_progress| last_progress.elapsed())?;
+
+        if since_progress < stall_timeout {
+            NOTIFIER.resolve(AlertKind::ServiceStalled, &service.to_string(), || {
+                format!("The {service} is making progress again")
+            });
+            return None;
+        }
+
+        *self.restarts.entry(service).or_default() += 1;
+        // Counts the stall timeout from the restart.
+        self.beat(service);
+        let message = format!(
+            "The {service} made no progress for {}m, restarting it",
+            since_progress.as_secs() / 60
+        );
+        tracing::error!("[🐕 Watchdog] {message}");
+        NOTIFIER.alert(AlertKind::ServiceStalled, &service.to_string(), || message);
+        Some(since_progress)
+    }
+
+    /// Runs the service, started by `start`, until it ends - restarting it
+    /// whenever it stalls.
+    pub async fn supervise<F, Fut>(
+        &self,
+        service: WatchedService,
+        mut start: F,
+    ) -> anyhow::Result<()>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = anyhow::Result<()>>,
+    {
+        if WatchdogConfig::get().stall_timeout.is_none() {
+            return start().await;
+        }
+
+        loop {
+            self.beat(service);
+            let stalled = async {
+                let mut interval = tokio::time::interval(Self::CHECK_INTERVAL);
+                loop {
+                    interval.tick().await;
+                    if self.check(service).is_some() {
+                        return;
+                    }
+                }
+            };
+            tokio::select! {
+                result = start() => return result,
+                () = stalled => {}
+            }
+        }
+    }
+
+    pub fn prometheus_metric(&self) -> String {
+        let mut metric = String::from(
+            "# HELP service_restarts_total Restarts of the services that stopped making progress.\n\
+             # TYPE service_restarts_total counter\n",
+        );
+        for entry in self.restarts.iter() {
+            metric.push_str(&format!(
+                "service_restarts_total{{service=\"{}\"}} {}\n",
+                entry.key(),
+                entry.value()
+            ));
+        }
+        metric
+    }
+}
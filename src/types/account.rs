@@ -1,30 +1,116 @@
 use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
 
 use anyhow::Result;
+use num_traits::Pow;
 use pragma_common::starknet::FallbackProvider;
+use rust_decimal::Decimal;
 use starknet::{
-    accounts::{Account, ExecutionEncoding, SingleOwnerAccount},
+    accounts::{Account, ConnectedAccount, ExecutionEncoding, SingleOwnerAccount},
     core::{
         chain_id,
-        types::{BlockId, BlockTag, Call, Felt},
+        types::{BlockId, BlockTag, Call, FeeEstimate, Felt, SimulatedTransaction},
     },
+    providers::Provider,
     signers::{LocalWallet, SigningKey},
 };
+use tokio::sync::Mutex;
 
-use crate::cli::RunCmd;
+use crate::{
+    cli::RunCmd,
+    services::audit::{AuditEvent, AuditLog, TxPurpose},
+    services::monitoring::{fee_cache::TransactionFee, liquidation_error::LiquidationError},
+    types::currency::Currency,
+    utils::{
+        devnet::impersonate_account,
+        erc20::balance_of,
+        rpc::{RpcPath, RpcProvider, guarded},
+    },
+};
+
+/// Margins of the bounds of a transaction over its estimated gas & prices, the
+/// ones the account applies when it estimates the fee itself.
+const GAS_MARGIN: f64 = 1.5;
+const GAS_PRICE_MARGIN: f64 = 1.5;
+
+fn with_margin<T: TryFrom<u128> + Into<u128> + Copy>(value: T, margin: f64) -> T {
+    T::try_from((value.into() as f64 * margin) as u128).unwrap_or(value)
+}
+
+/// Returns the max fee (FRI) of a transaction sent with the given fee, its
+/// bounds with their margins.
+fn max_fee(fee: TransactionFee) -> u128 {
+    let bound = |gas: u64, price: u128| {
+        u128::from(with_margin(gas, GAS_MARGIN))
+            .saturating_mul(with_margin(price, GAS_PRICE_MARGIN))
+    };
+    bound(fee.l1_gas, fee.prices.l1_gas)
+        .saturating_add(bound(fee.l2_gas, fee.prices.l2_gas))
+        .saturating_add(bound(fee.l1_data_gas, fee.prices.l1_data_gas))
+}
+
+/// Token used to pay the transaction fees.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum FeeToken {
+    #[default]
+    Strk,
+    /// Only payable with the legacy transactions, not supported.
+    Eth,
+}
+
+impl FeeToken {
+    pub fn currency(&self) -> Currency {
+        match self {
+            Self::Strk => Currency::STRK,
+            Self::Eth => Currency::ETH,
+        }
+    }
+}
 
 pub type StarknetSingleOwnerAccount = SingleOwnerAccount<FallbackProvider, LocalWallet>;
 
+/// The nonce of the next transaction of an account, None when it must be
+/// fetched again. Shared by the clones of the account, so the liquidations &
+/// the treasury sweeps it signs never reuse a nonce.
+#[derive(Debug, Clone, Default)]
+struct NextNonce(Arc<Mutex<Option<Felt>>>);
+
 #[derive(Debug, Clone)]
-pub struct StarknetAccount(pub StarknetSingleOwnerAccount);
+pub struct StarknetAccount(pub StarknetSingleOwnerAccount, NextNonce);
 
 impl StarknetAccount {
     /// Creates a StarknetAccount from the CLI args
-    pub fn from_cli(rpc_client: FallbackProvider, run_cmd: RunCmd) -> Result<StarknetAccount> {
-        let account_builder = StarknetAccountBuilder::default()
-            .as_account(run_cmd.account_params.account_address)
-            .on_mainnet()
-            .with_provider(rpc_client);
+    pub async fn from_cli(
+        rpc_client: FallbackProvider,
+        run_cmd: RunCmd,
+    ) -> Result<StarknetAccount> {
+        let account_builder =
+            StarknetAccountBuilder::default().as_account(run_cmd.account_params.account_address()?);
+
+        if run_cmd.devnet {
+            let chain_id = rpc_client.chain_id().await?;
+            let account_builder = account_builder.on_chain(chain_id).with_provider(rpc_client);
+
+            let params = run_cmd.account_params;
+            return match (
+                params.private_key,
+                params.keystore_path,
+                params.keystore_password,
+            ) {
+                (Some(private_key), _, _) => account_builder.from_secret(private_key),
+                (None, Some(path), Some(password)) => {
+                    account_builder.from_keystore(path, &password)
+                }
+                _ => {
+                    account_builder
+                        .impersonate(&run_cmd.rpc_url, params.account_address()?)
+                        .await
+                }
+            };
+        }
+
+        let account_builder = account_builder.on_mainnet().with_provider(rpc_client);
 
         if let Some(private_key) = run_cmd.account_params.private_key {
             account_builder.from_secret(private_key)
@@ -47,15 +133,159 @@ impl StarknetAccount {
         self.0.address()
     }
 
-    /// Executes a set of transactions and returns the transaction hash.
-    pub async fn execute_txs(&self, txs: &[Call]) -> Result<Felt> {
-        let res = self
-            .0
-            .execute_v3(txs.to_vec())
-            .send()
-            .await
-            .map_err(|e| anyhow::anyhow!(format!("{:?}", e)))?;
-        Ok(res.transaction_hash)
+    /// Returns the balance of the fee token, failing if there is nothing to
+    /// pay the fees with.
+    pub async fn ensure_fee_token_balance(
+        &self,
+        provider: &FallbackProvider,
+        fee_token: FeeToken,
+    ) -> Result<Decimal> {
+        let currency = fee_token.currency();
+        let balance = balance_of(provider, currency.address(), self.account_address()).await?;
+        let balance =
+            Decimal::from_str(&balance.low.to_string())? / Decimal::TEN.pow(currency.d_decimals());
+
+        anyhow::ensure!(
+            !balance.is_zero(),
+            "Liquidator account {:#x} has no {currency} to pay for fees",
+            self.account_address()
+        );
+        Ok(balance)
+    }
+
+    /// Executes a set of transactions and returns the transaction hash. The
+    /// send is recorded in the audit log with its purpose.
+    pub async fn execute_txs(&self, txs: &[Call], purpose: TxPurpose) -> Result<Felt> {
+        let res = guarded(RpcProvider::Starknet, RpcPath::Liquidation, async {
+            self.0
+                .execute_v3(txs.to_vec())
+                .send()
+                .await
+                .map_err(LiquidationError::from_account_error)
+        })
+        .await
+        .map(|res| res.transaction_hash);
+        AuditLog::record(AuditEvent::signed(
+            purpose,
+            self.account_address(),
+            None,
+            txs,
+            None,
+            &res,
+        ));
+        res
+    }
+
+    /// Executes a set of transactions with the given nonce and returns the
+    /// transaction hash. Their fee is estimated before sending them, unless
+    /// given: its bounds then get the margins of the estimation. The send is
+    /// recorded in the audit log with its purpose.
+    pub async fn execute_txs_with_nonce(
+        &self,
+        txs: &[Call],
+        nonce: Felt,
+        fee: Option<TransactionFee>,
+        purpose: TxPurpose,
+    ) -> Result<Felt> {
+        let res = guarded(RpcProvider::Starknet, RpcPath::Liquidation, async {
+            let execution = self.0.execute_v3(txs.to_vec()).nonce(nonce);
+            let execution = match fee {
+                Some(fee) => execution
+                    .l1_gas(with_margin(fee.l1_gas, GAS_MARGIN))
+                    .l1_gas_price(with_margin(fee.prices.l1_gas, GAS_PRICE_MARGIN))
+                    .l2_gas(with_margin(fee.l2_gas, GAS_MARGIN))
+                    .l2_gas_price(with_margin(fee.prices.l2_gas, GAS_PRICE_MARGIN))
+                    .l1_data_gas(with_margin(fee.l1_data_gas, GAS_MARGIN))
+                    .l1_data_gas_price(with_margin(fee.prices.l1_data_gas, GAS_PRICE_MARGIN)),
+                None => execution,
+            };
+            execution
+                .send()
+                .await
+                .map_err(LiquidationError::from_account_error)
+        })
+        .await
+        .map(|res| res.transaction_hash);
+        AuditLog::record(AuditEvent::signed(
+            purpose,
+            self.account_address(),
+            Some(nonce),
+            txs,
+            fee.map(max_fee),
+            &res,
+        ));
+        res
+    }
+
+    /// Executes a set of transactions with the next nonce of the account,
+    /// tracked locally & shared by all its clones, and returns the transaction
+    /// hash. The nonce is held until the send is done & fetched again after an
+    /// invalid one. See `execute_txs_with_nonce`.
+    pub async fn execute_txs_with_next_nonce(
+        &self,
+        txs: &[Call],
+        fee: Option<TransactionFee>,
+        purpose: TxPurpose,
+    ) -> Result<Felt> {
+        let mut next_nonce = self.1.0.lock().await;
+        let nonce = match *next_nonce {
+            Some(nonce) => nonce,
+            None => self.fetch_nonce().await?,
+        };
+
+        let res = self.execute_txs_with_nonce(txs, nonce, fee, purpose).await;
+        match &res {
+            Ok(_) => *next_nonce = Some(nonce + Felt::ONE),
+            Err(e) => {
+                if matches!(
+                    e.downcast_ref::<LiquidationError>(),
+                    Some(LiquidationError::InvalidNonce)
+                ) {
+                    *next_nonce = None;
+                }
+            }
+        }
+        res
+    }
+
+    /// Fetches the nonce again at the next transaction, e.g when the one of an
+    /// expired transaction may not have been consumed.
+    pub async fn reset_nonce(&self) {
+        *self.1.0.lock().await = None;
+    }
+
+    /// Returns the nonce of the next transaction of the account.
+    pub async fn fetch_nonce(&self) -> Result<Felt> {
+        guarded(
+            RpcProvider::Starknet,
+            RpcPath::Liquidation,
+            self.0.get_nonce(),
+        )
+        .await
+    }
+
+    /// Estimates the fee of a set of transactions. Fails if their simulation reverts.
+    pub async fn estimate_txs(&self, txs: &[Call]) -> Result<FeeEstimate> {
+        guarded(RpcProvider::Starknet, RpcPath::Liquidation, async {
+            self.0
+                .execute_v3(txs.to_vec())
+                .estimate_fee()
+                .await
+                .map_err(|e| LiquidationError::from_account_error(e).in_simulation())
+        })
+        .await
+    }
+
+    /// Simulates the execution of the TXs without sending them.
+    pub async fn simulate_txs(&self, txs: &[Call]) -> Result<SimulatedTransaction> {
+        guarded(RpcProvider::Starknet, RpcPath::Liquidation, async {
+            self.0
+                .execute_v3(txs.to_vec())
+                .simulate(false, false)
+                .await
+                .map_err(|e| LiquidationError::from_account_error(e).in_simulation())
+        })
+        .await
     }
 }
 
@@ -80,6 +310,11 @@ impl StarknetAccountBuilder {
         self.chain_id = Some(chain_id::SEPOLIA);
         self
     }
+
+    pub fn on_chain(mut self, chain_id: Felt) -> Self {
+        self.chain_id = Some(chain_id);
+        self
+    }
     pub fn as_account(mut self, account_address: Felt) -> Self {
         self.account_address = Some(account_address);
         self
@@ -106,6 +341,17 @@ impl StarknetAccountBuilder {
         self.build(signer)
     }
 
+    /// Impersonates the account on a forked devnet. The signer is a dummy key
+    /// since the devnet skips the signature validation of impersonated accounts.
+    pub async fn impersonate(
+        self,
+        devnet_url: &url::Url,
+        account_address: Felt,
+    ) -> Result<StarknetAccount> {
+        impersonate_account(devnet_url, account_address).await?;
+        self.from_secret(Felt::ONE)
+    }
+
     fn build(self, signer: LocalWallet) -> Result<StarknetAccount> {
         let mut account = SingleOwnerAccount::new(
             self.rpc_client.unwrap(),
@@ -117,6 +363,6 @@ impl StarknetAccountBuilder {
 
         account.set_block_id(BlockId::Tag(BlockTag::Latest));
 
-        Ok(StarknetAccount(account))
+        Ok(StarknetAccount(account, NextNonce::default()))
     }
 }
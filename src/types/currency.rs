@@ -1,6 +1,10 @@
 use rust_decimal::Decimal;
 
-use crate::{config::onchain_assets::ONCHAIN_ASSETS, services::oracle::vesu_prices::VESU_PRICES};
+use crate::{
+    config::onchain_assets::{AssetClass, ONCHAIN_ASSETS},
+    services::oracle::{exchange_rates::EXCHANGE_RATES, vesu_prices::VESU_PRICES},
+    utils::format::format_amount,
+};
 
 #[allow(non_camel_case_types)]
 #[derive(
@@ -12,6 +16,7 @@ use crate::{config::onchain_assets::ONCHAIN_ASSETS, services::oracle::vesu_price
     strum::Display,
     strum::AsRefStr,
     strum::EnumString,
+    strum::EnumIter,
     PartialEq,
     Eq,
     PartialOrd,
@@ -62,12 +67,29 @@ impl Currency {
         ONCHAIN_ASSETS[*self].address
     }
 
+    /// The risk class of the asset, from assets.toml.
+    pub fn class(&self) -> AssetClass {
+        ONCHAIN_ASSETS[*self].class
+    }
+
+    /// Returns true for the assets pegged to the USD.
+    pub fn is_stable(&self) -> bool {
+        self.class() == AssetClass::Stable
+    }
+
     pub fn is(&self, other: Currency) -> bool {
         *self == other
     }
 
+    /// Formats an amount of the asset for display, e.g `0.0123457 WBTC`.
+    pub fn format_amount(&self, amount: Decimal) -> String {
+        format!("{} {self}", format_amount(amount))
+    }
+
+    /// Returns the USD price of the asset. For the yield bearing assets, it
+    /// includes the appreciation since their last oracle update.
     pub fn price(&self) -> Decimal {
-        VESU_PRICES.of(*self)
+        VESU_PRICES.of(*self) * EXCHANGE_RATES.drift(self.as_ref())
     }
 
     pub fn ticker(&self) -> String {
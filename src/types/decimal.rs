@@ -0,0 +1,112 @@
+//! Serde helpers for the Decimals of every output - the API, the snapshots,
+//! the reports & the streams - used with `#[serde(with = "decimal::string")]`.
+//!
+//! The Decimals get serialized as strings with their scale, e.g `"1.50"`: never
+//! as floats, which lose precision in the JSON parsers, nor with an exponent
+//! or a locale separator. Unlike the `serde` feature of `rust_decimal`, this
+//! does not depend on the features the dependencies enable. The strings, the
+//! integers & the floats are all accepted when reading, for the older files.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use rust_decimal::Decimal;
+use serde::de::{self, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+struct DecimalVisitor;
+
+impl Visitor<'_> for DecimalVisitor {
+    type Value = Decimal;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "a decimal as a string or a number")
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<Decimal, E> {
+        Decimal::from_str(value)
+            .or_else(|_| Decimal::from_scientific(value))
+            .map_err(E::custom)
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<Decimal, E> {
+        Ok(Decimal::from(value))
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<Decimal, E> {
+        Ok(Decimal::from(value))
+    }
+
+    fn visit_f64<E: de::Error>(self, value: f64) -> Result<Decimal, E> {
+        Decimal::try_from(value).map_err(E::custom)
+    }
+}
+
+/// A Decimal, as a string.
+struct AsString(Decimal);
+
+impl Serialize for AsString {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for AsString {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(DecimalVisitor).map(Self)
+    }
+}
+
+/// `Decimal` fields.
+pub mod string {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(value: &Decimal, serializer: S) -> Result<S::Ok, S::Error> {
+        AsString(*value).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Decimal, D::Error> {
+        AsString::deserialize(deserializer).map(|value| value.0)
+    }
+}
+
+/// `Option<Decimal>` fields, None being null.
+pub mod option_string {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        value: &Option<Decimal>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        value.map(AsString).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Decimal>, D::Error> {
+        Option::<AsString>::deserialize(deserializer).map(|value| value.map(|value| value.0))
+    }
+}
+
+/// `BTreeMap<String, Decimal>` fields, e.g the prices per asset.
+pub mod map_string {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        values: &BTreeMap<String, Decimal>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(values.iter().map(|(key, value)| (key, AsString(*value))))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<BTreeMap<String, Decimal>, D::Error> {
+        let values = BTreeMap::<String, AsString>::deserialize(deserializer)?;
+        Ok(values
+            .into_iter()
+            .map(|(key, value)| (key, value.0))
+            .collect())
+    }
+}
@@ -0,0 +1,133 @@
+use anyhow::{Context, Result};
+use pragma_common::starknet::fallback_provider::FallbackProvider;
+use serde::Deserialize;
+use starknet::core::types::{BlockId, BlockTag, Call, ContractClass, Felt, FunctionCall};
+use starknet::macros::selector;
+use starknet::providers::Provider;
+
+use crate::types::liquidate_contract::LiquidateContracts;
+
+/// The keeper registration of a liquidate contract only liquidating for its
+/// registered keepers, through `is_keeper(keeper) -> bool` &
+/// `register_keeper(keeper)`.
+#[derive(Debug, Clone, Copy)]
+pub struct KeeperRegistry {
+    pub contract: Felt,
+}
+
+impl KeeperRegistry {
+    /// Entry points a contract must expose to have a keeper registration.
+    const ENTRYPOINTS: [&str; 2] = ["is_keeper", "register_keeper"];
+
+    /// Returns the keeper registration of the contract, None if it has none -
+    /// anyone can liquidate with it then.
+    pub async fn detect(provider: &FallbackProvider, contract: Felt) -> Result<Option<Self>> {
+        let class = provider
+            .get_class_at(BlockId::Tag(BlockTag::Latest), contract)
+            .await
+            .with_context(|| format!("Could not fetch the class of {contract:#x}"))?;
+        let ContractClass::Sierra(class) = class else {
+            return Ok(None);
+        };
+        Ok(Self::has_registration(&class.abi)?.then_some(Self { contract }))
+    }
+
+    /// Returns true if the ABI exposes the keeper registration entry points,
+    /// in an interface or at the top level.
+    fn has_registration(abi: &str) -> Result<bool> {
+        #[derive(Deserialize)]
+        struct AbiEntry {
+            r#type: String,
+            #[serde(default)]
+            name: String,
+            #[serde(default)]
+            items: Vec<AbiEntry>,
+        }
+
+        let entries: Vec<AbiEntry> = serde_json::from_str(abi)?;
+        let functions: Vec<&str> = entries
+            .iter()
+            .flat_map(|entry| std::iter::once(entry).chain(&entry.items))
+            .filter(|entry| entry.r#type == "function")
+            .map(|entry| entry.name.as_str())
+            .collect();
+        Ok(Self::ENTRYPOINTS
+            .iter()
+            .all(|entrypoint| functions.contains(entrypoint)))
+    }
+
+    pub async fn is_registered(&self, provider: &FallbackProvider, keeper: Felt) -> Result<bool> {
+        let result = provider
+            .call(
+                FunctionCall {
+                    contract_address: self.contract,
+                    entry_point_selector: selector!("is_keeper"),
+                    calldata: vec![keeper],
+                },
+                BlockId::Tag(BlockTag::Latest),
+            )
+            .await
+            .with_context(|| format!("Could not read the keepers of {:#x}", self.contract))?;
+        Ok(result
+            .first()
+            .is_some_and(|registered| *registered != Felt::ZERO))
+    }
+
+    pub fn register_call(&self, keeper: Felt) -> Call {
+        Call {
+            to: self.contract,
+            selector: selector!("register_keeper"),
+            calldata: vec![keeper],
+        }
+    }
+}
+
+/// Returns the keeper registrations of the liquidate contracts that have one.
+pub async fn keeper_registries(
+    provider: &FallbackProvider,
+    contracts: &LiquidateContracts,
+) -> Result<Vec<KeeperRegistry>> {
+    let addresses = std::iter::once(contracts.default_contract().address()).chain(
+        contracts
+            .overrides()
+            .map(|(_, contract)| contract.address()),
+    );
+
+    let mut registries: Vec<KeeperRegistry> = Vec::new();
+    for address in addresses {
+        if registries
+            .iter()
+            .any(|registry| registry.contract == address)
+        {
+            continue;
+        }
+        if let Some(registry) = KeeperRegistry::detect(provider, address).await? {
+            registries.push(registry);
+        }
+    }
+    Ok(registries)
+}
+
+/// Fails if the account is not a registered keeper of every liquidate contract
+/// with a keeper registration, with the instructions to register it.
+pub async fn ensure_registered_keeper(
+    provider: &FallbackProvider,
+    contracts: &LiquidateContracts,
+    keeper: Felt,
+) -> Result<()> {
+    for registry in keeper_registries(provider, contracts).await? {
+        if registry.is_registered(provider, keeper).await? {
+            tracing::info!(
+                "[🔭 Monitoring] 🔑 Registered keeper of the liquidate contract {:#x}",
+                registry.contract
+            );
+            continue;
+        }
+        anyhow::bail!(
+            "The account {keeper:#x} is not a registered keeper of the liquidate contract {:#x}, which only liquidates for its keepers. \
+             Run `register-keeper` with this account - or with `--keeper {keeper:#x}` from an account allowed to register it - then start the bot again.",
+            registry.contract
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,214 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use cainome::cairo_serde::{ContractAddress, U256};
+use pragma_common::starknet::fallback_provider::FallbackProvider;
+use serde::Deserialize;
+use starknet::core::types::{BlockId, BlockTag, Call, ContractClass, Felt};
+use starknet::providers::Provider;
+
+use crate::bindings::liquidate_v1::{self, Swap};
+use crate::types::account::{StarknetAccount, StarknetSingleOwnerAccount};
+use crate::types::pool::PoolName;
+
+/// Versions of the Vesu liquidate helper contract supported by the bot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, strum::Display)]
+pub enum LiquidateContractVersion {
+    V1,
+}
+
+impl LiquidateContractVersion {
+    /// Members of the `LiquidateParams` struct of each version, used to recognize
+    /// the interface implemented by a contract from its ABI.
+    const V1_PARAMS: &[&str] = &[
+        "pool",
+        "collateral_asset",
+        "debt_asset",
+        "user",
+        "recipient",
+        "min_collateral_to_receive",
+        "debt_to_repay",
+        "liquidate_swap",
+        "liquidate_swap_limit_amount",
+        "liquidate_swap_weights",
+        "withdraw_swap",
+        "withdraw_swap_limit_amount",
+        "withdraw_swap_weights",
+    ];
+
+    /// Returns the version matching the ABI of the contract, if supported.
+    pub fn from_abi(abi: &str) -> Result<Option<Self>> {
+        #[derive(Deserialize)]
+        struct AbiEntry {
+            r#type: String,
+            name: String,
+            #[serde(default)]
+            members: Vec<AbiMember>,
+        }
+
+        #[derive(Deserialize)]
+        struct AbiMember {
+            name: String,
+        }
+
+        let entries: Vec<AbiEntry> = serde_json::from_str(abi)?;
+
+        let Some(params) = entries
+            .iter()
+            .find(|e| e.r#type == "struct" && e.name.ends_with("::LiquidateParams"))
+        else {
+            return Ok(None);
+        };
+
+        let mut members: Vec<&str> = params.members.iter().map(|m| m.name.as_str()).collect();
+        members.sort_unstable();
+
+        let mut v1_members = Self::V1_PARAMS.to_vec();
+        v1_members.sort_unstable();
+
+        Ok((members == v1_members).then_some(Self::V1))
+    }
+}
+
+/// Version agnostic parameters of a liquidation.
+#[derive(Debug, Clone)]
+pub struct LiquidationRequest {
+    pub pool: Felt,
+    pub collateral_asset: Felt,
+    pub debt_asset: Felt,
+    pub user: Felt,
+    pub recipient: Felt,
+    /// Zero means repaying all the debt.
+    pub debt_to_repay: U256,
+    pub liquidate_swap: Vec<Swap>,
+    pub liquidate_swap_weights: Vec<u128>,
+}
+
+/// The liquidate helper contract, bound to the interface it implements.
+#[derive(Debug, Clone)]
+pub enum LiquidateContract {
+    V1(Arc<liquidate_v1::Liquidate<StarknetSingleOwnerAccount>>),
+}
+
+impl LiquidateContract {
+    /// Detects which interface the contract deployed at `address` implements.
+    pub async fn detect(
+        provider: &FallbackProvider,
+        account: &StarknetAccount,
+        address: Felt,
+    ) -> Result<Self> {
+        let class = provider
+            .get_class_at(BlockId::Tag(BlockTag::Latest), address)
+            .await
+            .with_context(|| format!("Could not fetch the class of {address:#x}"))?;
+
+        let ContractClass::Sierra(class) = class else {
+            anyhow::bail!("Liquidate contract {address:#x} is not a Sierra contract");
+        };
+
+        let version = LiquidateContractVersion::from_abi(&class.abi)?.with_context(|| {
+            format!("Liquidate contract {address:#x} implements an unsupported interface")
+        })?;
+
+        tracing::info!("[🔭 Monitoring] Using liquidate contract {address:#x} ({version})");
+
+        Ok(Self::from_version(version, account, address))
+    }
+
+    pub fn from_version(
+        version: LiquidateContractVersion,
+        account: &StarknetAccount,
+        address: Felt,
+    ) -> Self {
+        match version {
+            LiquidateContractVersion::V1 => Self::V1(Arc::new(liquidate_v1::Liquidate::new(
+                address,
+                account.0.clone(),
+            ))),
+        }
+    }
+
+    pub fn version(&self) -> LiquidateContractVersion {
+        match self {
+            Self::V1(_) => LiquidateContractVersion::V1,
+        }
+    }
+
+    pub fn address(&self) -> Felt {
+        match self {
+            Self::V1(contract) => contract.address,
+        }
+    }
+
+    /// Returns the call liquidating the position with this contract.
+    pub fn liquidate_call(&self, request: LiquidationRequest) -> Call {
+        match self {
+            Self::V1(contract) => {
+                let params = liquidate_v1::LiquidateParams {
+                    pool: ContractAddress(request.pool),
+                    collateral_asset: ContractAddress(request.collateral_asset),
+                    debt_asset: ContractAddress(request.debt_asset),
+                    user: ContractAddress(request.user),
+                    recipient: ContractAddress(request.recipient),
+                    min_collateral_to_receive: U256 { low: 0, high: 0 },
+                    debt_to_repay: request.debt_to_repay,
+                    liquidate_swap: request.liquidate_swap,
+                    liquidate_swap_weights: request.liquidate_swap_weights,
+                    liquidate_swap_limit_amount: u128::MAX,
+                    withdraw_swap: vec![],
+                    withdraw_swap_limit_amount: 0,
+                    withdraw_swap_weights: vec![],
+                };
+                contract.liquidate_getcall(&params)
+            }
+        }
+    }
+}
+
+/// The liquidate contract of every pool: the default one unless the pool needs
+/// its own helper, e.g for another swap venue or hook logic.
+#[derive(Debug, Clone)]
+pub struct LiquidateContracts {
+    default: LiquidateContract,
+    per_pool: HashMap<PoolName, LiquidateContract>,
+}
+
+impl LiquidateContracts {
+    /// Detects the interface of the default contract & of the ones of the pools.
+    pub async fn detect(
+        provider: &FallbackProvider,
+        account: &StarknetAccount,
+        default: Felt,
+        per_pool: &[(PoolName, Felt)],
+    ) -> Result<Self> {
+        let default = LiquidateContract::detect(provider, account, default).await?;
+
+        let mut contracts = HashMap::new();
+        for (pool, address) in per_pool {
+            let contract = LiquidateContract::detect(provider, account, *address)
+                .await
+                .with_context(|| format!("Invalid liquidate contract of {pool}"))?;
+            contracts.insert(*pool, contract);
+        }
+
+        Ok(Self {
+            default,
+            per_pool: contracts,
+        })
+    }
+
+    /// The contract liquidating the positions of the pool.
+    pub fn for_pool(&self, pool: PoolName) -> &LiquidateContract {
+        self.per_pool.get(&pool).unwrap_or(&self.default)
+    }
+
+    pub fn default_contract(&self) -> &LiquidateContract {
+        &self.default
+    }
+
+    /// The pools liquidated with their own contract.
+    pub fn overrides(&self) -> impl Iterator<Item = (&PoolName, &LiquidateContract)> {
+        self.per_pool.iter()
+    }
+}
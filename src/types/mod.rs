@@ -1,4 +1,8 @@
 pub mod account;
 pub mod currency;
+pub mod decimal;
+pub mod keeper;
+pub mod liquidate_contract;
+pub mod pair_config;
 pub mod pool;
 pub mod position;
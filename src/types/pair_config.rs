@@ -0,0 +1,186 @@
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, LazyLock, OnceLock};
+use std::time::Duration;
+
+use anyhow::Result;
+use dashmap::DashMap;
+use evian::vesu::v2::data::VesuDataClient;
+use pragma_common::starknet::FallbackProvider;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use starknet::core::types::Felt;
+
+use crate::types::decimal;
+use crate::types::pool::PoolName;
+
+pub const PAIR_CONFIGS_FILE: &str = "pair_configs.json";
+
+// The LLTVs of the pairs already read, shared by all the positions.
+pub static PAIR_CONFIGS: LazyLock<Arc<PairConfigCache>> =
+    LazyLock::new(|| Arc::new(PairConfigCache::default()));
+
+/// A cached LLTV is only used for the blocks of the bucket it got read for, so
+/// it gets read again about once a day even without any parameter event.
+const BUCKET_BLOCKS: u64 = 20_000;
+
+/// How many of the latest buckets are kept, the older ones getting pruned.
+const KEPT_BUCKETS: u64 = 2;
+
+/// How often the cache gets persisted if it changed.
+const PERSIST_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+struct PairKey {
+    pool: PoolName,
+    collateral: Felt,
+    debt: Felt,
+    bucket: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedPairConfig {
+    #[serde(flatten)]
+    key: PairKey,
+    #[serde(with = "decimal::string")]
+    max_ltv: Decimal,
+}
+
+/// Caches the LLTV of the pairs, on disk if a state directory is set, so the
+/// positions created on a backfill or a restart don't each read their pair
+/// config. Invalidated by the pool parameter events & the LLTV changes seen by
+/// the `LltvWatcher`.
+#[derive(Debug, Default)]
+pub struct PairConfigCache {
+    entries: DashMap<PairKey, Decimal>,
+    /// The most recent bucket read, older buckets get pruned from it.
+    latest_bucket: AtomicU64,
+    /// Set when the entries changed since they were last persisted.
+    dirty: AtomicBool,
+    /// Where the cache is persisted, if set.
+    path: OnceLock<PathBuf>,
+}
+
+impl PairConfigCache {
+    /// Loads the cache persisted at `path` & persists it there from now on.
+    pub fn load(&self, path: &Path) -> Result<()> {
+        let _ = self.path.set(path.to_path_buf());
+        if !path.exists() {
+            return Ok(());
+        }
+
+        let persisted: Vec<CachedPairConfig> = serde_json::from_reader(File::open(path)?)?;
+        for cached in persisted {
+            self.latest_bucket
+                .fetch_max(cached.key.bucket, Ordering::Relaxed);
+            self.entries.insert(cached.key, cached.max_ltv);
+        }
+        Ok(())
+    }
+
+    /// Prunes the stale buckets & persists the cache when it changed, off the
+    /// path of the positions reading it.
+    pub async fn persist_forever(self: Arc<Self>) {
+        if self.path.get().is_none() {
+            return;
+        }
+        let mut interval = tokio::time::interval(PERSIST_INTERVAL);
+        loop {
+            interval.tick().await;
+            self.prune();
+            if !self.dirty.swap(false, Ordering::Relaxed) {
+                continue;
+            }
+            let cache = Arc::clone(&self);
+            match tokio::task::spawn_blocking(move || cache.save()).await {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => {
+                    tracing::warn!("Could not persist the pair configs: {e}");
+                    self.dirty.store(true, Ordering::Relaxed);
+                }
+                Err(e) => tracing::warn!("Could not persist the pair configs: {e}"),
+            }
+        }
+    }
+
+    fn prune(&self) {
+        let oldest_kept = self
+            .latest_bucket
+            .load(Ordering::Relaxed)
+            .saturating_sub(KEPT_BUCKETS - 1);
+        self.invalidate(|key| key.bucket < oldest_kept);
+    }
+
+    fn save(&self) -> Result<()> {
+        let Some(path) = self.path.get() else {
+            return Ok(());
+        };
+        let cached: Vec<CachedPairConfig> = self
+            .entries
+            .iter()
+            .map(|entry| CachedPairConfig {
+                key: *entry.key(),
+                max_ltv: *entry.value(),
+            })
+            .collect();
+
+        // Write & rename so that a crash never leaves a partial cache.
+        let tmp_path = path.with_extension("json.tmp");
+        fs::write(&tmp_path, serde_json::to_vec(&cached)?)?;
+        fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+
+    /// The LLTV of the pair at `block_number`, read from the chain at that block
+    /// if not cached for its bucket. Always read at the latest block if the
+    /// block is not known.
+    pub async fn max_ltv(
+        &self,
+        vesu_client: &Arc<VesuDataClient<FallbackProvider>>,
+        pool: PoolName,
+        collateral: Felt,
+        debt: Felt,
+        block_number: Option<u64>,
+    ) -> Result<Decimal> {
+        let key = block_number.map(|block_number| PairKey {
+            pool,
+            collateral,
+            debt,
+            bucket: block_number / BUCKET_BLOCKS,
+        });
+        if let Some(max_ltv) = key.and_then(|key| self.entries.get(&key).map(|v| *v)) {
+            return Ok(max_ltv);
+        }
+
+        let max_ltv = vesu_client
+            .pair_config(pool.pool_address(), collateral, debt, block_number)
+            .await?
+            .max_ltv;
+        if let Some(key) = key {
+            self.latest_bucket.fetch_max(key.bucket, Ordering::Relaxed);
+            self.entries.insert(key, max_ltv);
+            self.dirty.store(true, Ordering::Relaxed);
+        }
+        Ok(max_ltv)
+    }
+
+    /// Drops the cached LLTVs of the pair, e.g once it changed.
+    pub fn invalidate_pair(&self, pool: PoolName, collateral: Felt, debt: Felt) {
+        self.invalidate(|key| key.pool == pool && key.collateral == collateral && key.debt == debt);
+    }
+
+    /// Drops the cached LLTVs of all the pairs of the pool, e.g on a parameter
+    /// event of the pool.
+    pub fn invalidate_pool(&self, pool: PoolName) {
+        self.invalidate(|key| key.pool == pool);
+    }
+
+    fn invalidate(&self, matches: impl Fn(&PairKey) -> bool) {
+        let len = self.entries.len();
+        self.entries.retain(|key, _| !matches(key));
+        if self.entries.len() != len {
+            self.dirty.store(true, Ordering::Relaxed);
+        }
+    }
+}
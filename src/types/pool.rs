@@ -1,14 +1,29 @@
-use anyhow::bail;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use anyhow::Context;
 use evian::vesu::v2::data::indexer::events::{
     CollateralAddress, DebtAddress, PoolAddress, PoolDetails,
 };
+use futures_util::future::join_all;
+use pragma_common::starknet::FallbackProvider;
 use serde::{Deserialize, Serialize};
-use starknet::{core::types::Felt, macros::felt_hex};
+use starknet::core::types::{BlockId, Felt, StarknetError};
+use starknet::providers::{Provider, ProviderError};
+use strum::IntoEnumIterator;
 
+use crate::config::addresses::AddressBook;
 use crate::types::currency::Currency;
+use crate::utils::rpc::{RpcPath, guarded_starknet};
 
 pub type VesuPoolId = Felt;
 
+/// Block of the first Vesu v2 pools.
+pub const V2_DEPLOYMENT_BLOCK: u64 = 2_383_614;
+
+/// The block each pool got created at, read from the chain at startup.
+static DEPLOYMENT_BLOCKS: OnceLock<HashMap<PoolName, u64>> = OnceLock::new();
+
 #[derive(
     Debug,
     Copy,
@@ -34,27 +49,83 @@ pub enum PoolName {
 }
 
 impl PoolName {
-    pub const fn pool_address(&self) -> VesuPoolId {
-        match self {
-            Self::Prime => {
-                felt_hex!("0x451fe483d5921a2919ddd81d0de6696669bccdacd859f72a4fba7656b97c3b5")
-            }
-            Self::Re7USDCPrime => {
-                felt_hex!("0x02eef0c13b10b487ea5916b54c0a7f98ec43fb3048f60fdeedaf5b08f6f88aaf")
-            }
-            Self::Re7USDCCore => {
-                felt_hex!("0x03976cac265a12609934089004df458ea29c776d77da423c96dc761d09d24124")
-            }
-            Self::Re7xBTC => {
-                felt_hex!("0x03a8416bf20d036df5b1cf3447630a2e1cb04685f6b0c3a70ed7fb1473548ecf")
-            }
-            Self::Re7USDCStableCore => {
-                felt_hex!("0x073702fce24aba36da1eac539bd4bae62d4d6a76747b7cdd3e016da754d7a135")
+    pub fn pool_address(&self) -> VesuPoolId {
+        AddressBook::get().pool(*self)
+    }
+
+    /// Block the pool got created at: it has no event to index before. The
+    /// block of the first Vesu v2 pools until resolved from the chain.
+    pub fn deployment_block(&self) -> u64 {
+        DEPLOYMENT_BLOCKS
+            .get()
+            .and_then(|blocks| blocks.get(self).copied())
+            .unwrap_or(V2_DEPLOYMENT_BLOCK)
+    }
+
+    /// Reads the block every pool got created at from the chain - the pools
+    /// can be overridden, so their blocks cannot be hardcoded. A pool whose
+    /// block cannot be read starts from the first Vesu v2 pools.
+    pub async fn resolve_deployment_blocks(provider: &FallbackProvider) -> anyhow::Result<()> {
+        let head_block =
+            guarded_starknet(provider, RpcPath::Background, |node| node.block_number()).await?;
+        let blocks = join_all(Self::iter().map(|pool| async move {
+            match pool.find_deployment_block(provider, head_block).await {
+                Ok(block) => {
+                    tracing::debug!("The {pool} pool got created at block #{block}");
+                    (pool, block)
+                }
+                Err(e) => {
+                    tracing::warn!(
+                        "Could not read the creation block of the {pool} pool, indexing it from block #{V2_DEPLOYMENT_BLOCK}: {e}"
+                    );
+                    (pool, V2_DEPLOYMENT_BLOCK)
+                }
             }
-            Self::Re7USDCFrontier => {
-                felt_hex!("0x05c03e7e0ccfe79c634782388eb1e6ed4e8e2a013ab0fcc055140805e46261bd")
+        }))
+        .await;
+        let _ = DEPLOYMENT_BLOCKS.set(blocks.into_iter().collect());
+        Ok(())
+    }
+
+    /// Bisects the first block the pool is deployed at, between the first
+    /// Vesu v2 pools & the head.
+    async fn find_deployment_block(
+        &self,
+        provider: &FallbackProvider,
+        head_block: u64,
+    ) -> anyhow::Result<u64> {
+        let address = self.pool_address();
+        let is_deployed_at = |block_number: u64| {
+            guarded_starknet(provider, RpcPath::Background, move |node| async move {
+                match node
+                    .get_class_hash_at(BlockId::Number(block_number), address)
+                    .await
+                {
+                    Ok(_) => Ok(true),
+                    Err(ProviderError::StarknetError(StarknetError::ContractNotFound)) => Ok(false),
+                    Err(e) => Err(e),
+                }
+            })
+        };
+
+        let (mut low, mut high) = (V2_DEPLOYMENT_BLOCK, head_block);
+        anyhow::ensure!(
+            is_deployed_at(high).await?,
+            "the pool {address:#x} is not deployed"
+        );
+        if is_deployed_at(low).await? {
+            return Ok(low);
+        }
+        // Not deployed at `low`, deployed at `high`.
+        while high - low > 1 {
+            let middle = low + (high - low) / 2;
+            if is_deployed_at(middle).await? {
+                high = middle;
+            } else {
+                low = middle;
             }
         }
+        Ok(high)
     }
 
     pub fn pool_details(&self, collateral: Currency, debt: Currency) -> PoolDetails {
@@ -70,14 +141,8 @@ impl TryFrom<&Felt> for PoolName {
     type Error = anyhow::Error;
 
     fn try_from(value: &Felt) -> Result<Self, Self::Error> {
-        match () {
-            _ if value == &Self::Prime.pool_address() => Ok(Self::Prime),
-            _ if value == &Self::Re7USDCPrime.pool_address() => Ok(Self::Re7USDCPrime),
-            _ if value == &Self::Re7USDCCore.pool_address() => Ok(Self::Re7USDCCore),
-            _ if value == &Self::Re7xBTC.pool_address() => Ok(Self::Re7xBTC),
-            _ if value == &Self::Re7USDCStableCore.pool_address() => Ok(Self::Re7USDCStableCore),
-            _ if value == &Self::Re7USDCFrontier.pool_address() => Ok(Self::Re7USDCFrontier),
-            () => bail!("Unknown VesuPool for address {value:x}"),
-        }
+        AddressBook::get()
+            .pool_name(value)
+            .with_context(|| format!("Unknown VesuPool for address {value:x}"))
     }
 }
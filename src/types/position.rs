@@ -5,25 +5,33 @@ use std::sync::Arc;
 
 use cainome::cairo_serde::U256;
 use colored::Colorize;
-use evian::utils::indexer::handler::StarknetEventMetadata;
 use evian::vesu::v2::data::VesuDataClient;
 use num_traits::Pow;
 use pragma_common::starknet::fallback_provider::FallbackProvider;
-use rust_decimal::Decimal;
+use rust_decimal::{Decimal, MathematicalOps};
 use rust_decimal_macros::dec;
 use serde::Deserialize;
 use serde::Serialize;
 use starknet::core::types::Call;
 use starknet::core::types::Felt;
+use starknet::core::types::{BlockId, FunctionCall};
+use starknet::macros::selector;
+use starknet::providers::Provider;
 
-use crate::bindings::liquidate::Liquidate;
-use crate::bindings::liquidate::LiquidateParams;
 use crate::config::onchain_assets::ONCHAIN_ASSETS;
-use crate::services::indexer::PositionDelta;
-use crate::services::monitoring::ekubo::get_ekubo_route;
-use crate::types::account::StarknetSingleOwnerAccount;
+use crate::services::indexer::{EventId, EventMetadata, PositionDelta};
+use crate::services::monitoring::ekubo::{
+    LiquidationQuote, describe_route, get_ekubo_quoted_route,
+};
+use crate::services::monitoring::liquidation_error::LiquidationError;
+use crate::services::oracle::pricing::{self, CROSS_RATES, NativeLtvConfig};
+use crate::services::oracle::volatility::pair_hourly_volatility;
 use crate::types::currency::Currency;
+use crate::types::decimal;
+use crate::types::liquidate_contract::{LiquidateContract, LiquidationRequest};
+use crate::types::pair_config::PAIR_CONFIGS;
 use crate::types::pool::PoolName;
+use crate::utils::rpc::{RpcPath, RpcProvider, guarded, guarded_starknet};
 
 const VESU_SCALE: Decimal = dec!(18);
 
@@ -33,27 +41,32 @@ pub struct VesuPosition {
     pub pool_name: PoolName,
     pub collateral: Asset,
     pub debt: Asset,
+    #[serde(with = "decimal::string")]
     pub lltv: Decimal,
+    /// Last event applied to the position, to not apply an event twice.
+    #[serde(default)]
+    pub last_event: Option<EventId>,
 }
 
 impl VesuPosition {
     /// Creates a new Position from a Vesu Event.
     pub async fn new(
-        event_metadata: &StarknetEventMetadata,
+        event_metadata: &EventMetadata,
         vesu_client: &Arc<VesuDataClient<FallbackProvider>>,
         event: PositionDelta,
     ) -> anyhow::Result<Self> {
         let mut new_position = Self {
             user_address: event.user_address,
-            pool_name: PoolName::try_from(&event_metadata.from_address).expect(
-                "Unsupported pool. Should never happen if we indexed the pools using `PoolName`",
-            ),
-            collateral: Asset::from_address(event.collateral_address),
-            debt: Asset::from_address(event.debt_address),
+            pool_name: PoolName::try_from(&event_metadata.from_address)?,
+            collateral: Asset::from_address(event.collateral_address)?,
+            debt: Asset::from_address(event.debt_address)?,
             lltv: Decimal::ZERO,
+            last_event: event_metadata.event_id(),
         };
 
-        new_position.update_lltv(vesu_client).await?;
+        new_position
+            .update_lltv(vesu_client, Some(event_metadata.block_number))
+            .await?;
         anyhow::ensure!(!new_position.lltv.is_zero(), "LLTV cannot be zero.");
 
         new_position.update_from_delta(event);
@@ -61,6 +74,64 @@ impl VesuPosition {
         Ok(new_position)
     }
 
+    /// Reads the position of a user from the chain state at `block_id`.
+    /// Returns None if the user has no position for this pool & pair.
+    pub async fn from_onchain(
+        vesu_client: &Arc<VesuDataClient<FallbackProvider>>,
+        provider: &FallbackProvider,
+        pool_name: PoolName,
+        collateral_address: Felt,
+        debt_address: Felt,
+        user_address: Felt,
+        block_id: BlockId,
+    ) -> anyhow::Result<Option<Self>> {
+        let position_request = FunctionCall {
+            contract_address: pool_name.pool_address(),
+            entry_point_selector: selector!("position"),
+            calldata: vec![collateral_address, debt_address, user_address],
+        };
+
+        // Returns (Position { collateral_shares, nominal_debt }, collateral, debt)
+        // where amounts are u256 in the assets decimals.
+        let call_result = guarded_starknet(provider, RpcPath::Background, |node| {
+            node.call(position_request.clone(), block_id)
+        })
+        .await?;
+        anyhow::ensure!(
+            call_result.len() >= 8,
+            "Unexpected position result for user {user_address:#x}"
+        );
+
+        let mut position = Self {
+            user_address,
+            pool_name,
+            collateral: Asset::from_address(collateral_address)?,
+            debt: Asset::from_address(debt_address)?,
+            lltv: Decimal::ZERO,
+            last_event: None,
+        };
+
+        let collateral_amount = Decimal::from_str(&call_result[4].to_string())?;
+        let debt_amount = Decimal::from_str(&call_result[6].to_string())?;
+        if collateral_amount.is_zero() && debt_amount.is_zero() {
+            return Ok(None);
+        }
+
+        position
+            .collateral
+            .apply_delta(scale(collateral_amount, position.collateral.decimals));
+        position
+            .debt
+            .apply_delta(scale(debt_amount, position.debt.decimals));
+        let block_number = match block_id {
+            BlockId::Number(block_number) => Some(block_number),
+            _ => None,
+        };
+        position.update_lltv(vesu_client, block_number).await?;
+
+        Ok(Some(position))
+    }
+
     /// Given a new delta event, update the position.
     pub fn update_from_delta(&mut self, delta: PositionDelta) {
         let collateral_delta = scale(delta.collateral_delta, VESU_SCALE);
@@ -69,24 +140,25 @@ impl VesuPosition {
         self.debt.apply_delta(debt_delta);
     }
 
-    /// Updates the LLTV of the position.
+    /// Updates the LLTV of the position, cached per pair around `block_number`.
     async fn update_lltv(
         &mut self,
         vesu_client: &Arc<VesuDataClient<FallbackProvider>>,
+        block_number: Option<u64>,
     ) -> anyhow::Result<()> {
-        let pair_config = vesu_client
-            .pair_config(
-                self.pool_name.pool_address(),
+        self.lltv = PAIR_CONFIGS
+            .max_ltv(
+                vesu_client,
+                self.pool_name,
                 self.collateral.address,
                 self.debt.address,
-                None,
+                block_number,
             )
             .await?;
 
-        self.lltv = pair_config.max_ltv;
-
-        if pair_config.max_ltv.is_zero() {
-            tracing::warn!(
+        // Alerted per pair by the `LltvWatcher`.
+        if self.lltv.is_zero() {
+            tracing::debug!(
                 "For {} {}-{} ; max LTV is zero...?",
                 self.pool_name,
                 self.collateral.currency,
@@ -118,10 +190,10 @@ impl VesuPosition {
 
     /// Computes the liquidation price in USD for the collateral asset.
     /// The position gets liquidated when the collateral price drops to this value.
-    /// Formula: (debt_amount * debt_price) / (collateral_amount * lltv)
+    /// Formula: collateral_price * ltv / lltv
     pub fn liquidation_price(&self) -> Decimal {
-        let debt_price = self.debt.currency.price();
-        (self.debt.amount * debt_price) / (self.collateral.amount * self.lltv)
+        let collateral_price = self.collateral.currency.price();
+        (collateral_price * self.ltv()) / self.lltv
     }
 
     /// Returns the position value in usd.
@@ -144,42 +216,117 @@ impl VesuPosition {
     }
 
     /// Returns the current LTV.
+    /// Uses the direct rate between the assets when available, so pools of
+    /// correlated assets (BTC wrappers...) are not impacted by their USD prices.
+    /// In the pools priced in native terms, the last direct rate known keeps
+    /// being used rather than falling back to the USD prices, until too old.
     pub fn ltv(&self) -> Decimal {
-        self.debt_value_in_usd() / self.collateral_value_in_usd()
+        let native_rate = if NativeLtvConfig::get().applies_to(
+            self.pool_name,
+            self.collateral.currency,
+            self.debt.currency,
+        ) {
+            CROSS_RATES.native(self.debt.currency, self.collateral.currency)
+        } else {
+            None
+        };
+        let debt_in_collateral = match native_rate {
+            Some(rate) => self.debt.amount * rate,
+            None => pricing::convert(
+                self.debt.amount,
+                self.debt.currency,
+                self.collateral.currency,
+            ),
+        };
+        debt_in_collateral / self.collateral.amount
+    }
+
+    /// Returns the health factor (lltv / ltv): the position is liquidable at 1 or
+    /// below. Saturates to `Decimal::MAX` without debt and to zero without
+    /// collateral.
+    pub fn health_factor(&self) -> Decimal {
+        if self.debt.amount.is_zero() {
+            return Decimal::MAX;
+        }
+        if self.collateral.amount.is_zero() {
+            return Decimal::ZERO;
+        }
+
+        let ltv = self.ltv();
+        if ltv.is_zero() {
+            return Decimal::MAX;
+        }
+        self.lltv / ltv
+    }
+
+    /// Returns how many hourly standard deviations of the collateral/debt price
+    /// ratio separate the position from its liquidation, given the volatility of
+    /// the ratio. Zero or below when liquidable, None without debt or volatility.
+    pub fn sigmas_to_liquidation(&self, pair_hourly_volatility: Decimal) -> Option<Decimal> {
+        let health_factor = self.health_factor();
+        if health_factor == Decimal::MAX || pair_hourly_volatility.is_zero() {
+            return None;
+        }
+        // Liquidable once the ratio dropped by a factor of 1 / health factor.
+        Some(health_factor.checked_ln()? / pair_hourly_volatility)
+    }
+
+    /// Estimated hours before the position gets liquidated, assuming the price
+    /// ratio follows a random walk: the time for the 1 sigma move to reach it.
+    pub fn hours_to_liquidation(&self, pair_hourly_volatility: Decimal) -> Option<Decimal> {
+        let sigmas = self.sigmas_to_liquidation(pair_hourly_volatility)?;
+        Some(if sigmas > Decimal::ZERO {
+            sigmas * sigmas
+        } else {
+            Decimal::ZERO
+        })
     }
 
     /// Check if the current position is liquidable.
     /// Also logs a warning if the position is close to being liquidable.
     pub fn is_liquidable(&self) -> bool {
-        const ALMOST_LIQUIDABLE_THRESHOLD: Decimal = dec!(0.1);
-
         if self.lltv.is_zero() {
             return false;
         }
 
-        let ltv_ratio = self.ltv();
+        let health_factor = self.health_factor();
+        let is_liquidable = health_factor <= Decimal::ONE;
 
-        // Avoid division by zero if collateral is zero
-        if ltv_ratio.is_zero() {
-            return !self.debt.amount.is_zero();
+        if is_liquidable || self.is_almost_liquidable(health_factor) {
+            self.logs_liquidation_state(is_liquidable, health_factor);
         }
 
-        let is_liquidable = ltv_ratio >= self.lltv;
-        let almost_liquidable_threshold = self.lltv - ALMOST_LIQUIDABLE_THRESHOLD;
-        let is_almost_liquidable = !is_liquidable && ltv_ratio > almost_liquidable_threshold;
+        is_liquidable
+    }
 
-        if is_liquidable || is_almost_liquidable {
-            self.logs_liquidation_state(is_liquidable, ltv_ratio);
+    /// Returns true if the position is liquidable or close to be: the positions
+    /// logged in detail by `is_liquidable`.
+    pub fn is_at_risk(&self) -> bool {
+        if self.lltv.is_zero() {
+            return false;
         }
 
-        is_liquidable
+        let health_factor = self.health_factor();
+        health_factor <= Decimal::ONE || self.is_almost_liquidable(health_factor)
+    }
+
+    /// Within ALMOST_LIQUIDABLE_THRESHOLD of LTV from the LLTV, not liquidable yet.
+    fn is_almost_liquidable(&self, health_factor: Decimal) -> bool {
+        const ALMOST_LIQUIDABLE_THRESHOLD: Decimal = dec!(0.1);
+
+        health_factor > Decimal::ONE
+            && self.lltv > ALMOST_LIQUIDABLE_THRESHOLD
+            && health_factor < self.lltv / (self.lltv - ALMOST_LIQUIDABLE_THRESHOLD)
     }
 
-    fn logs_liquidation_state(&self, is_liquidable: bool, ltv_ratio: Decimal) {
+    fn logs_liquidation_state(&self, is_liquidable: bool, health_factor: Decimal) {
+        let sigmas = pair_hourly_volatility(self.collateral.currency, self.debt.currency)
+            .and_then(|volatility| self.sigmas_to_liquidation(volatility))
+            .map_or_else(String::new, |sigmas| format!(", {sigmas:.1}σ/h away"));
         tracing::info!(
-            "{} is at ratio {:.2}%/{:.2}% => {}",
+            "{} has a health factor of {:.3} (LLTV {:.2}%{sigmas}) => {}",
             self,
-            ltv_ratio * dec!(100),
+            health_factor,
             self.lltv * dec!(100),
             if is_liquidable {
                 "liquidable! 🚨".green()
@@ -190,37 +337,66 @@ impl VesuPosition {
     }
 
     /// Returns the TX necessary to liquidate this position using the Vesu Liquidate
-    /// contract.
+    /// contract, whatever its version. The seized collateral goes to `recipient`.
+    /// If `debt_to_repay` is None, all the debt gets repaid. Also returns the
+    /// quote of the swap of the collateral into the debt.
     pub async fn get_vesu_liquidate_tx(
         &self,
-        liquidate_contract: &Arc<Liquidate<StarknetSingleOwnerAccount>>,
-        liquidator_address: &Felt,
-    ) -> anyhow::Result<Call> {
-        let (liquidate_swap, liquidate_swap_weights) = get_ekubo_route(
-            self.debt.address,
-            self.collateral.address,
-            &self.debt.amount,
-            self.debt.decimals,
+        liquidate_contract: &LiquidateContract,
+        recipient: &Felt,
+        debt_to_repay: Option<Decimal>,
+    ) -> anyhow::Result<(Call, LiquidationQuote)> {
+        let debt_out = debt_to_repay.unwrap_or(self.debt.amount);
+        let route = guarded(
+            RpcProvider::Ekubo,
+            RpcPath::Liquidation,
+            get_ekubo_quoted_route(
+                self.debt.address,
+                self.collateral.address,
+                &debt_out,
+                self.debt.decimals,
+            ),
         )
-        .await?;
+        .await
+        .map_err(|e| LiquidationError::RouteNotFound {
+            reason: format!("{e:#}"),
+        })?;
+        let quote = LiquidationQuote {
+            route: describe_route(&route.swaps, &route.weights),
+            collateral_in: Decimal::from(route.amount_in)
+                / Decimal::TEN.pow(self.collateral.decimals),
+            debt_out,
+        };
 
-        let liquidate_params = LiquidateParams {
-            pool: cainome::cairo_serde::ContractAddress(self.pool_name.pool_address()),
-            collateral_asset: cainome::cairo_serde::ContractAddress(self.collateral.address),
-            debt_asset: cainome::cairo_serde::ContractAddress(self.debt.address),
-            user: cainome::cairo_serde::ContractAddress(self.user_address),
-            recipient: cainome::cairo_serde::ContractAddress(*liquidator_address),
-            min_collateral_to_receive: U256 { low: 0, high: 0 },
-            debt_to_repay: U256 { low: 0, high: 0 },
-            liquidate_swap,
-            liquidate_swap_weights,
-            liquidate_swap_limit_amount: u128::MAX,
-            withdraw_swap: vec![],
-            withdraw_swap_limit_amount: 0,
-            withdraw_swap_weights: vec![],
+        // Zero means repaying all the debt.
+        let debt_to_repay = match debt_to_repay {
+            Some(amount) => {
+                let raw_amount: u128 = (amount * Decimal::TEN.pow(self.debt.decimals))
+                    .trunc()
+                    .try_into()?;
+                U256 {
+                    low: raw_amount,
+                    high: 0,
+                }
+            }
+            None => U256 { low: 0, high: 0 },
         };
 
-        Ok(liquidate_contract.liquidate_getcall(&liquidate_params))
+        let liquidation_request = LiquidationRequest {
+            pool: self.pool_name.pool_address(),
+            collateral_asset: self.collateral.address,
+            debt_asset: self.debt.address,
+            user: self.user_address,
+            recipient: *recipient,
+            debt_to_repay,
+            liquidate_swap: route.swaps,
+            liquidate_swap_weights: route.weights,
+        };
+
+        Ok((
+            liquidate_contract.liquidate_call(liquidation_request),
+            quote,
+        ))
     }
 }
 
@@ -229,29 +405,45 @@ pub struct Asset {
     pub name: String,
     pub currency: Currency,
     pub address: Felt,
+    #[serde(with = "decimal::string")]
     pub decimals: Decimal,
+    #[serde(with = "decimal::string")]
     pub amount: Decimal,
 }
 
 impl Asset {
-    pub fn from_address(address: Felt) -> Self {
-        let config = &ONCHAIN_ASSETS[&address];
+    pub fn from_address(address: Felt) -> anyhow::Result<Self> {
+        let config = ONCHAIN_ASSETS
+            .get_by_address(&address)
+            .ok_or_else(|| anyhow::anyhow!("Asset {address:#x} is not in assets.toml"))?;
 
-        let currency =
-            Currency::from_str(&config.ticker).expect("Could not convert ticker -> Currency");
+        let currency = Currency::from_str(&config.ticker)
+            .map_err(|_| anyhow::anyhow!("No currency for the asset {}", config.ticker))?;
 
-        Self {
+        Ok(Self {
             name: config.name.clone(),
             decimals: currency.d_decimals(),
             address: currency.address(),
             currency,
             amount: Decimal::ZERO,
-        }
+        })
+    }
+
+    /// Returns true if the asset is in assets.toml with a matching currency.
+    pub fn is_listed(address: &Felt) -> bool {
+        ONCHAIN_ASSETS
+            .get_by_address(address)
+            .is_some_and(|config| Currency::from_str(&config.ticker).is_ok())
     }
 
     pub fn apply_delta(&mut self, amount_delta: Decimal) {
         self.amount += amount_delta;
     }
+
+    /// Formats the amount for display, e.g `0.0123457 WBTC`.
+    pub fn formatted_amount(&self) -> String {
+        self.currency.format_amount(self.amount)
+    }
 }
 
 fn scale(nb: Decimal, scale: Decimal) -> Decimal {
@@ -262,16 +454,10 @@ impl std::fmt::Display for VesuPosition {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "Position #{} with {} {} of collateral and {} {} of debt",
+            "Position #{} with {} of collateral and {} of debt",
             self.position_id(),
-            self.collateral
-                .amount
-                .round_dp(self.collateral.decimals.try_into().expect("Must fit")),
-            self.collateral.currency,
-            self.debt
-                .amount
-                .round_dp(self.debt.decimals.try_into().expect("Must fit")),
-            self.debt.currency,
+            self.collateral.formatted_amount(),
+            self.debt.formatted_amount(),
         )
     }
 }
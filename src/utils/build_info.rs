@@ -0,0 +1,38 @@
+use serde::Serialize;
+
+/// Information about the running binary, embedded at build time by `build.rs`.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct BuildInfo {
+    pub version: &'static str,
+    pub git_sha: &'static str,
+    /// Unix timestamp of the build, in seconds.
+    pub build_timestamp: &'static str,
+    /// Comma separated list of the enabled cargo features.
+    pub features: &'static str,
+}
+
+pub const BUILD_INFO: BuildInfo = BuildInfo {
+    version: env!("CARGO_PKG_VERSION"),
+    git_sha: env!("GIT_SHA"),
+    build_timestamp: env!("BUILD_TIMESTAMP"),
+    features: env!("BUILD_FEATURES"),
+};
+
+impl BuildInfo {
+    /// Returns the `build_info` gauge in the Prometheus text format, always 1
+    /// with the build information as labels.
+    pub fn prometheus_metric(&self) -> String {
+        format!(
+            "# HELP build_info Build information of the liquidator.\n\
+             # TYPE build_info gauge\n\
+             build_info{{version=\"{}\",git_sha=\"{}\",build_timestamp=\"{}\",features=\"{}\"}} 1\n",
+            self.version, self.git_sha, self.build_timestamp, self.features
+        )
+    }
+}
+
+impl std::fmt::Display for BuildInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "v{} ({})", self.version, self.git_sha)
+    }
+}
@@ -0,0 +1,30 @@
+use anyhow::{Context, bail};
+use serde_json::{Value, json};
+use starknet::core::types::Felt;
+use url::Url;
+
+/// Asks a forked starknet-devnet-rs to impersonate the provided account, i.e
+/// to skip the signature validation of the transactions it sends.
+pub async fn impersonate_account(devnet_url: &Url, account_address: Felt) -> anyhow::Result<()> {
+    let request = json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "devnet_impersonateAccount",
+        "params": { "account_address": format!("{account_address:#x}") },
+    });
+
+    let response: Value = reqwest::Client::new()
+        .post(devnet_url.clone())
+        .json(&request)
+        .send()
+        .await
+        .context("Could not reach the devnet")?
+        .json()
+        .await?;
+
+    if let Some(error) = response.get("error") {
+        bail!("Could not impersonate account {account_address:#x}: {error}");
+    }
+
+    Ok(())
+}
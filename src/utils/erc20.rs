@@ -0,0 +1,148 @@
+use std::str::FromStr;
+
+use anyhow::Result;
+use cainome::cairo_serde::U256;
+use pragma_common::starknet::FallbackProvider;
+use starknet::{
+    core::{
+        types::{BlockId, BlockTag, Call, Felt, FunctionCall},
+        utils::parse_cairo_short_string,
+    },
+    macros::selector,
+    providers::Provider,
+};
+
+use crate::config::onchain_assets::{AssetClass, OnchainAssetConfig};
+
+/// Returns the raw ERC-20 balance of `owner` for `token`.
+pub async fn balance_of(provider: &FallbackProvider, token: Felt, owner: Felt) -> Result<U256> {
+    let balance_request = FunctionCall {
+        contract_address: token,
+        entry_point_selector: selector!("balance_of"),
+        calldata: vec![owner],
+    };
+
+    let call_result = provider
+        .call(balance_request, BlockId::Tag(BlockTag::Latest))
+        .await?;
+
+    anyhow::ensure!(
+        call_result.len() >= 2,
+        "Unexpected balance_of result for token {token:#x}"
+    );
+
+    Ok(U256 {
+        low: u128::from_str(&call_result[0].to_string())?,
+        high: u128::from_str(&call_result[1].to_string())?,
+    })
+}
+
+/// Returns the raw ERC-20 amount of `token` that `spender` can transfer from `owner`.
+pub async fn allowance(
+    provider: &FallbackProvider,
+    token: Felt,
+    owner: Felt,
+    spender: Felt,
+) -> Result<U256> {
+    let allowance_request = FunctionCall {
+        contract_address: token,
+        entry_point_selector: selector!("allowance"),
+        calldata: vec![owner, spender],
+    };
+
+    let call_result = provider
+        .call(allowance_request, BlockId::Tag(BlockTag::Latest))
+        .await?;
+
+    anyhow::ensure!(
+        call_result.len() >= 2,
+        "Unexpected allowance result for token {token:#x}"
+    );
+
+    Ok(U256 {
+        low: u128::from_str(&call_result[0].to_string())?,
+        high: u128::from_str(&call_result[1].to_string())?,
+    })
+}
+
+/// Reads the name, symbol & decimals of `token` from its contract.
+pub async fn token_metadata(
+    provider: &FallbackProvider,
+    token: Felt,
+) -> Result<OnchainAssetConfig> {
+    let name = read_string(provider, token, selector!("name")).await?;
+    let ticker = read_string(provider, token, selector!("symbol")).await?;
+
+    let decimals_request = FunctionCall {
+        contract_address: token,
+        entry_point_selector: selector!("decimals"),
+        calldata: vec![],
+    };
+    let call_result = provider
+        .call(decimals_request, BlockId::Tag(BlockTag::Latest))
+        .await?;
+    let decimals = call_result
+        .first()
+        .ok_or_else(|| anyhow::anyhow!("Empty decimals result for token {token:#x}"))?;
+
+    Ok(OnchainAssetConfig {
+        name,
+        ticker,
+        decimals: u32::from_str(&decimals.to_string())?,
+        address: token,
+        class: AssetClass::default(),
+    })
+}
+
+/// Reads a string returned by `selector`: a short string for the legacy tokens,
+/// a ByteArray { data: Array<bytes31>, pending_word, pending_word_len } otherwise.
+async fn read_string(provider: &FallbackProvider, token: Felt, selector: Felt) -> Result<String> {
+    let request = FunctionCall {
+        contract_address: token,
+        entry_point_selector: selector,
+        calldata: vec![],
+    };
+    let call_result = provider
+        .call(request, BlockId::Tag(BlockTag::Latest))
+        .await?;
+
+    if let [short_string] = call_result.as_slice() {
+        return Ok(parse_cairo_short_string(short_string)?);
+    }
+
+    let words = usize::from_str(&call_result.first().unwrap_or(&Felt::ZERO).to_string())?;
+    anyhow::ensure!(
+        words.checked_add(3) == Some(call_result.len()),
+        "Unexpected string result for token {token:#x}"
+    );
+    let mut bytes = Vec::new();
+    for word in &call_result[1..=words] {
+        bytes.extend_from_slice(&word.to_bytes_be()[1..]);
+    }
+    let pending_len = usize::from_str(&call_result[words + 2].to_string())?;
+    anyhow::ensure!(
+        pending_len < 31,
+        "Invalid pending word for token {token:#x}"
+    );
+    bytes.extend_from_slice(&call_result[words + 1].to_bytes_be()[32 - pending_len..]);
+
+    Ok(String::from_utf8(bytes)?)
+}
+
+/// Returns the call transferring `amount` of `token` to `recipient`.
+pub fn transfer_call(token: Felt, recipient: Felt, amount: U256) -> Call {
+    Call {
+        to: token,
+        selector: selector!("transfer"),
+        calldata: vec![recipient, amount.low.into(), amount.high.into()],
+    }
+}
+
+/// Returns the call allowing `spender` to transfer `amount` of `token`.
+pub fn approve_call(token: Felt, spender: Felt, amount: U256) -> Call {
+    Call {
+        to: token,
+        selector: selector!("approve"),
+        calldata: vec![spender, amount.low.into(), amount.high.into()],
+    }
+}
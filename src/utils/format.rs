@@ -0,0 +1,77 @@
+use std::sync::OnceLock;
+
+use rust_decimal::Decimal;
+
+static DISPLAY_CONFIG: OnceLock<DisplayConfig> = OnceLock::new();
+
+/// How the amounts get displayed in the logs, reports & APIs.
+#[derive(Debug, Clone, Copy)]
+pub struct DisplayConfig {
+    /// Significant digits of the asset amounts.
+    pub significant_digits: u32,
+    /// Decimals of the USD values.
+    pub usd_decimals: u32,
+}
+
+impl Default for DisplayConfig {
+    fn default() -> Self {
+        Self {
+            significant_digits: 6,
+            usd_decimals: 2,
+        }
+    }
+}
+
+impl DisplayConfig {
+    /// Sets the display config of the whole process. Only the first call has an
+    /// effect.
+    pub fn install(self) {
+        let _ = DISPLAY_CONFIG.set(self);
+    }
+
+    pub fn get() -> Self {
+        DISPLAY_CONFIG.get().copied().unwrap_or_default()
+    }
+}
+
+/// Formats an asset amount with the configured significant digits, without
+/// trailing zeros, e.g `0.0123457`.
+pub fn format_amount(amount: Decimal) -> String {
+    let significant_digits = DisplayConfig::get().significant_digits.max(1);
+    amount
+        .round_sf(significant_digits)
+        .unwrap_or(amount)
+        .normalize()
+        .to_string()
+}
+
+/// Formats a USD value with the configured decimals & thousands separators,
+/// e.g `$1,234.57`.
+pub fn format_usd(value: Decimal) -> String {
+    let usd_decimals = DisplayConfig::get().usd_decimals;
+    let rounded = value.abs().round_dp(usd_decimals);
+    let formatted = format!("{rounded:.precision$}", precision = usd_decimals as usize);
+
+    let (integer, fraction) = match formatted.split_once('.') {
+        Some((integer, fraction)) => (integer, Some(fraction)),
+        None => (formatted.as_str(), None),
+    };
+
+    let mut grouped = String::with_capacity(integer.len() + integer.len() / 3);
+    for (i, digit) in integer.chars().enumerate() {
+        if i > 0 && (integer.len() - i) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+
+    let sign = if value.is_sign_negative() && !rounded.is_zero() {
+        "-"
+    } else {
+        ""
+    };
+    match fraction {
+        Some(fraction) => format!("{sign}${grouped}.{fraction}"),
+        None => format!("{sign}${grouped}"),
+    }
+}
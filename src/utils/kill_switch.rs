@@ -0,0 +1,27 @@
+use std::path::PathBuf;
+
+/// Env variable engaging the kill switch when set to `1` or `true`.
+pub const KILL_SWITCH_ENV: &str = "KILL_SWITCH";
+
+/// Emergency toggle stopping all the transactions submission, while the rest of
+/// the bot (indexing, prices...) keeps running.
+/// It is engaged when the configured file exists or when `KILL_SWITCH` is set.
+#[derive(Debug, Clone, Default)]
+pub struct KillSwitch {
+    file: Option<PathBuf>,
+}
+
+impl KillSwitch {
+    pub fn new(file: Option<PathBuf>) -> Self {
+        Self { file }
+    }
+
+    /// Checked before every submission so it can be toggled at runtime.
+    pub fn is_engaged(&self) -> bool {
+        if self.file.as_ref().is_some_and(|file| file.exists()) {
+            return true;
+        }
+
+        std::env::var(KILL_SWITCH_ENV).is_ok_and(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+    }
+}
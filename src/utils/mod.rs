@@ -1,3 +1,10 @@
+pub mod build_info;
+pub mod devnet;
+pub mod erc20;
+pub mod format;
+pub mod kill_switch;
+pub mod rpc;
+
 use std::{
     sync::Arc,
     time::{Duration, SystemTime},
@@ -0,0 +1,305 @@
+use std::future::Future;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{LazyLock, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use anyhow::anyhow;
+use dashmap::DashMap;
+use pragma_common::starknet::FallbackProvider;
+use starknet::providers::ProviderError;
+use url::Url;
+
+use crate::services::monitoring::liquidation_error::LiquidationError;
+
+static RPC_CONFIG: OnceLock<RpcConfig> = OnceLock::new();
+
+/// Circuit breakers by provider, and by node URL for the Starknet nodes.
+static CIRCUIT_BREAKERS: LazyLock<DashMap<String, CircuitBreaker>> = LazyLock::new(DashMap::new);
+
+static STARKNET_NODES: OnceLock<Vec<StarknetNode>> = OnceLock::new();
+
+/// The remote services the bot depends on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, strum::Display)]
+pub enum RpcProvider {
+    /// The Starknet RPC nodes, behind the fallback provider.
+    Starknet,
+    /// The Ekubo quoter API, used to route the liquidation swaps.
+    Ekubo,
+    /// The Pragma API, for the assets priced from it.
+    PragmaApi,
+    /// The AVNU API, quoted to compare the swap routes of the liquidations.
+    Avnu,
+}
+
+/// A Starknet RPC node, called on its own by `guarded_starknet` so that a
+/// failing node only opens its own circuit breaker.
+pub struct StarknetNode {
+    url: String,
+    /// Over this node only.
+    provider: FallbackProvider,
+    /// Overrides the timeouts of the paths for this node.
+    timeout: Option<Duration>,
+}
+
+impl StarknetNode {
+    /// Sets the Starknet nodes of the whole process, by order of priority, with
+    /// the timeouts of the nodes slower or faster than the others. Only the
+    /// first call has an effect.
+    pub fn install(urls: &[Url], timeouts: &[(Url, Duration)]) -> anyhow::Result<()> {
+        let nodes = urls
+            .iter()
+            .map(|url| {
+                Ok(Self {
+                    url: url.to_string(),
+                    provider: FallbackProvider::new(vec![url.clone()])?,
+                    timeout: timeouts
+                        .iter()
+                        .find(|(node_url, _)| node_url == url)
+                        .map(|(_, timeout)| *timeout),
+                })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let _ = STARKNET_NODES.set(nodes);
+        Ok(())
+    }
+}
+
+/// What a call is made for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RpcPath {
+    /// Building, sending & tracking the liquidations. Never skipped by an open
+    /// circuit breaker.
+    Liquidation,
+    /// Everything else: prices, position reads, scans...
+    Background,
+}
+
+/// Timeouts & circuit breakers of the calls to the remote services.
+#[derive(Debug, Clone, Copy)]
+pub struct RpcConfig {
+    pub liquidation_timeout: Duration,
+    pub background_timeout: Duration,
+    /// Consecutive failures of a provider, or of a Starknet node, before its
+    /// circuit breaker opens.
+    pub breaker_failures: u32,
+    /// How long an open circuit breaker skips the background calls.
+    pub breaker_cooldown: Duration,
+}
+
+impl Default for RpcConfig {
+    fn default() -> Self {
+        Self {
+            liquidation_timeout: Duration::from_secs(5),
+            background_timeout: Duration::from_secs(10),
+            breaker_failures: 5,
+            breaker_cooldown: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RpcConfig {
+    /// Sets the RPC config of the whole process. Only the first call has an
+    /// effect.
+    pub fn install(self) {
+        let _ = RPC_CONFIG.set(self);
+    }
+
+    pub fn get() -> Self {
+        RPC_CONFIG.get().copied().unwrap_or_default()
+    }
+
+    fn timeout(&self, path: RpcPath) -> Duration {
+        match path {
+            RpcPath::Liquidation => self.liquidation_timeout,
+            RpcPath::Background => self.background_timeout,
+        }
+    }
+}
+
+/// Opens after too many consecutive failures of a provider so the background
+/// calls fail fast instead of waiting for their timeout every time.
+#[derive(Debug, Default)]
+struct CircuitBreaker {
+    consecutive_failures: AtomicU32,
+    open_until: Mutex<Option<Instant>>,
+}
+
+impl CircuitBreaker {
+    fn is_open(&self) -> bool {
+        self.open_until
+            .lock()
+            .expect("poisoned circuit breaker")
+            .is_some_and(|until| Instant::now() < until)
+    }
+
+    fn record_success(&self) {
+        self.consecutive_failures.store(0, Ordering::Relaxed);
+        *self.open_until.lock().expect("poisoned circuit breaker") = None;
+    }
+
+    /// Returns true if this failure opened the breaker.
+    fn record_failure(&self, config: &RpcConfig) -> bool {
+        let failures = self.consecutive_failures.fetch_add(1, Ordering::Relaxed) + 1;
+        if failures < config.breaker_failures.max(1) {
+            return false;
+        }
+
+        let mut open_until = self.open_until.lock().expect("poisoned circuit breaker");
+        let was_open = open_until.is_some_and(|until| Instant::now() < until);
+        *open_until = Some(Instant::now() + config.breaker_cooldown);
+        !was_open
+    }
+}
+
+/// Runs a call to `provider` with the timeout of its `path`, through the
+/// circuit breaker of the provider. The Starknet calls made through the whole
+/// fallback provider, e.g by the account, only get the timeout: the breakers of
+/// the Starknet nodes are the ones of `guarded_starknet`.
+pub async fn guarded<T, E>(
+    provider: RpcProvider,
+    path: RpcPath,
+    call: impl Future<Output = Result<T, E>>,
+) -> anyhow::Result<T>
+where
+    E: Into<anyhow::Error>,
+{
+    let config = RpcConfig::get();
+    let breaker_key = (provider != RpcProvider::Starknet).then(|| provider.to_string());
+    run_guarded(
+        &provider.to_string(),
+        breaker_key.as_deref(),
+        path,
+        config.timeout(path),
+        call,
+    )
+    .await
+    .map_err(|(e, _)| e)
+}
+
+/// Runs a Starknet call on the first node whose circuit breaker is closed,
+/// with the timeout of the node, failing over to the next one when the node
+/// fails. The liquidation calls also try the nodes with an open breaker once
+/// all the others failed. Until the nodes get installed, e.g in the CLI
+/// commands, the call goes through the given provider.
+pub async fn guarded_starknet<T, E, Fut>(
+    provider: &FallbackProvider,
+    path: RpcPath,
+    call: impl Fn(&'static FallbackProvider) -> Fut,
+) -> anyhow::Result<T>
+where
+    Fut: Future<Output = Result<T, E>>,
+    E: Into<anyhow::Error>,
+{
+    let config = RpcConfig::get();
+    let nodes = STARKNET_NODES.get_or_init(|| {
+        vec![StarknetNode {
+            url: RpcProvider::Starknet.to_string(),
+            provider: provider.clone(),
+            timeout: None,
+        }]
+    });
+
+    let (closed, open): (Vec<_>, Vec<_>) = nodes.iter().partition(|node| {
+        !CIRCUIT_BREAKERS
+            .get(&node.url)
+            .is_some_and(|breaker| breaker.is_open())
+    });
+    let candidates = match path {
+        RpcPath::Liquidation => closed.into_iter().chain(open).collect(),
+        RpcPath::Background => closed,
+    };
+    if candidates.is_empty() {
+        return Err(anyhow!(
+            "The circuit breakers of all the Starknet nodes are open, call skipped"
+        ));
+    }
+
+    let mut last_error = None;
+    for node in candidates {
+        let timeout = node.timeout.unwrap_or_else(|| config.timeout(path));
+        match run_guarded(
+            &node.url,
+            Some(&node.url),
+            path,
+            timeout,
+            call(&node.provider),
+        )
+        .await
+        {
+            Ok(value) => return Ok(value),
+            // The call itself failed, e.g reverted: the next node would fail too.
+            Err((e, false)) => return Err(e),
+            Err((e, true)) => last_error = Some(e),
+        }
+    }
+    Err(last_error.expect("at least one Starknet node was called"))
+}
+
+/// Runs the call with the timeout, recording its outcome in the circuit
+/// breaker of `breaker_key`, if any. The error comes with whether the provider
+/// failed, rather than the call.
+async fn run_guarded<T, E>(
+    label: &str,
+    breaker_key: Option<&str>,
+    path: RpcPath,
+    timeout: Duration,
+    call: impl Future<Output = Result<T, E>>,
+) -> Result<T, (anyhow::Error, bool)>
+where
+    E: Into<anyhow::Error>,
+{
+    let config = RpcConfig::get();
+
+    if path == RpcPath::Background
+        && let Some(breaker_key) = breaker_key
+        && CIRCUIT_BREAKERS
+            .get(breaker_key)
+            .is_some_and(|breaker| breaker.is_open())
+    {
+        return Err((
+            anyhow!("{label} circuit breaker is open, call skipped"),
+            true,
+        ));
+    }
+
+    let (result, provider_failed) = match tokio::time::timeout(timeout, call).await {
+        Ok(Ok(value)) => (Ok(value), false),
+        Ok(Err(e)) => {
+            let e: anyhow::Error = e.into();
+            let provider_failed = is_provider_failure(&e);
+            (Err(e), provider_failed)
+        }
+        Err(_) => (
+            Err(anyhow!("{label} call timed out after {timeout:?}")),
+            true,
+        ),
+    };
+
+    if let Some(breaker_key) = breaker_key {
+        let breaker = CIRCUIT_BREAKERS.entry(breaker_key.to_string()).or_default();
+        if !provider_failed {
+            breaker.record_success();
+        } else if let Err(e) = &result
+            && breaker.record_failure(&config)
+        {
+            tracing::error!(
+                "[🔌 RPC] {label} failed {} times in a row, skipping its background calls for {:?}: {e}",
+                config.breaker_failures,
+                config.breaker_cooldown
+            );
+        }
+    }
+    result.map_err(|e| (e, provider_failed))
+}
+
+/// Whether the error comes from the provider itself - a transport error, a rate
+/// limit... - rather than from the call, e.g a reverted call.
+fn is_provider_failure(e: &anyhow::Error) -> bool {
+    if let Some(e) = e.downcast_ref::<LiquidationError>() {
+        return e.is_provider_failure();
+    }
+    if let Some(e) = e.downcast_ref::<ProviderError>() {
+        return !matches!(e, ProviderError::StarknetError(_));
+    }
+    e.downcast_ref::<reqwest::Error>().is_some()
+}